@@ -0,0 +1,103 @@
+//! A mock server of many targets, and the files of their configs, shared by the benchmarks.
+
+// each benchmark uses a part of these.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// One of this many targets changes in each revision of the server.
+pub const CHANGING_EVERY: usize = 10;
+
+/// Serves a small page of each target at `/<index>`, on a port of the loopback.
+pub struct MockServer {
+    pub port: u16,
+    revision: Arc<AtomicU64>,
+}
+impl MockServer {
+    /// Starts serving on the current runtime, responding after the latency.
+    pub async fn start(latency: Duration) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let revision = Arc::new(AtomicU64::new(0));
+        {
+            let revision = revision.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (socket, _) = match listener.accept().await {
+                        Ok(x) => x,
+                        Err(_) => continue,
+                    };
+                    let revision = revision.load(Ordering::Relaxed);
+                    tokio::spawn(respond(socket, revision, latency));
+                }
+            });
+        }
+        Self { port, revision }
+    }
+
+    /// Changes the pages of one of `CHANGING_EVERY` targets.
+    pub fn revise(&self) {
+        let _ = self.revision.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Writes the config of the targets into a new directory, and returns the path of the config and the data.
+    pub fn write_configs(&self, targets: usize, name: &str) -> (String, String) {
+        let dir = std::env::temp_dir().join(format!("patrol-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut toml = String::new();
+        for i in 0..targets {
+            toml.push_str(&format!(
+                "[target-{i}]\nurl = \"http://127.0.0.1:{}/{i}\"\nselector = \"p.item\"\nmode = \"simple\"\n\n",
+                self.port
+            ));
+        }
+        let config = dir.join("config.toml");
+        let data = dir.join("data.toml");
+        std::fs::write(&config, toml).unwrap();
+        let _ = std::fs::remove_file(&data);
+        (path_str(config), path_str(data))
+    }
+}
+
+/// Removes the directory written by `MockServer::write_configs`.
+pub fn remove_configs(config_path: &str) {
+    if let Some(dir) = std::path::Path::new(config_path).parent() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+fn path_str(path: PathBuf) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+async fn respond(mut socket: tokio::net::TcpStream, revision: u64, latency: Duration) {
+    let mut buffer = [0u8; 4096];
+    let n = socket.read(&mut buffer).await.unwrap_or(0);
+    let request = String::from_utf8_lossy(&buffer[..n]);
+    let index: usize = request
+        .split_whitespace()
+        .nth(1)
+        .and_then(|x| x.trim_start_matches('/').parse().ok())
+        .unwrap_or(0);
+
+    let revision = match index % CHANGING_EVERY {
+        0 => revision,
+        _ => 0,
+    };
+    let page = format!(
+        "<html><body><h1>target {index}</h1><p class=\"item\">item {index} of revision {revision}</p><span>noise</span></body></html>"
+    );
+    tokio::time::sleep(latency).await;
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        page.len()
+    );
+    let _ = socket.write_all(header.as_bytes()).await;
+    let _ = socket.write_all(page.as_bytes()).await;
+}
@@ -0,0 +1,129 @@
+//! Patrols many targets of a mock server for a few cycles, to catch the regressions of the pollers and the cycle.
+//!
+//! Prints the time of each cycle, the resident memory of the process,
+//! and the most events waiting in a subscriber of the bus, which drains them every 10 ms.
+//! One of 10 targets changes in each cycle after the first.
+//!
+//! ```sh
+//! cargo bench --bench load -- --targets 1000 --cycles 5 --latency 50
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use patrol::application::{App, EventBus, PatrolEvent, RunPolicy};
+use patrol::infrastructure::{HttpPoller, TomlConfigRepository, TomlDataRepository};
+use tokio::sync::mpsc::error::TryRecvError;
+
+use common::MockServer;
+
+/// How often the subscriber drains the events of the bus.
+const DRAIN_PERIOD: Duration = Duration::from_millis(10);
+
+struct Options {
+    targets: usize,
+    cycles: u64,
+    /// How long the server takes to respond.
+    latency: Duration,
+}
+impl Options {
+    fn parse() -> Self {
+        let mut options = Self {
+            targets: 500,
+            cycles: 3,
+            latency: Duration::from_millis(20),
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut number = || match args.next().and_then(|x| x.parse::<u64>().ok()) {
+                Some(x) => x,
+                None => panic!("{arg} takes a number."),
+            };
+            match arg.as_str() {
+                "--targets" => options.targets = number() as usize,
+                "--cycles" => options.cycles = number(),
+                "--latency" => options.latency = Duration::from_millis(number()),
+                // passed by `cargo bench`.
+                "--bench" => (),
+                x => panic!("unknown option: {x}"),
+            }
+        }
+        options
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = Options::parse();
+    let server = MockServer::start(options.latency).await;
+    let (config_path, data_path) = server.write_configs(options.targets, "load");
+
+    let config_repo = TomlConfigRepository::new(&config_path).await.unwrap();
+    let data_repo = TomlDataRepository::new(&data_path).await.unwrap();
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let app = App::new(
+        config_repo,
+        data_repo,
+        HttpPoller::new(),
+        1,
+        RunPolicy::Cycles(options.cycles),
+    )
+    .with_event_bus(bus)
+    .with_summary(false)
+    .with_color(false);
+
+    println!(
+        "{} targets responding in {} ms, for {} cycles:",
+        options.targets,
+        options.latency.as_millis(),
+        options.cycles
+    );
+    let report = tokio::spawn(async move {
+        let mut backlog = 0;
+        loop {
+            tokio::time::sleep(DRAIN_PERIOD).await;
+            let mut waiting = 0;
+            loop {
+                let event = match rx.try_recv() {
+                    Ok(x) => x,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                };
+                waiting += 1;
+                if let PatrolEvent::CycleCompleted {
+                    cycle,
+                    succeeded,
+                    changed,
+                    failed,
+                    elapsed,
+                    ..
+                } = event
+                {
+                    backlog = backlog.max(waiting);
+                    println!(
+                        "cycle {cycle}: {succeeded} succeeded, {changed} changed, {failed} failed in {:.2}s, {} resident, {backlog} events waiting at most.",
+                        elapsed.as_secs_f64(),
+                        resident_memory().unwrap_or_else(|| "unknown memory".to_owned()),
+                    );
+                    backlog = 0;
+                    server.revise();
+                }
+            }
+            backlog = backlog.max(waiting);
+        }
+    });
+
+    app.run().await.unwrap();
+    report.await.unwrap();
+    common::remove_configs(&config_path);
+}
+
+/// The resident memory of the process, where `/proc` is available.
+fn resident_memory() -> Option<String> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|x| x.starts_with("VmRSS:"))?;
+    let kib: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(format!("{:.1} MiB", kib / 1024.0))
+}
@@ -0,0 +1,86 @@
+//! Benchmarks of a cycle over many targets of a mock server, and of handling the contents polled in it.
+//!
+//! ```sh
+//! cargo bench --bench pipeline
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use patrol::application::{App, RunPolicy};
+use patrol::domain::{diff_excerpt, ChangeKind, Hash, PollOutput};
+use patrol::infrastructure::{HttpPoller, TomlConfigRepository, TomlDataRepository};
+
+use common::MockServer;
+
+/// The numbers of the lines of the contents.
+const LINES: usize = 10_000;
+
+fn cycle(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MockServer::start(Duration::ZERO));
+
+    let mut group = c.benchmark_group("cycle");
+    group.sample_size(10);
+    for targets in [10, 100, 500] {
+        let (config_path, data_path) =
+            server.write_configs(targets, &format!("pipeline-{targets}"));
+        group.bench_with_input(BenchmarkId::from_parameter(targets), &targets, |b, _| {
+            b.to_async(&runtime).iter_custom(|iters| {
+                let (config_path, data_path) = (config_path.clone(), data_path.clone());
+                async move {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let config_repo = TomlConfigRepository::new(&config_path).await.unwrap();
+                        let data_repo = TomlDataRepository::new(&data_path).await.unwrap();
+                        let app = App::new(
+                            config_repo,
+                            data_repo,
+                            HttpPoller::new(),
+                            1,
+                            RunPolicy::Cycles(1),
+                        )
+                        .with_summary(false);
+
+                        let start = Instant::now();
+                        app.run().await.unwrap();
+                        elapsed += start.elapsed();
+                    }
+                    elapsed
+                }
+            });
+        });
+        common::remove_configs(&config_path);
+    }
+    group.finish();
+}
+
+fn content(c: &mut Criterion) {
+    let old = text(0);
+    let new = text(1);
+    let output = PollOutput::new(format!("\n  {new}  \n"));
+
+    let mut group = c.benchmark_group("content");
+    group.bench_function("trim", |b| b.iter(|| black_box(&output).trimmed()));
+    group.bench_function("hash", |b| b.iter(|| Hash::new(black_box(new.as_bytes()))));
+    group.bench_function("classify", |b| {
+        b.iter(|| ChangeKind::classify(black_box(&old), black_box(&new)))
+    });
+    group.bench_function("diff_excerpt", |b| {
+        b.iter(|| diff_excerpt(black_box(&old), black_box(&new)))
+    });
+    group.finish();
+}
+
+/// A content of many lines, whose last line differs by the revision.
+fn text(revision: u64) -> String {
+    let mut lines: Vec<_> = (0..LINES).map(|i| format!("item {i}")).collect();
+    lines.push(format!("revision {revision}"));
+    lines.join("\n")
+}
+
+criterion_group!(benches, cycle, content);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Measures how long the runtime is blocked while the simple mode polls large pages.
+//!
+//! A ticker on the same single-threaded runtime records the longest delay of its ticks,
+//! which grows to the time of parsing a page if the parsing runs on the runtime.
+//!
+//! ```sh
+//! cargo bench --bench responsiveness
+//! ```
+
+use std::time::{Duration, Instant};
+
+use patrol::domain::{Config, Id, Mode, Poller, Selector, Url};
+use patrol::infrastructure::HttpPoller;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const ITEMS: usize = 100_000;
+const POLLS: usize = 5;
+const TICK: Duration = Duration::from_millis(1);
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let page = page();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let page = page.clone();
+            tokio::spawn(async move {
+                let mut buffer = [0u8; 4096];
+                let _ = socket.read(&mut buffer).await;
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    page.len()
+                );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(page.as_bytes()).await;
+            });
+        }
+    });
+
+    let (tx_stop, mut rx_stop) = tokio::sync::oneshot::channel::<()>();
+    let ticker = tokio::spawn(async move {
+        let mut longest = Duration::ZERO;
+        loop {
+            let start = Instant::now();
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => (),
+                _ = &mut rx_stop => break,
+            }
+            longest = longest.max(start.elapsed().saturating_sub(TICK));
+        }
+        longest
+    });
+
+    let url = Url::new(format!("http://{addr}/")).unwrap();
+    let selector = Selector::new("p.item".to_owned()).unwrap();
+    let config = Config::builder(url, selector)
+        .mode(Mode::Simple)
+        .build()
+        .unwrap();
+    let id = Id::try_from("large".to_owned()).unwrap();
+
+    let mut poller = HttpPoller::new();
+    let start = Instant::now();
+    for _ in 0..POLLS {
+        let content = poller.poll(id.clone(), config.clone()).await.unwrap();
+        assert_eq!(content.text().lines().count(), ITEMS);
+    }
+    let elapsed = start.elapsed();
+
+    let _ = tx_stop.send(());
+    let longest = ticker.await.unwrap();
+    println!(
+        "{POLLS} polls of {ITEMS} elements: {:.1} ms per poll, the runtime blocked for {:.1} ms at most.",
+        elapsed.as_secs_f64() * 1000.0 / POLLS as f64,
+        longest.as_secs_f64() * 1000.0,
+    );
+}
+
+/// A page of many elements, of a few megabytes.
+fn page() -> std::sync::Arc<String> {
+    let mut page = String::from("<html><body>");
+    for i in 0..ITEMS {
+        page.push_str(&format!(
+            "<div><p class=\"item\">item {i}</p><span>noise</span></div>"
+        ));
+    }
+    page.push_str("</body></html>");
+    std::sync::Arc::new(page)
+}
@@ -1,140 +1,1228 @@
-use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use futures_util::{stream::SelectAll, StreamExt};
 use log::{debug, info, warn};
+use tokio::sync::{mpsc, watch};
+
+use crate::application::{
+    event::Event,
+    events::{EventBus, PatrolEvent},
+    rules,
+};
+use crate::domain::{
+    self, diff_excerpt, Baseline, Challenge, ChangeKind, Checkpoint, Condition, ConfigEvent,
+    Duration, Id, LanguageMismatch, Mode, PollErrorKind, PollOutput, Secrets, Status, TamperKind,
+    TimeZone, Timestamp,
+};
+use crate::{Error, Report};
+
+/// The number of the recent hashes of a target kept to detect flapping.
+///
+/// A target is flapping when these contain only two distinct hashes, i.e. the last changes alternated between them.
+const FLAP_HISTORY: usize = 5;
 
-use crate::domain::{self, Duration, Timestamp};
+/// How often the results polled so far are recorded while checkpointing a cycle.
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Determines how long the app keeps patrolling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunPolicy {
+    /// Patrol until the process is stopped.
+    #[default]
+    Unlimited,
+    /// Patrol the given number of cycles, then stop.
+    Cycles(u64),
+    /// Patrol until the given duration has elapsed, then stop.
+    Duration(std::time::Duration),
+}
 
 pub struct App<ConfigRepository, DataRepository, Poller> {
     config_repo: ConfigRepository,
     data_repo: DataRepository,
     poller: Poller,
     period: std::time::Duration,
-    limit: Option<u8>,
+    run_policy: RunPolicy,
+    bus: EventBus,
+    summary: bool,
+    color: bool,
+    overrun_events: bool,
+    checkpoints: bool,
+    ramp_up: Option<std::time::Duration>,
+    shutdown: watch::Receiver<bool>,
+    reload: Option<mpsc::UnboundedReceiver<()>>,
+    poll: Option<mpsc::UnboundedReceiver<()>>,
+    composites: HashMap<Id, Condition>,
+    time_zone: TimeZone,
+    secrets: Secrets,
+    retention: Option<Duration>,
 }
 
 impl<ConfigRepository, DataRepository, Poller> App<ConfigRepository, DataRepository, Poller>
 where
     ConfigRepository: domain::ConfigRepository,
     DataRepository: domain::DataRepository + Send + 'static,
-    Poller: domain::Poller,
-
-    ConfigRepository::Error: std::error::Error,
-    DataRepository::Error: std::error::Error,
-    Poller::Error: std::error::Error,
+    Poller: domain::Poller + Send,
 {
     pub fn new(
         config_repo: ConfigRepository,
         data_repo: DataRepository,
         poller: Poller,
         interval_period_secs: u64,
-        interval_limit: Option<u8>,
+        run_policy: RunPolicy,
     ) -> Self {
         Self {
             config_repo,
             data_repo,
             poller,
             period: std::time::Duration::from_secs(interval_period_secs),
-            limit: interval_limit,
+            run_policy,
+            bus: EventBus::new(),
+            summary: true,
+            color: true,
+            overrun_events: false,
+            checkpoints: false,
+            ramp_up: None,
+            shutdown: watch::channel(false).1,
+            reload: None,
+            poll: None,
+            composites: HashMap::new(),
+            time_zone: TimeZone::Utc,
+            secrets: Secrets::default(),
+            retention: None,
         }
     }
 
-    pub async fn run(
-        self,
-    ) -> Result<(), Error<ConfigRepository::Error, DataRepository::Error, Poller::Error>> {
+    /// Publishes the events that occurred while patrolling on the bus.
+    ///
+    /// The content of each target is also published whenever it differs from the last one published.
+    pub fn with_event_bus(mut self, bus: EventBus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    /// Enables or disables the summary logged at the end of each cycle. (default: enabled)
+    pub fn with_summary(mut self, enabled: bool) -> Self {
+        self.summary = enabled;
+        self
+    }
+
+    /// Colors the summary and the statuses in the log by the ANSI escape codes. (default: enabled)
+    pub fn with_color(mut self, enabled: bool) -> Self {
+        self.color = enabled;
+        self
+    }
+
+    /// Emits a `failed` event of each target skipped as it was not polled within the cycle or its `budget`,
+    /// e.g. to notify of it. (default: disabled, only logged and counted)
+    pub fn with_overrun_events(mut self, enabled: bool) -> Self {
+        self.overrun_events = enabled;
+        self
+    }
+
+    /// Checkpoints the targets polled in each cycle in the data repository,
+    /// and resumes the cycle interrupted by a crash or a restart instead of starting it over. (default: disabled)
+    ///
+    /// The results are recorded every 10 seconds while polling, rather than after all the targets are polled.
+    pub fn with_checkpoints(mut self, enabled: bool) -> Self {
+        self.checkpoints = enabled;
+        self
+    }
+
+    /// Spreads the polls of the first cycle over the duration, the targets checked the longest ago first,
+    /// rather than polling all of them at once on startup. (default: all at once)
+    ///
+    /// The duration is cut down to the interval, for the cycle to poll all the targets within it.
+    pub fn with_ramp_up(mut self, ramp_up: std::time::Duration) -> Self {
+        self.ramp_up = ramp_up.into();
+        self
+    }
+
+    /// Stops patrolling when `true` is sent to the receiver.
+    ///
+    /// The content polled so far is saved before `run` returns.
+    pub fn with_shutdown_signal(mut self, rx: watch::Receiver<bool>) -> Self {
+        self.shutdown = rx;
+        self
+    }
+
+    /// Reloads the configurations whenever a message is sent to the receiver.
+    ///
+    /// The reload takes place while waiting for the next cycle,
+    /// so the polls in progress are not affected.
+    pub fn with_reload_signal(mut self, rx: mpsc::UnboundedReceiver<()>) -> Self {
+        self.reload = rx.into();
+        self
+    }
+
+    /// Starts the next cycle at once whenever a message is sent to the receiver, instead of waiting for the interval.
+    ///
+    /// The interval is counted again from the cycle.
+    pub fn with_poll_signal(mut self, rx: mpsc::UnboundedReceiver<()>) -> Self {
+        self.poll = rx.into();
+        self
+    }
+
+    /// Evaluates the composite targets at the end of each cycle, whose states derive from the other targets.
+    pub fn with_composites(mut self, composites: HashMap<Id, Condition>) -> Self {
+        self.composites = composites;
+        self
+    }
+
+    /// Displays the times in the summary in the zone. (default: UTC)
+    pub fn with_time_zone(mut self, zone: TimeZone) -> Self {
+        self.time_zone = zone;
+        self
+    }
+
+    /// Resolves the references to the secrets in the configs, only when the targets are polled.
+    pub fn with_secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Deletes the data of the targets removed from the configs for longer than the duration. (default: kept)
+    ///
+    /// The time of the removal is taken as the last check.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention.into();
+        self
+    }
+
+    pub async fn run(self) -> Result<(), Error> {
         let Self {
             mut data_repo,
             mut config_repo,
             mut poller,
             period,
-            mut limit,
+            run_policy,
+            bus,
+            summary,
+            color,
+            overrun_events,
+            checkpoints,
+            ramp_up,
+            mut shutdown,
+            mut reload,
+            mut poll,
+            composites,
+            time_zone,
+            secrets,
+            retention,
         } = self;
 
+        let emit = |event: Event| bus.publish(PatrolEvent::Recorded(event));
+
         let mut interval = tokio::time::interval(period);
+        // the contents of the last cycle, to classify the changes.
+        let mut last_contents: HashMap<Id, PollOutput> = HashMap::new();
+        // the pinned versions of the contents polled while running, to classify the changes from them.
+        let mut pinned_contents: HashMap<Id, PollOutput> = HashMap::new();
+        // the new hashes waiting for the confirmations, with the number of the polls observing them.
+        let mut pending: HashMap<Id, (domain::Hash, u16)> = HashMap::new();
+        // the recent hashes of the changed targets, to detect flapping.
+        let mut history: HashMap<Id, Vec<domain::Hash>> = HashMap::new();
+        // the targets whose metadata has been discovered, or tried to, while running.
+        let mut discovered: HashSet<Id> = HashSet::new();
 
+        let mut remaining_cycles = match run_policy {
+            RunPolicy::Cycles(n) => Some(n),
+            _ => None,
+        };
+        let until = match run_policy {
+            RunPolicy::Duration(d) => Some(tokio::time::Instant::now() + d),
+            _ => None,
+        };
+
+        let mut cycle = 0;
         loop {
-            match &mut limit {
+            match &mut remaining_cycles {
                 Some(0) => break,
                 Some(x) => *x -= 1,
                 None => (),
             }
 
             info!("waiting for next interval period...");
-            let now = interval.tick().await;
-            let deadline = now + period;
+            let mut forced = false;
+            let now = {
+                let tick = async {
+                    match until {
+                        Some(until) => tokio::time::timeout_at(until, interval.tick()).await.ok(),
+                        None => Some(interval.tick().await),
+                    }
+                };
+                tokio::pin!(tick);
+                loop {
+                    tokio::select! {
+                        now = &mut tick => break now,
+                        _ = wait_for_shutdown(&mut shutdown) => break None,
+                        _ = wait_for_message(&mut reload) => reload_configs(&mut config_repo, &bus).await,
+                        _ = wait_for_message(&mut poll) => {
+                            forced = true;
+                            break Some(tokio::time::Instant::now());
+                        }
+                    }
+                }
+            };
+            if forced {
+                interval.reset();
+            }
+            // the missed ticks after a cycle longer than the interval are in the past,
+            // and the cycles started by them still have the whole period.
+            let now = match now {
+                Some(now) => now.max(tokio::time::Instant::now()),
+                None => break,
+            };
+            cycle += 1;
+            let cycle_started = std::time::Instant::now();
+            let deadline = match until {
+                Some(until) => until.min(now + period),
+                None => now + period,
+            };
 
             let configs = config_repo
                 .get_all()
                 .await
-                .map_err(Error::ConfigRepositoryError)?;
+                .map_err(Error::config_repository)?;
 
+            let previous = match data_repo.get_all().await {
+                Ok(x) => x,
+                Err(why) => {
+                    warn!("{}", Report(&why));
+                    HashMap::new()
+                }
+            };
+
+            let mut statuses = HashMap::new();
             let mut rem = configs.clone();
+            let started_at = Timestamp::now();
+            // the paused targets are not depended on, to poll their dependents as if they were not there.
+            let paused: HashSet<_> = configs
+                .iter()
+                .filter(|(_, config)| config.paused)
+                .map(|(id, _)| id.clone())
+                .collect();
+            rem.retain(|id, config| {
+                if config.paused {
+                    let _ = statuses.insert(id.clone(), Status::Paused);
+                    return false;
+                }
+                match config.maintenance.iter().find(|x| x.contains(started_at)) {
+                    Some(window) => {
+                        info!("[{id}]: in the maintenance window {window}.");
+                        let _ = statuses.insert(id.clone(), Status::Maintenance);
+                        false
+                    }
+                    None => true,
+                }
+            });
+
             let mut retry = 3;
+            let mut failures = HashMap::new();
+            let mut kinds = HashMap::new();
+            let mut contents = HashMap::new();
 
-            while 0 < rem.len() && 0 < retry {
-                let poll_stream = poller.poll_multiple(rem.clone()).await;
-                tokio::pin!(poll_stream);
+            let mut succeeded = HashSet::new();
+            let mut unconfirmed = HashSet::new();
+            let mut changed = 0;
+            // the configs of the targets watched for the integrity, whose versions are pinned in the cycle.
+            let mut pins: HashMap<Id, domain::Config> = HashMap::new();
+            // when the budget of each target runs out, counted from its first poll in the cycle.
+            let mut budgets = HashMap::new();
+            // the targets which ran out of their budgets or the cycle, with the reasons.
+            let mut overruns = HashMap::new();
+            let mut over_budget = HashMap::new();
+            let mut cut_off = false;
 
-                while let Ok(Some((id, result))) =
-                    tokio::time::timeout_at(deadline, poll_stream.next()).await
-                {
-                    let content = match result {
-                        Ok(x) => x,
+            let mut checkpoint = Checkpoint {
+                started_at,
+                statuses: HashMap::new(),
+            };
+            // the targets polled in the cycle interrupted last time are not polled again.
+            if checkpoints && cycle == 1 {
+                match data_repo.get_checkpoint().await {
+                    Ok(Some(last)) => {
+                        info!(
+                            "resuming the cycle started at {}, whose {} targets have been polled.",
+                            last.started_at.display_in(time_zone),
+                            last.statuses.len()
+                        );
+                        for (id, status) in last.statuses.into_iter() {
+                            if rem.remove(&id).is_none() {
+                                continue;
+                            }
+                            if matches!(status, Status::Ok | Status::Changed | Status::Flapping) {
+                                let _ = succeeded.insert(id.clone());
+                            }
+                            let _ = statuses.insert(id.clone(), status);
+                            let _ = checkpoint.statuses.insert(id, status);
+                        }
+                        checkpoint.started_at = last.started_at;
+                    }
+                    Ok(None) => (),
+                    Err(why) => warn!("failed to read the checkpoint: {}", Report(&why)),
+                }
+            }
+
+            // the first polls on startup are spread, not to hammer all the targets at once.
+            let mut ramping = ramp_up.filter(|_| cycle == 1).map(|x| x.min(period));
+            while 0 < rem.len() && 0 < retry && !*shutdown.borrow() && !cut_off {
+                // the targets depending on others wait until all of them succeed.
+                let ready: HashMap<_, _> = rem
+                    .iter()
+                    .filter(|(_, config)| unmet_dependency(config, &succeeded, &paused).is_none())
+                    .map(|(id, config)| (id.clone(), config.clone()))
+                    .collect();
+                if ready.len() == 0 {
+                    break;
+                }
+
+                // the configs with the secrets are only passed to the poller, not to be stored or reported.
+                let mut resolved = HashMap::new();
+                for (id, config) in ready.iter() {
+                    match secrets.resolve_config(config.clone()) {
+                        Ok(x) => {
+                            let _ = resolved.insert(id.clone(), x);
+                        }
                         Err(why) => {
                             warn!("[{id}]: {why}");
-                            continue;
+                            let _ = failures.insert(id.clone(), (Status::Failing, why.to_string()));
                         }
+                    }
+                }
+
+                let mut in_flight: HashSet<_> = resolved.keys().cloned().collect();
+
+                // the targets to poll together, with the times to start them, in order.
+                let mut waves: VecDeque<(_, HashMap<_, _>)> = VecDeque::new();
+                let polled_at = tokio::time::Instant::now();
+                match ramping.take() {
+                    Some(ramp_up) => {
+                        info!(
+                            "spreading the polls of {} targets over {:.1}s.",
+                            resolved.len(),
+                            ramp_up.as_secs_f64()
+                        );
+                        // the targets never checked come first, as the oldest.
+                        let mut targets: Vec<_> = resolved.into_iter().collect();
+                        targets.sort_by_key(|(id, _)| previous.get(id).map(|x| x.last_checked));
+                        let n = targets.len();
+                        for (i, (id, config)) in targets.into_iter().enumerate() {
+                            // the targets starting within the same second are polled together.
+                            let offset = ramp_up.mul_f64(i as f64 / n as f64).as_secs();
+                            let start_at = polled_at + std::time::Duration::from_secs(offset);
+                            match waves.back_mut() {
+                                Some((at, wave)) if *at == start_at => {
+                                    let _ = wave.insert(id, config);
+                                }
+                                _ => waves.push_back((start_at, HashMap::from([(id, config)]))),
+                            }
+                        }
+                    }
+                    None => waves.push_back((polled_at, resolved)),
+                }
+                let mut poll_streams = SelectAll::new();
+
+                let mut finished = false;
+                while !finished {
+                    let mut hashes = HashMap::new();
+                    let mut lossy = HashMap::new();
+
+                    // the results are recorded in chunks while checkpointing, not to lose all of them by a crash.
+                    let flush_at = match checkpoints {
+                        true => tokio::time::Instant::now() + CHECKPOINT_INTERVAL,
+                        false => deadline,
                     };
+                    loop {
+                        // the polls of the targets which have run out of their budgets are dropped.
+                        if in_flight.len() == 0 {
+                            finished = true;
+                            break;
+                        }
+                        // the budgets of the targets are counted from the starts of their waves.
+                        let now = tokio::time::Instant::now();
+                        while matches!(waves.front(), Some((at, _)) if *at <= now) {
+                            let Some((_, wave)) = waves.pop_front() else {
+                                break;
+                            };
+                            for (id, config) in wave.iter() {
+                                if let Some(budget) = config.budget {
+                                    let _ =
+                                        budgets.entry(id.clone()).or_insert(now + budget.as_std());
+                                }
+                            }
+                            bus.publish(PatrolEvent::PollStarted {
+                                ids: wave.keys().cloned().collect(),
+                            });
+                            poll_streams.push(Box::pin(poller.poll_multiple(wave).await));
+                        }
+                        // wake up at the end of the earliest budget of the targets in flight, or of the cycle,
+                        // or at the start of the next wave.
+                        let wake_at = in_flight
+                            .iter()
+                            .filter_map(|id| budgets.get(id))
+                            .chain(waves.front().map(|(at, _)| at))
+                            .fold(deadline.min(flush_at), |a, b| a.min(*b));
+                        let waiting = poll_streams.is_empty() && 0 < waves.len();
+                        let polled = async {
+                            match waiting {
+                                true => std::future::pending().await,
+                                false => poll_streams.next().await,
+                            }
+                        };
+                        let next = tokio::select! {
+                            next = tokio::time::timeout_at(wake_at, polled) => next,
+                            _ = wait_for_shutdown(&mut shutdown) => {
+                                finished = true;
+                                break;
+                            }
+                        };
+                        let now = tokio::time::Instant::now();
+                        let (id, result) = match next {
+                            Ok(Some(x)) => x,
+                            // the streams of the waves started so far may end before the next wave.
+                            Ok(None) if 0 < waves.len() => continue,
+                            Ok(None) => {
+                                finished = true;
+                                break;
+                            }
+                            Err(_) if deadline <= now => {
+                                cut_off = true;
+                                finished = true;
+                                break;
+                            }
+                            Err(_) if flush_at <= now => break,
+                            Err(_) => {
+                                in_flight.retain(|id| {
+                                    let overrun =
+                                        budgets.get(id).map(|x| *x <= now).unwrap_or(false);
+                                    if let (true, Some(config)) = (overrun, rem.remove(id)) {
+                                        let _ = over_budget.insert(id.clone(), config);
+                                    }
+                                    !overrun
+                                });
+                                continue;
+                            }
+                        };
+                        // the result of a target which has run out of its budget is too late.
+                        if !in_flight.remove(&id) {
+                            continue;
+                        }
+
+                        let output = match result {
+                            Ok(x) => x.trimmed(),
+                            Err(why) => {
+                                // a page served in another language is not compared, keeping the previous hash and status.
+                                if let Some(x) = LanguageMismatch::find_in(&why) {
+                                    warn!("[{id}]: ignore the page {x}");
+                                    if let Some(x) = previous.get(&id) {
+                                        let _ = statuses.insert(id.clone(), x.status);
+                                    }
+                                    let _ = failures.remove(&id);
+                                    let _ = rem.remove(&id);
+                                    let _ = succeeded.insert(id);
+                                    continue;
+                                }
+                                let kind = PollErrorKind::of(&why);
+                                warn!("({kind}) {}", Report(&why));
+                                let _ = kinds.insert(id.clone(), kind);
+                                let status = match Challenge::find_in(&why) {
+                                    Some(_) => Status::Blocked,
+                                    None => Status::Failing,
+                                };
+                                // keep the challenge as the reason, rather than a failure of the retry.
+                                if let (Status::Failing, Some((Status::Blocked, _))) =
+                                    (status, failures.get(&id))
+                                {
+                                    continue;
+                                }
+                                // a challenge page may be passed by a real browser.
+                                if let (Status::Blocked, Some(config)) = (status, rem.get_mut(&id))
+                                {
+                                    if config.mode == Mode::Simple {
+                                        info!("[{id}]: retry in the full mode.");
+                                        config.mode = Mode::Full;
+                                    }
+                                }
+                                let _ = failures.insert(id, (status, Report(&why).to_string()));
+                                continue;
+                            }
+                        };
+
+                        let content = output.text();
+
+                        if content.len() == 0 {
+                            warn!("[{id}]: ignore empty content.");
+                            let _ = failures
+                                .insert(id, (Status::SelectorMiss, "empty content".to_owned()));
+                            continue;
+                        }
+
+                        debug!(
+                            "[{id}]: polled in {:.1}s{}.",
+                            output.elapsed.as_secs_f64(),
+                            output
+                                .status
+                                .map(|x| format!(", status {x}"))
+                                .unwrap_or_default()
+                        );
+                        debug!("[{id}]:\n{}", content);
+
+                        // the pollers replace the bytes failing to be decoded, rather than failing.
+                        let is_lossy = output.lossy;
+                        if previous.get(&id).map(|x| x.lossy).unwrap_or(false) != is_lossy {
+                            if is_lossy {
+                                warn!("[{id}]: the content is not valid text, and has been decoded lossily.");
+                            }
+                            let _ = lossy.insert(id.clone(), is_lossy);
+                        }
+
+                        let mut hash = domain::Hash::new(content.as_bytes());
+                        let stored = previous.get(&id).and_then(|x| x.hash.as_ref());
+                        // the integrity alerts are immediate, without the variants or the confirmations.
+                        let integrity = rem.get(&id).is_some_and(|x| x.integrity.is_some());
+                        let known = previous
+                            .get(&id)
+                            .filter(|_| !integrity)
+                            .map(|x| x.known_hashes.as_slice());
+                        if let (Some(stored), Some(known)) = (stored, known) {
+                            if *stored != hash && known.contains(&hash) {
+                                debug!("[{id}]: a known variant.");
+                                hash = stored.clone();
+                            }
+                        }
+                        let confirmations = rem
+                            .get(&id)
+                            .filter(|_| !integrity)
+                            .and_then(|x| x.confirmations)
+                            .unwrap_or(1);
+                        match stored {
+                            Some(stored) if *stored != hash && 1 < confirmations => {
+                                let count = match pending.remove(&id) {
+                                    Some((x, n)) if x == hash => n + 1,
+                                    _ => 1,
+                                };
+                                if count < confirmations {
+                                    info!(
+                                        "[{id}]: unconfirmed change ({count} of {confirmations})."
+                                    );
+                                    let _ = pending.insert(id.clone(), (hash, count));
+                                    let _ = unconfirmed.insert(id.clone());
+                                    // keep the stored hash until the change is confirmed.
+                                    hash = stored.clone();
+                                }
+                            }
+                            _ => {
+                                let _ = pending.remove(&id);
+                            }
+                        }
+                        let _ = hashes.insert(id.clone(), hash);
+                        let _ = contents.insert(id.clone(), output);
+
+                        let _ = rem.remove(&id);
+                        let _ = succeeded.insert(id);
+                    }
+
+                    let polled: Vec<_> = hashes.keys().cloned().collect();
+                    if 0 < hashes.len() {
+                        let checked_at = Timestamp::now();
+                        let mut known_hashes = HashMap::new();
+                        match data_repo.update_multiple(hashes.clone()).await {
+                            Ok(results) => {
+                                let updated = results.values().filter(|x| x.is_some()).count();
+                                info!("{updated} of {} targets updated.", results.len());
+
+                                for (id, last_updated) in results.into_iter() {
+                                    let (url, hash) = match (configs.get(&id), hashes.remove(&id)) {
+                                        (Some(config), Some(hash)) => (config.url.clone(), hash),
+                                        _ => continue,
+                                    };
+
+                                    // a pinned version is the reference, and every other one is a change.
+                                    let pinned = configs.get(&id).and_then(|x| x.pinned.as_ref());
+                                    if pinned == Some(&hash) {
+                                        if let Some(content) = contents.get(&id) {
+                                            let _ =
+                                                pinned_contents.insert(id.clone(), content.clone());
+                                        }
+                                    }
+
+                                    // the first hash is a baseline, which is a change only if the target says so.
+                                    let had_hash = previous.get(&id).and_then(|x| x.hash.as_ref());
+                                    let baseline =
+                                        configs.get(&id).map(|x| x.baseline).filter(|_| {
+                                            last_updated.is_some()
+                                                && had_hash.is_none()
+                                                && pinned.is_none()
+                                        });
+                                    let mut status =
+                                        match (pinned, last_updated, had_hash, baseline) {
+                                            (Some(pinned), ..) if *pinned == hash => Status::Ok,
+                                            (Some(_), ..)
+                                            | (None, Some(_), Some(_), _)
+                                            | (None, _, _, Some(Baseline::TreatAsChange)) => {
+                                                Status::Changed
+                                            }
+                                            _ => Status::Ok,
+                                        };
+                                    // the changes from a pinned version are never muted.
+                                    if let (Status::Changed, Some(had_hash), None) =
+                                        (status, had_hash, pinned)
+                                    {
+                                        let recent = history
+                                            .entry(id.clone())
+                                            .or_insert_with(|| vec![had_hash.clone()]);
+                                        recent.push(hash.clone());
+                                        if FLAP_HISTORY < recent.len() {
+                                            let _ = recent.remove(0);
+                                        }
+                                        let variants: HashSet<_> = recent.iter().collect();
+                                        if recent.len() == FLAP_HISTORY && variants.len() <= 2 {
+                                            status = Status::Flapping;
+                                        }
+                                    }
+                                    let was_flapping = previous.get(&id).map(|x| x.status)
+                                        == Some(Status::Flapping);
+                                    if status == Status::Flapping && !was_flapping {
+                                        warn!("[{id}]: flapping between two variants, muted.");
+                                        emit(Event::Flapping {
+                                            id: id.clone(),
+                                            url: url.clone(),
+                                            checked_at,
+                                        });
+                                    }
+                                    // keep flapping until the content changes to another variant.
+                                    if status == Status::Ok && was_flapping && pinned.is_none() {
+                                        status = Status::Flapping;
+                                    }
+                                    let style = match status {
+                                        _ if !color => ansi_term::Style::new(),
+                                        Status::Changed => ansi_term::Color::Fixed(15).bold(),
+                                        _ => ansi_term::Color::Fixed(8).normal(),
+                                    };
+                                    // the first content is compared with the empty one.
+                                    let old = match (baseline, pinned) {
+                                        (Some(Baseline::Notify | Baseline::TreatAsChange), _) => {
+                                            Some("")
+                                        }
+                                        (_, Some(_)) if status == Status::Changed => {
+                                            pinned_contents.get(&id).map(PollOutput::text)
+                                        }
+                                        _ if status == Status::Changed => {
+                                            last_contents.get(&id).map(PollOutput::text)
+                                        }
+                                        _ => None,
+                                    };
+                                    let (change, excerpt) = match (old, contents.get(&id)) {
+                                        (Some(old), Some(new)) => (
+                                            Some(ChangeKind::classify(old, new.text())),
+                                            diff_excerpt(old, new.text()),
+                                        ),
+                                        _ => (None, None),
+                                    };
+                                    match change {
+                                        Some(change) => info!(
+                                            "[{id}]: {}",
+                                            style.paint(format!("{status} ({change})"))
+                                        ),
+                                        None => info!("[{id}]: {}", style.paint(status.as_str())),
+                                    }
+                                    let _ = statuses.insert(id.clone(), status);
 
-                    let content = content.trim_start().trim_end();
+                                    // the first version of a target watched for the integrity is pinned.
+                                    if let Some(config) =
+                                        configs.get(&id).filter(|x| x.integrity.is_some())
+                                    {
+                                        match pinned {
+                                            Some(pinned) if *pinned != hash => {
+                                                warn!("[{id}]: the content differs from the pinned version.");
+                                                emit(Event::Tampered {
+                                                    id: id.clone(),
+                                                    url: url.clone(),
+                                                    kind: TamperKind::Content,
+                                                    pinned: pinned.clone(),
+                                                    hash: hash.clone(),
+                                                    checked_at,
+                                                });
+                                            }
+                                            Some(_) => (),
+                                            None => {
+                                                info!("[{id}]: pinned the content {hash}.");
+                                                pins.entry(id.clone())
+                                                    .or_insert_with(|| config.clone())
+                                                    .pinned = Some(hash.clone());
+                                            }
+                                        }
+                                    }
 
-                    if content.len() <= 0 {
-                        warn!("[{id}]: ignore empty content.");
+                                    let variants = configs.get(&id).and_then(|x| x.variants);
+                                    if let (Some(_), Some(previous), Some(variants)) =
+                                        (last_updated, previous.get(&id), variants)
+                                    {
+                                        // the current hash is also a variant served again.
+                                        let mut known = match previous.known_hashes.len() {
+                                            0 => previous.hash.iter().cloned().collect(),
+                                            _ => previous.known_hashes.clone(),
+                                        };
+                                        known.retain(|x| *x != hash);
+                                        known.push(hash.clone());
+                                        let excess =
+                                            known.len().saturating_sub(variants.max(1) as usize);
+                                        let _ = known.drain(..excess);
+                                        let _ = known_hashes.insert(id.clone(), known);
+                                    }
+
+                                    let (old_hash, last_updated_previous) =
+                                        match (last_updated, previous.get(&id)) {
+                                            (Some(_), Some(x)) => (x.hash.clone(), x.last_updated),
+                                            _ => (None, None),
+                                        };
+                                    // the change of a pinned target is from the pinned version.
+                                    let old_hash = match pinned {
+                                        Some(pinned) if status == Status::Changed => {
+                                            Some(pinned.clone())
+                                        }
+                                        _ => old_hash,
+                                    };
+                                    if status == Status::Changed {
+                                        changed += 1;
+                                        bus.publish(PatrolEvent::ChangeDetected {
+                                            id: id.clone(),
+                                            url: url.clone(),
+                                            hash: hash.clone(),
+                                            old_hash: old_hash.clone(),
+                                            change,
+                                        });
+                                    }
+                                    // a silent baseline is recorded without being notified.
+                                    let updated = match pinned {
+                                        Some(_) => status == Status::Changed,
+                                        None => {
+                                            last_updated.is_some()
+                                                && baseline != Some(Baseline::Silent)
+                                        }
+                                    };
+                                    emit(Event::Checked {
+                                        id,
+                                        url,
+                                        updated,
+                                        hash,
+                                        change,
+                                        old_hash,
+                                        last_updated_previous,
+                                        excerpt,
+                                        checked_at,
+                                    });
+                                }
+                            }
+                            Err(why) => warn!("{}", Report(&why)),
+                        }
+                        if 0 < known_hashes.len() {
+                            if let Err(why) = data_repo.update_known_hashes(known_hashes).await {
+                                warn!("{}", Report(&why));
+                            }
+                        }
+                        if 0 < lossy.len() {
+                            if let Err(why) = data_repo.update_lossy(lossy).await {
+                                warn!("{}", Report(&why));
+                            }
+                        }
+                    }
+
+                    if checkpoints {
+                        for id in polled.into_iter() {
+                            if let Some(status) = statuses.get(&id) {
+                                let _ = checkpoint.statuses.insert(id, *status);
+                            }
+                        }
+                        if let Err(why) = data_repo.save_checkpoint(Some(checkpoint.clone())).await
+                        {
+                            warn!("failed to save the checkpoint: {}", Report(&why));
+                        }
+                    }
+                }
+
+                // a retry is spent only if any of the targets failed, not to unblock the dependents.
+                if ready.keys().any(|id| rem.contains_key(id)) {
+                    retry -= 1;
+                }
+            }
+
+            if *shutdown.borrow() {
+                info!("shutting down.");
+                break;
+            }
+
+            // the targets which have failed before running out of the time are reported as failed.
+            for (id, config) in over_budget.into_iter() {
+                match failures.contains_key(&id) {
+                    true => {
+                        let _ = rem.insert(id, config);
+                    }
+                    false => {
+                        let reason = match config.budget {
+                            Some(budget) => format!("not polled within the budget of {budget}"),
+                            None => "not polled within the budget".to_owned(),
+                        };
+                        let _ = overruns.insert(id, (config.url, reason));
+                    }
+                }
+            }
+            if cut_off {
+                let unpolled: Vec<_> = rem
+                    .iter()
+                    .filter(|(id, config)| {
+                        !failures.contains_key(*id)
+                            && unmet_dependency(config, &succeeded, &paused).is_none()
+                    })
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for id in unpolled.into_iter() {
+                    if let Some(config) = rem.remove(&id) {
+                        let reason = "not polled within the cycle".to_owned();
+                        let _ = overruns.insert(id, (config.url, reason));
+                    }
+                }
+            }
+
+            // the metadata is discovered on the first successful poll.
+            let mut metadata = HashMap::new();
+            for id in succeeded.iter() {
+                let known = previous
+                    .get(id)
+                    .map(|x| x.title.is_some() || x.favicon.is_some());
+                let config = match configs.get(id) {
+                    Some(x) if known != Some(true) && discovered.insert(id.clone()) => x,
+                    _ => continue,
+                };
+                let config = match secrets.resolve_config(config.clone()) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                match poller.metadata(id.clone(), config).await {
+                    Ok(x) if x != domain::Metadata::default() => {
+                        debug!("[{id}]: {x:?}");
+                        let _ = metadata.insert(id.clone(), x);
+                    }
+                    Ok(_) => (),
+                    Err(why) => debug!("failed to discover the metadata: {}", Report(&why)),
+                }
+            }
+            if 0 < metadata.len() {
+                if let Err(why) = data_repo.update_metadata(metadata).await {
+                    warn!("{}", Report(&why));
+                }
+            }
+
+            // the TLS certificates of the targets watched for the integrity are compared on every successful poll.
+            for id in succeeded.iter() {
+                let config = match configs.get(id) {
+                    Some(x) if x.integrity.as_ref().is_some_and(|x| x.tls) => x,
+                    _ => continue,
+                };
+                let resolved = match secrets.resolve_config(config.clone()) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let fingerprint = match poller.certificate(id.clone(), resolved).await {
+                    Ok(Some(x)) => x,
+                    Ok(None) => continue,
+                    Err(why) => {
+                        warn!("{}", Report(&why));
                         continue;
                     }
+                };
+                match config
+                    .integrity
+                    .as_ref()
+                    .and_then(|x| x.certificate.as_ref())
+                {
+                    Some(pinned) if *pinned != fingerprint => {
+                        warn!("[{id}]: the TLS certificate differs from the pinned one.");
+                        emit(Event::Tampered {
+                            id: id.clone(),
+                            url: config.url.clone(),
+                            kind: TamperKind::Certificate,
+                            pinned: pinned.clone(),
+                            hash: fingerprint,
+                            checked_at: Timestamp::now(),
+                        });
+                    }
+                    Some(_) => (),
+                    None => {
+                        info!("[{id}]: pinned the TLS certificate {fingerprint}.");
+                        let pinned = pins.entry(id.clone()).or_insert_with(|| config.clone());
+                        if let Some(x) = pinned.integrity.as_mut() {
+                            x.certificate = Some(fingerprint);
+                        }
+                    }
+                }
+            }
+            if 0 < pins.len() {
+                let changes = pins.into_iter().map(|(id, x)| (id, Some(x))).collect();
+                if let Err(why) = config_repo.apply(changes).await {
+                    warn!("failed to pin the versions: {why}");
+                }
+            }
+
+            let failed = rem.len();
+            let mut failed_polls = Vec::new();
+            for (id, config) in rem.into_iter() {
+                let dependency = unmet_dependency(&config, &succeeded, &paused);
+                let mut kind = kinds.remove(&id);
+                let (status, reason) = match dependency {
+                    Some(x) => {
+                        let reason = match statuses.get(x) {
+                            Some(Status::Maintenance) => {
+                                format!("{x} is in the maintenance window")
+                            }
+                            _ if !configs.contains_key(x) => format!("{x} is not a target"),
+                            _ => format!("{x} did not succeed"),
+                        };
+                        warn!("[{id}]: skipped, since {reason}.");
+                        kind = None;
+                        (Status::Skipped, reason)
+                    }
+                    None => failures
+                        .remove(&id)
+                        .unwrap_or_else(|| (Status::Failing, "timed out".to_owned())),
+                };
+                let url = config.url;
+                if status != Status::Skipped {
+                    failed_polls.push(id.clone());
+                }
+                let _ = statuses.insert(id.clone(), status);
+                bus.publish(PatrolEvent::PollFailed {
+                    id: id.clone(),
+                    url: url.clone(),
+                    status,
+                    reason: reason.clone(),
+                    kind,
+                });
+                emit(Event::Failed {
+                    id,
+                    url,
+                    status,
+                    reason,
+                    kind,
+                });
+            }
 
-                    debug!("[{id}]:\n{}", content);
+            let overrun = overruns.len();
+            for (id, (url, reason)) in overruns.into_iter() {
+                warn!("[{id}]: skipped, {reason}.");
+                let status = Status::Skipped;
+                let _ = statuses.insert(id.clone(), status);
+                bus.publish(PatrolEvent::PollFailed {
+                    id: id.clone(),
+                    url: url.clone(),
+                    status,
+                    reason: reason.clone(),
+                    kind: None,
+                });
+                if overrun_events {
+                    emit(Event::Failed {
+                        id,
+                        url,
+                        status,
+                        reason,
+                        kind: None,
+                    });
+                }
+            }
 
-                    let hash = domain::Hash::new(content.as_bytes());
+            if 0 < composites.len() {
+                let data_map = match data_repo.get_all().await {
+                    Ok(x) => x,
+                    Err(why) => {
+                        warn!("{}", Report(&why));
+                        HashMap::new()
+                    }
+                };
+                let checked_at = Timestamp::now();
+                let states: HashMap<_, _> = composites
+                    .iter()
+                    .map(|(id, condition)| {
+                        let met = rules::evaluate(condition, &contents, &data_map, checked_at);
+                        (id.clone(), met)
+                    })
+                    .collect();
+                let hashes = states
+                    .iter()
+                    .map(|(id, met)| {
+                        let state = if *met { "met" } else { "unmet" };
+                        (id.clone(), domain::Hash::new(state.as_bytes()))
+                    })
+                    .collect();
 
-                    if let Err(why) = data_repo.update(id.clone(), hash).await {
-                        warn!("[{id}]: {why}")
+                match data_repo.update_multiple(hashes).await {
+                    Ok(results) => {
+                        for (id, last_updated) in results.into_iter() {
+                            let had_hash = previous.get(&id).and_then(|x| x.hash.as_ref());
+                            let status = match (last_updated, had_hash) {
+                                (Some(_), Some(_)) => Status::Changed,
+                                _ => Status::Ok,
+                            };
+                            if status == Status::Changed && states.get(&id) == Some(&true) {
+                                info!("[{id}]: triggered.");
+                                emit(Event::Triggered {
+                                    id: id.clone(),
+                                    checked_at,
+                                });
+                            }
+                            let _ = statuses.insert(id, status);
+                        }
                     }
+                    Err(why) => warn!("{}", Report(&why)),
+                }
+            }
+
+            // the statistics of the targets polled in the cycle, with the durations of the successful polls.
+            let recorded_at = Timestamp::now();
+            let mut stats = HashMap::new();
+            for (id, output) in contents.iter() {
+                let mut x = previous
+                    .get(id)
+                    .map(|x| x.stats.clone())
+                    .unwrap_or_default();
+                let changed = statuses.get(id) == Some(&Status::Changed);
+                x.record_success(recorded_at, output.elapsed, changed);
+                x.record_steps(&output.steps);
+                let _ = stats.insert(id.clone(), x);
+            }
+            for id in failed_polls.into_iter() {
+                let mut x = previous
+                    .get(&id)
+                    .map(|x| x.stats.clone())
+                    .unwrap_or_default();
+                x.record_failure(recorded_at);
+                let _ = stats.insert(id, x);
+            }
 
-                    let _ = rem.remove(&id);
+            // the changes are classified from the confirmed contents.
+            contents.retain(|id, _| !unconfirmed.contains(id));
+            for (id, content) in contents.iter() {
+                let changed = last_contents.get(id).map(PollOutput::text) != Some(content.text());
+                bus.publish(PatrolEvent::ContentPolled {
+                    id: id.clone(),
+                    content: content.clone(),
+                    changed,
+                });
+            }
+            last_contents.extend(contents);
+
+            for id in previous
+                .keys()
+                .filter(|id| !configs.contains_key(id) && !composites.contains_key(id))
+            {
+                let _ = statuses.insert(id.clone(), Status::Expired);
+            }
+            if let Some(retention) = retention {
+                let outdated: Vec<_> = previous
+                    .iter()
+                    .filter(|(id, _)| statuses.get(*id) == Some(&Status::Expired))
+                    .filter(|(_, x)| {
+                        x.last_checked
+                            .checked_add(retention)
+                            .is_some_and(|x| x < started_at)
+                    })
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for id in outdated.into_iter() {
+                    let _ = statuses.remove(&id);
+                    match data_repo.delete(id.clone()).await {
+                        Ok(_) => info!("[{id}]: deleted, removed longer than {retention} ago."),
+                        Err(why) => warn!("{}", Report(&why)),
+                    }
                 }
+            }
 
-                retry -= 1;
+            statuses.retain(|id, status| previous.get(id).map(|x| x.status) != Some(*status));
+            if 0 < statuses.len() {
+                if let Err(why) = data_repo.update_statuses(statuses).await {
+                    warn!("{}", Report(&why));
+                }
+            }
+            // the statuses have added the data of the new targets.
+            if 0 < stats.len() {
+                match data_repo.update_stats(stats.clone()).await {
+                    Ok(()) => bus.publish(PatrolEvent::StatsRecorded { stats }),
+                    Err(why) => warn!("{}", Report(&why)),
+                }
+            }
+            if checkpoints {
+                if let Err(why) = data_repo.save_checkpoint(None).await {
+                    warn!("failed to remove the checkpoint: {}", Report(&why));
+                }
             }
 
+            bus.publish(PatrolEvent::CycleCompleted {
+                cycle,
+                succeeded: succeeded.len(),
+                changed,
+                failed,
+                overrun,
+                elapsed: cycle_started.elapsed(),
+            });
+
             let data_map = data_repo.get_all().await;
             let data_map = match data_map {
                 Ok(x) => x,
                 Err(why) => {
-                    warn!("{why}");
+                    warn!("{}", Report(&why));
                     continue;
                 }
             };
             let mut data_list: Vec<_> = data_map.into_iter().collect();
-            data_list.sort_by_key(|x| x.1.last_updated.clone());
+            data_list.sort_by_key(|x| x.1.last_updated);
+
+            for (id, data) in data_list.iter() {
+                emit(Event::Status {
+                    id: id.clone(),
+                    url: configs.get(id).map(|x| x.url.clone()),
+                    status: data.status,
+                    last_updated: data.last_updated,
+                    last_checked: data.last_checked,
+                });
+            }
+
+            if !summary {
+                continue;
+            }
 
             let now = Timestamp::now();
             let yesterday_now = now - Duration::from_days(1);
             let one_hour_ago = now - Duration::from_hours(1);
 
-            for (id, last_updated) in data_list
-                .into_iter()
-                .filter_map(|x| x.1.last_updated.map(|l| (x.0, l)))
-            {
+            for (id, data) in data_list.into_iter() {
                 let config = configs.get(&id);
                 let url = config.map(|x| x.url.as_str()).unwrap_or("-");
+                let status = data.status;
 
-                let style = match last_updated {
-                    _ if one_hour_ago < last_updated => ansi_term::Color::Fixed(15).bold(),
-                    _ if yesterday_now < last_updated => ansi_term::Color::Fixed(7).normal(),
-                    _ => ansi_term::Color::Fixed(8).normal(),
+                let (style, last_updated) = match data.last_updated {
+                    Some(x) if one_hour_ago < x => (
+                        ansi_term::Color::Fixed(15).bold(),
+                        x.display_in(time_zone).to_string(),
+                    ),
+                    Some(x) if yesterday_now < x => (
+                        ansi_term::Color::Fixed(7).normal(),
+                        x.display_in(time_zone).to_string(),
+                    ),
+                    Some(x) => (
+                        ansi_term::Color::Fixed(8).normal(),
+                        x.display_in(time_zone).to_string(),
+                    ),
+                    None => (ansi_term::Color::Fixed(8).normal(), "-".to_owned()),
+                };
+                let style = if color {
+                    style
+                } else {
+                    ansi_term::Style::new()
                 };
                 info!(
                     "[{id}]: {}",
-                    style.paint(format!("last_updated: {last_updated}, url: {url}"))
+                    style.paint(format!(
+                        "status: {status}, last_updated: {last_updated}, url: {url}"
+                    ))
                 );
             }
         }
@@ -143,34 +1231,65 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Error<ConfigRepositoryError, DataRepositoryError, PollerError>
-where
-    ConfigRepositoryError: std::error::Error,
-    DataRepositoryError: std::error::Error,
-    PollerError: std::error::Error,
-{
-    ConfigRepositoryError(ConfigRepositoryError),
-    DataRepositoryError(DataRepositoryError),
-    PollerError(PollerError),
+/// The first of the targets which the target depends on and which has not succeeded in the cycle, if any.
+///
+/// The paused ones are ignored, while those in the maintenance windows are not polled, and are unmet.
+fn unmet_dependency<'a>(
+    config: &'a domain::Config,
+    succeeded: &HashSet<Id>,
+    paused: &HashSet<Id>,
+) -> Option<&'a Id> {
+    config
+        .depends_on
+        .iter()
+        .find(|x| !succeeded.contains(*x) && !paused.contains(*x))
 }
-impl<ConfigRepositoryError, DataRepositoryError, PollerError> std::fmt::Display
-    for Error<ConfigRepositoryError, DataRepositoryError, PollerError>
-where
-    ConfigRepositoryError: std::error::Error,
-    DataRepositoryError: std::error::Error,
-    PollerError: std::error::Error,
-{
-    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        todo!()
+
+/// Resolves when `true` is sent to the receiver.
+///
+/// Never resolves if the sender is dropped before that.
+async fn wait_for_shutdown(rx: &mut watch::Receiver<bool>) {
+    while !*rx.borrow() {
+        if rx.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
     }
 }
 
-impl<ConfigRepositoryError, DataRepositoryError, PollerError> std::error::Error
-    for Error<ConfigRepositoryError, DataRepositoryError, PollerError>
-where
-    ConfigRepositoryError: std::error::Error,
-    DataRepositoryError: std::error::Error,
-    PollerError: std::error::Error,
-{
+/// Resolves when a message is sent to the receiver.
+///
+/// Never resolves if there is no receiver or the sender is dropped.
+async fn wait_for_message(rx: &mut Option<mpsc::UnboundedReceiver<()>>) {
+    if let Some(rx) = rx.as_mut() {
+        if rx.recv().await.is_some() {
+            return;
+        }
+    }
+    std::future::pending::<()>().await;
+}
+
+async fn reload_configs<ConfigRepository: domain::ConfigRepository>(
+    config_repo: &mut ConfigRepository,
+    bus: &EventBus,
+) {
+    let events = match config_repo.reload().await {
+        Ok(x) => x,
+        Err(why) => {
+            warn!("failed to reload the configurations: {why}");
+            return;
+        }
+    };
+
+    info!("configurations reloaded. {} changes.", events.len());
+    for event in events.iter() {
+        let change = match event {
+            ConfigEvent::Added { .. } => "added.",
+            ConfigEvent::Updated { .. } => "updated.",
+            ConfigEvent::Removed { .. } => "removed.",
+        };
+        info!("[{}]: {change}", event.id());
+    }
+    for event in events.into_iter() {
+        bus.publish(PatrolEvent::ConfigChanged(event));
+    }
 }
@@ -1,9 +1,7 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-};
+use std::collections::{HashMap, HashSet};
 
 use crate::domain::{self, Id};
+use crate::error::{ActorMessageError, Error};
 use tokio::sync::{mpsc, oneshot};
 
 pub struct DataRepositoryActor<DataRepository> {
@@ -33,6 +31,10 @@ where
                         let result = self.inner.get_all().await;
                         let _ = tx.send(result);
                     }
+                    Message::Query { tx, query } => {
+                        let result = self.inner.query(query).await;
+                        let _ = tx.send(result);
+                    }
                     Message::Update { tx, id, hash } => {
                         let result = self.inner.update(id, hash).await;
                         let _ = tx.send(result);
@@ -41,10 +43,38 @@ where
                         let result = self.inner.update_multiple(map).await;
                         let _ = tx.send(result);
                     }
+                    Message::UpdateStatuses { tx, map } => {
+                        let result = self.inner.update_statuses(map).await;
+                        let _ = tx.send(result);
+                    }
+                    Message::UpdateKnownHashes { tx, map } => {
+                        let result = self.inner.update_known_hashes(map).await;
+                        let _ = tx.send(result);
+                    }
+                    Message::UpdateMetadata { tx, map } => {
+                        let result = self.inner.update_metadata(map).await;
+                        let _ = tx.send(result);
+                    }
+                    Message::UpdateLossy { tx, map } => {
+                        let result = self.inner.update_lossy(map).await;
+                        let _ = tx.send(result);
+                    }
+                    Message::UpdateStats { tx, map } => {
+                        let result = self.inner.update_stats(map).await;
+                        let _ = tx.send(result);
+                    }
                     Message::Delete { tx, id } => {
                         let result = self.inner.delete(id).await;
                         let _ = tx.send(result);
                     }
+                    Message::GetCheckpoint { tx } => {
+                        let result = self.inner.get_checkpoint().await;
+                        let _ = tx.send(result);
+                    }
+                    Message::SaveCheckpoint { tx, checkpoint } => {
+                        let result = self.inner.save_checkpoint(checkpoint).await;
+                        let _ = tx.send(result);
+                    }
                 }
             }
         });
@@ -65,51 +95,82 @@ enum Message<E> {
     GetAll {
         tx: oneshot::Sender<Result<HashMap<Id, domain::Data>, E>>,
     },
+    Query {
+        tx: oneshot::Sender<Result<Vec<(Id, domain::Data)>, E>>,
+        query: domain::DataQuery,
+    },
     Update {
-        tx: oneshot::Sender<Result<(), E>>,
+        tx: oneshot::Sender<Result<Option<domain::Timestamp>, E>>,
         id: Id,
         hash: domain::Hash,
     },
     UpdateMultiple {
-        tx: oneshot::Sender<Result<(), E>>,
+        tx: oneshot::Sender<Result<HashMap<Id, Option<domain::Timestamp>>, E>>,
         map: HashMap<Id, domain::Hash>,
     },
+    UpdateStatuses {
+        tx: oneshot::Sender<Result<(), E>>,
+        map: HashMap<Id, domain::Status>,
+    },
+    UpdateKnownHashes {
+        tx: oneshot::Sender<Result<(), E>>,
+        map: HashMap<Id, Vec<domain::Hash>>,
+    },
+    UpdateMetadata {
+        tx: oneshot::Sender<Result<(), E>>,
+        map: HashMap<Id, domain::Metadata>,
+    },
+    UpdateLossy {
+        tx: oneshot::Sender<Result<(), E>>,
+        map: HashMap<Id, bool>,
+    },
+    UpdateStats {
+        tx: oneshot::Sender<Result<(), E>>,
+        map: HashMap<Id, domain::Stats>,
+    },
     Delete {
         tx: oneshot::Sender<Result<Option<domain::Data>, E>>,
         id: Id,
     },
+    GetCheckpoint {
+        tx: oneshot::Sender<Result<Option<domain::Checkpoint>, E>>,
+    },
+    SaveCheckpoint {
+        tx: oneshot::Sender<Result<(), E>>,
+        checkpoint: Option<domain::Checkpoint>,
+    },
 }
 
 pub struct DataRepositoryActorClient<DataRepository: domain::DataRepository> {
     tx_message: mpsc::UnboundedSender<Message<DataRepository::Error>>,
 }
-impl<DataRepository: domain::DataRepository> DataRepositoryActorClient<DataRepository> {
-    pub fn clone(&self) -> Self {
+impl<DataRepository: domain::DataRepository> Clone for DataRepositoryActorClient<DataRepository> {
+    fn clone(&self) -> Self {
         let tx_message = self.tx_message.clone();
         Self { tx_message }
     }
 }
 
 #[async_trait::async_trait]
-impl<DataRepository: domain::DataRepository> domain::DataRepository
+impl<DataRepository: domain::DataRepository> domain::DataReader
     for DataRepositoryActorClient<DataRepository>
 {
-    type Error = Error<DataRepository::Error>;
+    type Error = Error;
 
-    async fn get(&mut self, id: Id) -> Result<Option<domain::Data>, Self::Error> {
+    async fn get(&self, id: Id) -> Result<Option<domain::Data>, Self::Error> {
         let (tx, rx) = oneshot::channel();
         if let Err(_e) = self.tx_message.send(Message::Get { tx, id }) {
             return Err(Error::ActorMessageError(ActorMessageError::SendError));
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
 
     async fn get_multiple(
-        &mut self,
+        &self,
         ids: HashSet<Id>,
     ) -> Result<HashMap<Id, domain::Data>, Self::Error> {
         let (tx, rx) = oneshot::channel();
@@ -118,43 +179,151 @@ impl<DataRepository: domain::DataRepository> domain::DataRepository
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
 
-    async fn get_all(&mut self) -> Result<HashMap<Id, domain::Data>, Self::Error> {
+    async fn get_all(&self) -> Result<HashMap<Id, domain::Data>, Self::Error> {
         let (tx, rx) = oneshot::channel();
         if let Err(_e) = self.tx_message.send(Message::GetAll { tx }) {
             return Err(Error::ActorMessageError(ActorMessageError::SendError));
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn query(
+        &self,
+        query: domain::DataQuery,
+    ) -> Result<Vec<(Id, domain::Data)>, Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::Query { tx, query }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn get_checkpoint(&self) -> Result<Option<domain::Checkpoint>, Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::GetCheckpoint { tx }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
+}
 
-    async fn update(&mut self, id: Id, hash: domain::Hash) -> Result<(), Self::Error> {
+#[async_trait::async_trait]
+impl<DataRepository: domain::DataRepository> domain::DataWriter
+    for DataRepositoryActorClient<DataRepository>
+{
+    async fn update(
+        &mut self,
+        id: Id,
+        hash: domain::Hash,
+    ) -> Result<Option<domain::Timestamp>, Self::Error> {
         let (tx, rx) = oneshot::channel();
         if let Err(_e) = self.tx_message.send(Message::Update { tx, id, hash }) {
             return Err(Error::ActorMessageError(ActorMessageError::SendError));
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
 
-    async fn update_multiple(&mut self, map: HashMap<Id, domain::Hash>) -> Result<(), Self::Error> {
+    async fn update_multiple(
+        &mut self,
+        map: HashMap<Id, domain::Hash>,
+    ) -> Result<HashMap<Id, Option<domain::Timestamp>>, Self::Error> {
         let (tx, rx) = oneshot::channel();
         if let Err(_e) = self.tx_message.send(Message::UpdateMultiple { tx, map }) {
             return Err(Error::ActorMessageError(ActorMessageError::SendError));
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn update_statuses(
+        &mut self,
+        map: HashMap<Id, domain::Status>,
+    ) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::UpdateStatuses { tx, map }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn update_known_hashes(
+        &mut self,
+        map: HashMap<Id, Vec<domain::Hash>>,
+    ) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::UpdateKnownHashes { tx, map }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn update_metadata(
+        &mut self,
+        map: HashMap<Id, domain::Metadata>,
+    ) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::UpdateMetadata { tx, map }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn update_lossy(&mut self, map: HashMap<Id, bool>) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::UpdateLossy { tx, map }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
+        }
+    }
+
+    async fn update_stats(&mut self, map: HashMap<Id, domain::Stats>) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self.tx_message.send(Message::UpdateStats { tx, map }) {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
@@ -166,42 +335,26 @@ impl<DataRepository: domain::DataRepository> domain::DataRepository
         }
 
         match rx.await {
-            Ok(result) => result.map_err(Error::DataRepositoryError),
+            Ok(result) => result.map_err(Error::data_repository),
             Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Error<E: std::error::Error> {
-    ActorMessageError(ActorMessageError),
-    DataRepositoryError(E),
-}
-impl<E: std::error::Error> Display for Error<E> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::ActorMessageError(e) => f.write_fmt(format_args!("Actor message error: {e}")),
-            Error::DataRepositoryError(e) => f.write_fmt(format_args!("DataRepository error: {e}")),
+    async fn save_checkpoint(
+        &mut self,
+        checkpoint: Option<domain::Checkpoint>,
+    ) -> Result<(), Self::Error> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(_e) = self
+            .tx_message
+            .send(Message::SaveCheckpoint { tx, checkpoint })
+        {
+            return Err(Error::ActorMessageError(ActorMessageError::SendError));
         }
-    }
-}
-impl<E: std::error::Error> std::error::Error for Error<E> {}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ActorMessageError {
-    SendError,
-    RecvError,
-}
-impl Display for ActorMessageError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ActorMessageError::SendError => {
-                f.write_fmt(format_args!("failed to send the message to the actor."))
-            }
-            ActorMessageError::RecvError => f.write_fmt(format_args!(
-                "failed to receive the message from the actor."
-            )),
+        match rx.await {
+            Ok(result) => result.map_err(Error::data_repository),
+            Err(_e) => Err(Error::ActorMessageError(ActorMessageError::RecvError)),
         }
     }
 }
-impl std::error::Error for ActorMessageError {}
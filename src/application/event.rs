@@ -0,0 +1,422 @@
+use serde_derive::Serialize;
+
+use crate::domain::{
+    ChangeKind, Hash, Id, Notification, PollErrorKind, Status, TamperKind, Timestamp, Url,
+};
+
+/// The latest version of the JSON of the events.
+pub const EVENT_SCHEMA: u32 = 2;
+
+/// The color of an event in the hex digits and in the names of the Adaptive Cards.
+struct Color {
+    hex: &'static str,
+    adaptive_card: &'static str,
+}
+
+/// The header of the CSV of the events, in the order of `Event::to_csv_record`.
+pub const CSV_HEADER: &str = "occurred_at,event,target,url,summary,body";
+
+/// The fields of the events which are times.
+const TIME_FIELDS: &[&str] = &[
+    "checked_at",
+    "last_updated",
+    "last_checked",
+    "last_updated_previous",
+];
+
+/// An event that occurred while patrolling.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The target has been checked.
+    Checked {
+        id: Id,
+        url: Url,
+        updated: bool,
+        hash: Hash,
+        /// The kind of the change from the content of the previous cycle, if updated.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        change: Option<ChangeKind>,
+        /// The hash before the change, if updated and any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        old_hash: Option<Hash>,
+        /// When the target was updated before the change, if updated and any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_updated_previous: Option<Timestamp>,
+        /// The lines removed and added by the change from the content of the previous cycle, if updated.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        excerpt: Option<String>,
+        checked_at: Timestamp,
+    },
+    /// Failed to check the target.
+    Failed {
+        id: Id,
+        url: Url,
+        status: Status,
+        reason: String,
+        /// The kind of the failure, if the poller failed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        kind: Option<PollErrorKind>,
+    },
+    /// The target has started flapping between two variants of the content, whose changes are muted from now on.
+    Flapping {
+        id: Id,
+        url: Url,
+        checked_at: Timestamp,
+    },
+    /// The condition of the composite target has become met.
+    Triggered { id: Id, checked_at: Timestamp },
+    /// The content or the TLS certificate of the target watched for the integrity differs from the pinned one.
+    Tampered {
+        id: Id,
+        url: Url,
+        kind: TamperKind,
+        /// The hash of the pinned content, or the fingerprint of the pinned certificate.
+        pinned: Hash,
+        /// The hash or the fingerprint of the one served.
+        hash: Hash,
+        checked_at: Timestamp,
+    },
+    /// The current status of the target, reported at the end of each cycle.
+    Status {
+        id: Id,
+        url: Option<Url>,
+        status: Status,
+        last_updated: Option<Timestamp>,
+        last_checked: Timestamp,
+    },
+}
+impl Event {
+    /// The name of the event in the JSON, e.g. `checked`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Event::Checked { .. } => "checked",
+            Event::Failed { .. } => "failed",
+            Event::Flapping { .. } => "flapping",
+            Event::Triggered { .. } => "triggered",
+            Event::Tampered { .. } => "tampered",
+            Event::Status { .. } => "status",
+        }
+    }
+
+    /// The id of the target of the event.
+    pub fn id(&self) -> &Id {
+        match self {
+            Event::Checked { id, .. }
+            | Event::Failed { id, .. }
+            | Event::Flapping { id, .. }
+            | Event::Triggered { id, .. }
+            | Event::Tampered { id, .. }
+            | Event::Status { id, .. } => id,
+        }
+    }
+
+    /// When the event occurred, if it has the time.
+    pub fn occurred_at(&self) -> Option<Timestamp> {
+        match self {
+            Event::Checked { checked_at, .. }
+            | Event::Flapping { checked_at, .. }
+            | Event::Triggered { checked_at, .. }
+            | Event::Tampered { checked_at, .. } => Some(*checked_at),
+            Event::Status { last_checked, .. } => Some(*last_checked),
+            Event::Failed { .. } => None,
+        }
+    }
+
+    /// The event in a sentence, e.g. `news has changed: modified`.
+    pub fn summary(&self) -> String {
+        match self {
+            Event::Checked {
+                id,
+                updated: true,
+                change: Some(change),
+                ..
+            } => format!("{id} has changed: {change}"),
+            Event::Checked {
+                id, updated: true, ..
+            } => format!("{id} has changed"),
+            Event::Checked { id, .. } => format!("{id} has been checked"),
+            Event::Failed { id, reason, .. } => format!("{id} has failed: {reason}"),
+            Event::Flapping { id, .. } => format!("{id} has started flapping"),
+            Event::Triggered { id, .. } => format!("{id} has been triggered"),
+            Event::Tampered {
+                id,
+                kind: TamperKind::Content,
+                ..
+            } => {
+                format!("{id} has been tampered with: the content differs from the pinned version")
+            }
+            Event::Tampered {
+                id,
+                kind: TamperKind::Certificate,
+                ..
+            } => format!(
+                "{id} has been tampered with: the TLS certificate differs from the pinned one"
+            ),
+            Event::Status { id, status, .. } => format!("{id} is {status}"),
+        }
+    }
+
+    /// Converts the event into a flat JSON object for the automation services like Zapier, which map its fields by name.
+    ///
+    /// `id` is unique to the event, `target` is the id of the target, and `occurred_at` is the time given if the event has none.
+    /// The nested values are flattened into strings.
+    pub fn to_flat_json(&self, at: Timestamp) -> serde_json::Value {
+        let occurred_at = self.occurred_at().unwrap_or(at);
+        let mut object = serde_json::Map::new();
+        let unique = format!(
+            "{}:{}:{}",
+            self.id(),
+            self.name(),
+            occurred_at.unix_millis()
+        );
+        let _ = object.insert("id".to_owned(), unique.into());
+        let _ = object.insert("target".to_owned(), self.id().as_str().into());
+        let _ = object.insert("summary".to_owned(), self.summary().into());
+        let _ = object.insert(
+            "occurred_at".to_owned(),
+            serde_json::to_value(occurred_at).unwrap_or_default(),
+        );
+        let _ = object.insert(
+            "occurred_at_unix_millis".to_owned(),
+            occurred_at.unix_millis().into(),
+        );
+
+        if let serde_json::Value::Object(fields) = serde_json::to_value(self).unwrap_or_default() {
+            for (key, value) in fields.into_iter() {
+                let value = match value {
+                    serde_json::Value::Null => continue,
+                    x @ (serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+                        x.to_string().into()
+                    }
+                    x => x,
+                };
+                // the id of the target is `target`, not to be confused with the one of the event.
+                if key == "id" {
+                    continue;
+                }
+                let _ = object.entry(key).or_insert(value);
+            }
+        }
+        serde_json::Value::Object(object)
+    }
+
+    /// Converts the event into a line of the CSV of `CSV_HEADER`, ending with a newline.
+    ///
+    /// `occurred_at` is the time given if the event has none, and the body is the one of the notification.
+    pub fn to_csv_record(&self, at: Timestamp) -> String {
+        let notification = self.to_notification();
+        // in RFC 3339 as in the JSON, to be parsed by the spreadsheets.
+        let occurred_at = match serde_json::to_value(self.occurred_at().unwrap_or(at)) {
+            Ok(serde_json::Value::String(x)) => x,
+            _ => String::new(),
+        };
+        let fields = [
+            occurred_at.as_str(),
+            self.name(),
+            self.id().as_str(),
+            notification
+                .url
+                .as_ref()
+                .map(Url::as_str)
+                .unwrap_or_default(),
+            notification.summary.as_str(),
+            notification.body.as_str(),
+        ];
+        let mut record = fields.map(escape_csv).join(",");
+        record.push('\n');
+        record
+    }
+
+    /// Converts the event into the notification to a person, whose body is the lines of the change or the reason of the failure.
+    pub fn to_notification(&self) -> Notification {
+        let fingerprints;
+        let (url, body) = match self {
+            Event::Checked { url, excerpt, .. } => (Some(url), excerpt.as_deref()),
+            Event::Failed { url, reason, .. } => (Some(url), Some(reason.as_str())),
+            Event::Flapping { url, .. } => (Some(url), None),
+            Event::Status { url, .. } => (url.as_ref(), None),
+            Event::Triggered { .. } => (None, None),
+            Event::Tampered {
+                url, pinned, hash, ..
+            } => {
+                fingerprints = format!("pinned: {pinned}\nserved: {hash}");
+                (Some(url), Some(fingerprints.as_str()))
+            }
+        };
+        Notification {
+            summary: self.summary(),
+            body: body.unwrap_or_default().to_owned(),
+            url: url.cloned(),
+        }
+    }
+
+    /// Converts the event into the JSON of the Webhooks of IFTTT, which passes just `value1`, `value2` and `value3` to the applet.
+    ///
+    /// They are the summary, the URL of the target, and the body of the notification.
+    pub fn to_ifttt_json(&self) -> serde_json::Value {
+        let notification = self.to_notification();
+        serde_json::json!({
+            "value1": notification.summary,
+            "value2": notification.url.as_ref().map(Url::as_str).unwrap_or_default(),
+            "value3": notification.body,
+        })
+    }
+
+    /// Converts the event into the Adaptive Card posted to the Workflows of Microsoft Teams,
+    /// with a button opening the URL of the target if any.
+    pub fn to_adaptive_card_json(&self) -> serde_json::Value {
+        let notification = self.to_notification();
+        let mut body = vec![serde_json::json!({
+            "type": "TextBlock",
+            "text": notification.summary,
+            "weight": "Bolder",
+            "size": "Medium",
+            "color": self.color().adaptive_card,
+            "wrap": true,
+        })];
+        if 0 < notification.body.len() {
+            body.push(serde_json::json!({
+                "type": "TextBlock",
+                "text": notification.body,
+                "fontType": "Monospace",
+                "wrap": true,
+            }));
+        }
+        let actions: Vec<_> = notification
+            .url
+            .iter()
+            .map(|url| serde_json::json!({ "type": "Action.OpenUrl", "title": "Open", "url": url.as_str() }))
+            .collect();
+
+        serde_json::json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": body,
+                    "actions": actions,
+                },
+            }],
+        })
+    }
+
+    /// Converts the event into the MessageCard posted to the incoming webhooks of the connectors of Microsoft Teams.
+    pub fn to_message_card_json(&self) -> serde_json::Value {
+        let notification = self.to_notification();
+        let actions: Vec<_> = notification
+            .url
+            .iter()
+            .map(|url| {
+                serde_json::json!({
+                    "@type": "OpenUri",
+                    "name": "Open",
+                    "targets": [{ "os": "default", "uri": url.as_str() }],
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": notification.summary,
+            "title": notification.summary,
+            "themeColor": self.color().hex,
+            // the lines of the change are kept as they are, not to be read as Markdown.
+            "text": match notification.body.len() {
+                0 => String::new(),
+                _ => format!("<pre>{}</pre>", escape_html(&notification.body)),
+            },
+            "potentialAction": actions,
+        })
+    }
+
+    /// Converts the event into the message posted to the incoming webhooks of Google Chat.
+    ///
+    /// The summary is in bold, and the body is in a block of code.
+    pub fn to_google_chat_json(&self) -> serde_json::Value {
+        let notification = self.to_notification();
+        let mut text = format!("*{}*", notification.summary);
+        if let Some(url) = notification.url.as_ref() {
+            text.push_str(&format!("\n<{}|{}>", url.as_str(), url.as_str()));
+        }
+        if 0 < notification.body.len() {
+            text.push_str(&format!("\n```\n{}\n```", notification.body));
+        }
+        serde_json::json!({ "text": text })
+    }
+
+    /// The color of the event on the cards, e.g. red for a failure.
+    fn color(&self) -> Color {
+        match self {
+            Event::Failed { .. } | Event::Tampered { .. } => Color {
+                hex: "D13438",
+                adaptive_card: "Attention",
+            },
+            Event::Flapping { .. } => Color {
+                hex: "FFB900",
+                adaptive_card: "Warning",
+            },
+            Event::Triggered { .. } => Color {
+                hex: "107C10",
+                adaptive_card: "Good",
+            },
+            Event::Checked { .. } | Event::Status { .. } => Color {
+                hex: "0078D7",
+                adaptive_card: "Accent",
+            },
+        }
+    }
+
+    /// Converts the event into the JSON of the schema version.
+    ///
+    /// Version 2 has `schema`, and the unix milliseconds of each time next to it, e.g. `checked_at_unix_millis`.
+    /// Version 1 has neither, and its times are without the offset.
+    pub fn to_json(&self, schema: u32) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        let object = match value.as_object_mut() {
+            Some(x) => x,
+            None => return value,
+        };
+
+        for field in TIME_FIELDS.iter() {
+            let time = object
+                .get(*field)
+                .and_then(|x| serde::Deserialize::deserialize(x).ok());
+            let time: Timestamp = match time {
+                Some(x) => x,
+                None => continue,
+            };
+            match schema {
+                1 => {
+                    let _ = object.insert(field.to_string(), time.to_naive_string().into());
+                }
+                _ => {
+                    let key = format!("{field}_unix_millis");
+                    let _ = object.insert(key, time.unix_millis().into());
+                }
+            }
+        }
+        if 1 < schema {
+            let _ = object.insert("schema".to_owned(), schema.into());
+        }
+        value
+    }
+}
+
+/// Quotes the field of a CSV if it has a comma, a quote or a line break, doubling the quotes.
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+/// Escapes the text in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
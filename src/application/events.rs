@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+
+use crate::application::event::Event;
+use crate::domain::{
+    ChangeKind, ConfigEvent, Hash, Id, PollErrorKind, PollOutput, Stats, Status, Url,
+};
+
+/// A typed event published on the `EventBus` while patrolling.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum PatrolEvent {
+    /// The targets are about to be polled, including the retries.
+    PollStarted { ids: Vec<Id> },
+    /// Failed to poll the target in the cycle.
+    PollFailed {
+        id: Id,
+        url: Url,
+        status: Status,
+        reason: String,
+        kind: Option<PollErrorKind>,
+    },
+    /// The content of the target has changed.
+    ChangeDetected {
+        id: Id,
+        url: Url,
+        hash: Hash,
+        old_hash: Option<Hash>,
+        change: Option<ChangeKind>,
+    },
+    /// The cycle has been completed.
+    CycleCompleted {
+        /// The number of the cycle, counted from 1.
+        cycle: u64,
+        succeeded: usize,
+        changed: usize,
+        failed: usize,
+        /// The number of the targets skipped as they were not polled within the cycle or their budgets.
+        overrun: usize,
+        elapsed: std::time::Duration,
+    },
+    /// The configurations have been reloaded.
+    ConfigChanged(ConfigEvent),
+    /// The content of the target polled in the cycle, with whether it differs from the one published last.
+    ///
+    /// The clones for the subscribers share the buffer of the content.
+    ContentPolled {
+        id: Id,
+        content: PollOutput,
+        changed: bool,
+    },
+    /// The statistics of the targets polled in the cycle have been recorded.
+    StatsRecorded { stats: HashMap<Id, Stats> },
+    /// An event printed for the others, e.g. as JSON lines.
+    Recorded(Event),
+}
+
+/// Publishes the events of the app to every subscriber, e.g. the printer, the notifiers and the reporters.
+///
+/// Each subscriber receives every event in order, without being lagged behind like a broadcast.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<PatrolEvent>>>>,
+}
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes to the events published from now on.
+    ///
+    /// The receiver is closed when the bus and all of its clones are dropped.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<PatrolEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Publishes the event, forgetting the subscribers which have dropped their receivers.
+    pub fn publish(&self, event: PatrolEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
@@ -1,7 +1,14 @@
 pub mod app;
 pub mod data_repository_actor;
+pub mod event;
+pub mod events;
+pub mod poller_registry;
+pub mod rules;
 pub mod selective_poller;
 
-pub use app::App;
+pub use app::{App, RunPolicy};
 pub use data_repository_actor::DataRepositoryActor;
+pub use event::{Event, CSV_HEADER, EVENT_SCHEMA};
+pub use events::{EventBus, PatrolEvent};
+pub use poller_registry::{PollerRegistry, UnregisteredPoller};
 pub use selective_poller::SelectivePoller;
@@ -0,0 +1,69 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::domain::{Config, DynPoller};
+
+/// The pollers keyed by the names, which are the modes, the schemes of the URLs, or any names of custom pollers.
+///
+/// A target is polled by the poller named by its `poller`, or the one of the scheme of its URL if registered,
+/// or the one of its mode otherwise.
+/// `ftp`, `ftps` and `sftp` URLs are polled by the `ftp` poller, and `imap` and `imaps` ones by the `imap` poller.
+#[derive(Default)]
+pub struct PollerRegistry {
+    pollers: HashMap<String, Box<dyn DynPoller>>,
+}
+impl PollerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the poller by the name, replacing the one registered by the same name.
+    pub fn register<P: DynPoller + 'static>(mut self, name: impl Into<String>, poller: P) -> Self {
+        let _ = self.pollers.insert(name.into(), Box::new(poller));
+        self
+    }
+
+    /// The names of the registered pollers.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.pollers.keys().map(String::as_str)
+    }
+
+    /// The name of the poller which polls the target of the config, whether it is registered or not.
+    pub fn name_of(&self, config: &Config) -> String {
+        if let Some(name) = config.poller.as_ref() {
+            return name.clone();
+        }
+
+        let url = &config.url;
+        let scheme = match url.scheme() {
+            _ if url.is_ftp() => "ftp",
+            _ if url.is_imap() => "imap",
+            _ if url.is_file() => "file",
+            x => x,
+        };
+        match self.pollers.contains_key(scheme) {
+            true => scheme.to_owned(),
+            false => config.mode.to_string(),
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Box<dyn DynPoller>, UnregisteredPoller> {
+        self.pollers
+            .get_mut(name)
+            .ok_or_else(|| UnregisteredPoller(name.to_owned()))
+    }
+}
+impl std::fmt::Debug for PollerRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.pollers.keys()).finish()
+    }
+}
+
+/// No poller is registered by the name.
+#[derive(Debug, Clone)]
+pub struct UnregisteredPoller(pub String);
+impl Display for UnregisteredPoller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("no poller is registered as {}.", self.0))
+    }
+}
+impl std::error::Error for UnregisteredPoller {}
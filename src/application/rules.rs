@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+use crate::domain::{Condition, Data, Id, PollOutput, Timestamp};
+
+/// Determines whether the condition of a composite target is met.
+///
+/// `contents` are the contents polled in the current cycle, and `data` is the stored data after it.
+pub fn evaluate(
+    condition: &Condition,
+    contents: &HashMap<Id, PollOutput>,
+    data: &HashMap<Id, Data>,
+    now: Timestamp,
+) -> bool {
+    match condition {
+        Condition::All(x) => x.iter().all(|x| evaluate(x, contents, data, now)),
+        Condition::Any(x) => x.iter().any(|x| evaluate(x, contents, data, now)),
+        Condition::Not(x) => !evaluate(x, contents, data, now),
+        Condition::Contains { target, text } => contents
+            .get(target)
+            .is_some_and(|x| x.text().contains(text.as_str())),
+        Condition::ChangedWithin { target, within } => {
+            // within longer than the range of the time, since anytime.
+            let since = now.checked_sub(*within);
+            data.get(target)
+                .and_then(|x| x.last_updated)
+                .is_some_and(|x| since.map_or(true, |since| since < x))
+        }
+    }
+}
@@ -1,126 +1,93 @@
-use std::{collections::HashMap, fmt::Display};
+use std::collections::HashMap;
 
-use futures_util::{Stream, StreamExt};
+use futures_util::StreamExt;
 
-use crate::domain::{Config, Id, Mode, Poller};
+use crate::application::PollerRegistry;
+use crate::domain::{Config, Id, Mode, PollOutput, PollStream, Poller};
 
 use crate::domain;
+use crate::Error;
 
+/// A poller dispatching each target to the registered poller of its mode, the scheme of its URL, or its `poller`.
+///
+/// See `PollerRegistry` for which poller polls a target.
 #[derive(Debug)]
-pub struct SelectivePoller<FullModePoller, SimpleModePoller> {
-    full_mode_poller: FullModePoller,
-    simple_mode_poller: SimpleModePoller,
+pub struct SelectivePoller {
+    registry: PollerRegistry,
 }
 
-impl<FullModePoller, SimpleModePoller> SelectivePoller<FullModePoller, SimpleModePoller>
-where
-    FullModePoller: domain::Poller + Send + Sync,
-    SimpleModePoller: domain::Poller + Send + Sync,
-
-    FullModePoller::Stream: Send,
-    SimpleModePoller::Stream: Send,
-{
-    pub fn new(full_mode_poller: FullModePoller, simple_mode_poller: SimpleModePoller) -> Self {
-        Self {
-            full_mode_poller,
-            simple_mode_poller,
-        }
+impl SelectivePoller {
+    pub fn new(registry: PollerRegistry) -> Self {
+        Self { registry }
     }
 }
 
 #[async_trait::async_trait]
-impl<FullModePoller, SimpleModePoller> Poller for SelectivePoller<FullModePoller, SimpleModePoller>
-where
-    FullModePoller: domain::Poller + Send + Sync,
-    SimpleModePoller: domain::Poller + Send + Sync,
-
-    FullModePoller::Stream: Send,
-    SimpleModePoller::Stream: Send,
-{
-    type Error = Error<FullModePoller::Error, SimpleModePoller::Error>;
-    type Stream = impl Stream<Item = (Id, Result<String, Self::Error>)>;
-
-    async fn poll(&mut self, id: Id, config: Config) -> Result<String, Self::Error> {
-        match config.mode {
-            Mode::Full => {
-                let result = self.full_mode_poller.poll(id, config).await;
-                result.map_err(Error::FullModePollerError)
-            }
-            Mode::Simple => {
-                let result = self.simple_mode_poller.poll(id, config).await;
-                result.map_err(Error::SimpleModePollerError)
-            }
-        }
+impl Poller for SelectivePoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        let name = self.registry.name_of(&config);
+        let poller = self.registry.get_mut(&name);
+        let poller = poller.map_err(|e| Error::poller(id.clone(), e))?;
+        let result = poller.poll_dyn(id.clone(), config).await;
+        result.map_err(|e| Error::poller(id, e))
     }
 
     async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
-        let mut full_mode_configs = HashMap::new();
-        let mut simple_mode_configs = HashMap::new();
-
+        let mut grouped = HashMap::<String, HashMap<Id, Config>>::new();
         for (id, config) in configs.into_iter() {
-            match config.mode {
-                Mode::Full => {
-                    let _ = full_mode_configs.insert(id, config);
-                }
-                Mode::Simple => {
-                    let _ = simple_mode_configs.insert(id, config);
-                }
-            }
+            let name = self.registry.name_of(&config);
+            let _ = grouped.entry(name).or_default().insert(id, config);
         }
 
-        let full_mode_stream = self.full_mode_poller.poll_multiple(full_mode_configs).await;
-        let simple_mode_stream = self
-            .simple_mode_poller
-            .poll_multiple(simple_mode_configs)
-            .await;
-
-        async_stream::stream! {
-            tokio::pin!(full_mode_stream);
-            tokio::pin!(simple_mode_stream);
+        let mut streams = Vec::with_capacity(grouped.len());
+        let mut unregistered = Vec::new();
+        for (name, configs) in grouped.into_iter() {
+            match self.registry.get_mut(&name) {
+                Ok(poller) => streams.push(poller.poll_multiple_dyn(configs).await),
+                Err(e) => unregistered.extend(
+                    configs
+                        .into_keys()
+                        .map(|id| (id.clone(), Err(Error::poller(id, e.clone())))),
+                ),
+            }
+        }
 
-            loop {
-                let result = tokio::select! {
-                    Some((id, x)) = full_mode_stream.next() => (id, x.map_err(Error::FullModePollerError)),
-                    Some((id, x)) = simple_mode_stream.next() => (id, x.map_err(Error::SimpleModePollerError)),
-                    else => break,
-                };
+        let polled = futures_util::stream::select_all(streams)
+            .map(|(id, x)| (id.clone(), x.map_err(|e| Error::poller(id, e))));
+        Box::pin(futures_util::stream::iter(unregistered).chain(polled))
+    }
 
-                yield result;
+    async fn metadata(&mut self, id: Id, config: Config) -> Result<domain::Metadata, Self::Error> {
+        // the metadata is read from the HTML served to any client, whatever the mode is.
+        let is_page = config.url.is_http() && config.poller.is_none();
+        match config.mode {
+            Mode::Full | Mode::Simple if is_page => {
+                let poller = self.registry.get_mut("simple");
+                let poller = poller.map_err(|e| Error::poller(id.clone(), e))?;
+                let result = poller.metadata_dyn(id.clone(), config).await;
+                result.map_err(|e| Error::poller(id, e))
             }
+            _ => Ok(domain::Metadata::default()),
         }
     }
-}
 
-#[derive(Debug)]
-pub enum Error<FullModePollerError, SimpleModePollerError>
-where
-    FullModePollerError: std::error::Error,
-    SimpleModePollerError: std::error::Error,
-{
-    FullModePollerError(FullModePollerError),
-    SimpleModePollerError(SimpleModePollerError),
-}
-impl<FullModePollerError, SimpleModePollerError> Display
-    for Error<FullModePollerError, SimpleModePollerError>
-where
-    FullModePollerError: std::error::Error,
-    SimpleModePollerError: std::error::Error,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::FullModePollerError(e) => {
-                f.write_fmt(format_args!("failed to poll the content: {e}"))
-            }
-            Error::SimpleModePollerError(e) => {
-                f.write_fmt(format_args!("failed to poll the content: {e}"))
+    async fn certificate(
+        &mut self,
+        id: Id,
+        config: Config,
+    ) -> Result<Option<domain::Hash>, Self::Error> {
+        // the certificate is of the host, whatever the mode is.
+        match config.url.scheme() == "https" && config.poller.is_none() {
+            true => {
+                let poller = self.registry.get_mut("simple");
+                let poller = poller.map_err(|e| Error::poller(id.clone(), e))?;
+                let result = poller.certificate_dyn(id.clone(), config).await;
+                result.map_err(|e| Error::poller(id, e))
             }
+            false => Ok(None),
         }
     }
 }
-impl<FullModePollerError, SimpleModePollerError> std::error::Error
-    for Error<FullModePollerError, SimpleModePollerError>
-where
-    FullModePollerError: std::error::Error,
-    SimpleModePollerError: std::error::Error,
-{
-}
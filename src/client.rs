@@ -0,0 +1,469 @@
+//! A typed client of the API served by `--api`, for other Rust services to read and manage the targets.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use futures_util::Stream;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+use crate::domain::{DataOrder, Duration, Id, StatsSummary, Status, Subscription, Timestamp, Url};
+
+/// How long before the latest update each poll of `Client::updates_stream` asks from,
+/// not to miss the updates received a little later than they occurred.
+const UPDATES_OVERLAP: Duration = Duration::from_mins(1);
+
+/// A client of the API of a running patrol.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base: url::Url,
+    token: Option<String>,
+}
+impl Client {
+    /// The API at the URL, e.g. `http://127.0.0.1:8080/`.
+    pub fn new(base: Url) -> Result<Self, Error> {
+        if base.as_url().cannot_be_a_base() {
+            return Err(Error::InvalidUrl(base.as_str().to_owned()));
+        }
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base: base.as_url().clone(),
+            token: None,
+        })
+    }
+
+    /// Sends the token of `--api-token` or `--api-viewer-token` as `Authorization: Bearer <token>`.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// `GET /targets`
+    pub async fn targets(&self, query: &TargetQuery) -> Result<TargetPage, Error> {
+        let response = self
+            .send(reqwest::Method::GET, &["targets"], &query.pairs(), None)
+            .await?;
+        json(response).await
+    }
+
+    /// `GET /targets/:id`, or `None` if no target has the id.
+    pub async fn target(&self, id: &Id) -> Result<Option<Target>, Error> {
+        let path = ["targets", id.as_str()];
+        let response = self.send(reqwest::Method::GET, &path, &[], None).await;
+        match not_found_as_none(response)? {
+            Some(response) => Ok(Some(json(response).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// `DELETE /targets/:id`, moving the target into the trash.
+    pub async fn remove(&self, id: &Id) -> Result<(), Error> {
+        let path = ["targets", id.as_str()];
+        self.send(reqwest::Method::DELETE, &path, &[], None).await?;
+        Ok(())
+    }
+
+    /// `POST /targets/:id/restore`, moving the target back from the trash.
+    pub async fn restore(&self, id: &Id) -> Result<(), Error> {
+        let path = ["targets", id.as_str(), "restore"];
+        self.send(reqwest::Method::POST, &path, &[], None).await?;
+        Ok(())
+    }
+
+    /// `POST /targets:batch`, putting the targets in the tables of the config file and deleting the others at once.
+    pub async fn batch(
+        &self,
+        put: HashMap<Id, serde_json::Value>,
+        delete: Vec<Id>,
+    ) -> Result<(), Error> {
+        let body = serde_json::json!({ "put": put, "delete": delete });
+        self.send(reqwest::Method::POST, &["targets:batch"], &[], Some(body))
+            .await?;
+        Ok(())
+    }
+
+    /// `POST /targets:pause` if `true`, or `POST /targets:resume`, returning the ids of the targets with the tag.
+    pub async fn pause(&self, tag: &str, paused: bool) -> Result<Vec<Id>, Error> {
+        let path = match paused {
+            true => "targets:pause",
+            false => "targets:resume",
+        };
+        let query = [("tag", tag.to_owned())];
+        let response = self
+            .send(reqwest::Method::POST, &[path], &query, None)
+            .await?;
+        let Paused { targets } = json(response).await?;
+        Ok(targets)
+    }
+
+    /// `GET /targets/:id/stats`, or `None` if the target has not been polled.
+    pub async fn stats(&self, id: &Id) -> Result<Option<StatsSummary>, Error> {
+        let path = ["targets", id.as_str(), "stats"];
+        let response = self.send(reqwest::Method::GET, &path, &[], None).await;
+        match not_found_as_none(response)? {
+            Some(response) => Ok(Some(json(response).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// `GET /search?q=`, the lines of the latest contents with the words.
+    pub async fn search(&self, text: &str) -> Result<Vec<SearchResult>, Error> {
+        let query = [("q", text.to_owned())];
+        let response = self
+            .send(reqwest::Method::GET, &["search"], &query, None)
+            .await?;
+        let SearchResults { results } = json(response).await?;
+        Ok(results)
+    }
+
+    /// `GET /calendar.ics`, `GET /feed.atom` or `GET /feed.json`, as the text of the feed.
+    pub async fn feed(&self, feed: Feed, selection: &Selection) -> Result<String, Error> {
+        let path = match feed {
+            Feed::Calendar => "calendar.ics",
+            Feed::Atom => "feed.atom",
+            Feed::Json => "feed.json",
+        };
+        let response = self
+            .send(reqwest::Method::GET, &[path], &selection.pairs(), None)
+            .await?;
+        Ok(response.text().await?)
+    }
+
+    /// `GET /updates`, the recent events after the time if given, the latest first.
+    pub async fn updates(
+        &self,
+        selection: &Selection,
+        since: Option<Timestamp>,
+    ) -> Result<Vec<Update>, Error> {
+        let mut query = selection.pairs();
+        if let Some(since) = since {
+            query.push(("since", since.unix_millis().to_string()));
+        }
+        let response = self
+            .send(reqwest::Method::GET, &["updates"], &query, None)
+            .await?;
+        json(response).await
+    }
+
+    /// Polls `GET /updates` at the period, yielding each update once, oldest first, after the time if given.
+    ///
+    /// The API serves no push of the updates, so they are as late as the period at most.
+    /// A failed poll is yielded as the error, and the polls go on.
+    pub fn updates_stream(
+        &self,
+        selection: Selection,
+        since: Option<Timestamp>,
+        period: Duration,
+    ) -> impl Stream<Item = Result<Update, Error>> + Send + 'static {
+        let client = self.clone();
+        let start = since;
+        let overlapped = |x: Timestamp| x.checked_sub(UPDATES_OVERLAP).unwrap_or(x);
+        async_stream::stream! {
+            let mut since = since;
+            // the ids of the updates yielded, by the times they occurred.
+            let mut seen = HashMap::new();
+            loop {
+                match client.updates(&selection, since.map(overlapped)).await {
+                    Ok(updates) => {
+                        for update in updates.into_iter().rev() {
+                            if Some(update.occurred_at) <= start || seen.contains_key(&update.id) {
+                                continue;
+                            }
+                            let _ = seen.insert(update.id.clone(), update.occurred_at);
+                            since = since.max(Some(update.occurred_at));
+                            yield Ok(update);
+                        }
+                        // those before the overlap are not served again.
+                        if let Some(asked) = since.map(overlapped) {
+                            seen.retain(|_, at| asked < *at);
+                        }
+                    }
+                    Err(e) => yield Err(e),
+                }
+                tokio::time::sleep(period.as_std()).await;
+            }
+        }
+    }
+
+    /// `GET /subscriptions`
+    pub async fn subscriptions(&self) -> Result<Vec<SubscriptionEntry>, Error> {
+        let response = self
+            .send(reqwest::Method::GET, &["subscriptions"], &[], None)
+            .await?;
+        json(response).await
+    }
+
+    /// `POST /subscriptions`, returning the id of the subscription.
+    pub async fn subscribe(&self, subscription: &Subscription) -> Result<Id, Error> {
+        let body = serde_json::to_value(subscription).unwrap_or_default();
+        let response = self
+            .send(reqwest::Method::POST, &["subscriptions"], &[], Some(body))
+            .await?;
+        let SubscriptionEntry { id, .. } = json(response).await?;
+        Ok(id)
+    }
+
+    /// `DELETE /subscriptions/:id`
+    pub async fn unsubscribe(&self, id: &Id) -> Result<(), Error> {
+        let path = ["subscriptions", id.as_str()];
+        self.send(reqwest::Method::DELETE, &path, &[], None).await?;
+        Ok(())
+    }
+
+    /// `POST /notifiers/:name/test`, sending a made-up change of the target if given.
+    pub async fn test_notifier(&self, name: &str, target: Option<&Id>) -> Result<(), Error> {
+        let query: Vec<_> = target
+            .map(|x| ("target", x.as_str().to_owned()))
+            .into_iter()
+            .collect();
+        let path = ["notifiers", name, "test"];
+        self.send(reqwest::Method::POST, &path, &query, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the request to the path of the segments, failing unless the response is successful.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        segments: &[&str],
+        query: &[(&str, String)],
+        body: Option<serde_json::Value>,
+    ) -> Result<reqwest::Response, Error> {
+        let mut url = self.base.clone();
+        // the segments are percent-encoded, e.g. `/` in an id.
+        if let Ok(mut path) = url.path_segments_mut() {
+            let _ = path.pop_if_empty().extend(segments);
+        }
+        if 0 < query.len() {
+            let _ = url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let mut request = self.http.request(method, url);
+        if let Some(token) = self.token.as_ref() {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let message = response
+            .json::<ErrorBody>()
+            .await
+            .map(|x| x.error)
+            .unwrap_or_default();
+        Err(Error::Status(status, message))
+    }
+}
+
+async fn json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+    Ok(response.json().await?)
+}
+
+fn not_found_as_none<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(x) => Ok(Some(x)),
+        Err(Error::Status(reqwest::StatusCode::NOT_FOUND, _)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The query of `Client::targets`, of every target by default.
+#[derive(Debug, Clone, Default)]
+pub struct TargetQuery {
+    tag: Option<String>,
+    statuses: Vec<Status>,
+    updated_since: Option<Timestamp>,
+    order: DataOrder,
+    offset: usize,
+    limit: Option<usize>,
+}
+impl TargetQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Only the targets with the tag.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+    /// Only the targets in any of the statuses.
+    pub fn with_statuses(mut self, statuses: Vec<Status>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+    /// Only the targets updated after the time.
+    pub fn with_updated_since(mut self, at: Timestamp) -> Self {
+        self.updated_since = Some(at);
+        self
+    }
+    pub fn with_order(mut self, order: DataOrder) -> Self {
+        self.order = order;
+        self
+    }
+    /// Skips the targets, and returns at most `limit` of the rest.
+    pub fn with_page(mut self, offset: usize, limit: usize) -> Self {
+        self.offset = offset;
+        self.limit = Some(limit);
+        self
+    }
+
+    fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(tag) = self.tag.as_ref() {
+            pairs.push(("tag", tag.clone()));
+        }
+        if 0 < self.statuses.len() {
+            let statuses: Vec<_> = self.statuses.iter().map(Status::as_str).collect();
+            pairs.push(("status", statuses.join(",")));
+        }
+        if let Some(at) = self.updated_since {
+            pairs.push(("updated_since", at.unix_millis().to_string()));
+        }
+        let sort = match self.order {
+            DataOrder::Id => "id",
+            DataOrder::LastUpdated => "last_updated",
+            DataOrder::LastChecked => "last_checked",
+        };
+        pairs.push(("sort", sort.to_owned()));
+        pairs.push(("offset", self.offset.to_string()));
+        if let Some(limit) = self.limit {
+            pairs.push(("limit", limit.to_string()));
+        }
+        pairs
+    }
+}
+
+/// The targets whose changes and updates are read, as `?tag=` and `?target=`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    All,
+    Tag(String),
+    Target(Id),
+}
+impl Selection {
+    fn pairs(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Selection::All => Vec::new(),
+            Selection::Tag(tag) => vec![("tag", tag.clone())],
+            Selection::Target(id) => vec![("target", id.as_str().to_owned())],
+        }
+    }
+}
+
+/// The formats of the feeds of the recent changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feed {
+    Calendar,
+    Atom,
+    Json,
+}
+
+/// A target in `GET /targets` and `GET /targets/:id`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub id: Id,
+    /// `None` if the target is no longer in the configurations.
+    pub url: Option<Url>,
+    pub tags: Vec<String>,
+    pub status: Status,
+    pub title: Option<String>,
+    /// The URL of the favicon of the page.
+    pub favicon: Option<Url>,
+    pub last_updated: Option<Timestamp>,
+    pub last_checked: Timestamp,
+}
+
+/// A page of the targets of `GET /targets`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TargetPage {
+    pub targets: Vec<Target>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// The lines of the latest content of a target found by `GET /search`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub id: Id,
+    pub lines: Vec<String>,
+}
+
+/// An event in the flat JSON of `GET /updates`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Update {
+    /// Unique to each event.
+    pub id: String,
+    pub target: Id,
+    /// The event in a sentence.
+    pub summary: String,
+    pub occurred_at: Timestamp,
+    /// The other fields of the event, e.g. `event` and `url`.
+    #[serde(flatten)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A subscription of `GET /subscriptions`, whose secret is never served.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionEntry {
+    pub id: Id,
+    #[serde(flatten)]
+    pub subscription: Subscription,
+}
+
+#[derive(Deserialize)]
+struct Paused {
+    targets: Vec<Id>,
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    results: Vec<SearchResult>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidUrl(String),
+    Request(reqwest::Error),
+    /// The status of the response, with the message of the error if any.
+    Status(reqwest::StatusCode, String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidUrl(url) => {
+                f.write_fmt(format_args!("{url} cannot be the base of the API"))
+            }
+            Error::Request(e) => f.write_fmt(format_args!("failed to request the API: {e}")),
+            Error::Status(status, message) if message.len() == 0 => {
+                f.write_fmt(format_args!("the API responded with {status}"))
+            }
+            Error::Status(status, message) => {
+                f.write_fmt(format_args!("the API responded with {status}: {message}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            Error::InvalidUrl(_) | Error::Status(..) => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
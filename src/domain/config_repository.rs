@@ -1,13 +1,44 @@
 use crate::domain::{Config, Id};
 use std::collections::HashMap;
+use tokio::sync::broadcast;
 
 #[async_trait::async_trait]
 pub trait ConfigRepository {
-    type Error: std::error::Error + Send;
+    type Error: std::error::Error + Send + Sync + 'static;
 
     async fn get_all(&mut self) -> Result<HashMap<Id, Config>, Self::Error>;
 
     async fn update(&mut self, id: Id, config: Config) -> Result<(), Self::Error>;
 
     async fn delete(&mut self, id: Id) -> Result<Option<Config>, Self::Error>;
+
+    /// Updates the targets with `Some` and deletes the ones with `None` at once.
+    ///
+    /// Either all of the changes are applied, or none of them is.
+    async fn apply(&mut self, changes: HashMap<Id, Option<Config>>) -> Result<(), Self::Error>;
+
+    /// Reloads the configurations from the underlying storage.
+    ///
+    /// Returns the changes, which are also sent to the subscribers.
+    async fn reload(&mut self) -> Result<Vec<ConfigEvent>, Self::Error>;
+
+    /// Subscribes to the changes made through this repository.
+    fn subscribe(&self) -> broadcast::Receiver<ConfigEvent>;
+}
+
+/// A change of the configurations.
+#[derive(Debug, Clone)]
+pub enum ConfigEvent {
+    Added { id: Id, config: Config },
+    Updated { id: Id, config: Config },
+    Removed { id: Id, config: Config },
+}
+impl ConfigEvent {
+    pub fn id(&self) -> &Id {
+        match self {
+            ConfigEvent::Added { id, .. } => id,
+            ConfigEvent::Updated { id, .. } => id,
+            ConfigEvent::Removed { id, .. } => id,
+        }
+    }
 }
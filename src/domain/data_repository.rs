@@ -1,16 +1,89 @@
-use crate::domain::{Data, Hash, Id};
+use crate::domain::{Checkpoint, Data, Hash, Id, Metadata, Stats, Status, Timestamp};
 use std::collections::{HashMap, HashSet};
 
+/// The read half of a data repository.
 #[async_trait::async_trait]
-pub trait DataRepository {
-    type Error: std::error::Error + Send;
+pub trait DataReader {
+    type Error: std::error::Error + Send + Sync + 'static;
 
-    async fn get(&mut self, id: Id) -> Result<Option<Data>, Self::Error>;
-    async fn get_multiple(&mut self, ids: HashSet<Id>) -> Result<HashMap<Id, Data>, Self::Error>;
-    async fn get_all(&mut self) -> Result<HashMap<Id, Data>, Self::Error>;
+    async fn get(&self, id: Id) -> Result<Option<Data>, Self::Error>;
+    async fn get_multiple(&self, ids: HashSet<Id>) -> Result<HashMap<Id, Data>, Self::Error>;
+    async fn get_all(&self) -> Result<HashMap<Id, Data>, Self::Error>;
+    /// Lists the data matching the query, in its order.
+    async fn query(&self, query: DataQuery) -> Result<Vec<(Id, Data)>, Self::Error>;
+    /// The checkpoint of the cycle interrupted last time, if any.
+    async fn get_checkpoint(&self) -> Result<Option<Checkpoint>, Self::Error>;
+}
+
+/// The conditions, the order and the range of the data listed by `DataReader::query`.
+#[derive(Debug, Clone, Default)]
+pub struct DataQuery {
+    /// The data of the targets, or of any target if `None`, e.g. of those with a tag.
+    pub ids: Option<HashSet<Id>>,
+    /// The data with any of the statuses, or with any status if empty.
+    pub statuses: Vec<Status>,
+    /// The data updated at or after the time.
+    pub updated_since: Option<Timestamp>,
+    pub order: DataOrder,
+    /// The number of the data skipped from the first.
+    pub offset: usize,
+    /// The maximum number of the data listed.
+    pub limit: Option<usize>,
+}
+
+/// The order of the data listed by `DataReader::query`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataOrder {
+    #[default]
+    Id,
+    /// The most recently updated first, and the never updated last.
+    LastUpdated,
+    /// The most recently checked first.
+    LastChecked,
+}
 
-    async fn update(&mut self, id: Id, hash: Hash) -> Result<(), Self::Error>;
-    async fn update_multiple(&mut self, map: HashMap<Id, Hash>) -> Result<(), Self::Error>;
+/// The write half of a data repository.
+#[async_trait::async_trait]
+pub trait DataWriter: DataReader {
+    /// Updates the hash of the target.
+    ///
+    /// Returns the new `last_updated` if the hash has changed, otherwise `None`.
+    async fn update(&mut self, id: Id, hash: Hash) -> Result<Option<Timestamp>, Self::Error>;
+    /// Updates the hashes of the targets at once.
+    ///
+    /// Returns the result of `update` for each target.
+    async fn update_multiple(
+        &mut self,
+        map: HashMap<Id, Hash>,
+    ) -> Result<HashMap<Id, Option<Timestamp>>, Self::Error>;
+    /// Updates the statuses of the targets at once.
+    ///
+    /// Targets that have no data yet are added without a hash.
+    async fn update_statuses(&mut self, map: HashMap<Id, Status>) -> Result<(), Self::Error>;
+    /// Replaces the hashes of the known variants of the targets at once.
+    ///
+    /// Targets that have no data are ignored.
+    async fn update_known_hashes(&mut self, map: HashMap<Id, Vec<Hash>>)
+        -> Result<(), Self::Error>;
+    /// Replaces the metadata of the targets at once.
+    ///
+    /// Targets that have no data are ignored.
+    async fn update_metadata(&mut self, map: HashMap<Id, Metadata>) -> Result<(), Self::Error>;
+    /// Replaces the flags of the targets whether their contents have failed to be decoded, at once.
+    ///
+    /// Targets that have no data are ignored.
+    async fn update_lossy(&mut self, map: HashMap<Id, bool>) -> Result<(), Self::Error>;
+    /// Replaces the statistics of the polls of the targets at once.
+    ///
+    /// Targets that have no data are ignored.
+    async fn update_stats(&mut self, map: HashMap<Id, Stats>) -> Result<(), Self::Error>;
 
     async fn delete(&mut self, id: Id) -> Result<Option<Data>, Self::Error>;
+
+    /// Replaces the checkpoint of the cycle in flight, or removes it if `None`.
+    async fn save_checkpoint(&mut self, checkpoint: Option<Checkpoint>) -> Result<(), Self::Error>;
 }
+
+/// A data repository that can be both read and written.
+pub trait DataRepository: DataReader + DataWriter {}
+impl<T: DataReader + DataWriter> DataRepository for T {}
@@ -1,9 +1,13 @@
 pub mod config_repository;
 pub mod data_repository;
 pub mod models;
+pub mod notifier;
 pub mod poller;
+pub mod publisher;
 
 pub use self::config_repository::*;
 pub use self::data_repository::*;
 pub use self::models::*;
+pub use self::notifier::*;
 pub use self::poller::*;
+pub use self::publisher::*;
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+/// Markers of the challenge pages, paired with the service serving them.
+///
+/// Every marker is lowercase, and specific to the challenge or block pages rather than the protected pages.
+const MARKERS: &[(&str, &str)] = &[
+    ("cloudflare", "window._cf_chl_opt"),
+    ("cloudflare", "<title>just a moment...</title>"),
+    (
+        "cloudflare",
+        "<title>attention required! | cloudflare</title>",
+    ),
+    ("akamai", "errors.edgesuite.net"),
+    ("akamai", "/_sec/cp_challenge/"),
+    ("datadome", "captcha-delivery.com"),
+    ("perimeterx", "px-captcha"),
+    ("imperva", "_incapsula_resource"),
+];
+
+/// A challenge page of an anti-bot service, served instead of the content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Challenge {
+    pub vendor: &'static str,
+}
+impl Challenge {
+    /// Detects a challenge page from the HTML of the page.
+    pub fn detect(html: &str) -> Option<Self> {
+        let html = html.to_lowercase();
+        MARKERS
+            .iter()
+            .find(|(_, marker)| html.contains(marker))
+            .map(|(vendor, _)| Self { vendor })
+    }
+
+    /// Finds a challenge in the chain of the sources of the error.
+    pub fn find_in(e: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        let mut source = Some(e);
+        while let Some(e) = source {
+            if let Some(x) = e.downcast_ref::<Self>() {
+                return Some(*x);
+            }
+            source = e.source();
+        }
+        None
+    }
+}
+impl Display for Challenge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "blocked by a challenge page of {}.",
+            self.vendor
+        ))
+    }
+}
+impl std::error::Error for Challenge {}
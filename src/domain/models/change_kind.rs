@@ -0,0 +1,138 @@
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display};
+
+/// The maximum number of the lines in an excerpt of a change.
+const EXCERPT_LINES: usize = 5;
+/// The maximum number of the characters of each line in an excerpt of a change.
+const EXCERPT_LINE_CHARS: usize = 200;
+
+/// The kind of a change of the content.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Lines are added, and none is removed.
+    Added,
+    /// Lines are removed, and none is added.
+    Removed,
+    /// Only numbers have changed, e.g. a counter or a price.
+    Numbers,
+    /// Only whitespace has changed.
+    Whitespace,
+    /// Any other change.
+    Modified,
+}
+impl ChangeKind {
+    /// Classifies the change from the old content to the new one.
+    pub fn classify(old: &str, new: &str) -> Self {
+        let without_whitespace = |x: &str| x.split_whitespace().collect::<String>();
+        if without_whitespace(old) == without_whitespace(new) {
+            return ChangeKind::Whitespace;
+        }
+
+        let old_masked = mask_numbers(&without_whitespace(old));
+        if old_masked.contains('#') && old_masked == mask_numbers(&without_whitespace(new)) {
+            return ChangeKind::Numbers;
+        }
+
+        let old_lines = count_lines(old);
+        let new_lines = count_lines(new);
+        let contains = |a: &HashMap<&str, usize>, b: &HashMap<&str, usize>| {
+            b.iter().all(|(line, n)| n <= a.get(line).unwrap_or(&0))
+        };
+        match (
+            contains(&new_lines, &old_lines),
+            contains(&old_lines, &new_lines),
+        ) {
+            (true, false) => ChangeKind::Added,
+            (false, true) => ChangeKind::Removed,
+            _ => ChangeKind::Modified,
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Numbers => "numbers",
+            ChangeKind::Whitespace => "whitespace",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Excerpts the lines removed from the old content, prefixed with `- `, and the lines added to the new one, prefixed with `+ `.
+///
+/// Returns `None` if no line is changed, e.g. only whitespace around the lines.
+pub fn diff_excerpt(old: &str, new: &str) -> Option<String> {
+    let old_lines = count_lines(old);
+    let new_lines = count_lines(new);
+    let mut removed = old_lines.clone();
+    for (line, n) in new_lines.iter() {
+        if let Some(x) = removed.get_mut(line) {
+            *x = x.saturating_sub(*n);
+        }
+    }
+    let mut added = new_lines;
+    for (line, n) in old_lines.iter() {
+        if let Some(x) = added.get_mut(line) {
+            *x = x.saturating_sub(*n);
+        }
+    }
+
+    let mut lines = vec![];
+    let changed =
+        |text: &str, counts: &mut HashMap<&str, usize>, sign: &str, lines: &mut Vec<String>| {
+            for line in text.lines().map(str::trim) {
+                match counts.get_mut(line) {
+                    Some(n) if 0 < *n => *n -= 1,
+                    _ => continue,
+                }
+                let line: String = line.chars().take(EXCERPT_LINE_CHARS).collect();
+                lines.push(format!("{sign} {line}"));
+            }
+        };
+    changed(old, &mut removed, "-", &mut lines);
+    changed(new, &mut added, "+", &mut lines);
+
+    if lines.len() == 0 {
+        return None;
+    }
+    let omitted = lines.len().saturating_sub(EXCERPT_LINES);
+    lines.truncate(EXCERPT_LINES);
+    if 0 < omitted {
+        lines.push(format!("({omitted} more lines)"));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Replaces each number, e.g. `1,234.5`, with `#`.
+fn mask_numbers(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_digit() {
+            masked.push(c);
+            continue;
+        }
+        masked.push('#');
+        while chars
+            .next_if(|x| x.is_ascii_digit() || *x == '.' || *x == ',')
+            .is_some()
+        {}
+    }
+    masked
+}
+
+/// Counts the non-empty lines, trimmed.
+fn count_lines(text: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for line in text.lines().map(str::trim).filter(|x| 0 < x.len()) {
+        *counts.entry(line).or_default() += 1;
+    }
+    counts
+}
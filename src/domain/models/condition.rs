@@ -0,0 +1,25 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{duration_in, Duration, Id};
+
+/// A condition on the child targets, which determines the state of a composite target.
+///
+/// In TOML, e.g. `{ all = [{ contains = { target = "a", text = "X" } }, { changed_within = { target = "b", within = "1h" } }] }`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// All of the conditions are met.
+    All(Vec<Condition>),
+    /// Any of the conditions is met.
+    Any(Vec<Condition>),
+    /// The condition is not met.
+    Not(Box<Condition>),
+    /// The content of the target polled in the current cycle contains the text.
+    Contains { target: Id, text: String },
+    /// The content of the target has changed within the duration, which is in minutes if an integer.
+    ChangedWithin {
+        target: Id,
+        #[serde(deserialize_with = "duration_in::minutes")]
+        within: Duration,
+    },
+}
@@ -0,0 +1,388 @@
+use std::fmt::Display;
+
+use super::{
+    Baseline, BrowserSession, Config, Duration, Hash, Id, Integrity, MaintenanceWindow, Mode,
+    ResourceType, Selector, Step, StepAction, Url, Viewport, WaitStrategy,
+};
+
+/// The shortest timeout of a wait.
+pub const MIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// The longest timeout of a wait, and the longest delay.
+pub const MAX_TIMEOUT: Duration = Duration::from_mins(10);
+
+/// Whether the browser to impersonate is made of `a-z`, `0-9` and `_`, e.g. `chrome116`,
+/// since it names the command `curl_<browser>` to run.
+pub fn is_browser_name(browser: &str) -> bool {
+    browser.len() != 0
+        && browser
+            .chars()
+            .all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == '_')
+}
+
+impl Config {
+    /// Starts a config watching the element of the selector on the page of the URL.
+    ///
+    /// The other fields are the defaults of a config file, e.g. the full mode, until they are set.
+    pub fn builder(url: Url, selector: Selector) -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config {
+                url,
+                selector,
+                mode: Mode::default(),
+                poller: None,
+                delay: None,
+                paused: false,
+                session: BrowserSession::default(),
+                wait: WaitStrategy::default(),
+                script: None,
+                frames: Vec::new(),
+                pierce_shadow: false,
+                viewport: None,
+                block: Vec::new(),
+                block_urls: Vec::new(),
+                impersonate: None,
+                local_address: None,
+                tunnel: None,
+                headers: Vec::new(),
+                headers_only: false,
+                query: None,
+                variables: None,
+                path: None,
+                send: None,
+                filter: None,
+                timeout: None,
+                from: None,
+                subject: None,
+                depends_on: Vec::new(),
+                ignore_selectors: Vec::new(),
+                confirmations: None,
+                variants: None,
+                language: None,
+                maintenance: Vec::new(),
+                budget: None,
+                tags: Vec::new(),
+                baseline: Baseline::default(),
+                pinned: None,
+                integrity: None,
+                steps: Vec::new(),
+            },
+        }
+    }
+
+    /// Checks that the fields are used by the mode of this, and that the times are in the ranges.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // a custom poller may use any of the fields.
+        let polled_by_mode = self.poller.is_none()
+            && !(self.url.is_file() || self.url.is_ftp() || self.url.is_imap());
+        let require = |used: bool, mode: Mode, field: &'static str| match used
+            && polled_by_mode
+            && self.mode != mode
+        {
+            true => Err(ConfigError::ModeMismatch { field, mode }),
+            false => Ok(()),
+        };
+
+        require(self.wait != WaitStrategy::default(), Mode::Full, "wait")?;
+        require(self.script.is_some(), Mode::Full, "script")?;
+        require(0 < self.frames.len(), Mode::Full, "frames")?;
+        require(0 < self.steps.len(), Mode::Full, "steps")?;
+        require(self.pierce_shadow, Mode::Full, "pierce_shadow")?;
+        require(self.viewport.is_some(), Mode::Full, "viewport")?;
+        require(0 < self.block.len(), Mode::Full, "block")?;
+        require(0 < self.block_urls.len(), Mode::Full, "block_urls")?;
+        require(self.impersonate.is_some(), Mode::Simple, "impersonate")?;
+        require(0 < self.headers.len(), Mode::Simple, "headers")?;
+        require(self.headers_only, Mode::Simple, "headers_only")?;
+        require(self.query.is_some(), Mode::Graphql, "query")?;
+        require(self.variables.is_some(), Mode::Graphql, "variables")?;
+        require(self.path.is_some(), Mode::Graphql, "path")?;
+        require(self.send.is_some(), Mode::Websocket, "send")?;
+        require(self.filter.is_some(), Mode::Websocket, "filter")?;
+        require(self.timeout.is_some(), Mode::Websocket, "timeout")?;
+
+        if polled_by_mode && self.mode == Mode::Graphql && self.query.is_none() {
+            return Err(ConfigError::Missing("query"));
+        }
+        if self.headers_only && self.headers.len() == 0 {
+            return Err(ConfigError::Missing("headers"));
+        }
+        if (self.from.is_some() || self.subject.is_some()) && !self.url.is_imap() {
+            return Err(ConfigError::NotImap);
+        }
+        if let Some(x) = self.impersonate.as_ref().filter(|x| !is_browser_name(x)) {
+            return Err(ConfigError::UnknownBrowser(x.clone()));
+        }
+
+        let out_of_range = |field, range: String| Err(ConfigError::OutOfRange { field, range });
+        let timeout = match Duration::try_from(self.wait.timeout()) {
+            Ok(x) if MIN_TIMEOUT <= x && x <= MAX_TIMEOUT => x,
+            _ => return out_of_range("wait.timeout", format!("{MIN_TIMEOUT} to {MAX_TIMEOUT}")),
+        };
+        if Duration::try_from(self.wait.idle()).map_or(true, |idle| timeout < idle) {
+            return out_of_range("wait.idle", "at most wait.timeout".to_owned());
+        }
+        for step in self.steps.iter() {
+            if let StepAction::Wait {
+                timeout: Some(timeout),
+                ..
+            } = step.action
+            {
+                if timeout < MIN_TIMEOUT || MAX_TIMEOUT < timeout {
+                    return out_of_range(
+                        "steps.timeout",
+                        format!("{MIN_TIMEOUT} to {MAX_TIMEOUT}"),
+                    );
+                }
+            }
+        }
+        if self.delay.map(|x| MAX_TIMEOUT < x).unwrap_or(false) {
+            return out_of_range("delay", format!("at most {MAX_TIMEOUT}"));
+        }
+        if let Some(timeout) = self.timeout {
+            if timeout < MIN_TIMEOUT || MAX_TIMEOUT < timeout {
+                return out_of_range("timeout", format!("{MIN_TIMEOUT} to {MAX_TIMEOUT}"));
+            }
+        }
+        if self.confirmations == Some(0) {
+            return out_of_range("confirmations", "at least 1".to_owned());
+        }
+        if self.variants == Some(0) {
+            return out_of_range("variants", "at least 1".to_owned());
+        }
+        if self.budget.map(|x| x.as_nanos() == 0).unwrap_or(false) {
+            return out_of_range("budget", "longer than 0".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Builds a `Config` field by field, and validates it at last.
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+impl ConfigBuilder {
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn poller(mut self, name: String) -> Self {
+        self.config.poller = Some(name);
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.config.delay = Some(delay);
+        self
+    }
+
+    pub fn paused(mut self, paused: bool) -> Self {
+        self.config.paused = paused;
+        self
+    }
+
+    pub fn session(mut self, session: BrowserSession) -> Self {
+        self.config.session = session;
+        self
+    }
+
+    pub fn wait(mut self, wait: WaitStrategy) -> Self {
+        self.config.wait = wait;
+        self
+    }
+
+    pub fn script(mut self, script: String) -> Self {
+        self.config.script = Some(script);
+        self
+    }
+
+    pub fn frames(mut self, frames: Vec<Selector>) -> Self {
+        self.config.frames = frames;
+        self
+    }
+
+    pub fn pierce_shadow(mut self, pierce_shadow: bool) -> Self {
+        self.config.pierce_shadow = pierce_shadow;
+        self
+    }
+
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.config.viewport = Some(viewport);
+        self
+    }
+
+    pub fn block(mut self, block: Vec<ResourceType>) -> Self {
+        self.config.block = block;
+        self
+    }
+
+    pub fn block_urls(mut self, block_urls: Vec<String>) -> Self {
+        self.config.block_urls = block_urls;
+        self
+    }
+
+    pub fn impersonate(mut self, browser: String) -> Self {
+        self.config.impersonate = Some(browser);
+        self
+    }
+
+    pub fn local_address(mut self, address: std::net::IpAddr) -> Self {
+        self.config.local_address = Some(address);
+        self
+    }
+
+    pub fn tunnel(mut self, tunnel: String) -> Self {
+        self.config.tunnel = Some(tunnel);
+        self
+    }
+
+    pub fn headers(mut self, headers: Vec<String>) -> Self {
+        self.config.headers = headers;
+        self
+    }
+
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.config.headers_only = headers_only;
+        self
+    }
+
+    pub fn query(mut self, query: String) -> Self {
+        self.config.query = Some(query);
+        self
+    }
+
+    pub fn variables(mut self, variables: serde_json::Value) -> Self {
+        self.config.variables = Some(variables);
+        self
+    }
+
+    pub fn path(mut self, path: String) -> Self {
+        self.config.path = Some(path);
+        self
+    }
+
+    pub fn send(mut self, send: String) -> Self {
+        self.config.send = Some(send);
+        self
+    }
+
+    pub fn filter(mut self, filter: String) -> Self {
+        self.config.filter = Some(filter);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn from(mut self, from: String) -> Self {
+        self.config.from = Some(from);
+        self
+    }
+
+    pub fn subject(mut self, subject: String) -> Self {
+        self.config.subject = Some(subject);
+        self
+    }
+
+    pub fn depends_on(mut self, ids: Vec<Id>) -> Self {
+        self.config.depends_on = ids;
+        self
+    }
+
+    pub fn ignore_selectors(mut self, selectors: Vec<Selector>) -> Self {
+        self.config.ignore_selectors = selectors;
+        self
+    }
+
+    pub fn confirmations(mut self, confirmations: u16) -> Self {
+        self.config.confirmations = Some(confirmations);
+        self
+    }
+
+    pub fn variants(mut self, variants: u16) -> Self {
+        self.config.variants = Some(variants);
+        self
+    }
+
+    pub fn language(mut self, language: String) -> Self {
+        self.config.language = Some(language);
+        self
+    }
+
+    pub fn maintenance(mut self, windows: Vec<MaintenanceWindow>) -> Self {
+        self.config.maintenance = windows;
+        self
+    }
+
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.config.tags = tags;
+        self
+    }
+
+    pub fn baseline(mut self, baseline: Baseline) -> Self {
+        self.config.baseline = baseline;
+        self
+    }
+
+    pub fn pinned(mut self, hash: Hash) -> Self {
+        self.config.pinned = Some(hash);
+        self
+    }
+
+    pub fn integrity(mut self, integrity: Integrity) -> Self {
+        self.config.integrity = Some(integrity);
+        self
+    }
+
+    pub fn steps(mut self, steps: Vec<Step>) -> Self {
+        self.config.steps = steps;
+        self
+    }
+
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The field is only used in the mode.
+    ModeMismatch { field: &'static str, mode: Mode },
+    /// The field is required by the others.
+    Missing(&'static str),
+    /// `from` and `subject` are only used for an `imap://` URL.
+    NotImap,
+    /// The time or the number of the field is out of the range.
+    OutOfRange { field: &'static str, range: String },
+    /// The browser to impersonate is not made of `a-z`, `0-9` and `_`.
+    UnknownBrowser(String),
+}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::ModeMismatch { field, mode } => {
+                f.write_fmt(format_args!("{field} is only used in the {mode} mode."))
+            }
+            ConfigError::Missing(field) => f.write_fmt(format_args!("{field} is required.")),
+            ConfigError::NotImap => {
+                f.write_str("from and subject are only used for an imap:// URL.")
+            }
+            ConfigError::OutOfRange { field, range } => {
+                f.write_fmt(format_args!("{field} must be {range}."))
+            }
+            ConfigError::UnknownBrowser(x) => f.write_fmt(format_args!(
+                "unknown browser to impersonate: {x}, which must be made of a-z, 0-9 and _."
+            )),
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
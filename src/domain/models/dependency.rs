@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::{Config, Id};
+
+/// A `depends_on` which can never be satisfied, so that the target would be skipped in every cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    /// The target depends on an id which is not of any target.
+    Unknown { id: Id, dependency: Id },
+    /// The targets depend on each other, in the order, e.g. `a`, `b`, `a`.
+    Cycle(Vec<Id>),
+}
+impl DependencyError {
+    /// Checks that the targets depend only on the existing targets, and not on themselves through the others.
+    pub fn check(configs: &HashMap<Id, Config>) -> Result<(), Self> {
+        for (id, config) in configs.iter() {
+            if let Some(x) = config.depends_on.iter().find(|x| !configs.contains_key(*x)) {
+                return Err(DependencyError::Unknown {
+                    id: id.clone(),
+                    dependency: x.clone(),
+                });
+            }
+        }
+
+        // depth-first, keeping the path from the root to find the cycle in it.
+        let mut finished = std::collections::HashSet::new();
+        for root in configs.keys() {
+            let mut path: Vec<(&Id, usize)> = vec![(root, 0)];
+            while let Some((id, next)) = path.last_mut() {
+                let id = *id;
+                if finished.contains(id) {
+                    let _ = path.pop();
+                    continue;
+                }
+                let dependency = match configs[id].depends_on.get(*next) {
+                    Some(x) => x,
+                    None => {
+                        let _ = finished.insert(id);
+                        let _ = path.pop();
+                        continue;
+                    }
+                };
+                *next += 1;
+                if let Some(i) = path.iter().position(|(x, _)| *x == dependency) {
+                    let mut cycle: Vec<_> = path[i..].iter().map(|(x, _)| (*x).clone()).collect();
+                    cycle.push(dependency.clone());
+                    return Err(DependencyError::Cycle(cycle));
+                }
+                path.push((dependency, 0));
+            }
+        }
+        Ok(())
+    }
+}
+impl Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyError::Unknown { id, dependency } => f.write_fmt(format_args!(
+                "{id} depends on {dependency}, which is not a target."
+            )),
+            DependencyError::Cycle(ids) => {
+                let ids: Vec<_> = ids.iter().map(Id::as_str).collect();
+                f.write_fmt(format_args!(
+                    "the targets depend on each other: {}.",
+                    ids.join(" -> ")
+                ))
+            }
+        }
+    }
+}
+impl std::error::Error for DependencyError {}
@@ -0,0 +1,243 @@
+use std::{fmt::Display, str::FromStr};
+
+const NANOS_PER_MILLI: u64 = 1_000_000;
+const NANOS_PER_SEC: u64 = 1_000 * NANOS_PER_MILLI;
+const NANOS_PER_MIN: u64 = 60 * NANOS_PER_SEC;
+const NANOS_PER_HOUR: u64 = 60 * NANOS_PER_MIN;
+const NANOS_PER_DAY: u64 = 24 * NANOS_PER_HOUR;
+
+/// The units of the human-friendly strings, largest first.
+const UNITS: &[(&str, u64)] = &[
+    ("d", NANOS_PER_DAY),
+    ("h", NANOS_PER_HOUR),
+    ("m", NANOS_PER_MIN),
+    ("s", NANOS_PER_SEC),
+    ("ms", NANOS_PER_MILLI),
+];
+
+/// A span of time.
+///
+/// Parsed from and serialized into strings like `90s`, `15m`, `2h`, `1d`, `500ms` or `1h30m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u64);
+impl Duration {
+    pub const fn from_days(days: u32) -> Self {
+        Self(days as u64 * NANOS_PER_DAY)
+    }
+    pub const fn from_hours(hours: u32) -> Self {
+        Self(hours as u64 * NANOS_PER_HOUR)
+    }
+    pub const fn from_mins(mins: u32) -> Self {
+        Self(mins as u64 * NANOS_PER_MIN)
+    }
+    pub const fn from_secs(secs: u32) -> Self {
+        Self(secs as u64 * NANOS_PER_SEC)
+    }
+    pub const fn from_millis(millis: u32) -> Self {
+        Self(millis as u64 * NANOS_PER_MILLI)
+    }
+    /// `None` if the seconds overflow the nanoseconds.
+    pub const fn checked_from_secs(secs: u64) -> Option<Self> {
+        match secs.checked_mul(NANOS_PER_SEC) {
+            Some(x) => Some(Self(x)),
+            None => None,
+        }
+    }
+    /// `None` if the milliseconds overflow the nanoseconds.
+    pub const fn checked_from_millis(millis: u64) -> Option<Self> {
+        match millis.checked_mul(NANOS_PER_MILLI) {
+            Some(x) => Some(Self(x)),
+            None => None,
+        }
+    }
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos)
+    }
+
+    pub const fn as_nanos(&self) -> u64 {
+        self.0
+    }
+    pub const fn as_std(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.0)
+    }
+
+    /// Parses a string like `90s`, or a bare integer in the unit, which the older versions took.
+    pub fn parse_in(text: &str, unit: Duration) -> Result<Self, DurationParseError> {
+        match text.trim().parse::<u64>() {
+            Ok(x) => x.checked_mul(unit.0).map(Self).ok_or(DurationParseError),
+            Err(_) => text.parse(),
+        }
+    }
+}
+impl TryFrom<std::time::Duration> for Duration {
+    type Error = std::num::TryFromIntError;
+
+    /// Fails if the duration overflows the nanoseconds.
+    fn try_from(x: std::time::Duration) -> Result<Self, Self::Error> {
+        u64::try_from(x.as_nanos()).map(Self)
+    }
+}
+impl FromStr for Duration {
+    type Err = DurationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s.trim();
+        if rest.len() == 0 {
+            return Err(DurationParseError);
+        }
+
+        let mut nanos = 0;
+        while 0 < rest.len() {
+            let digits = rest
+                .find(|x: char| !x.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let value: u64 = rest[..digits].parse().map_err(|_| DurationParseError)?;
+            rest = &rest[digits..];
+            let letters = rest
+                .find(|x: char| x.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (_, unit) = UNITS
+                .iter()
+                .find(|(name, _)| *name == &rest[..letters])
+                .ok_or(DurationParseError)?;
+            nanos = value
+                .checked_mul(*unit)
+                .and_then(|x| x.checked_add(nanos))
+                .ok_or(DurationParseError)?;
+            rest = &rest[letters..];
+        }
+        Ok(Self(nanos))
+    }
+}
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rest = self.0;
+        if rest < NANOS_PER_MILLI {
+            return f.write_str("0s");
+        }
+        for (name, unit) in UNITS.iter() {
+            if *unit <= rest {
+                f.write_fmt(format_args!("{}{name}", rest / unit))?;
+                rest %= unit;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Duration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DurationVisitor(None))
+    }
+}
+
+/// Visits a string like `90s`, or an integer in the unit if any.
+struct DurationVisitor(Option<Duration>);
+impl<'de> serde::de::Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            Some(unit) => write!(f, "duration like `90s`, or an integer in {unit}"),
+            None => write!(f, "duration like `90s`"),
+        }
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match s.parse() {
+            Ok(x) => Ok(x),
+            Err(_e) => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(s),
+                &self,
+            )),
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match self.0 {
+            Some(unit) => match v.checked_mul(unit.0) {
+                Some(x) => Ok(Duration(x)),
+                None => Err(serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Unsigned(v),
+                    &self,
+                )),
+            },
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Unsigned(v),
+                &self,
+            )),
+        }
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Signed(v),
+                &self,
+            )),
+        }
+    }
+}
+
+/// Deserializers of the durations, which also take an integer in the unit of the field for compatibility.
+///
+/// e.g. `#[serde(default, deserialize_with = "duration_in::optional_seconds")]`.
+pub mod duration_in {
+    use super::{Duration, DurationVisitor};
+
+    pub fn optional_seconds<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_any(DurationVisitor(Some(Duration::from_secs(1))))
+            .map(Some)
+    }
+
+    pub fn optional_millis<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_any(DurationVisitor(Some(Duration::from_millis(1))))
+            .map(Some)
+    }
+
+    pub fn minutes<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DurationVisitor(Some(Duration::from_mins(1))))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DurationParseError;
+impl Display for DurationParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failed to parse the duration, which must be like 90s, 15m, 2h, 1d or 1h30m.")
+    }
+}
+impl std::error::Error for DurationParseError {}
@@ -14,6 +14,19 @@ impl Hash {
         Hash(hasher.finalize().into())
     }
 
+    /// Calculate hash value from the bytes read from the reader, without holding all of them in memory.
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            match reader.read(&mut buffer)? {
+                0 => break,
+                n => hasher.update(&buffer[..n]),
+            }
+        }
+        Ok(Hash(hasher.finalize().into()))
+    }
+
     /// Try to construct `Hash` from the string that represents the hash value.
     pub fn from_hash_str(s: &str) -> Result<Self, FromHashStrError> {
         fn f(b: u8) -> Option<u8> {
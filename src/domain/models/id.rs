@@ -1,7 +1,16 @@
 use serde::Deserialize;
 use serde_derive::Serialize;
 use std::fmt::Display;
+use unicode_normalization::UnicodeNormalization;
 
+use super::Url;
+
+/// The maximum number of the characters of an id.
+pub const MAX_LENGTH: usize = 128;
+/// The maximum number of the characters of a slug generated from a URL.
+const MAX_SLUG_LENGTH: usize = 64;
+
+/// The id of a target, which is a string in NFC of at most 128 characters without any control character.
 #[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id(String);
 impl Id {
@@ -11,6 +20,40 @@ impl Id {
         Self(s.to_owned())
     }
 
+    /// Generates a readable id from the host and the path of the URL, e.g. `example-com-news` for `https://www.example.com/news/`.
+    ///
+    /// Falls back to a random one if the URL has no alphanumeric character to make it of.
+    pub fn slug_from_url(url: &Url) -> Self {
+        let host = url.host().unwrap_or_default();
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        let source = format!("{host}{}", url.path()).to_lowercase();
+
+        let mut slug = String::new();
+        for c in source.chars() {
+            if c.is_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let slug: String = slug.chars().take(MAX_SLUG_LENGTH).collect();
+        match Self::try_from(slug.trim_end_matches('-').to_owned()) {
+            Ok(x) => x,
+            Err(_) => Self::new(),
+        }
+    }
+
+    /// Returns this, or this with the smallest suffix like `-2` not to collide with the existing ids.
+    pub fn unique_among(self, exists: impl Fn(&Id) -> bool) -> Self {
+        if !exists(&self) {
+            return self;
+        }
+        (2..)
+            .map(|n| Id(format!("{}-{n}", self.0)))
+            .find(|x| !exists(x))
+            .unwrap()
+    }
+
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
@@ -24,11 +67,17 @@ impl TryFrom<String> for Id {
     type Error = FromStringError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if 0 < value.len() {
-            Ok(Self(value))
-        } else {
-            Err(FromStringError {})
+        let value: String = value.nfc().collect();
+        if value.len() == 0 {
+            return Err(FromStringError::Empty);
+        }
+        if MAX_LENGTH < value.chars().count() {
+            return Err(FromStringError::TooLong);
+        }
+        if value.chars().any(char::is_control) {
+            return Err(FromStringError::ControlCharacter);
         }
+        Ok(Self(value))
     }
 }
 
@@ -52,7 +101,10 @@ impl<'de> serde::de::Visitor<'de> for IdVisitor {
     type Value = Id;
 
     fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "a string of length more than 1")
+        write!(
+            f,
+            "a string of 1 to {MAX_LENGTH} characters without control characters"
+        )
     }
 
     fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
@@ -70,10 +122,24 @@ impl<'de> serde::de::Visitor<'de> for IdVisitor {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct FromStringError {}
+pub enum FromStringError {
+    Empty,
+    TooLong,
+    ControlCharacter,
+}
 impl Display for FromStringError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("Id must be a string that the length is more than 0.")
+        match self {
+            FromStringError::Empty => {
+                f.write_str("Id must be a string that the length is more than 0.")
+            }
+            FromStringError::TooLong => f.write_fmt(format_args!(
+                "Id must be a string of at most {MAX_LENGTH} characters."
+            )),
+            FromStringError::ControlCharacter => {
+                f.write_str("Id must not contain control characters.")
+            }
+        }
     }
 }
 
@@ -0,0 +1,51 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::Hash;
+
+/// The preset watching a target for unauthorized changes, e.g. a defacement of your own site.
+///
+/// The content is compared with the `pinned` version of the target, which is pinned on the first poll if not given,
+/// and the alerts are sent immediately, without the confirmations or the variants.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Integrity {
+    /// Whether the TLS certificate served for the target is also compared with the pinned one. (default: true)
+    #[serde(default = "watches_tls")]
+    pub tls: bool,
+    /// The fingerprint of the pinned certificate, the SHA-256 of it in DER, which is pinned on the first poll if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<Hash>,
+}
+impl Default for Integrity {
+    fn default() -> Self {
+        Self {
+            tls: watches_tls(),
+            certificate: None,
+        }
+    }
+}
+
+fn watches_tls() -> bool {
+    true
+}
+
+/// What of a target watched for the integrity differs from the pinned one.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperKind {
+    Content,
+    Certificate,
+}
+impl TamperKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            TamperKind::Content => "content",
+            TamperKind::Certificate => "certificate",
+        }
+    }
+}
+impl Display for TamperKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+/// A page served in another language than the requested one, e.g. by a geo-targeted site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageMismatch {
+    pub requested: String,
+    pub served: String,
+}
+impl LanguageMismatch {
+    /// Checks the language of the page, i.e. the `lang` of `<html>`, against the requested one.
+    ///
+    /// Only the primary subtags are compared, e.g. `en-GB` matches `en-US`,
+    /// and a page without the language matches any.
+    pub fn check(requested: &str, served: &str) -> Result<(), Self> {
+        let primary = |x: &str| {
+            let x = x.trim();
+            x.split(['-', '_']).next().unwrap_or(x).to_ascii_lowercase()
+        };
+        if served.trim().len() == 0 || primary(requested) == primary(served) {
+            return Ok(());
+        }
+        Err(Self {
+            requested: requested.to_owned(),
+            served: served.trim().to_owned(),
+        })
+    }
+
+    /// Finds a mismatch in the chain of the sources of the error.
+    pub fn find_in(e: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        let mut source = Some(e);
+        while let Some(e) = source {
+            if let Some(x) = e.downcast_ref::<Self>() {
+                return Some(x.clone());
+            }
+            source = e.source();
+        }
+        None
+    }
+}
+impl Display for LanguageMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "served in {} instead of {}.",
+            self.served, self.requested
+        ))
+    }
+}
+impl std::error::Error for LanguageMismatch {}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+
+use chrono::Weekday;
+
+use super::Timestamp;
+
+/// A weekly window of maintenance in UTC, during which the target is not polled.
+///
+/// e.g. `Sun 02:00-04:00`, `Mon-Fri,Sun 23:00-01:00`, or `03:00-03:30` for every day.
+/// A window ending before it starts ends on the next day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    source: String,
+    /// The days on which the window starts, indexed from Monday.
+    days: [bool; 7],
+    /// The minutes from midnight.
+    start: u32,
+    end: u32,
+}
+impl MaintenanceWindow {
+    pub fn new(source: String) -> Result<Self, MaintenanceWindowParseError> {
+        let (days, times) = match source.trim().rsplit_once(' ') {
+            Some((days, times)) => (parse_days(days.trim())?, times),
+            None => ([true; 7], source.trim()),
+        };
+        let (start, end) = times.split_once('-').ok_or(MaintenanceWindowParseError)?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+        Ok(Self {
+            source,
+            days,
+            start,
+            end,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Determines whether the time is within the window.
+    pub fn contains(&self, time: Timestamp) -> bool {
+        let day = time.weekday().num_days_from_monday() as usize;
+        let minute = time.minute_of_day();
+        if self.start <= self.end {
+            self.days[day] && self.start <= minute && minute < self.end
+        } else {
+            let yesterday = (day + 6) % 7;
+            (self.days[day] && self.start <= minute) || (self.days[yesterday] && minute < self.end)
+        }
+    }
+}
+impl Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the days separated by commas, each of which is a day or a range of days, e.g. `Mon-Fri,Sun`.
+fn parse_days(text: &str) -> Result<[bool; 7], MaintenanceWindowParseError> {
+    let day = |x: &str| {
+        x.trim()
+            .parse::<Weekday>()
+            .map(|x| x.num_days_from_monday() as usize)
+            .map_err(|_| MaintenanceWindowParseError)
+    };
+
+    let mut days = [false; 7];
+    for item in text.split(',') {
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (day(first)?, day(last)?),
+            None => (day(item)?, day(item)?),
+        };
+        let mut x = first;
+        loop {
+            days[x] = true;
+            if x == last {
+                break;
+            }
+            x = (x + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+/// Parses `HH:MM` into the minutes from midnight.
+fn parse_time(text: &str) -> Result<u32, MaintenanceWindowParseError> {
+    let (hours, minutes) = text
+        .trim()
+        .split_once(':')
+        .ok_or(MaintenanceWindowParseError)?;
+    let hours: u32 = hours.parse().map_err(|_| MaintenanceWindowParseError)?;
+    let minutes: u32 = minutes.parse().map_err(|_| MaintenanceWindowParseError)?;
+    if 24 < hours || 59 < minutes || (hours == 24 && 0 < minutes) {
+        return Err(MaintenanceWindowParseError);
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl Serialize for MaintenanceWindow {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for MaintenanceWindow {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(MaintenanceWindowVisitor)
+    }
+}
+
+struct MaintenanceWindowVisitor;
+impl<'de> serde::de::Visitor<'de> for MaintenanceWindowVisitor {
+    type Value = MaintenanceWindow;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "maintenance window like `Mon-Fri 23:00-01:00`")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match MaintenanceWindow::new(s.to_owned()) {
+            Ok(x) => Ok(x),
+            Err(_e) => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(s),
+                &self,
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MaintenanceWindowParseError;
+impl Display for MaintenanceWindowParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failed to parse the maintenance window.")
+    }
+}
+impl std::error::Error for MaintenanceWindowParseError {}
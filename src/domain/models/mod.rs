@@ -1,14 +1,46 @@
+pub mod challenge;
+pub mod change_kind;
+pub mod condition;
+pub mod config_builder;
+pub mod dependency;
+pub mod duration;
 pub mod hash;
 pub mod id;
+pub mod integrity;
+pub mod language;
+pub mod maintenance_window;
+pub mod poll_error;
+pub mod secrets;
 pub mod selector;
+pub mod stats;
+pub mod status;
+pub mod step;
+pub mod subscription;
 pub mod timestamp;
 pub mod url;
+pub mod wait_strategy;
 
+pub use self::challenge::Challenge;
+pub use self::change_kind::{diff_excerpt, ChangeKind};
+pub use self::condition::Condition;
+pub use self::config_builder::{is_browser_name, ConfigBuilder, ConfigError};
+pub use self::dependency::DependencyError;
+pub use self::duration::{duration_in, Duration, DurationParseError};
 pub use self::hash::Hash;
 pub use self::id::Id;
+pub use self::integrity::{Integrity, TamperKind};
+pub use self::language::LanguageMismatch;
+pub use self::maintenance_window::MaintenanceWindow;
+pub use self::poll_error::{ExtractionError, ParseError, PollErrorKind};
+pub use self::secrets::{Decrypter, SecretStore, Secrets, UnknownSecret};
 pub use self::selector::Selector;
-pub use self::timestamp::{Duration, Timestamp};
+pub use self::stats::{Stats, StatsSummary, StepStats, StepSummary};
+pub use self::status::{Status, StatusParseError};
+pub use self::step::{Step, StepAction, StepTiming};
+pub use self::subscription::{Subscription, SUBSCRIBABLE_EVENTS};
+pub use self::timestamp::{TimeZone, Timestamp};
 pub use self::url::Url;
+pub use self::wait_strategy::WaitStrategy;
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -17,7 +49,92 @@ pub struct Config {
     pub url: Url,
     pub selector: Selector,
     pub mode: Mode,
-    pub wait_seconds: Option<u16>,
+    /// Name of the registered poller which polls the target instead of the one of the mode, e.g. `feed`.
+    pub poller: Option<String>,
+    /// How long to wait after the page is loaded, or for a message in the websocket mode.
+    pub delay: Option<Duration>,
+    pub paused: bool,
+    pub session: BrowserSession,
+    pub wait: WaitStrategy,
+    /// JavaScript whose return value is used as the content instead of the text of the element, in the full mode.
+    ///
+    /// The element matched by the selector is passed as `arguments[0]`.
+    pub script: Option<String>,
+    /// Selectors of the iframes to enter in order, before applying the selector in the full mode.
+    pub frames: Vec<Selector>,
+    /// Whether the selector also looks into open shadow roots in the full mode.
+    pub pierce_shadow: bool,
+    /// The viewport to emulate in the full mode.
+    pub viewport: Option<Viewport>,
+    /// Types of resources not to load in the full mode.
+    pub block: Vec<ResourceType>,
+    /// URL patterns (`*` is a wildcard) not to load in the full mode, e.g. third-party scripts.
+    pub block_urls: Vec<String>,
+    /// Browser whose TLS and HTTP/2 fingerprint is impersonated in the simple mode, e.g. `chrome116`.
+    ///
+    /// `curl_<browser>` of curl-impersonate must be in the PATH.
+    pub impersonate: Option<String>,
+    /// Local address to send the requests from in the simple mode, e.g. on a multi-homed host.
+    pub local_address: Option<std::net::IpAddr>,
+    /// Name of the SSH tunnel to connect through, e.g. to reach a host only reachable from a bastion network.
+    ///
+    /// The full mode uses a fresh session through the tunnel, whatever `session` is.
+    pub tunnel: Option<String>,
+    /// Names of the response headers included in the content in the simple mode, e.g. `Last-Modified`.
+    pub headers: Vec<String>,
+    /// Whether only the `headers` are watched, without reading the body in the simple mode.
+    pub headers_only: bool,
+    /// GraphQL query sent to the URL in the graphql mode.
+    pub query: Option<String>,
+    /// Variables of the `query`, a table in TOML.
+    pub variables: Option<serde_json::Value>,
+    /// Dot-separated path of the value used as the content in the graphql mode, e.g. `data.product.price`.
+    ///
+    /// A number indexes an array. The whole `data` is used if not given.
+    pub path: Option<String>,
+    /// Message sent after connecting in the websocket mode, e.g. to subscribe to the data.
+    pub send: Option<String>,
+    /// Text which the message used as the content must contain in the websocket mode.
+    pub filter: Option<String>,
+    /// How long to wait for the message in the websocket mode. (default: 30s)
+    pub timeout: Option<Duration>,
+    /// Text which the sender of the messages must contain, for an `imap://` target.
+    pub from: Option<String>,
+    /// Text which the subject of the messages must contain, for an `imap://` target.
+    pub subject: Option<String>,
+    /// Ids of the targets which must succeed in the same cycle before this is polled, e.g. a probe of the login.
+    ///
+    /// This is skipped if any of them fails or is in a maintenance window, while a paused one is ignored.
+    pub depends_on: Vec<Id>,
+    /// Selectors of the elements removed before reading the content, e.g. ads or cookie banners inside the element.
+    pub ignore_selectors: Vec<Selector>,
+    /// The number of consecutive polls which must observe a new content identically before it is recorded. (default: 1)
+    pub confirmations: Option<u16>,
+    /// The number of the known variants of the content, which are not changes when served again. (default: 1)
+    pub variants: Option<u16>,
+    /// The language requested with `Accept-Language`, e.g. `en-US`.
+    /// A page served in another language by its `<html lang>` is ignored, keeping the previous hash and status.
+    pub language: Option<String>,
+    /// Weekly windows of maintenance in UTC, e.g. `Sun 02:00-04:00`, during which the target is not polled.
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// The longest time to wait for the target in a cycle, counted from its first poll in the cycle including the retries.
+    ///
+    /// The target is skipped if it is not polled within this or the cycle.
+    pub budget: Option<Duration>,
+    /// Labels to filter the targets by, e.g. in the calendar of the changes.
+    pub tags: Vec<String>,
+    /// How the first content captured from the target is reported.
+    pub baseline: Baseline,
+    /// The hash of the version of the content which the contents are compared with, instead of the previous one.
+    ///
+    /// Every content differing from it is a change, e.g. to detect a defacement of a page which should never change.
+    pub pinned: Option<Hash>,
+    /// The preset watching the target for unauthorized changes, with the pinned TLS certificate.
+    pub integrity: Option<Integrity>,
+    /// Steps of a scripted flow run in order after loading the URL in the full mode, e.g. logging in and adding to the cart.
+    ///
+    /// The selector reads the content on the page where the steps end, and the time of each step is recorded.
+    pub steps: Vec<Step>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -25,16 +142,108 @@ pub struct Data {
     pub hash: Option<Hash>,
     pub last_updated: Option<Timestamp>,
     pub last_checked: Timestamp,
+    #[serde(default)]
+    pub status: Status,
+    /// The hashes of the known variants of the content, including the current one, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub known_hashes: Vec<Hash>,
+    /// The title of the page, discovered on the first successful poll.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The URL of the favicon of the page, discovered on the first successful poll.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<Url>,
+    /// Whether the content has failed to be decoded, and the invalid bytes have been replaced with `U+FFFD`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lossy: bool,
+    /// The statistics of the polls, e.g. the durations and the changes per week.
+    #[serde(default, skip_serializing_if = "Stats::is_empty")]
+    pub stats: Stats,
+}
+
+/// The progress of a cycle in flight, kept to resume the cycle after a crash or a restart.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub started_at: Timestamp,
+    /// The statuses of the targets polled so far in the cycle, whose hashes have been recorded.
+    pub statuses: std::collections::HashMap<Id, Status>,
+}
+
+/// The metadata of the page of a target, to identify it by something friendlier than the id.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub favicon: Option<Url>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum Mode {
     Simple,
+    #[default]
     Full,
+    /// Send the `query` to the GraphQL endpoint of the URL, and use the value at the `path` of the response.
+    Graphql,
+    /// Connect to the websocket URL, and use a message received from it.
+    Websocket,
 }
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Full
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Mode::Simple => "simple",
+            Mode::Full => "full",
+            Mode::Graphql => "graphql",
+            Mode::Websocket => "websocket",
+        })
     }
 }
+
+/// How the first content captured from a target, which has no hash to compare with, is reported.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[derive(Default)]
+pub enum Baseline {
+    /// Record the hash without notifying.
+    #[default]
+    Silent,
+    /// Notify of the content, without counting it as a change.
+    Notify,
+    /// Report the content as a change, which is added to the empty content.
+    TreatAsChange,
+}
+
+/// How the browser session is used to poll a target in the full mode.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[derive(Default)]
+pub enum BrowserSession {
+    /// Use a pooled session shared with the other targets.
+    #[default]
+    Shared,
+    /// Use a pooled session, clearing cookies, storages and cache before and after each poll.
+    Cleared,
+    /// Create a fresh session for each poll, and close it afterwards.
+    Isolated,
+}
+
+/// The viewport and device to emulate in the full mode.
+///
+/// `device_scale_factor` and `mobile` are only available with chrome.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    pub device_scale_factor: Option<f64>,
+    #[serde(default)]
+    pub mobile: bool,
+}
+
+/// A type of resource loaded by the page.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceType {
+    Image,
+    Font,
+    Media,
+}
@@ -0,0 +1,65 @@
+use serde_derive::Serialize;
+use std::fmt::Display;
+
+/// The kind of a failure to poll a target.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PollErrorKind {
+    /// Failed to fetch the response, e.g. a refused connection or a challenge page.
+    Network,
+    /// Failed to parse the response, e.g. a malformed JSON.
+    Parse,
+    /// Failed to extract the content from the parsed response, e.g. by a selector the parser does not support.
+    Extraction,
+}
+impl PollErrorKind {
+    /// Classifies the error by the first `ParseError` or `ExtractionError` in the chain of its sources.
+    ///
+    /// The others are failures to fetch the response.
+    pub fn of(e: &(dyn std::error::Error + 'static)) -> Self {
+        let mut source = Some(e);
+        while let Some(e) = source {
+            if e.is::<ParseError>() {
+                return Self::Parse;
+            }
+            if e.is::<ExtractionError>() {
+                return Self::Extraction;
+            }
+            source = e.source();
+        }
+        Self::Network
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Parse => "parse",
+            Self::Extraction => "extraction",
+        }
+    }
+}
+impl Display for PollErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The response of a target could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("failed to parse the response: {}", self.0))
+    }
+}
+impl std::error::Error for ParseError {}
+
+/// The content of a target could not be extracted from the parsed response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractionError(pub String);
+impl Display for ExtractionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("failed to extract the content: {}", self.0))
+    }
+}
+impl std::error::Error for ExtractionError {}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use super::{Config, Step, StepAction, Url};
+
+/// The prefix of a reference to a secret, e.g. `${secrets.imap_password}`.
+const SECRETS_PREFIX: &str = "secrets.";
+/// The prefix of a reference to an environment variable, e.g. `${env.IMAP_PASSWORD}`.
+const ENV_PREFIX: &str = "env.";
+/// The prefix of a reference to a secret kept by the OS, e.g. `${keyring.imap_password}`.
+const KEYRING_PREFIX: &str = "keyring.";
+/// The prefix of a value encrypted at rest, e.g. `enc:...` of `encrypt`.
+const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Decrypts the values of the configs encrypted at rest, which start with `enc:`.
+pub trait Decrypter: Send + Sync + std::fmt::Debug {
+    fn decrypt(&self, value: &str) -> Result<String, String>;
+}
+
+/// Reads the secrets kept by the OS, e.g. the keychain of macOS or the Secret Service of Linux.
+pub trait SecretStore: Send + Sync + std::fmt::Debug {
+    fn get(&self, name: &str) -> Result<String, String>;
+}
+
+/// Credentials referenced by name from the configs, kept apart from them.
+#[derive(Debug, Clone, Default)]
+pub struct Secrets {
+    map: HashMap<String, String>,
+    decrypter: Option<Arc<dyn Decrypter>>,
+    /// The values decrypted so far, not to derive the key again on every poll.
+    decrypted: Arc<Mutex<HashMap<String, String>>>,
+    store: Option<Arc<dyn SecretStore>>,
+    /// The secrets read from the store so far, not to ask the OS on every poll.
+    stored: Arc<Mutex<HashMap<String, String>>>,
+}
+impl Secrets {
+    pub fn new(map: HashMap<String, String>) -> Self {
+        Self {
+            map,
+            ..Self::default()
+        }
+    }
+
+    /// Decrypts the values of the configs which start with `enc:` by the decrypter.
+    pub fn with_decrypter(mut self, decrypter: Arc<dyn Decrypter>) -> Self {
+        self.decrypter = Some(decrypter);
+        self
+    }
+
+    /// Resolves each `${keyring.NAME}` by the secret of the name in the store.
+    pub fn with_store(mut self, store: Arc<dyn SecretStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Replaces each `${secrets.NAME}` in the text with the secret, each `${env.NAME}` with the environment variable,
+    /// and each `${keyring.NAME}` with the secret in the store.
+    ///
+    /// The text starting with `enc:` is decrypted as a whole instead.
+    pub fn resolve(&self, text: &str) -> Result<String, UnknownSecret> {
+        if text.starts_with(ENCRYPTED_PREFIX) {
+            return self.decrypt(text);
+        }
+        let mut resolved = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            let end = match rest[start..].find('}') {
+                Some(x) => start + x,
+                None => break,
+            };
+            let reference = &rest[start + 2..end];
+            let value = if let Some(name) = reference.strip_prefix(SECRETS_PREFIX) {
+                self.map.get(name).cloned()
+            } else if let Some(name) = reference.strip_prefix(ENV_PREFIX) {
+                std::env::var(name).ok()
+            } else if let Some(name) = reference.strip_prefix(KEYRING_PREFIX) {
+                Some(self.get_stored(name)?)
+            } else {
+                // not a reference, e.g. a template literal of a script.
+                resolved.push_str(&rest[..end + 1]);
+                rest = &rest[end + 1..];
+                continue;
+            };
+            let value = value.ok_or_else(|| UnknownSecret(reference.to_owned()))?;
+            resolved.push_str(&rest[..start]);
+            resolved.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        resolved.push_str(rest);
+        Ok(resolved)
+    }
+
+    /// Resolves the references in the URL, the `script`, the `query`, the string values of the `variables`, the `send` and the `steps` of the config,
+    /// and decrypts those encrypted at rest.
+    pub fn resolve_config(&self, config: Config) -> Result<Config, UnknownSecret> {
+        let resolve_option = |x: Option<String>| x.map(|x| self.resolve(&x)).transpose();
+
+        let url = self.resolve_url(&config.url)?;
+        let variables = match config.variables {
+            Some(mut x) => {
+                self.resolve_json(&mut x)?;
+                Some(x)
+            }
+            None => None,
+        };
+        let steps = config
+            .steps
+            .into_iter()
+            .map(|step| {
+                let action = match step.action {
+                    StepAction::Goto { url } => StepAction::Goto {
+                        url: self.resolve_url(&url)?,
+                    },
+                    StepAction::Fill { selector, value } => StepAction::Fill {
+                        selector,
+                        value: self.resolve(&value)?,
+                    },
+                    StepAction::Script { script } => StepAction::Script {
+                        script: self.resolve(&script)?,
+                    },
+                    x => x,
+                };
+                Ok(Step { action, ..step })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Config {
+            url,
+            steps,
+            script: resolve_option(config.script)?,
+            query: resolve_option(config.query)?,
+            send: resolve_option(config.send)?,
+            variables,
+            ..config
+        })
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String, UnknownSecret> {
+        if let Some(x) = self.decrypted.lock().unwrap().get(value) {
+            return Ok(x.clone());
+        }
+        let decrypted = match self.decrypter.as_ref() {
+            Some(x) => x.decrypt(value),
+            None => Err("no passphrase is given".to_owned()),
+        };
+        let decrypted =
+            decrypted.map_err(|e| UnknownSecret(format!("the encrypted value ({e})")))?;
+        let _ = self
+            .decrypted
+            .lock()
+            .unwrap()
+            .insert(value.to_owned(), decrypted.clone());
+        Ok(decrypted)
+    }
+
+    fn get_stored(&self, name: &str) -> Result<String, UnknownSecret> {
+        if let Some(x) = self.stored.lock().unwrap().get(name) {
+            return Ok(x.clone());
+        }
+        let stored = match self.store.as_ref() {
+            Some(x) => x.get(name),
+            None => Err("no keyring is used".to_owned()),
+        };
+        let stored = stored.map_err(|e| UnknownSecret(format!("{KEYRING_PREFIX}{name} ({e})")))?;
+        let _ = self
+            .stored
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), stored.clone());
+        Ok(stored)
+    }
+
+    fn resolve_url(&self, url: &Url) -> Result<Url, UnknownSecret> {
+        // the braces of the references in the path or the userinfo are percent-encoded by the normalization.
+        let resolved = match url.as_str().contains("$%7B") {
+            true => url.as_str().replace("%7B", "{").replace("%7D", "}"),
+            false => url.as_str().to_owned(),
+        };
+        let resolved = self.resolve(&resolved)?;
+        Url::new(resolved).map_err(|_| UnknownSecret(url.as_str().to_owned()))
+    }
+
+    fn resolve_json(&self, value: &mut serde_json::Value) -> Result<(), UnknownSecret> {
+        match value {
+            serde_json::Value::String(x) => *x = self.resolve(x)?,
+            serde_json::Value::Array(x) => {
+                for x in x.iter_mut() {
+                    self.resolve_json(x)?;
+                }
+            }
+            serde_json::Value::Object(x) => {
+                for (_, x) in x.iter_mut() {
+                    self.resolve_json(x)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// A reference to a secret or an environment variable which is not defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSecret(pub String);
+impl Display for UnknownSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("failed to resolve {}.", self.0))
+    }
+}
+impl std::error::Error for UnknownSecret {}
@@ -7,7 +7,7 @@ use std::fmt::Display;
 pub struct Selector(String);
 impl Selector {
     pub fn new(selector: String) -> Result<Self, SelectorParseError> {
-        if let Err(_) = scraper::Selector::parse(selector.as_str()) {
+        if scraper::Selector::parse(selector.as_str()).is_err() {
             return Err(SelectorParseError);
         }
 
@@ -67,3 +67,4 @@ impl Display for SelectorParseError {
         f.write_str("failed to parse the selector.")
     }
 }
+impl std::error::Error for SelectorParseError {}
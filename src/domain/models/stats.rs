@@ -0,0 +1,184 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{Duration, StepTiming, Timestamp};
+
+/// The number of the durations of the recent polls kept for the percentiles.
+const DURATIONS_TO_KEEP: usize = 50;
+/// How long the times of the changes are kept for the changes per week.
+const CHANGES_KEPT_FOR: Duration = Duration::from_days(28);
+const WEEK: Duration = Duration::from_days(7);
+
+/// The statistics of the polls of a target, to tune its interval from its actual behavior.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// When the first poll was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<Timestamp>,
+    #[serde(default)]
+    pub polls: u64,
+    #[serde(default)]
+    pub failures: u64,
+    /// The durations of the recent successful polls in milliseconds, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub durations_ms: Vec<u64>,
+    /// The times of the changes within the last 4 weeks, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<Timestamp>,
+    /// The durations of the steps of the scripted flow in the recent successful polls, in the order of the last one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepStats>,
+}
+impl Stats {
+    pub fn is_empty(&self) -> bool {
+        self.polls == 0
+    }
+
+    /// Records a successful poll taking the duration, which may have changed the content.
+    pub fn record_success(&mut self, at: Timestamp, elapsed: std::time::Duration, changed: bool) {
+        self.record(at);
+        push_duration(&mut self.durations_ms, elapsed);
+        if changed {
+            self.changes.push(at);
+        }
+    }
+
+    /// Records the timings of the steps of a successful poll, forgetting the steps which are no longer run.
+    pub fn record_steps(&mut self, timings: &[StepTiming]) {
+        let mut old = std::mem::take(&mut self.steps);
+        self.steps = timings
+            .iter()
+            .map(|timing| {
+                let mut x = match old.iter().position(|x| x.name == timing.name) {
+                    Some(i) => old.remove(i),
+                    None => StepStats {
+                        name: timing.name.clone(),
+                        durations_ms: Vec::new(),
+                    },
+                };
+                push_duration(&mut x.durations_ms, timing.elapsed);
+                x
+            })
+            .collect();
+    }
+
+    /// Records a failed poll.
+    pub fn record_failure(&mut self, at: Timestamp) {
+        self.record(at);
+        self.failures += 1;
+    }
+
+    fn record(&mut self, at: Timestamp) {
+        let _ = self.since.get_or_insert(at);
+        self.polls += 1;
+        self.changes.retain(|x| at < *x + CHANGES_KEPT_FOR);
+    }
+
+    /// Summarizes the statistics at the time.
+    pub fn summary(&self, now: Timestamp) -> StatsSummary {
+        let (mean_ms, p50_ms, p95_ms) = summarize(&self.durations_ms);
+
+        // the changes are averaged over the weeks observed, up to the weeks kept.
+        let observed = match self.since {
+            Some(since) => (now.unix_millis() - since.unix_millis()).max(0) as u64,
+            None => 0,
+        };
+        let window = observed.min(CHANGES_KEPT_FOR.as_std().as_millis() as u64);
+        let recent = self
+            .changes
+            .iter()
+            .filter(|x| now < **x + CHANGES_KEPT_FOR)
+            .count();
+        let changes_per_week = match window {
+            0 => 0.0,
+            _ => recent as f64 * WEEK.as_std().as_millis() as f64 / window as f64,
+        };
+
+        StatsSummary {
+            since: self.since,
+            polls: self.polls,
+            failures: self.failures,
+            failure_rate: match self.polls {
+                0 => 0.0,
+                n => self.failures as f64 / n as f64,
+            },
+            mean_ms,
+            p50_ms,
+            p95_ms,
+            changes_per_week,
+            steps: self
+                .steps
+                .iter()
+                .map(|x| {
+                    let (mean_ms, p50_ms, p95_ms) = summarize(&x.durations_ms);
+                    StepSummary {
+                        name: x.name.clone(),
+                        mean_ms,
+                        p50_ms,
+                        p95_ms,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The durations of the recent successful runs of a step of the scripted flow.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepStats {
+    pub name: String,
+    /// The durations in milliseconds, oldest first.
+    #[serde(default)]
+    pub durations_ms: Vec<u64>,
+}
+
+/// Appends the duration in milliseconds, keeping the recent ones.
+fn push_duration(durations_ms: &mut Vec<u64>, elapsed: std::time::Duration) {
+    durations_ms.push(elapsed.as_millis() as u64);
+    let excess = durations_ms.len().saturating_sub(DURATIONS_TO_KEEP);
+    let _ = durations_ms.drain(..excess);
+}
+
+/// The mean, the median and the 95th percentile of the durations.
+fn summarize(durations_ms: &[u64]) -> (Option<u64>, Option<u64>, Option<u64>) {
+    let mut durations = durations_ms.to_vec();
+    durations.sort_unstable();
+    // the nearest rank, e.g. the largest of less than 20 durations is the 95th percentile.
+    let percentile = |p: usize| match durations.len() {
+        0 => None,
+        n => Some(durations[(n * p).div_ceil(100) - 1]),
+    };
+    let mean = match durations.len() {
+        0 => None,
+        n => Some(durations.iter().sum::<u64>() / n as u64),
+    };
+    (mean, percentile(50), percentile(95))
+}
+
+/// The figures of `Stats` at a time.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct StatsSummary {
+    pub since: Option<Timestamp>,
+    pub polls: u64,
+    pub failures: u64,
+    /// The ratio of the failed polls, from 0 to 1.
+    pub failure_rate: f64,
+    /// The mean duration of the recent successful polls in milliseconds.
+    pub mean_ms: Option<u64>,
+    /// The median duration of the recent successful polls in milliseconds.
+    pub p50_ms: Option<u64>,
+    /// The 95th percentile duration of the recent successful polls in milliseconds.
+    pub p95_ms: Option<u64>,
+    pub changes_per_week: f64,
+    /// The figures of the steps of the scripted flow, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepSummary>,
+}
+
+/// The figures of `StepStats` in `StatsSummary`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StepSummary {
+    pub name: String,
+    pub mean_ms: Option<u64>,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+}
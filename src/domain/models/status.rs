@@ -0,0 +1,84 @@
+use serde_derive::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// The status of a target.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+#[derive(Default)]
+pub enum Status {
+    /// The target has never been checked successfully.
+    #[default]
+    New,
+    /// The content has not changed since the last check.
+    Ok,
+    /// The content has changed at the last check.
+    Changed,
+    /// The content has alternated between two variants recently, e.g. of A/B testing, so the changes between them are muted.
+    Flapping,
+    /// Failed to poll the target at the last check.
+    Failing,
+    /// The selector matched nothing, or only empty content, at the last check.
+    SelectorMiss,
+    /// The target served a challenge page of an anti-bot service at the last check.
+    Blocked,
+    /// A target which this depends on did not succeed at the last check, so this was not polled.
+    Skipped,
+    /// The target is paused and not polled.
+    Paused,
+    /// The target is in a maintenance window, and not polled.
+    Maintenance,
+    /// The target has been removed from the configurations.
+    Expired,
+}
+impl Status {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Status::New => "new",
+            Status::Ok => "ok",
+            Status::Changed => "changed",
+            Status::Flapping => "flapping",
+            Status::Failing => "failing",
+            Status::SelectorMiss => "selector_miss",
+            Status::Blocked => "blocked",
+            Status::Skipped => "skipped",
+            Status::Paused => "paused",
+            Status::Maintenance => "maintenance",
+            Status::Expired => "expired",
+        }
+    }
+}
+impl FromStr for Status {
+    type Err = StatusParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let status = match s {
+            "new" => Status::New,
+            "ok" => Status::Ok,
+            "changed" => Status::Changed,
+            "flapping" => Status::Flapping,
+            "failing" => Status::Failing,
+            "selector_miss" => Status::SelectorMiss,
+            "blocked" => Status::Blocked,
+            "skipped" => Status::Skipped,
+            "paused" => Status::Paused,
+            "maintenance" => Status::Maintenance,
+            "expired" => Status::Expired,
+            _ => return Err(StatusParseError),
+        };
+        Ok(status)
+    }
+}
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatusParseError;
+impl Display for StatusParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failed to parse the status.")
+    }
+}
+impl std::error::Error for StatusParseError {}
@@ -0,0 +1,66 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{duration_in, Duration, Selector, Url};
+
+/// A step of a scripted flow run in the full mode after the page is loaded, e.g. logging in before reading the price.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The name of the step in its timing, e.g. `login`. (default: the number and the action, e.g. `2:click`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub action: StepAction,
+}
+impl Step {
+    /// The name of the step, which is the `index`-th from 1 if not named.
+    pub fn label(&self, index: usize) -> String {
+        match self.name.as_ref() {
+            Some(x) => x.clone(),
+            None => format!("{index}:{}", self.action.as_str()),
+        }
+    }
+}
+
+/// What a step does in the browser.
+///
+/// The selectors of `click` and `fill` are waited for up to the timeout of the `wait` of the target.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum StepAction {
+    /// Navigate to the URL, and wait for it to be loaded.
+    Goto { url: Url },
+    /// Click the element matched by the selector, e.g. a submit button.
+    Click { selector: Selector },
+    /// Type the value into the element matched by the selector, replacing its value, e.g. a password field.
+    Fill { selector: Selector, value: String },
+    /// Wait until the selector matches an element, e.g. the page after the login.
+    Wait {
+        selector: Selector,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "duration_in::optional_seconds"
+        )]
+        timeout: Option<Duration>,
+    },
+    /// Run the JavaScript, e.g. to pick an option which cannot be clicked.
+    Script { script: String },
+}
+impl StepAction {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            StepAction::Goto { .. } => "goto",
+            StepAction::Click { .. } => "click",
+            StepAction::Fill { .. } => "fill",
+            StepAction::Wait { .. } => "wait",
+            StepAction::Script { .. } => "script",
+        }
+    }
+}
+
+/// How long a step of a scripted flow took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepTiming {
+    pub name: String,
+    pub elapsed: std::time::Duration,
+}
@@ -0,0 +1,35 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{Id, Url};
+
+/// The names of the events posted to the webhooks, which a subscription can filter.
+pub const SUBSCRIBABLE_EVENTS: &[&str] =
+    &["checked", "failed", "flapping", "triggered", "tampered"];
+
+/// A webhook registered at runtime, receiving the events of the targets matching its filter as `--webhook`.
+///
+/// Each list of the filter is ignored if empty.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub url: Url,
+    /// The secret signing the payloads, as `--webhook-secret`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// The names of the events to receive, e.g. `checked` for the changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// The ids of the targets whose events are received.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Id>,
+    /// The tags of the targets whose events are received, any of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+impl Subscription {
+    /// Whether the event of the target with the tags passes the filter.
+    pub fn matches(&self, event: &str, id: &Id, tags: &[String]) -> bool {
+        (self.events.len() == 0 || self.events.iter().any(|x| x == event))
+            && (self.targets.len() == 0 || self.targets.contains(id))
+            && (self.tags.len() == 0 || self.tags.iter().any(|x| tags.contains(x)))
+    }
+}
@@ -1,21 +1,34 @@
-use serde_derive::{Deserialize, Serialize};
-use std::fmt::Display;
+use chrono::TimeZone as _;
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use super::Duration;
+use std::{fmt::Display, str::FromStr};
+
+/// A point in time in UTC.
+///
+/// Serialized in RFC 3339 with the offset, e.g. `2022-01-02T03:04:05.678Z`.
+/// A time without the offset is also deserialized as UTC, which the older versions wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Timestamp(chrono::NaiveDateTime);
 impl Timestamp {
     pub fn now() -> Self {
         Self(chrono::Utc::now().naive_utc())
     }
+    /// The time out of the range of the nanoseconds in `i64`, about 1677 to 2262, is saturated to it.
     pub fn from_unix_secs(secs: i64) -> Self {
-        Self::from_unix_nanos(secs * 1_000_000_000)
+        Self::from_unix_nanos(secs.saturating_mul(1_000_000_000))
     }
+    /// The time out of the range of the nanoseconds in `i64`, about 1677 to 2262, is saturated to it.
     pub fn from_unix_millis(millis: i64) -> Self {
-        Self::from_unix_nanos(millis * 1_000_000)
+        Self::from_unix_nanos(millis.saturating_mul(1_000_000))
+    }
+    /// Returns `None` if the time is out of the range of the nanoseconds in `i64`, about 1677 to 2262.
+    pub fn checked_from_unix_millis(millis: i64) -> Option<Self> {
+        millis.checked_mul(1_000_000).map(Self::from_unix_nanos)
     }
     pub fn from_unix_nanos(nanos: i64) -> Self {
-        let secs = nanos / 1_000_000_000;
-        let subsec_nanos = (nanos / 1_000_000_000) as u32;
+        // the remainder is never negative, also before 1970.
+        let secs = nanos.div_euclid(1_000_000_000);
+        let subsec_nanos = nanos.rem_euclid(1_000_000_000) as u32;
         let dt = chrono::NaiveDateTime::from_timestamp(secs, subsec_nanos);
         Self(dt)
     }
@@ -29,58 +42,179 @@ impl Timestamp {
     pub fn unix_nanos(&self) -> i64 {
         self.0.timestamp_nanos()
     }
+
+    pub fn weekday(&self) -> chrono::Weekday {
+        chrono::Datelike::weekday(&self.0)
+    }
+    /// The minutes from midnight.
+    pub fn minute_of_day(&self) -> u32 {
+        chrono::Timelike::num_seconds_from_midnight(&self.0) / 60
+    }
+
+    /// Formats the time without the offset, e.g. `2022-01-02T03:04:05.678`, as the older versions serialized.
+    pub fn to_naive_string(&self) -> String {
+        self.0.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+    }
+
+    /// Displays the time in the zone, to the seconds with the offset, e.g. `2022-01-02 12:04:05 +09:00`.
+    pub fn display_in(&self, zone: TimeZone) -> impl Display {
+        const FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+        match zone {
+            TimeZone::Utc => chrono::Utc
+                .from_utc_datetime(&self.0)
+                .format(FORMAT)
+                .to_string(),
+            TimeZone::Local => chrono::Local
+                .from_utc_datetime(&self.0)
+                .format(FORMAT)
+                .to_string(),
+            TimeZone::Fixed(offset) => offset.from_utc_datetime(&self.0).format(FORMAT).to_string(),
+        }
+    }
 }
 
 impl Display for Timestamp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self.0))
+        f.write_fmt(format_args!("{}", self.display_in(TimeZone::Utc)))
+    }
+}
+
+impl serde::Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let utc = chrono::DateTime::<chrono::Utc>::from_utc(self.0, chrono::Utc);
+        serializer.serialize_str(&utc.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+impl<'de> serde::de::Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "time in RFC 3339")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if let Ok(x) = chrono::DateTime::parse_from_rfc3339(s) {
+            return Ok(Timestamp(x.naive_utc()));
+        }
+        match chrono::NaiveDateTime::from_str(s) {
+            Ok(x) => Ok(Timestamp(x)),
+            Err(_e) => Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(s),
+                &self,
+            )),
+        }
+    }
+}
+
+/// The time zone to display the times in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeZone {
+    #[default]
+    Utc,
+    /// The time zone of the host.
+    Local,
+    Fixed(chrono::FixedOffset),
+}
+impl FromStr for TimeZone {
+    type Err = TimeZoneParseError;
+
+    /// Parses `utc`, `local`, or an offset like `+09:00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" | "z" => return Ok(TimeZone::Utc),
+            "local" => return Ok(TimeZone::Local),
+            _ => (),
+        }
+
+        let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+            (1, rest)
+        } else if let Some(rest) = s.strip_prefix('-') {
+            (-1, rest)
+        } else {
+            return Err(TimeZoneParseError);
+        };
+        let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+        let parse = |x: &str| {
+            // `i32::from_str` would take another sign, e.g. `+-09:00`.
+            if x.is_empty() || !x.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(TimeZoneParseError);
+            }
+            x.parse::<i32>().map_err(|_| TimeZoneParseError)
+        };
+        let hours = parse(hours)?;
+        let minutes = parse(minutes)?;
+        if 23 < hours || 59 < minutes {
+            return Err(TimeZoneParseError);
+        }
+        let offset = chrono::FixedOffset::east(sign * (hours * 60 + minutes) * 60);
+        Ok(TimeZone::Fixed(offset))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeZoneParseError;
+impl Display for TimeZoneParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failed to parse the time zone, which must be utc, local or like +09:00.")
+    }
+}
+impl std::error::Error for TimeZoneParseError {}
+
+impl Timestamp {
+    /// Returns `None` if the time is out of the range of `chrono`.
+    pub fn checked_add(&self, rhs: Duration) -> Option<Self> {
+        let rhs = chrono::Duration::nanoseconds(i64::try_from(rhs.as_nanos()).ok()?);
+        self.0.checked_add_signed(rhs).map(Self)
+    }
+    /// Returns `None` if the time is out of the range of `chrono`.
+    pub fn checked_sub(&self, rhs: Duration) -> Option<Self> {
+        let rhs = chrono::Duration::nanoseconds(i64::try_from(rhs.as_nanos()).ok()?);
+        self.0.checked_sub_signed(rhs).map(Self)
     }
 }
 
+/// Panics if the time is out of the range, as `std::time::Instant` does.
 impl core::ops::Sub<Duration> for Timestamp {
     type Output = Self;
 
     fn sub(self, rhs: Duration) -> Self::Output {
-        Self(self.0 - chrono::Duration::nanoseconds(rhs.0 as i64))
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting the duration from the timestamp")
     }
 }
+/// Panics if the time is out of the range, as `std::time::Instant` does.
 impl core::ops::Add<Duration> for Timestamp {
     type Output = Self;
 
     fn add(self, rhs: Duration) -> Self::Output {
-        Self(self.0 + chrono::Duration::nanoseconds(rhs.0 as i64))
+        self.checked_add(rhs)
+            .expect("overflow when adding the duration to the timestamp")
     }
 }
 impl core::ops::SubAssign<Duration> for Timestamp {
     fn sub_assign(&mut self, rhs: Duration) {
-        self.0 -= chrono::Duration::nanoseconds(rhs.0 as i64)
+        *self = *self - rhs;
     }
 }
 impl core::ops::AddAssign<Duration> for Timestamp {
     fn add_assign(&mut self, rhs: Duration) {
-        self.0 += chrono::Duration::nanoseconds(rhs.0 as i64)
-    }
-}
-
-#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Duration(u64);
-impl Duration {
-    pub const fn from_days(days: u32) -> Self {
-        Self(days as u64 * 24 * 60 * 60 * 1_000_000_000)
-    }
-    pub const fn from_hours(hours: u32) -> Self {
-        Self(hours as u64 * 60 * 60 * 1_000_000_000)
-    }
-    pub const fn from_mins(mins: u32) -> Self {
-        Self(mins as u64 * 60 * 1_000_000_000)
-    }
-    pub const fn from_secs(secs: u64) -> Self {
-        Self(secs * 1_000_000_000)
-    }
-    pub const fn from_millis(millis: u64) -> Self {
-        Self(millis * 1_000_000)
-    }
-    pub const fn from_nanos(nanos: u64) -> Self {
-        Self(nanos)
+        *self = *self + rhs;
     }
 }
@@ -1,24 +1,77 @@
-use serde::Deserialize;
-use serde_derive::Serialize;
+use serde::{Deserialize, Serialize};
 
 use std::fmt::Display;
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Url(String);
+/// A parsed URL, normalized at the construction.
+///
+/// The scheme and the host are lowercased, the default port is dropped, an empty path is `/`,
+/// and the characters not allowed are percent-encoded, so the same page is the same URL however it is written.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Url(url::Url);
 impl Url {
     pub fn new(url: String) -> Result<Self, UrlParseError> {
         url::Url::parse(url.as_ref())
-            .map(|_| Self(url))
+            .map(Self)
             .map_err(|_| UrlParseError)
     }
 
+    /// The normalized form of the URL.
     pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn as_url(&self) -> &url::Url {
         &self.0
     }
+
+    pub fn scheme(&self) -> &str {
+        self.0.scheme()
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.0.host_str()
+    }
+
+    /// The port, or the default one of the scheme.
+    pub fn port(&self) -> Option<u16> {
+        self.0.port_or_known_default()
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.path()
+    }
+
+    /// Whether this is an `http://` or `https://` URL of a page.
+    pub fn is_http(&self) -> bool {
+        ["http", "https"].contains(&self.scheme())
+    }
+
+    /// Whether this is a `file://` URL of local files.
+    pub fn is_file(&self) -> bool {
+        self.scheme() == "file"
+    }
+
+    /// Whether this is an `ftp://`, `ftps://` or `sftp://` URL.
+    pub fn is_ftp(&self) -> bool {
+        ["ftp", "ftps", "sftp"].contains(&self.scheme())
+    }
+
+    /// Whether this is an `imap://` or `imaps://` URL of a mailbox.
+    pub fn is_imap(&self) -> bool {
+        ["imap", "imaps"].contains(&self.scheme())
+    }
 }
 impl Into<String> for Url {
     fn into(self) -> String {
-        self.0
+        self.0.into()
+    }
+}
+impl Serialize for Url {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
     }
 }
 impl AsRef<str> for Url {
@@ -64,3 +117,4 @@ impl Display for UrlParseError {
         f.write_str("failed to parse the URL.")
     }
 }
+impl std::error::Error for UrlParseError {}
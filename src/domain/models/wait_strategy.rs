@@ -0,0 +1,84 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{duration_in, Duration};
+
+/// Timeout of a wait when none is specified.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Idle time of the network when none is specified.
+const DEFAULT_IDLE: Duration = Duration::from_millis(500);
+
+/// How to wait for the page to be ready, before reading the content in the full mode.
+///
+/// `timeout` and `idle` were `timeout_seconds` and `idle_millis`, which are still accepted.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "until", rename_all = "snake_case")]
+pub enum WaitStrategy {
+    /// Wait until the selector matches an element.
+    Selector {
+        #[serde(
+            default,
+            alias = "timeout_seconds",
+            deserialize_with = "duration_in::optional_seconds"
+        )]
+        timeout: Option<Duration>,
+    },
+    /// Wait until the element matched by the selector has a non-empty text.
+    Text {
+        #[serde(
+            default,
+            alias = "timeout_seconds",
+            deserialize_with = "duration_in::optional_seconds"
+        )]
+        timeout: Option<Duration>,
+    },
+    /// Wait until no resource has been loaded for `idle`.
+    NetworkIdle {
+        #[serde(
+            default,
+            alias = "idle_millis",
+            deserialize_with = "duration_in::optional_millis"
+        )]
+        idle: Option<Duration>,
+        #[serde(
+            default,
+            alias = "timeout_seconds",
+            deserialize_with = "duration_in::optional_seconds"
+        )]
+        timeout: Option<Duration>,
+    },
+    /// Wait until the JavaScript expression evaluates to a truthy value.
+    Script {
+        script: String,
+        #[serde(
+            default,
+            alias = "timeout_seconds",
+            deserialize_with = "duration_in::optional_seconds"
+        )]
+        timeout: Option<Duration>,
+    },
+}
+impl WaitStrategy {
+    pub fn timeout(&self) -> std::time::Duration {
+        let timeout = match self {
+            WaitStrategy::Selector { timeout }
+            | WaitStrategy::Text { timeout }
+            | WaitStrategy::NetworkIdle { timeout, .. }
+            | WaitStrategy::Script { timeout, .. } => timeout.unwrap_or(DEFAULT_TIMEOUT),
+        };
+        timeout.as_std()
+    }
+
+    /// Returns the idle time for `NetworkIdle`.
+    pub fn idle(&self) -> std::time::Duration {
+        let idle = match self {
+            WaitStrategy::NetworkIdle { idle, .. } => idle.unwrap_or(DEFAULT_IDLE),
+            _ => DEFAULT_IDLE,
+        };
+        idle.as_std()
+    }
+}
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy::Selector { timeout: None }
+    }
+}
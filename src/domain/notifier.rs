@@ -0,0 +1,33 @@
+use crate::domain::Url;
+
+/// A notification of an event to a person, e.g. of a change of a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// The event in a sentence, e.g. `news has changed: modified`.
+    pub summary: String,
+    /// The details, e.g. the lines of the change or the reason of the failure, which may be empty.
+    pub body: String,
+    /// The URL of the target, if any.
+    pub url: Option<Url>,
+}
+impl Notification {
+    /// The notification in plain text, the summary, the URL and the body on their own lines.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![self.summary.as_str()];
+        if let Some(url) = self.url.as_ref() {
+            lines.push(url.as_str());
+        }
+        if 0 < self.body.len() {
+            lines.push(&self.body);
+        }
+        lines.join("\n")
+    }
+}
+
+/// A backend sending the notifications to a person, e.g. over a chat.
+#[async_trait::async_trait]
+pub trait Notifier {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::Error>;
+}
@@ -1,15 +1,140 @@
 use std::collections::HashMap;
 
-use futures_util::stream::Stream;
+use bytes::Bytes;
+use futures_util::stream::{Stream, StreamExt};
 
-use crate::domain::{Config, Id};
+use crate::domain::{Config, Hash, Id, Metadata, StepTiming, Url};
+
+/// The boxed stream of the results of `Poller::poll_multiple`, which every poller of patrol returns as its `Stream`.
+pub type PollStream<E> = std::pin::Pin<Box<dyn Stream<Item = (Id, Result<PollOutput, E>)> + Send>>;
+
+/// The content polled from a target, with what is known of the response.
+///
+/// The content is kept in `Bytes`, so the clones passed on through the streams and the events share the buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PollOutput {
+    content: Bytes,
+    /// The status code of the response, if polled over HTTP.
+    pub status: Option<u16>,
+    /// The `Content-Type` of the response, if any.
+    pub content_type: Option<String>,
+    /// The URL of the page after the redirects, if known.
+    pub final_url: Option<Url>,
+    /// How long it took to fetch the content.
+    pub elapsed: std::time::Duration,
+    /// How long each step of the scripted flow took, in the full mode.
+    pub steps: Vec<StepTiming>,
+    /// Whether some bytes of the page failed to be decoded, and were replaced with `U+FFFD`.
+    pub lossy: bool,
+}
+impl PollOutput {
+    pub fn new(content: String) -> Self {
+        Self {
+            content: Bytes::from(content),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_elapsed(mut self, elapsed: std::time::Duration) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+
+    /// The content as text, which the pollers have decoded.
+    pub fn text(&self) -> &str {
+        // the content is always made of a `String`.
+        std::str::from_utf8(&self.content).unwrap_or_default()
+    }
+
+    /// The output whose content is trimmed, sharing the buffer with this.
+    pub fn trimmed(&self) -> Self {
+        let text = self.text().trim_start().trim_end();
+        Self {
+            content: self.content.slice_ref(text.as_bytes()),
+            ..self.clone()
+        }
+    }
+}
+impl From<String> for PollOutput {
+    fn from(content: String) -> Self {
+        Self::new(content)
+    }
+}
 
 #[async_trait::async_trait]
 pub trait Poller {
-    type Error: std::error::Error + Send;
-    type Stream: Stream<Item = (Id, Result<String, Self::Error>)>;
+    type Error: std::error::Error + Send + Sync + 'static;
+    type Stream: Stream<Item = (Id, Result<PollOutput, Self::Error>)>;
 
-    async fn poll(&mut self, id: Id, config: Config) -> Result<String, Self::Error>;
+    async fn poll(&mut self, id: Id, config: Config) -> Result<PollOutput, Self::Error>;
 
     async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream;
+
+    /// Discovers the metadata of the page of the target, e.g. its title.
+    ///
+    /// Returns the empty metadata by default.
+    async fn metadata(&mut self, _id: Id, _config: Config) -> Result<Metadata, Self::Error> {
+        Ok(Metadata::default())
+    }
+
+    /// Reads the fingerprint of the TLS certificate served for the target, the SHA-256 of it in DER.
+    ///
+    /// Returns `None` by default, and for a target not served over TLS.
+    async fn certificate(&mut self, _id: Id, _config: Config) -> Result<Option<Hash>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// The error of a poller behind `DynPoller`.
+pub type DynPollerError = Box<dyn std::error::Error + Send + Sync + 'static>;
+/// The stream of the results of a poller behind `DynPoller`.
+pub type DynPollStream = PollStream<DynPollerError>;
+
+/// An object-safe variant of `Poller`, to keep pollers of different types together, e.g. as `Box<dyn DynPoller>`.
+///
+/// Every `Poller` whose stream is `Send` is a `DynPoller`.
+#[async_trait::async_trait]
+pub trait DynPoller: Send {
+    async fn poll_dyn(&mut self, id: Id, config: Config) -> Result<PollOutput, DynPollerError>;
+
+    async fn poll_multiple_dyn(&mut self, configs: HashMap<Id, Config>) -> DynPollStream;
+
+    async fn metadata_dyn(&mut self, id: Id, config: Config) -> Result<Metadata, DynPollerError>;
+
+    async fn certificate_dyn(
+        &mut self,
+        id: Id,
+        config: Config,
+    ) -> Result<Option<Hash>, DynPollerError>;
+}
+
+#[async_trait::async_trait]
+impl<P> DynPoller for P
+where
+    P: Poller + Send,
+    P::Stream: Send + 'static,
+{
+    async fn poll_dyn(&mut self, id: Id, config: Config) -> Result<PollOutput, DynPollerError> {
+        let result = self.poll(id, config).await;
+        result.map_err(Into::into)
+    }
+
+    async fn poll_multiple_dyn(&mut self, configs: HashMap<Id, Config>) -> DynPollStream {
+        let stream = self.poll_multiple(configs).await;
+        Box::pin(stream.map(|(id, x)| (id, x.map_err(Into::into))))
+    }
+
+    async fn metadata_dyn(&mut self, id: Id, config: Config) -> Result<Metadata, DynPollerError> {
+        let result = self.metadata(id, config).await;
+        result.map_err(Into::into)
+    }
+
+    async fn certificate_dyn(
+        &mut self,
+        id: Id,
+        config: Config,
+    ) -> Result<Option<Hash>, DynPollerError> {
+        let result = self.certificate(id, config).await;
+        result.map_err(Into::into)
+    }
 }
@@ -0,0 +1,8 @@
+/// A backend publishing the JSON of the events to the other services, e.g. a topic of a cloud pub/sub.
+#[async_trait::async_trait]
+pub trait Publisher {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Publishes the JSON of the event of the name, e.g. `checked`, which the subscribers may filter by.
+    async fn publish(&self, name: &str, json: &str) -> Result<(), Self::Error>;
+}
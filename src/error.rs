@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use crate::domain::Id;
+
+/// Boxed error used to carry the failure of a repository or a poller.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The error type shared by the application layer.
+///
+/// Use `code` to match on the kind of failure.
+/// The original error can be obtained from `source` and downcast if needed,
+/// and is not in the message, so print it with `Report`.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to access the configurations")]
+    ConfigRepositoryError(#[source] BoxError),
+    #[error("failed to access the data")]
+    DataRepositoryError(#[source] BoxError),
+    #[error("failed to poll {id}")]
+    PollerError {
+        id: Id,
+        #[source]
+        source: BoxError,
+    },
+    #[error(transparent)]
+    ActorMessageError(#[from] ActorMessageError),
+}
+impl Error {
+    pub fn config_repository<E: Into<BoxError>>(e: E) -> Self {
+        Error::ConfigRepositoryError(e.into())
+    }
+    pub fn data_repository<E: Into<BoxError>>(e: E) -> Self {
+        Error::DataRepositoryError(e.into())
+    }
+    pub fn poller<E: Into<BoxError>>(id: Id, e: E) -> Self {
+        Error::PollerError {
+            id,
+            source: e.into(),
+        }
+    }
+
+    /// Returns the machine-readable code of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::ConfigRepositoryError(_) => ErrorCode::ConfigRepository,
+            Error::DataRepositoryError(_) => ErrorCode::DataRepository,
+            Error::PollerError { .. } => ErrorCode::Poller,
+            Error::ActorMessageError(_) => ErrorCode::ActorMessage,
+        }
+    }
+}
+
+/// Displays the error followed by its sources, e.g. `failed to poll shop: connection refused`.
+///
+/// A source whose message is already printed is skipped, since the errors of some crates embed their sources.
+pub struct Report<'a>(pub &'a (dyn std::error::Error + 'static));
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut message = self.0.to_string();
+        let mut source = self.0.source();
+        while let Some(e) = source {
+            let x = e.to_string();
+            if !message.contains(&x) {
+                message.push_str(": ");
+                message.push_str(&x);
+            }
+            source = e.source();
+        }
+        f.write_str(&message)
+    }
+}
+
+/// Stable machine-readable code of `Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCode {
+    ConfigRepository,
+    DataRepository,
+    Poller,
+    ActorMessage,
+}
+impl ErrorCode {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::ConfigRepository => "config_repository",
+            ErrorCode::DataRepository => "data_repository",
+            ErrorCode::Poller => "poller",
+            ErrorCode::ActorMessage => "actor_message",
+        }
+    }
+}
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ActorMessageError {
+    #[error("failed to send the message to the actor.")]
+    SendError,
+    #[error("failed to receive the message from the actor.")]
+    RecvError,
+}
@@ -0,0 +1,1244 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr as _;
+use std::sync::{Arc, Mutex};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use log::info;
+use serde_derive::Deserialize;
+
+use super::change_feeds::{self, RecentChange};
+use super::config_repository::toml_config_repository;
+use super::content_directory::ContentDirectory;
+use super::debug_bundles::{self, DebugBundles};
+use super::{
+    TomlConfigRepository, TomlConfigTrash, TomlDataRepository, TomlSubscriptionRepository,
+};
+use crate::domain::{
+    Config, ConfigEvent, ConfigRepository as _, Data, DataOrder, DataQuery, DataReader as _,
+    Duration, Id, PollOutput, Stats, Status, Subscription, Timestamp, Url, SUBSCRIBABLE_EVENTS,
+};
+
+/// The largest body of a request.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// The largest body of `POST /targets:batch`, which may have hundreds of targets.
+const MAX_BATCH_BYTES: usize = 4 * 1024 * 1024;
+/// The number of the recent events kept for `GET /updates`.
+const UPDATES_TO_KEEP: usize = 100;
+
+/// Serves the statistics and the recent changes of the targets over HTTP.
+///
+/// - `GET /targets` responds with the stored data of the targets, queried by `tag`, `status`, `updated_since`,
+///   `sort`, `limit` and `offset`.
+/// - `GET /targets/:id/stats` responds with the JSON of the summary of the statistics of the target.
+/// - `GET /targets/:id/debug` responds with `bundle.json` of the debug bundle of the latest failed poll of the target,
+///   and `GET /targets/:id/debug/:file` with a file in it, e.g. `screenshot.png`.
+/// - `GET /calendar.ics` responds with the iCalendar feed of the recent changes.
+/// - `GET /feed.atom` and `GET /feed.json` respond with the Atom feed and the JSON Feed of the recent changes.
+/// - `GET /subscriptions`, `POST /subscriptions` and `DELETE /subscriptions/:id` manage the webhook subscriptions.
+/// - `DELETE /targets/:id` moves the target into the trash, keeping its data, and `POST /targets/:id/restore` moves it back.
+/// - `POST /targets:batch` adds or updates the targets of `put` and moves those of `delete` into the trash, all or none of them.
+/// - `POST /targets:pause` and `POST /targets:resume` pause and resume the targets with the `tag` in the query at once.
+/// - `POST /notifiers/:name/test` sends a made-up change by the notifier, e.g. `webhook`, of the `target` in the query if given.
+/// - `GET /search?q=` responds with the lines of the latest contents having the text, of each target.
+/// - `GET /updates` responds with the recent events, the latest first, which occurred after `since` if given,
+///   for the automation services polling for new items like Zapier.
+///
+/// The changes and the events are of the targets with the `tag`, or of the `target`, if given in the query.
+///
+/// Every route requires the token if any, as `Authorization: Bearer <token>` or `?token=` for the feed readers and the calendars.
+/// The token of the viewer is also accepted, only by the routes reading the targets, the statistics, the feeds and the updates,
+/// e.g. for a dashboard which must not manage the subscriptions nor read the pages in the debug bundles.
+#[derive(Clone, Default)]
+pub struct ApiServer {
+    state: Arc<Mutex<State>>,
+    subscriptions: Option<Arc<tokio::sync::Mutex<TomlSubscriptionRepository>>>,
+    /// The bearer token required by every route.
+    token: Option<Arc<str>>,
+    /// The bearer token of the viewer, accepted only by the routes reading.
+    viewer_token: Option<Arc<str>>,
+    debug_bundles: Option<DebugBundles>,
+    /// The data file queried by `GET /targets`.
+    data_path: Option<PathBuf>,
+    /// The latest contents searched by `GET /search`.
+    contents: Option<ContentDirectory>,
+    targets: Option<Arc<TargetFiles>>,
+    /// Held while changing the targets, not to lose the changes made at once.
+    targets_lock: Arc<tokio::sync::Mutex<()>>,
+    notifier_test: Option<Arc<dyn NotifierTest>>,
+}
+/// Sends the test notifications of `POST /notifiers/:name/test` by the notifiers of the app.
+#[async_trait::async_trait]
+pub trait NotifierTest: Send + Sync {
+    /// Sends a made-up change of the target, if given, by the notifier of the name,
+    /// or returns `None` if no notifier of the name is set up.
+    async fn send(&self, name: &str, target: Option<(Id, Url)>) -> Option<Result<(), String>>;
+}
+/// The files of the targets managed by the API, which the app reloads after they are changed.
+#[derive(Debug)]
+pub struct TargetFiles {
+    pub config_path: PathBuf,
+    /// The file overlaying the read-only config file, if any.
+    pub managed_path: Option<PathBuf>,
+    /// The file keeping the removed targets.
+    pub trash_path: PathBuf,
+    /// How long the removed targets are kept in the trash. (default: kept)
+    pub retention: Option<Duration>,
+    /// Tells the app to reload the targets.
+    pub reload: tokio::sync::mpsc::UnboundedSender<()>,
+}
+#[derive(Debug, Default)]
+struct State {
+    configs: HashMap<Id, Config>,
+    stats: HashMap<Id, Stats>,
+    /// The latest contents polled while serving.
+    contents: HashMap<Id, PollOutput>,
+    /// The recent events, oldest first.
+    updates: VecDeque<Update>,
+}
+#[derive(Debug)]
+struct Update {
+    id: Id,
+    at: Timestamp,
+    json: serde_json::Value,
+}
+impl ApiServer {
+    pub fn new(configs: HashMap<Id, Config>, stats: HashMap<Id, Stats>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                configs,
+                stats,
+                contents: HashMap::new(),
+                updates: VecDeque::new(),
+            })),
+            subscriptions: None,
+            token: None,
+            viewer_token: None,
+            debug_bundles: None,
+            data_path: None,
+            contents: None,
+            targets: None,
+            targets_lock: Arc::default(),
+            notifier_test: None,
+        }
+    }
+
+    /// Manages the subscriptions in the repository, shared with the poster of the events.
+    pub fn with_subscriptions(
+        mut self,
+        subscriptions: Arc<tokio::sync::Mutex<TomlSubscriptionRepository>>,
+    ) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    /// Manages the targets in the files.
+    pub fn with_targets(mut self, targets: TargetFiles) -> Self {
+        self.targets = Some(Arc::new(targets));
+        self
+    }
+
+    /// Sends the test notifications by the notifiers.
+    pub fn with_notifier_test(mut self, notifier_test: impl NotifierTest + 'static) -> Self {
+        self.notifier_test = Some(Arc::new(notifier_test));
+        self
+    }
+
+    /// Lists the targets by their data in the file.
+    pub fn with_data(mut self, path: impl Into<PathBuf>) -> Self {
+        self.data_path = Some(path.into());
+        self
+    }
+
+    /// Searches the latest contents in the directory, shared with the keeper of them.
+    pub fn with_contents(mut self, contents: ContentDirectory) -> Self {
+        self.contents = Some(contents);
+        self
+    }
+
+    /// Serves the debug bundles of the failed polls in the directory.
+    pub fn with_debug_bundles(mut self, debug_bundles: DebugBundles) -> Self {
+        self.debug_bundles = Some(debug_bundles);
+        self
+    }
+
+    /// Requires every request to have the token as `Authorization: Bearer <token>` or `?token=`.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Also accepts the token of the viewer, only by the routes reading the targets, the statistics, the feeds and the updates.
+    pub fn with_viewer_token(mut self, token: &str) -> Self {
+        self.viewer_token = Some(token.into());
+        self
+    }
+
+    /// Replaces the statistics of the targets, keeping those of the others.
+    pub fn update_stats(&self, stats: HashMap<Id, Stats>) {
+        self.state.lock().unwrap().stats.extend(stats);
+    }
+
+    /// Replaces the latest content of the target, for the entry of its latest change.
+    pub fn update_content(&self, id: Id, content: PollOutput) {
+        let _ = self.state.lock().unwrap().contents.insert(id, content);
+    }
+
+    /// Adds the JSON of the event of the target which occurred at the time, forgetting the oldest beyond the limit.
+    pub fn push_update(&self, id: Id, at: Timestamp, json: serde_json::Value) {
+        let updates = &mut self.state.lock().unwrap().updates;
+        updates.push_back(Update { id, at, json });
+        while UPDATES_TO_KEEP < updates.len() {
+            let _ = updates.pop_front();
+        }
+    }
+
+    /// Applies the change of the configurations.
+    pub fn update_config(&self, event: ConfigEvent) {
+        let configs = &mut self.state.lock().unwrap().configs;
+        match event {
+            ConfigEvent::Added { id, config } | ConfigEvent::Updated { id, config } => {
+                let _ = configs.insert(id, config);
+            }
+            ConfigEvent::Removed { id, .. } => {
+                let _ = configs.remove(&id);
+            }
+        }
+    }
+
+    /// Binds the address, and returns the future serving the requests until it is dropped.
+    pub fn bind(self, addr: &SocketAddr) -> Result<impl Future<Output = Result<(), Error>>, Error> {
+        let builder = hyper::Server::try_bind(addr).map_err(Error::Bind)?;
+        let make_service = make_service_fn(move |_| {
+            let server = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.respond(request).await) }
+                }))
+            }
+        });
+        let serving = builder.serve(make_service);
+        Ok(async move { serving.await.map_err(Error::Serve) })
+    }
+
+    async fn respond(&self, request: Request<Body>) -> Response<Body> {
+        let role = match self.role(&request) {
+            Some(x) => x,
+            None => return respond_error(StatusCode::UNAUTHORIZED, "the token is wrong"),
+        };
+
+        // decoded after the split, so an encoded `/` stays in the segment, and is rejected by `Id`.
+        let segments: Result<Vec<_>, _> = request
+            .uri()
+            .path()
+            .trim_matches('/')
+            .split('/')
+            .map(|x| percent_encoding::percent_decode_str(x).decode_utf8())
+            .collect();
+        let segments = match segments {
+            Ok(x) => x,
+            Err(_) => return respond_error(StatusCode::BAD_REQUEST, "the path is not UTF-8"),
+        };
+        let segments: Vec<&str> = segments.iter().map(|x| x.as_ref()).collect();
+        let route = match segments.as_slice() {
+            ["targets"] => Route::Targets,
+            ["targets:batch"] => Route::Batch,
+            ["targets:pause"] => Route::Pause(true),
+            ["targets:resume"] => Route::Pause(false),
+            ["targets", id] => Route::Target(Id::try_from(id.to_string()).ok()),
+            ["targets", id, "restore"] => Route::Restore(Id::try_from(id.to_string()).ok()),
+            ["targets", id, "stats"] => Route::Stats(Id::try_from(id.to_string()).ok()),
+            ["targets", id, "debug"] => Route::Debug(
+                Id::try_from(id.to_string()).ok(),
+                debug_bundles::MANIFEST.to_owned(),
+            ),
+            ["targets", id, "debug", file] => {
+                Route::Debug(Id::try_from(id.to_string()).ok(), file.to_string())
+            }
+            ["calendar.ics"] => Route::Calendar,
+            ["feed.atom"] => Route::Feed,
+            ["feed.json"] => Route::JsonFeed,
+            ["updates"] => Route::Updates,
+            ["search"] => Route::Search,
+            ["subscriptions"] => Route::Subscriptions,
+            ["subscriptions", id] => Route::Subscription(Id::try_from(id.to_string()).ok()),
+            ["notifiers", name, "test"] => Route::NotifierTest(name.to_string()),
+            ["openapi.json"] => Route::OpenApi,
+            _ => return respond_error(StatusCode::NOT_FOUND, "not found"),
+        };
+        if !route.methods().contains(request.method()) {
+            return respond_error(StatusCode::METHOD_NOT_ALLOWED, "the method is not allowed");
+        }
+        if role == Role::Viewer && route.requires_admin(request.method()) {
+            return respond_error(
+                StatusCode::FORBIDDEN,
+                "the token of the viewer cannot use the route",
+            );
+        }
+        if let Route::OpenApi = route {
+            return respond_json(StatusCode::OK, openapi().to_string());
+        }
+        if let Route::Subscriptions | Route::Subscription(_) = route {
+            return self.respond_subscriptions(route, request).await;
+        }
+        if let Route::Debug(id, file) = route {
+            return self.respond_debug(id, &file).await;
+        }
+        if let (Route::Target(id), &Method::GET) = (&route, request.method()) {
+            return self.respond_target_data(id.clone()).await;
+        }
+        if let Route::Target(_) | Route::Restore(_) | Route::Batch | Route::Pause(_) = route {
+            return self.respond_targets(route, request).await;
+        }
+
+        let query = request.uri().query().unwrap_or_default();
+        let param = |name: &str| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let selection = match (param("tag"), param("target")) {
+            (Some(tag), _) => Selection::Tag(tag),
+            (None, Some(target)) => match Id::try_from(target) {
+                Ok(id) => Selection::Target(id),
+                Err(e) => return respond_error(StatusCode::BAD_REQUEST, &e.to_string()),
+            },
+            (None, None) => Selection::All,
+        };
+
+        match route {
+            Route::Search => match param("q") {
+                Some(text) if !text.trim().is_empty() => self.respond_search(&text).await,
+                _ => respond_error(StatusCode::BAD_REQUEST, "q is required"),
+            },
+            Route::Targets => {
+                let query = match self.data_query(&param) {
+                    Ok(x) => x,
+                    Err(message) => return respond_error(StatusCode::BAD_REQUEST, &message),
+                };
+                self.respond_targets_data(query).await
+            }
+            Route::NotifierTest(name) => self.respond_notifier_test(&name, param("target")).await,
+            Route::Stats(id) => self.respond_stats(id),
+            Route::Calendar => {
+                let calendar = self.with_changes(&selection, |changes, now| {
+                    change_feeds::calendar(changes, now)
+                });
+                respond_text(calendar, "text/calendar; charset=utf-8")
+            }
+            Route::Feed => {
+                let feed = self.with_changes(&selection, |changes, now| {
+                    change_feeds::atom(changes, &selection.to_string(), now)
+                });
+                respond_text(feed, "application/atom+xml; charset=utf-8")
+            }
+            Route::JsonFeed => {
+                let feed = self.with_changes(&selection, |changes, _| {
+                    change_feeds::json_feed(changes, &selection.to_string()).to_string()
+                });
+                respond_text(feed, "application/feed+json; charset=utf-8")
+            }
+            Route::Updates => {
+                let since = match param("since").map(|x| parse_time(&x)) {
+                    Some(Ok(x)) => Some(x),
+                    Some(Err(message)) => return respond_error(StatusCode::BAD_REQUEST, message),
+                    None => None,
+                };
+                self.respond_updates(&selection, since)
+            }
+            Route::Subscriptions
+            | Route::Subscription(_)
+            | Route::Debug(..)
+            | Route::Target(_)
+            | Route::Restore(_)
+            | Route::Batch
+            | Route::Pause(_)
+            | Route::OpenApi => unreachable!(),
+        }
+    }
+
+    async fn respond_debug(&self, id: Option<Id>, file: &str) -> Response<Body> {
+        let debug_bundles = match self.debug_bundles.as_ref() {
+            Some(x) => x,
+            None => {
+                return respond_error(StatusCode::NOT_FOUND, "the debug bundles are not enabled")
+            }
+        };
+        let result = match id {
+            Some(id) => debug_bundles.read(&id, file).await,
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        };
+        match result {
+            Ok(bytes) => {
+                let mut response = Response::new(Body::from(bytes));
+                let _ = response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    header::HeaderValue::from_static(debug_bundles::content_type(file)),
+                );
+                response
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                respond_error(StatusCode::NOT_FOUND, "no debug bundle has the file")
+            }
+            Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    /// The query of `GET /targets` by the parameters, whose `tag` is of the targets served.
+    fn data_query(&self, param: &dyn Fn(&str) -> Option<String>) -> Result<DataQuery, String> {
+        let ids = param("tag").map(|tag| {
+            let configs = &self.state.lock().unwrap().configs;
+            configs
+                .iter()
+                .filter(|(_, x)| x.tags.contains(&tag))
+                .map(|(id, _)| id.clone())
+                .collect()
+        });
+        let statuses = match param("status") {
+            Some(x) => x
+                .split(',')
+                .map(Status::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?,
+            None => Vec::new(),
+        };
+        let updated_since = match param("updated_since") {
+            Some(x) => Some(parse_time(&x)?),
+            None => None,
+        };
+        let order = match param("sort").as_deref() {
+            None | Some("id") => DataOrder::Id,
+            Some("last_updated") => DataOrder::LastUpdated,
+            Some("last_checked") => DataOrder::LastChecked,
+            Some(_) => return Err("sort must be id, last_updated or last_checked".to_owned()),
+        };
+        let number = |name: &str| match param(name) {
+            Some(x) => x
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("{name} must be a number")),
+            None => Ok(None),
+        };
+        Ok(DataQuery {
+            ids,
+            statuses,
+            updated_since,
+            order,
+            offset: number("offset")?.unwrap_or_default(),
+            limit: number("limit")?,
+        })
+    }
+
+    async fn respond_targets_data(&self, query: DataQuery) -> Response<Body> {
+        let path = match self.data_path.as_ref() {
+            Some(x) => x,
+            None => return respond_error(StatusCode::NOT_FOUND, "the data are not served"),
+        };
+        let (offset, limit) = (query.offset, query.limit);
+        let page = match TomlDataRepository::new(path).await {
+            Ok(repo) => repo.query(query).await,
+            Err(e) => Err(e),
+        };
+        let page = match page {
+            Ok(x) => x,
+            Err(e) => return respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+        let targets: Vec<_> = page
+            .into_iter()
+            .map(|(id, data)| self.target_json(&id, &data))
+            .collect();
+        let json = serde_json::json!({ "targets": targets, "offset": offset, "limit": limit });
+        respond_json(StatusCode::OK, json.to_string())
+    }
+
+    async fn respond_target_data(&self, id: Option<Id>) -> Response<Body> {
+        let path = match self.data_path.as_ref() {
+            Some(x) => x,
+            None => return respond_error(StatusCode::NOT_FOUND, "the data are not served"),
+        };
+        let data = match id {
+            Some(id) => match TomlDataRepository::new(path).await {
+                Ok(repo) => repo.get(id.clone()).await.map(|x| x.map(|x| (id, x))),
+                Err(e) => Err(e),
+            },
+            None => Ok(None),
+        };
+        match data {
+            Ok(Some((id, data))) => {
+                respond_json(StatusCode::OK, self.target_json(&id, &data).to_string())
+            }
+            Ok(None) => respond_error(StatusCode::NOT_FOUND, "no target by the id"),
+            Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    /// The JSON of the target in `GET /targets` and `GET /targets/:id`.
+    fn target_json(&self, id: &Id, data: &Data) -> serde_json::Value {
+        let configs = &self.state.lock().unwrap().configs;
+        let config = configs.get(id);
+        serde_json::json!({
+            "id": id.as_str(),
+            "url": config.map(|x| x.url.as_str()),
+            "tags": config.map(|x| x.tags.clone()).unwrap_or_default(),
+            "status": data.status.as_str(),
+            "title": data.title,
+            "favicon": data.favicon.as_ref().map(|x| x.as_str()),
+            "last_updated": data.last_updated,
+            "last_checked": data.last_checked,
+        })
+    }
+
+    async fn respond_search(&self, text: &str) -> Response<Body> {
+        let contents = match self.contents.as_ref() {
+            Some(x) => x,
+            None => return respond_error(StatusCode::NOT_FOUND, "the contents are not kept"),
+        };
+        match contents.search(text).await {
+            Ok(found) => {
+                let results: Vec<_> = found
+                    .into_iter()
+                    .map(|(id, lines)| serde_json::json!({ "id": id.as_str(), "lines": lines }))
+                    .collect();
+                let json = serde_json::json!({ "results": results });
+                respond_json(StatusCode::OK, json.to_string())
+            }
+            Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    async fn respond_notifier_test(&self, name: &str, target: Option<String>) -> Response<Body> {
+        let notifier_test = match self.notifier_test.as_ref() {
+            Some(x) => x,
+            None => return respond_error(StatusCode::NOT_FOUND, "no notifier is set up"),
+        };
+        let target = match target.map(Id::try_from) {
+            Some(Ok(id)) => match self.state.lock().unwrap().configs.get(&id) {
+                Some(config) => Some((id, config.url.clone())),
+                None => return respond_error(StatusCode::NOT_FOUND, "no target by the id"),
+            },
+            Some(Err(e)) => return respond_error(StatusCode::BAD_REQUEST, &e.to_string()),
+            None => None,
+        };
+        match notifier_test.send(name, target).await {
+            Some(Ok(())) => {
+                info!("sent a test notification by {name}.");
+                let mut response = Response::new(Body::empty());
+                *response.status_mut() = StatusCode::NO_CONTENT;
+                response
+            }
+            Some(Err(message)) => respond_error(StatusCode::BAD_GATEWAY, &message),
+            None => respond_error(StatusCode::NOT_FOUND, "no notifier of the name is set up"),
+        }
+    }
+
+    async fn respond_targets(&self, route: Route, request: Request<Body>) -> Response<Body> {
+        let files = match self.targets.as_ref() {
+            Some(x) => x,
+            None => return respond_error(StatusCode::NOT_FOUND, "the targets are not managed"),
+        };
+        let tag = match route {
+            Route::Pause(_) => {
+                let query = request.uri().query().unwrap_or_default();
+                let tag = url::form_urlencoded::parse(query.as_bytes())
+                    .find(|(key, _)| key == "tag")
+                    .map(|(_, value)| value.into_owned());
+                match tag {
+                    Some(x) => Some(x),
+                    None => return respond_error(StatusCode::BAD_REQUEST, "tag is required"),
+                }
+            }
+            _ => None,
+        };
+        let batch = match route {
+            Route::Batch => {
+                let body = match read_body(request.into_body(), MAX_BATCH_BYTES).await {
+                    Ok(x) => x,
+                    Err(message) => return respond_error(StatusCode::BAD_REQUEST, &message),
+                };
+                let batch: Batch = match serde_json::from_slice(&body) {
+                    Ok(x) => x,
+                    Err(e) => return respond_error(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                if let Some(id) = batch.delete.iter().find(|x| batch.put.contains_key(*x)) {
+                    let message = format!("{id} is both put and deleted");
+                    return respond_error(StatusCode::BAD_REQUEST, &message);
+                }
+                Some(batch)
+            }
+            _ => None,
+        };
+        let _lock = self.targets_lock.lock().await;
+        let opened = async {
+            let config_repo =
+                TomlConfigRepository::open(&files.config_path, files.managed_path.as_deref())
+                    .await?;
+            let mut trash = TomlConfigTrash::new(&files.trash_path).await?;
+            if let Some(retention) = files.retention {
+                // a retention longer than the range of the time purges none.
+                if let Some(before) = Timestamp::now().checked_sub(retention) {
+                    for id in trash.purge(before).await? {
+                        info!("[{id}]: purged from the trash.");
+                    }
+                }
+            }
+            Ok::<_, toml_config_repository::Error>((config_repo, trash))
+        };
+        let (mut config_repo, mut trash) = match opened.await {
+            Ok(x) => x,
+            Err(e) => return respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+
+        let result = match route {
+            Route::Batch => {
+                let Batch { put, delete } = batch.unwrap();
+                let mut configs = match config_repo.get_all().await {
+                    Ok(x) => x,
+                    Err(e) => return respond_targets_error(e),
+                };
+                let mut removed = HashMap::new();
+                for id in delete.iter() {
+                    match configs.remove(id) {
+                        Some(config) => {
+                            let _ = removed.insert(id.clone(), config);
+                        }
+                        None => {
+                            let message = format!("no target by the id {id}");
+                            return respond_error(StatusCode::NOT_FOUND, &message);
+                        }
+                    }
+                }
+                let (put_count, delete_count) = (put.len(), delete.len());
+                let mut changes: HashMap<_, _> =
+                    put.into_iter().map(|(id, x)| (id, Some(x))).collect();
+                changes.extend(delete.iter().map(|id| (id.clone(), None)));
+                // kept in the trash first, not to lose them if the config fails to be saved.
+                let kept = match removed.len() {
+                    0 => Ok(()),
+                    _ => trash.put(removed, Timestamp::now()).await,
+                };
+                match kept {
+                    Ok(()) => match config_repo.apply(changes).await {
+                        Ok(()) => {
+                            info!("{put_count} targets put and {delete_count} deleted at once.");
+                            Ok(None)
+                        }
+                        Err(e) => {
+                            if 0 < delete.len() {
+                                let _ = trash.take(&delete).await;
+                            }
+                            Err(e)
+                        }
+                    },
+                    Err(e) => Err(e),
+                }
+            }
+            Route::Pause(paused) => {
+                let tag = tag.unwrap();
+                let configs = match config_repo.get_all().await {
+                    Ok(x) => x,
+                    Err(e) => return respond_targets_error(e),
+                };
+                let mut ids: Vec<_> = configs
+                    .iter()
+                    .filter(|(_, x)| x.tags.contains(&tag))
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                ids.sort();
+                let changes = configs
+                    .into_iter()
+                    .filter(|(_, x)| x.tags.contains(&tag) && x.paused != paused)
+                    .map(|(id, x)| (id, Some(Config { paused, ..x })))
+                    .collect();
+                match config_repo.apply(changes).await {
+                    Ok(()) => {
+                        let ids: Vec<_> = ids.iter().map(Id::as_str).collect();
+                        Ok(Some(serde_json::json!({ "targets": ids })))
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Route::Target(Some(id)) => {
+                let config = match config_repo.get_all().await.map(|mut x| x.remove(&id)) {
+                    Ok(Some(x)) => x,
+                    Ok(None) => return respond_error(StatusCode::NOT_FOUND, "no target by the id"),
+                    Err(e) => return respond_targets_error(e),
+                };
+                // kept in the trash first, not to lose it if the config fails to be saved.
+                let removed = HashMap::from([(id.clone(), config)]);
+                match trash.put(removed, Timestamp::now()).await {
+                    Ok(()) => match config_repo.delete(id.clone()).await {
+                        Ok(_) => {
+                            info!("[{id}]: removed into the trash.");
+                            Ok(None)
+                        }
+                        Err(e) => {
+                            let _ = trash.take(&[id]).await;
+                            Err(e)
+                        }
+                    },
+                    Err(e) => Err(e),
+                }
+            }
+            Route::Restore(Some(id)) => {
+                match config_repo.get_all().await.map(|x| x.contains_key(&id)) {
+                    Ok(true) => {
+                        return respond_error(StatusCode::CONFLICT, "a target has the id already")
+                    }
+                    Ok(false) => (),
+                    Err(e) => return respond_targets_error(e),
+                }
+                let config = match trash
+                    .take(std::slice::from_ref(&id))
+                    .await
+                    .map(|mut x| x.remove(&id))
+                {
+                    Ok(Some(x)) => x,
+                    Ok(None) => {
+                        return respond_error(
+                            StatusCode::NOT_FOUND,
+                            "no target in the trash by the id",
+                        )
+                    }
+                    Err(e) => return respond_targets_error(e),
+                };
+                match config_repo.update(id.clone(), config.clone()).await {
+                    Ok(()) => {
+                        info!("[{id}]: restored from the trash.");
+                        Ok(None)
+                    }
+                    Err(e) => {
+                        let _ = trash
+                            .put(HashMap::from([(id, config)]), Timestamp::now())
+                            .await;
+                        Err(e)
+                    }
+                }
+            }
+            Route::Target(None) | Route::Restore(None) => {
+                return respond_error(StatusCode::NOT_FOUND, "no target by the id")
+            }
+            _ => unreachable!(),
+        };
+        match result {
+            Ok(json) => {
+                // the app polls the targets as changed in the files.
+                let _ = files.reload.send(());
+                match json {
+                    Some(json) => respond_json(StatusCode::OK, json.to_string()),
+                    None => {
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::NO_CONTENT;
+                        response
+                    }
+                }
+            }
+            Err(e) => respond_targets_error(e),
+        }
+    }
+
+    /// The role of the token of the request, or `None` if it has neither the token nor the token of the viewer.
+    fn role(&self, request: &Request<Body>) -> Option<Role> {
+        let token = match self.token.as_ref() {
+            Some(x) => x,
+            None => return Some(Role::Admin),
+        };
+        let bearer = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.strip_prefix("Bearer "))
+            .map(str::to_owned);
+        let given = bearer.or_else(|| {
+            let query = request.uri().query().unwrap_or_default();
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "token")
+                .map(|(_, value)| value.into_owned())
+        });
+        let given = given?;
+        if equals_in_constant_time(&given, token) {
+            return Some(Role::Admin);
+        }
+        match self.viewer_token.as_ref() {
+            Some(x) if equals_in_constant_time(&given, x) => Some(Role::Viewer),
+            _ => None,
+        }
+    }
+
+    async fn respond_subscriptions(&self, route: Route, request: Request<Body>) -> Response<Body> {
+        let repo = match self.subscriptions.as_ref() {
+            Some(x) => x,
+            None => {
+                return respond_error(StatusCode::NOT_FOUND, "the subscriptions are not enabled")
+            }
+        };
+
+        match (route, request.method().clone()) {
+            (Route::Subscriptions, Method::GET) => {
+                let list: Vec<_> = repo
+                    .lock()
+                    .await
+                    .list()
+                    .into_iter()
+                    .map(|(id, x)| subscription_json(&id, x))
+                    .collect();
+                respond_json(StatusCode::OK, serde_json::Value::from(list).to_string())
+            }
+            (Route::Subscriptions, _) => {
+                let body = match read_body(request.into_body(), MAX_BODY_BYTES).await {
+                    Ok(x) => x,
+                    Err(message) => return respond_error(StatusCode::BAD_REQUEST, &message),
+                };
+                let subscription: Subscription = match serde_json::from_slice(&body) {
+                    Ok(x) => x,
+                    Err(e) => return respond_error(StatusCode::BAD_REQUEST, &e.to_string()),
+                };
+                if !subscription.url.is_http() {
+                    return respond_error(StatusCode::BAD_REQUEST, "the url must be http or https");
+                }
+                if let Some(x) = subscription
+                    .events
+                    .iter()
+                    .find(|x| !SUBSCRIBABLE_EVENTS.contains(&x.as_str()))
+                {
+                    let message = format!(
+                        "unknown event {x}, which must be one of {}",
+                        SUBSCRIBABLE_EVENTS.join(", ")
+                    );
+                    return respond_error(StatusCode::BAD_REQUEST, &message);
+                }
+
+                let result = repo.lock().await.add(subscription.clone()).await;
+                match result {
+                    Ok(id) => {
+                        info!("[{id}]: subscribed by {}.", subscription.url.as_str());
+                        let json = subscription_json(&id, subscription);
+                        respond_json(StatusCode::CREATED, json.to_string())
+                    }
+                    Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+                }
+            }
+            (Route::Subscription(id), _) => {
+                let result = match id {
+                    Some(id) => repo.lock().await.remove(&id).await.map(|x| x.map(|_| id)),
+                    None => Ok(None),
+                };
+                match result {
+                    Ok(Some(id)) => {
+                        info!("[{id}]: unsubscribed.");
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::NO_CONTENT;
+                        response
+                    }
+                    Ok(None) => respond_error(StatusCode::NOT_FOUND, "no subscription by the id"),
+                    Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn respond_stats(&self, id: Option<Id>) -> Response<Body> {
+        let stats = id.and_then(|id| self.state.lock().unwrap().stats.get(&id).cloned());
+        let summary = match stats {
+            Some(x) => x.summary(Timestamp::now()),
+            None => {
+                return respond_error(StatusCode::NOT_FOUND, "no target has been polled by the id")
+            }
+        };
+        match serde_json::to_string(&summary) {
+            Ok(json) => respond_json(StatusCode::OK, json),
+            Err(e) => respond_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    fn respond_updates(&self, selection: &Selection, since: Option<Timestamp>) -> Response<Body> {
+        let state = self.state.lock().unwrap();
+        let mut updates: Vec<_> = state
+            .updates
+            .iter()
+            .filter(|x| since.map(|since| since < x.at).unwrap_or(true))
+            .filter(|x| match selection {
+                Selection::All => true,
+                Selection::Tag(tag) => state
+                    .configs
+                    .get(&x.id)
+                    .map(|config| config.tags.contains(tag))
+                    .unwrap_or(false),
+                Selection::Target(target) => &x.id == target,
+            })
+            .collect();
+        // the order received may differ from the order occurred a little.
+        updates.sort_by_key(|x| std::cmp::Reverse(x.at));
+        let list: Vec<_> = updates.into_iter().map(|x| x.json.clone()).collect();
+        respond_json(StatusCode::OK, serde_json::Value::from(list).to_string())
+    }
+
+    /// Formats the recent changes of the selected targets, oldest first.
+    fn with_changes<F>(&self, selection: &Selection, format: F) -> String
+    where
+        F: FnOnce(&[RecentChange<'_>], Timestamp) -> String,
+    {
+        let state = self.state.lock().unwrap();
+        let mut changes = Vec::new();
+        for (id, config) in state.configs.iter() {
+            let selected = match selection {
+                Selection::All => true,
+                Selection::Tag(tag) => config.tags.contains(tag),
+                Selection::Target(target) => id == target,
+            };
+            let stats = match state.stats.get(id) {
+                Some(x) if selected => x,
+                _ => continue,
+            };
+            let latest = stats.changes.iter().max();
+            changes.extend(stats.changes.iter().map(|changed_at| RecentChange {
+                id,
+                url: &config.url,
+                tags: &config.tags,
+                changed_at: *changed_at,
+                content: match Some(changed_at) == latest {
+                    true => state.contents.get(id).map(PollOutput::text),
+                    false => None,
+                },
+            }));
+        }
+        changes.sort_by_key(|x| x.changed_at);
+
+        format(&changes, Timestamp::now())
+    }
+}
+
+enum Route {
+    Targets,
+    Stats(Option<Id>),
+    Calendar,
+    Feed,
+    JsonFeed,
+    Updates,
+    Subscriptions,
+    Subscription(Option<Id>),
+    /// The file of the debug bundle of the target.
+    Debug(Option<Id>, String),
+    Target(Option<Id>),
+    /// Restores the target from the trash.
+    Restore(Option<Id>),
+    /// Sends a test notification by the notifier of the name.
+    NotifierTest(String),
+    Search,
+    /// Changes many targets at once.
+    Batch,
+    /// Pauses the targets with the tag if `true`, or resumes them.
+    Pause(bool),
+    /// The OpenAPI document of the routes.
+    OpenApi,
+}
+
+/// The body of `POST /targets:batch`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Batch {
+    /// The targets added or updated, in the tables of the config file.
+    #[serde(default, deserialize_with = "toml_config_repository::parse_configs")]
+    put: HashMap<Id, Config>,
+    /// The ids of the targets moved into the trash.
+    #[serde(default)]
+    delete: Vec<Id>,
+}
+
+impl Route {
+    /// Every route, which the OpenAPI document lists.
+    fn all() -> Vec<Route> {
+        vec![
+            Route::Targets,
+            Route::Target(None),
+            Route::Restore(None),
+            Route::Stats(None),
+            Route::Debug(None, debug_bundles::MANIFEST.to_owned()),
+            Route::Debug(None, String::new()),
+            Route::Batch,
+            Route::Pause(true),
+            Route::Pause(false),
+            Route::Search,
+            Route::Calendar,
+            Route::Feed,
+            Route::JsonFeed,
+            Route::Updates,
+            Route::Subscriptions,
+            Route::Subscription(None),
+            Route::NotifierTest(String::new()),
+            Route::OpenApi,
+        ]
+    }
+
+    /// The path of the route, with the parameters in it like `{id}`.
+    fn path(&self) -> &'static str {
+        match self {
+            Route::Targets => "/targets",
+            Route::Target(_) => "/targets/{id}",
+            Route::Restore(_) => "/targets/{id}/restore",
+            Route::Stats(_) => "/targets/{id}/stats",
+            Route::Debug(_, file) if file == debug_bundles::MANIFEST => "/targets/{id}/debug",
+            Route::Debug(..) => "/targets/{id}/debug/{file}",
+            Route::Batch => "/targets:batch",
+            Route::Pause(true) => "/targets:pause",
+            Route::Pause(false) => "/targets:resume",
+            Route::Search => "/search",
+            Route::Calendar => "/calendar.ics",
+            Route::Feed => "/feed.atom",
+            Route::JsonFeed => "/feed.json",
+            Route::Updates => "/updates",
+            Route::Subscriptions => "/subscriptions",
+            Route::Subscription(_) => "/subscriptions/{id}",
+            Route::NotifierTest(_) => "/notifiers/{name}/test",
+            Route::OpenApi => "/openapi.json",
+        }
+    }
+
+    /// The methods allowed on the route.
+    fn methods(&self) -> Vec<Method> {
+        match self {
+            Route::Subscriptions => vec![Method::GET, Method::POST],
+            Route::Subscription(_) => vec![Method::DELETE],
+            Route::Target(_) => vec![Method::GET, Method::DELETE],
+            Route::Restore(_) | Route::NotifierTest(_) | Route::Batch | Route::Pause(_) => {
+                vec![Method::POST]
+            }
+            _ => vec![Method::GET],
+        }
+    }
+
+    /// What the route does with the method, as the summary of the operation.
+    fn summary(&self, method: &Method) -> &'static str {
+        match self {
+            Route::Targets => "List the targets by their data",
+            Route::Target(_) if method == Method::GET => "Get the target by its data",
+            Route::Target(_) => "Move the target into the trash",
+            Route::Restore(_) => "Restore the target from the trash",
+            Route::Stats(_) => "Get the statistics of the polls of the target",
+            Route::Debug(_, file) if file == debug_bundles::MANIFEST => {
+                "Get the manifest of the debug bundle of the target"
+            }
+            Route::Debug(..) => "Get the file of the debug bundle of the target",
+            Route::Batch => "Put and delete many targets at once",
+            Route::Pause(true) => "Pause the targets with the tag",
+            Route::Pause(false) => "Resume the targets with the tag",
+            Route::Search => "Search the latest contents",
+            Route::Calendar => "The recent changes as an iCalendar feed",
+            Route::Feed => "The recent changes as an Atom feed",
+            Route::JsonFeed => "The recent changes as a JSON Feed",
+            Route::Updates => "The recent events",
+            Route::Subscriptions if method == Method::GET => "List the webhook subscriptions",
+            Route::Subscriptions => "Subscribe a webhook to the events",
+            Route::Subscription(_) => "Remove the webhook subscription",
+            Route::NotifierTest(_) => "Send a test notification by the notifier",
+            Route::OpenApi => "This OpenAPI document",
+        }
+    }
+
+    /// The parameters of the query taken by the route.
+    fn query(&self) -> &'static [&'static str] {
+        match self {
+            Route::Targets => &["tag", "status", "updated_since", "sort", "limit", "offset"],
+            Route::Pause(_) => &["tag"],
+            Route::Search => &["q"],
+            Route::Calendar | Route::Feed | Route::JsonFeed => &["tag", "target"],
+            Route::Updates => &["tag", "target", "since"],
+            Route::NotifierTest(_) => &["target"],
+            _ => &[],
+        }
+    }
+
+    /// Whether the route needs the token of the admin with the method,
+    /// which is the case for every route but those reading the targets, the statistics, the feeds and the updates.
+    fn requires_admin(&self, method: &Method) -> bool {
+        let reading = matches!(
+            self,
+            Route::Targets
+                | Route::Target(_)
+                | Route::Stats(_)
+                | Route::OpenApi
+                | Route::Calendar
+                | Route::Feed
+                | Route::JsonFeed
+                | Route::Updates
+        );
+        !reading || method != Method::GET
+    }
+}
+
+/// Who the token of a request is of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Uses every route.
+    Admin,
+    /// Only reads the targets, the statistics, the feeds and the updates.
+    Viewer,
+}
+
+/// The targets whose changes are served.
+enum Selection {
+    All,
+    Tag(String),
+    Target(Id),
+}
+impl Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selection::All => f.write_str("all"),
+            Selection::Tag(tag) => f.write_fmt(format_args!("tag:{tag}")),
+            Selection::Target(id) => f.write_fmt(format_args!("target:{id}")),
+        }
+    }
+}
+
+/// Parses the time in the unix milliseconds or in RFC 3339.
+fn parse_time(text: &str) -> Result<Timestamp, &'static str> {
+    if let Ok(millis) = text.parse::<i64>() {
+        return Timestamp::checked_from_unix_millis(millis)
+            .filter(|_| 0 <= millis)
+            .ok_or("the time must not be negative nor too far in the future");
+    }
+    serde::Deserialize::deserialize(serde_json::Value::from(text))
+        .map_err(|_| "the time must be in the unix milliseconds or in RFC 3339")
+}
+
+/// The JSON of the subscription with its id, without the secret.
+fn subscription_json(id: &Id, subscription: Subscription) -> serde_json::Value {
+    let mut value = serde_json::to_value(Subscription {
+        secret: None,
+        ..subscription
+    })
+    .unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        let _ = object.insert("id".to_owned(), id.as_str().into());
+    }
+    value
+}
+
+/// Reads the body up to the limit of the bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        if limit < bytes.len() + chunk.len() {
+            return Err(format!("the body must be at most {limit} bytes"));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Compares the token without leaking where it differs by the time.
+fn equals_in_constant_time(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |x, (a, b)| x | (a ^ b)) == 0
+}
+
+/// The OpenAPI document of every route, each of which takes the token by either way of `with_token`.
+fn openapi() -> serde_json::Value {
+    let mut paths = serde_json::Map::new();
+    for route in Route::all() {
+        let path = route.path();
+        let mut parameters: Vec<_> = path
+            .split(['{', '}'])
+            .skip(1)
+            .step_by(2)
+            .map(|name| {
+                serde_json::json!({
+                    "name": name, "in": "path", "required": true, "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        parameters.extend(route.query().iter().map(|name| {
+            serde_json::json!({ "name": name, "in": "query", "schema": { "type": "string" } })
+        }));
+        let operations: serde_json::Map<_, _> = route
+            .methods()
+            .iter()
+            .map(|method| {
+                let operation = serde_json::json!({
+                    "summary": route.summary(method),
+                    "parameters": parameters,
+                    "responses": {
+                        "default": { "description": "the response, or the JSON of the error like `{\"error\": \"...\"}`" },
+                    },
+                });
+                (method.as_str().to_ascii_lowercase(), operation)
+            })
+            .collect();
+        let _ = paths.insert(path.to_owned(), operations.into());
+    }
+    serde_json::json!({
+        "openapi": "3.0.3",
+        "info": { "title": "patrol", "version": env!("CARGO_PKG_VERSION") },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+                "query": { "type": "apiKey", "in": "query", "name": "token" },
+            },
+        },
+        "security": [{ "bearer": [] }, { "query": [] }],
+    })
+}
+
+fn respond_json(status: StatusCode, json: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(json));
+    *response.status_mut() = status;
+    let _ = response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn respond_text(text: String, content_type: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(text));
+    let _ = response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(content_type),
+    );
+    response
+}
+
+fn respond_error(status: StatusCode, message: &str) -> Response<Body> {
+    let json = serde_json::json!({ "error": message }).to_string();
+    respond_json(status, json)
+}
+
+/// Responds with the error of changing the targets, which is a conflict if the target cannot be changed.
+fn respond_targets_error(e: toml_config_repository::Error) -> Response<Body> {
+    let status = match e {
+        toml_config_repository::Error::ReadOnly(_)
+        | toml_config_repository::Error::Dependency(_) => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    respond_error(status, &e.to_string())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Bind(hyper::Error),
+    Serve(hyper::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Bind(e) => f.write_fmt(format_args!("failed to bind the API server: {e}")),
+            Error::Serve(e) => f.write_fmt(format_args!("the API server stopped: {e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Bind(e) => Some(e),
+            Error::Serve(e) => Some(e),
+        }
+    }
+}
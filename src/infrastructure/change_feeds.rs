@@ -0,0 +1,203 @@
+use crate::domain::{Id, Timestamp, Url};
+
+/// The longest line of iCalendar in octets, beyond which it is folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A recent change of a target, an event of the calendar or an entry of the feed.
+#[derive(Debug, Clone)]
+pub struct RecentChange<'a> {
+    pub id: &'a Id,
+    pub url: &'a Url,
+    pub tags: &'a [String],
+    pub changed_at: Timestamp,
+    /// The content after the change, if it is still the latest one.
+    pub content: Option<&'a str>,
+}
+
+/// Formats the changes as an iCalendar feed of RFC 5545, an event at the time of each change.
+pub fn calendar(changes: &[RecentChange<'_>], now: Timestamp) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//patrol//changes//EN".to_owned(),
+        "X-WR-CALNAME:patrol changes".to_owned(),
+    ];
+    for change in changes.iter() {
+        let at = format_time(change.changed_at);
+        lines.push("BEGIN:VEVENT".to_owned());
+        // the uid stays the same for the change, as the calendar clients update the events by it.
+        lines.push(format!(
+            "UID:{}-{}@patrol",
+            escape(change.id.as_str()),
+            change.changed_at.unix_secs()
+        ));
+        lines.push(format!("DTSTAMP:{}", format_time(now)));
+        lines.push(format!("DTSTART:{at}"));
+        lines.push(format!("DTEND:{at}"));
+        lines.push(format!("SUMMARY:{} changed", escape(change.id.as_str())));
+        lines.push(format!("URL:{}", change.url.as_str()));
+        if 0 < change.tags.len() {
+            let tags: Vec<_> = change.tags.iter().map(|x| escape(x)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    let mut calendar = String::new();
+    for line in lines.iter() {
+        fold(&mut calendar, line);
+    }
+    calendar
+}
+
+/// Formats the changes as an Atom feed of RFC 4287, an entry of each change, the latest first.
+///
+/// The id of the feed is unique to the selection of the changes, e.g. `all`, `tag:shop` or `target:example`.
+pub fn atom(changes: &[RecentChange<'_>], selection: &str, now: Timestamp) -> String {
+    let updated = changes.iter().map(|x| x.changed_at).max().unwrap_or(now);
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    feed.push_str(&format!(
+        "  <id>urn:patrol:feed:{}</id>\n",
+        escape_xml(selection)
+    ));
+    feed.push_str(&format!(
+        "  <title>patrol changes ({})</title>\n",
+        escape_xml(selection)
+    ));
+    feed.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        format_rfc3339(updated)
+    ));
+    feed.push_str("  <author><name>patrol</name></author>\n");
+
+    let mut changes: Vec<_> = changes.iter().collect();
+    changes.sort_by_key(|x| std::cmp::Reverse(x.changed_at));
+    for change in changes.into_iter() {
+        let id = escape_xml(change.id.as_str());
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!(
+            "    <id>urn:patrol:change:{id}:{}</id>\n",
+            change.changed_at.unix_secs()
+        ));
+        feed.push_str(&format!("    <title>{id} changed</title>\n"));
+        feed.push_str(&format!(
+            "    <link href=\"{}\"/>\n",
+            escape_xml(change.url.as_str())
+        ));
+        feed.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            format_rfc3339(change.changed_at)
+        ));
+        for tag in change.tags.iter() {
+            feed.push_str(&format!("    <category term=\"{}\"/>\n", escape_xml(tag)));
+        }
+        if let Some(content) = change.content {
+            feed.push_str(&format!(
+                "    <content type=\"text\">{}</content>\n",
+                escape_xml(content)
+            ));
+        }
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+/// Formats the changes as a JSON Feed of version 1.1, an item of each change, the latest first.
+///
+/// The selection is of `atom`.
+pub fn json_feed(changes: &[RecentChange<'_>], selection: &str) -> serde_json::Value {
+    let mut changes: Vec<_> = changes.iter().collect();
+    changes.sort_by_key(|x| std::cmp::Reverse(x.changed_at));
+    let items: Vec<_> = changes
+        .into_iter()
+        .map(|change| {
+            let title = format!("{} changed", change.id);
+            serde_json::json!({
+                "id": format!("urn:patrol:change:{}:{}", change.id, change.changed_at.unix_secs()),
+                "url": change.url.as_str(),
+                "title": title,
+                // an item must have a content.
+                "content_text": change.content.unwrap_or(&title),
+                "date_published": format_rfc3339(change.changed_at),
+                "tags": change.tags,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": format!("patrol changes ({selection})"),
+        "authors": [{ "name": "patrol" }],
+        "items": items,
+    })
+}
+
+/// Formats the time in UTC, e.g. `20220102T030405Z`.
+fn format_time(at: Timestamp) -> String {
+    match chrono::NaiveDateTime::from_timestamp_opt(at.unix_secs(), 0) {
+        Some(x) => x.format("%Y%m%dT%H%M%SZ").to_string(),
+        None => "19700101T000000Z".to_owned(),
+    }
+}
+
+/// Formats the time in UTC to the seconds, e.g. `2022-01-02T03:04:05Z`.
+fn format_rfc3339(at: Timestamp) -> String {
+    match chrono::NaiveDateTime::from_timestamp_opt(at.unix_secs(), 0) {
+        Some(x) => x.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        None => "1970-01-01T00:00:00Z".to_owned(),
+    }
+}
+
+/// Escapes the text of an element or an attribute of XML.
+pub(super) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // the control characters other than the whitespaces are not allowed in XML.
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes the text of a value of iCalendar, i.e. the backslashes, the separators and the line breaks.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends the line ended with CRLF, folded into the lines continued by a space not to exceed the octets.
+fn fold(calendar: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if MAX_LINE_OCTETS < octets + c.len_utf8() {
+            calendar.push_str("\r\n ");
+            // the leading space counts.
+            octets = 1;
+        }
+        calendar.push(c);
+        octets += c.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde_derive::Deserialize;
+
+use crate::domain::{Condition, Id};
+
+/// A composite target in TOML, whose condition must be an inline table to be parsed.
+#[derive(Deserialize)]
+struct TomlComposite {
+    condition: Condition,
+}
+
+/// Reads the composite targets from the TOML file, keyed by the ids.
+pub async fn load(path: &str) -> Result<HashMap<Id, Condition>, Error> {
+    let toml = tokio::fs::read_to_string(path).await?;
+    let composites: HashMap<Id, TomlComposite> = toml::from_str(&toml)?;
+    Ok(composites
+        .into_iter()
+        .map(|(id, x)| (id, x.condition))
+        .collect())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    TomlParseError(toml::de::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => {
+                f.write_fmt(format_args!("failed to read the composite targets: {e}"))
+            }
+            Error::TomlParseError(e) => {
+                f.write_fmt(format_args!("failed to parse the composite targets: {e}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::TomlParseError(e) => Some(e),
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlParseError(e)
+    }
+}
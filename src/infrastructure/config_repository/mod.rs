@@ -1,3 +1,5 @@
 pub mod toml_config_repository;
+pub mod toml_config_trash;
 
 pub use self::toml_config_repository::TomlConfigRepository;
+pub use self::toml_config_trash::TomlConfigTrash;
@@ -1,21 +1,80 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, path::Path};
 
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 
 use crate::infrastructure::toml_file_proxy::{Error as TomlProxyError, TomlFileProxy};
 
 use crate::domain::{
-    config_repository::ConfigRepository, selector::SelectorParseError, url::UrlParseError, Config,
-    Id, Mode, Selector, Url,
+    config_repository::{ConfigEvent, ConfigRepository},
+    duration_in,
+    selector::SelectorParseError,
+    url::UrlParseError,
+    Baseline, BrowserSession, Config, DependencyError, Duration, Hash, Id, Integrity,
+    MaintenanceWindow, Mode, ResourceType, Selector, Step, Url, Viewport, WaitStrategy,
 };
 
-#[derive(Deserialize, Serialize, Clone)]
-struct TomlConfig {
+/// Capacity of the channel for `ConfigEvent`s.
+const EVENT_CAPACITY: usize = 64;
+
+/// Parses the targets in the tables of the config file, e.g. from the JSON of a request.
+pub fn parse_configs<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Id, Config>, D::Error> {
+    let configs: HashMap<Id, TomlConfig> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(configs.into_iter().map(|(id, x)| (id, x.into())).collect())
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub(super) struct TomlConfig {
     url: Url,
     selector: Selector,
     mode: Option<Mode>,
-    wait_seconds: Option<u16>,
+    poller: Option<String>,
+    #[serde(
+        default,
+        alias = "wait_seconds",
+        deserialize_with = "duration_in::optional_seconds"
+    )]
+    delay: Option<Duration>,
+    paused: Option<bool>,
+    session: Option<BrowserSession>,
+    wait: Option<WaitStrategy>,
+    script: Option<String>,
+    frames: Option<Vec<Selector>>,
+    pierce_shadow: Option<bool>,
+    viewport: Option<Viewport>,
+    block: Option<Vec<ResourceType>>,
+    block_urls: Option<Vec<String>>,
+    impersonate: Option<String>,
+    local_address: Option<std::net::IpAddr>,
+    tunnel: Option<String>,
+    headers: Option<Vec<String>>,
+    headers_only: Option<bool>,
+    query: Option<String>,
+    path: Option<String>,
+    send: Option<String>,
+    filter: Option<String>,
+    #[serde(default, deserialize_with = "duration_in::optional_seconds")]
+    timeout: Option<Duration>,
+    from: Option<String>,
+    subject: Option<String>,
+    depends_on: Option<Vec<Id>>,
+    ignore_selectors: Option<Vec<Selector>>,
+    confirmations: Option<u16>,
+    variants: Option<u16>,
+    language: Option<String>,
+    maintenance: Option<Vec<MaintenanceWindow>>,
+    #[serde(default, deserialize_with = "duration_in::optional_seconds")]
+    budget: Option<Duration>,
+    tags: Option<Vec<String>>,
+    baseline: Option<Baseline>,
+    pinned: Option<Hash>,
+    /// Tables, which must come after the values to be serialized.
+    variables: Option<serde_json::Value>,
+    integrity: Option<Integrity>,
+    steps: Option<Vec<Step>>,
 }
 impl From<Config> for TomlConfig {
     fn from(c: Config) -> Self {
@@ -23,13 +82,83 @@ impl From<Config> for TomlConfig {
             url,
             selector,
             mode,
-            wait_seconds,
+            poller,
+            delay,
+            paused,
+            session,
+            wait,
+            script,
+            frames,
+            pierce_shadow,
+            viewport,
+            block,
+            block_urls,
+            impersonate,
+            local_address,
+            tunnel,
+            headers,
+            headers_only,
+            query,
+            variables,
+            path,
+            send,
+            filter,
+            timeout,
+            from,
+            subject,
+            depends_on,
+            ignore_selectors,
+            confirmations,
+            variants,
+            language,
+            maintenance,
+            budget,
+            tags,
+            baseline,
+            pinned,
+            integrity,
+            steps,
         } = c;
         Self {
             url,
             selector,
-            mode: mode.into(),
-            wait_seconds,
+            mode: Some(mode).filter(|x| *x != Mode::default()),
+            poller,
+            delay,
+            paused: if paused { Some(true) } else { None },
+            session: Some(session).filter(|x| *x != BrowserSession::default()),
+            wait: Some(wait).filter(|x| *x != WaitStrategy::default()),
+            script,
+            frames: Some(frames).filter(|x| 0 < x.len()),
+            pierce_shadow: if pierce_shadow { Some(true) } else { None },
+            viewport,
+            block: Some(block).filter(|x| 0 < x.len()),
+            block_urls: Some(block_urls).filter(|x| 0 < x.len()),
+            impersonate,
+            local_address,
+            tunnel,
+            headers: Some(headers).filter(|x| 0 < x.len()),
+            headers_only: if headers_only { Some(true) } else { None },
+            query,
+            variables,
+            path,
+            send,
+            filter,
+            timeout,
+            from,
+            subject,
+            depends_on: Some(depends_on).filter(|x| 0 < x.len()),
+            ignore_selectors: Some(ignore_selectors).filter(|x| 0 < x.len()),
+            confirmations,
+            variants,
+            language,
+            maintenance: Some(maintenance).filter(|x| 0 < x.len()),
+            budget,
+            tags: Some(tags).filter(|x| 0 < x.len()),
+            baseline: Some(baseline).filter(|x| *x != Baseline::default()),
+            pinned,
+            integrity,
+            steps: Some(steps).filter(|x| 0 < x.len()),
         }
     }
 }
@@ -39,52 +168,277 @@ impl Into<Config> for TomlConfig {
             url,
             selector,
             mode,
-            wait_seconds,
+            poller,
+            delay,
+            paused,
+            session,
+            wait,
+            script,
+            frames,
+            pierce_shadow,
+            viewport,
+            block,
+            block_urls,
+            impersonate,
+            local_address,
+            tunnel,
+            headers,
+            headers_only,
+            query,
+            variables,
+            path,
+            send,
+            filter,
+            timeout,
+            from,
+            subject,
+            depends_on,
+            ignore_selectors,
+            confirmations,
+            variants,
+            language,
+            maintenance,
+            budget,
+            tags,
+            baseline,
+            pinned,
+            integrity,
+            steps,
         } = self;
         Config {
             url,
             selector,
             mode: mode.unwrap_or_default(),
-            wait_seconds,
+            poller,
+            delay,
+            paused: paused.unwrap_or_default(),
+            session: session.unwrap_or_default(),
+            wait: wait.unwrap_or_default(),
+            script,
+            frames: frames.unwrap_or_default(),
+            pierce_shadow: pierce_shadow.unwrap_or_default(),
+            viewport,
+            block: block.unwrap_or_default(),
+            block_urls: block_urls.unwrap_or_default(),
+            impersonate,
+            local_address,
+            tunnel,
+            headers: headers.unwrap_or_default(),
+            headers_only: headers_only.unwrap_or_default(),
+            query,
+            variables,
+            path,
+            send,
+            filter,
+            timeout,
+            from,
+            subject,
+            depends_on: depends_on.unwrap_or_default(),
+            ignore_selectors: ignore_selectors.unwrap_or_default(),
+            confirmations,
+            variants,
+            language,
+            maintenance: maintenance.unwrap_or_default(),
+            budget,
+            tags: tags.unwrap_or_default(),
+            baseline: baseline.unwrap_or_default(),
+            pinned,
+            integrity,
+            steps: steps.unwrap_or_default(),
         }
     }
 }
 
 pub struct TomlConfigRepository {
     proxy: TomlFileProxy<HashMap<Id, TomlConfig>>,
+    /// The file of the targets added or changed through this repository, which overlays the read-only `proxy`.
+    managed: Option<TomlFileProxy<HashMap<Id, TomlConfig>>>,
+    events: broadcast::Sender<ConfigEvent>,
 }
 impl TomlConfigRepository {
-    pub async fn new(path: &str) -> Result<Self, Error> {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
         let mut proxy = TomlFileProxy::<HashMap<Id, TomlConfig>>::new(path).await?;
         let map = proxy.load().await?;
-        debug!("{} has {} configurations.", path, map.len());
+        debug!("{} has {} configurations.", path.display(), map.len());
+
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        let repo = Self {
+            proxy,
+            managed: None,
+            events,
+        };
+        repo.check_dependencies()?;
+        Ok(repo)
+    }
+
+    /// Reads the config file without writing it, and keeps the targets added or changed through this repository in the managed file.
+    ///
+    /// A target in the managed file replaces the one with the same id in the config file,
+    /// and the targets of the config file cannot be deleted.
+    pub async fn with_managed(
+        path: impl AsRef<Path>,
+        managed_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let (path, managed_path) = (path.as_ref(), managed_path.as_ref());
+        let mut proxy = TomlFileProxy::<HashMap<Id, TomlConfig>>::new_read_only(path).await?;
+        let map = proxy.load().await?;
+        debug!("{} has {} configurations.", path.display(), map.len());
+
+        if let Some(dir) = managed_path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(TomlProxyError::from)?;
+        }
+        let mut managed = TomlFileProxy::<HashMap<Id, TomlConfig>>::new(managed_path).await?;
+        let map = managed.load().await?;
+        debug!(
+            "{} has {} configurations.",
+            managed_path.display(),
+            map.len()
+        );
+
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        let repo = Self {
+            proxy,
+            managed: Some(managed),
+            events,
+        };
+        repo.check_dependencies()?;
+        Ok(repo)
+    }
+
+    /// Opens the config file, overlaid by the managed file if given as `with_managed`.
+    pub async fn open(path: impl AsRef<Path>, managed_path: Option<&Path>) -> Result<Self, Error> {
+        match managed_path {
+            Some(managed_path) => Self::with_managed(path, managed_path).await,
+            None => Self::new(path).await,
+        }
+    }
+
+    /// Reads the config file, and the managed file if it exists, without writing them, e.g. to diagnose them.
+    pub async fn open_read_only(
+        path: impl AsRef<Path>,
+        managed_path: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut proxy = TomlFileProxy::<HashMap<Id, TomlConfig>>::new_read_only(path).await?;
+        let map = proxy.load().await?;
+        debug!("{} has {} configurations.", path.display(), map.len());
+
+        let managed = match managed_path.filter(|x| x.exists()) {
+            Some(managed_path) => {
+                let mut managed =
+                    TomlFileProxy::<HashMap<Id, TomlConfig>>::new_read_only(managed_path).await?;
+                let map = managed.load().await?;
+                debug!(
+                    "{} has {} configurations.",
+                    managed_path.display(),
+                    map.len()
+                );
+                Some(managed)
+            }
+            None => None,
+        };
+
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        let repo = Self {
+            proxy,
+            managed,
+            events,
+        };
+        repo.check_dependencies()?;
+        Ok(repo)
+    }
+
+    /// The file written by this repository.
+    fn writable(&mut self) -> &mut TomlFileProxy<HashMap<Id, TomlConfig>> {
+        match self.managed.as_mut() {
+            Some(managed) => managed,
+            None => &mut self.proxy,
+        }
+    }
+
+    /// The targets of the config file if it is read-only.
+    fn read_only(&self) -> Option<&HashMap<Id, TomlConfig>> {
+        self.managed.as_ref().and(self.proxy.get_cache())
+    }
+
+    /// The targets of the config file overlaid by the managed file.
+    fn merged(&self) -> HashMap<Id, TomlConfig> {
+        let mut map = self.proxy.get_cache().cloned().unwrap_or_default();
+        if let Some(managed) = self.managed.as_ref() {
+            map.extend(managed.get_cache().cloned().unwrap_or_default());
+        }
+        map
+    }
+
+    fn contains(&self, id: &Id) -> bool {
+        self.proxy.get_cache().is_some_and(|x| x.contains_key(id))
+            || self
+                .managed
+                .as_ref()
+                .and_then(|x| x.get_cache())
+                .is_some_and(|x| x.contains_key(id))
+    }
+
+    /// Fails if a target depends on an unknown target or on itself through the others, which would be skipped forever.
+    fn check_dependencies(&self) -> Result<(), Error> {
+        let configs = self
+            .merged()
+            .into_iter()
+            .map(|(id, config)| (id, config.into()))
+            .collect();
+        DependencyError::check(&configs).map_err(Error::Dependency)
+    }
 
-        Ok(Self { proxy })
+    /// Checks the dependencies of the targets changed in the cache, and saves them.
+    async fn check_and_save(&mut self) -> Result<(), Error> {
+        self.check_dependencies()?;
+        Ok(self.writable().save().await?)
+    }
+
+    /// Fails if the target is in the read-only config file.
+    fn check_deletable(&self, id: &Id) -> Result<(), Error> {
+        match self.read_only().is_some_and(|x| x.contains_key(id)) {
+            true => Err(Error::ReadOnly(id.clone())),
+            false => Ok(()),
+        }
     }
 
     /// Updates the inner hashmap and returns the old element.
     fn update_map(&mut self, id: Id, config: Config) -> RestoreInfo {
-        let old_data = self
-            .proxy
-            .get_cache_mut()
-            .unwrap()
-            .insert(id.clone(), config.into());
+        let config = TomlConfig::from(config);
+        // the target same as the one in the read-only config file needs no overlay.
+        let unchanged = self
+            .read_only()
+            .and_then(|x| x.get(&id))
+            .is_some_and(|x| TomlConfig::from(Into::<Config>::into(x.clone())) == config);
+        let map = self.writable().get_cache_mut().unwrap();
+        let old_data = match unchanged {
+            true => map.remove(&id),
+            false => map.insert(id.clone(), config),
+        };
         RestoreInfo { id, data: old_data }
     }
 
     fn delete_map(&mut self, id: Id) -> RestoreInfo {
-        let old_data = self.proxy.get_cache_mut().unwrap().remove(&id);
+        let old_data = self.writable().get_cache_mut().unwrap().remove(&id);
         RestoreInfo { id, data: old_data }
     }
 
     fn restore(&mut self, restore_info: RestoreInfo) {
         let RestoreInfo { id, data } = restore_info;
+        let map = self.writable().get_cache_mut().unwrap();
         match data {
             Some(data) => {
-                let _ = self.proxy.get_cache_mut().unwrap().insert(id, data);
+                let _ = map.insert(id, data);
             }
             None => {
-                let _ = self.proxy.get_cache_mut().unwrap().remove(&id);
+                let _ = map.remove(&id);
             }
         }
     }
@@ -95,35 +449,156 @@ impl ConfigRepository for TomlConfigRepository {
     type Error = Error;
 
     async fn get_all(&mut self) -> Result<HashMap<Id, Config>, Self::Error> {
-        let map = self.proxy.get_cache().unwrap();
-        let map = map
+        let map = self
+            .merged()
             .into_iter()
-            .map(|(id, config)| (id.clone(), config.clone().into()))
+            .map(|(id, config)| (id, config.into()))
             .collect();
         Ok(map)
     }
 
     async fn update(&mut self, id: Id, config: Config) -> Result<(), Self::Error> {
+        let event = match self.contains(&id) {
+            true => ConfigEvent::Updated {
+                id: id.clone(),
+                config: config.clone(),
+            },
+            false => ConfigEvent::Added {
+                id: id.clone(),
+                config: config.clone(),
+            },
+        };
         let restore_info = self.update_map(id, config);
 
-        if let Err(e) = self.proxy.save().await {
+        if let Err(e) = self.check_and_save().await {
             self.restore(restore_info);
-            Err(e.into())
+            Err(e)
         } else {
+            let _ = self.events.send(event);
             Ok(())
         }
     }
 
     async fn delete(&mut self, id: Id) -> Result<Option<Config>, Self::Error> {
+        self.check_deletable(&id)?;
         let restore_info = self.delete_map(id);
 
-        if let Err(e) = self.proxy.save().await {
+        if let Err(e) = self.check_and_save().await {
             self.restore(restore_info);
-            Err(e.into())
+            Err(e)
         } else {
-            Ok(restore_info.data.map(|x| x.into()))
+            let RestoreInfo { id, data } = restore_info;
+            let config: Option<Config> = data.map(|x| x.into());
+            if let Some(config) = config.clone() {
+                let _ = self.events.send(ConfigEvent::Removed { id, config });
+            }
+            Ok(config)
+        }
+    }
+
+    async fn apply(&mut self, changes: HashMap<Id, Option<Config>>) -> Result<(), Self::Error> {
+        for (id, _) in changes.iter().filter(|(_, config)| config.is_none()) {
+            self.check_deletable(id)?;
+        }
+
+        let mut restore_infos = Vec::new();
+        let mut events = Vec::new();
+        for (id, config) in changes.into_iter() {
+            let restore_info = match config {
+                Some(config) => {
+                    let existed = self.contains(&id);
+                    let restore_info = self.update_map(id.clone(), config.clone());
+                    events.push(match existed {
+                        true => ConfigEvent::Updated { id, config },
+                        false => ConfigEvent::Added { id, config },
+                    });
+                    restore_info
+                }
+                None => {
+                    let restore_info = self.delete_map(id.clone());
+                    if let Some(config) = restore_info.data.clone() {
+                        let config = config.into();
+                        events.push(ConfigEvent::Removed { id, config });
+                    }
+                    restore_info
+                }
+            };
+            restore_infos.push(restore_info);
+        }
+
+        if let Err(e) = self.check_and_save().await {
+            // in reverse, in case an id is changed twice.
+            for restore_info in restore_infos.into_iter().rev() {
+                self.restore(restore_info);
+            }
+            Err(e)
+        } else {
+            for event in events.into_iter() {
+                let _ = self.events.send(event);
+            }
+            Ok(())
         }
     }
+
+    async fn reload(&mut self) -> Result<Vec<ConfigEvent>, Self::Error> {
+        let old = self.merged();
+        let old_caches = (
+            self.proxy.get_cache().cloned(),
+            self.managed.as_ref().and_then(|x| x.get_cache().cloned()),
+        );
+        self.proxy.reopen().await?;
+        let _ = self.proxy.load().await?;
+        if let Some(managed) = self.managed.as_mut() {
+            managed.reopen().await?;
+            let _ = managed.load().await?;
+        }
+        // the targets loaded before are kept, not to skip the dependents forever.
+        if let Err(e) = self.check_dependencies() {
+            if let (Some(cache), Some(old)) = (self.proxy.get_cache_mut(), old_caches.0) {
+                *cache = old;
+            }
+            let managed_cache = self.managed.as_mut().and_then(|x| x.get_cache_mut());
+            if let (Some(cache), Some(old)) = (managed_cache, old_caches.1) {
+                *cache = old;
+            }
+            return Err(e);
+        }
+        let new = self.merged();
+
+        let mut events = Vec::new();
+        for (id, config) in new.iter() {
+            let event = match old.get(id) {
+                None => ConfigEvent::Added {
+                    id: id.clone(),
+                    config: config.clone().into(),
+                },
+                Some(old_config) if old_config != config => ConfigEvent::Updated {
+                    id: id.clone(),
+                    config: config.clone().into(),
+                },
+                Some(_) => continue,
+            };
+            events.push(event);
+        }
+        for (id, config) in old.into_iter() {
+            if !new.contains_key(&id) {
+                events.push(ConfigEvent::Removed {
+                    id,
+                    config: config.into(),
+                });
+            }
+        }
+
+        for event in events.iter() {
+            let _ = self.events.send(event.clone());
+        }
+
+        Ok(events)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.events.subscribe()
+    }
 }
 
 struct RestoreInfo {
@@ -136,12 +611,24 @@ pub enum Error {
     TomlProxyError(TomlProxyError),
     UrlParseError(UrlParseError),
     SelectorParseError(SelectorParseError),
+    /// The target is in the read-only config file.
+    ReadOnly(Id),
+    Dependency(DependencyError),
 }
 impl Display for Error {
-    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TomlProxyError(e) => f.write_fmt(format_args!("Toml proxy error: {e}")),
+            Error::UrlParseError(e) => f.write_fmt(format_args!("Url parse error: {e}")),
+            Error::SelectorParseError(e) => f.write_fmt(format_args!("Selector parse error: {e}")),
+            Error::ReadOnly(id) => f.write_fmt(format_args!(
+                "{id} is in the read-only config file, which is not written."
+            )),
+            Error::Dependency(e) => f.write_fmt(format_args!("{e}")),
+        }
     }
 }
+// the errors are in the messages, so not the sources.
 impl std::error::Error for Error {}
 impl From<TomlProxyError> for Error {
     fn from(e: TomlProxyError) -> Self {
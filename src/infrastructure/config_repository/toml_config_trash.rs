@@ -0,0 +1,103 @@
+use std::{collections::HashMap, path::Path};
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::infrastructure::toml_file_proxy::TomlFileProxy;
+
+use super::toml_config_repository::{Error, TomlConfig};
+use crate::domain::{Config, Id, Timestamp};
+
+/// A removed config, kept to be restored.
+#[derive(Deserialize, Serialize, Clone)]
+struct TrashEntry {
+    removed_at: Timestamp,
+    /// A table, which must come after the values to be serialized.
+    config: TomlConfig,
+}
+
+/// A TOML file keeping the removed configs, until they are restored or purged.
+pub struct TomlConfigTrash {
+    proxy: TomlFileProxy<HashMap<Id, TrashEntry>>,
+}
+impl TomlConfigTrash {
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut proxy = TomlFileProxy::<HashMap<Id, TrashEntry>>::new(path).await?;
+        let map = proxy.load().await?;
+        debug!(
+            "{} has {} removed configurations.",
+            path.display(),
+            map.len()
+        );
+
+        Ok(Self { proxy })
+    }
+
+    /// Lists the removed configs with the times of the removals, the latest first.
+    pub fn list(&self) -> Vec<(Id, Timestamp, Config)> {
+        let map = self.proxy.get_cache().unwrap();
+        let mut list: Vec<_> = map
+            .iter()
+            .map(|(id, x)| (id.clone(), x.removed_at, x.config.clone().into()))
+            .collect();
+        list.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        list
+    }
+
+    /// Keeps the configs removed at the time, replacing the ones with the same ids.
+    pub async fn put(
+        &mut self,
+        configs: HashMap<Id, Config>,
+        removed_at: Timestamp,
+    ) -> Result<(), Error> {
+        let old = self.proxy.get_cache().cloned();
+        let map = self.proxy.get_cache_mut().unwrap();
+        for (id, config) in configs.into_iter() {
+            let config = config.into();
+            let _ = map.insert(id, TrashEntry { removed_at, config });
+        }
+        self.save_or_restore(old).await
+    }
+
+    /// Takes the configs of the ids out, ignoring the ids not found.
+    pub async fn take(&mut self, ids: &[Id]) -> Result<HashMap<Id, Config>, Error> {
+        let old = self.proxy.get_cache().cloned();
+        let map = self.proxy.get_cache_mut().unwrap();
+        let taken = ids
+            .iter()
+            .filter_map(|id| map.remove(id).map(|x| (id.clone(), x.config.into())))
+            .collect();
+        self.save_or_restore(old).await?;
+        Ok(taken)
+    }
+
+    /// Drops the configs removed before the time, and returns their ids.
+    pub async fn purge(&mut self, before: Timestamp) -> Result<Vec<Id>, Error> {
+        let old = self.proxy.get_cache().cloned();
+        let map = self.proxy.get_cache_mut().unwrap();
+        let purged: Vec<_> = map
+            .iter()
+            .filter(|(_, x)| x.removed_at < before)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if purged.len() == 0 {
+            return Ok(purged);
+        }
+        for id in purged.iter() {
+            let _ = map.remove(id);
+        }
+        self.save_or_restore(old).await?;
+        Ok(purged)
+    }
+
+    async fn save_or_restore(&mut self, old: Option<HashMap<Id, TrashEntry>>) -> Result<(), Error> {
+        if let Err(e) = self.proxy.save().await {
+            if let Some(old) = old {
+                self.proxy.update_cache(old);
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::domain::Id;
+
+/// The extension of the files of the contents.
+const EXTENSION: &str = "txt";
+
+/// A directory keeping the latest content of each target, in a file named after its id.
+///
+/// The words of the contents are indexed in memory, so that a search reads only the contents having them.
+#[derive(Clone)]
+pub struct ContentDirectory {
+    path: PathBuf,
+    index: Arc<Mutex<Index>>,
+}
+impl ContentDirectory {
+    /// Opens the directory, creating it if it does not exist, and indexes the contents in it.
+    pub async fn new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        tokio::fs::create_dir_all(path).await?;
+
+        let mut index = Index::default();
+        let mut entries = tokio::fs::read_dir(path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if let Some(id) = id_of(&path) {
+                index.insert(id, &tokio::fs::read_to_string(&path).await?);
+            }
+        }
+        Ok(Self {
+            path: path.into(),
+            index: Arc::new(Mutex::new(index)),
+        })
+    }
+
+    fn path_of(&self, id: &Id) -> PathBuf {
+        self.path
+            .join(format!("{}.{EXTENSION}", encode(id.as_str())))
+    }
+
+    /// Replaces the content of the target.
+    pub async fn save(&self, id: &Id, content: &str) -> std::io::Result<()> {
+        let path = self.path_of(id);
+        // written to another file first, not to leave a partial content.
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, content).await?;
+        tokio::fs::rename(&temporary, &path).await?;
+        self.index.lock().unwrap().insert(id.clone(), content);
+        Ok(())
+    }
+
+    /// Reads the content of the target, if kept.
+    pub async fn get(&self, id: &Id) -> std::io::Result<Option<String>> {
+        match tokio::fs::read_to_string(self.path_of(id)).await {
+            Ok(x) => Ok(Some(x)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Searches the contents for the text case-insensitively.
+    ///
+    /// The words of the text are looked up in the index, the last one as the start of a word, e.g. `prod` of `product`,
+    /// and only the contents having all of them are read.
+    /// Returns the lines containing the text for each target, ordered by the ids.
+    pub async fn search(&self, text: &str) -> std::io::Result<Vec<(Id, Vec<String>)>> {
+        let text = text.to_lowercase();
+        let mut candidates = self.index.lock().unwrap().candidates(&words(&text));
+        candidates.sort();
+
+        let mut found = Vec::new();
+        for id in candidates.into_iter() {
+            let content = match tokio::fs::read_to_string(self.path_of(&id)).await {
+                Ok(x) => x,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let lines: Vec<_> = content
+                .lines()
+                .filter(|x| x.to_lowercase().contains(&text))
+                .map(str::to_owned)
+                .collect();
+            if 0 < lines.len() {
+                found.push((id, lines));
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// The inverted index of the words of the contents.
+#[derive(Debug, Default)]
+struct Index {
+    /// The targets whose contents have each word.
+    postings: BTreeMap<String, HashSet<Id>>,
+    /// The words of the content of each target, to remove them when it is replaced.
+    words: HashMap<Id, HashSet<String>>,
+}
+impl Index {
+    /// Replaces the words of the content of the target.
+    fn insert(&mut self, id: Id, content: &str) {
+        for word in self.words.remove(&id).into_iter().flatten() {
+            if let Some(ids) = self.postings.get_mut(&word) {
+                let _ = ids.remove(&id);
+                if ids.is_empty() {
+                    let _ = self.postings.remove(&word);
+                }
+            }
+        }
+        let words = words(&content.to_lowercase());
+        for word in words.iter() {
+            let _ = self
+                .postings
+                .entry(word.clone())
+                .or_default()
+                .insert(id.clone());
+        }
+        let _ = self.words.insert(id, words.into_iter().collect());
+    }
+
+    /// The targets whose contents have all of the words, the last one as the start of a word,
+    /// or every target if there is no word.
+    fn candidates(&self, words: &[String]) -> Vec<Id> {
+        let (last, rest) = match words.split_last() {
+            Some(x) => x,
+            None => return self.words.keys().cloned().collect(),
+        };
+        let mut matched: HashSet<&Id> = self
+            .postings
+            .range(last.clone()..)
+            .take_while(|(word, _)| word.starts_with(last.as_str()))
+            .flat_map(|(_, ids)| ids.iter())
+            .collect();
+        for word in rest.iter() {
+            let ids = match self.postings.get(word) {
+                Some(x) => x,
+                None => return Vec::new(),
+            };
+            matched.retain(|x| ids.contains(*x));
+        }
+        matched.into_iter().cloned().collect()
+    }
+}
+
+/// The words of the lowercase text, split by the characters other than the alphanumerics, in the order.
+fn words(text: &str) -> Vec<String> {
+    text.split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The id of the target of the file of a content, if it is.
+fn id_of(path: &Path) -> Option<Id> {
+    if path.extension().and_then(|x| x.to_str()) != Some(EXTENSION) {
+        return None;
+    }
+    path.file_stem()
+        .and_then(|x| x.to_str())
+        .and_then(decode)
+        .and_then(|x| Id::try_from(x).ok())
+}
+
+/// Percent-encodes the characters other than ASCII alphanumerics, `-` and `_`, to be a file name.
+pub(super) fn encode(id: &str) -> String {
+    let mut encoded = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn decode(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::content_directory::encode;
+use super::RotatingFile;
+use crate::domain::{Id, Timestamp};
+
+/// The extension of the files of the samples.
+const EXTENSION: &str = "log";
+
+/// A directory appending the contents polled from each target to a file named after its id, to debug the selectors later.
+///
+/// Each file is rotated as `RotatingFile` when it grows larger than the limit,
+/// and each sample is cut down to the limit.
+pub struct ContentSamples {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+}
+impl ContentSamples {
+    /// Opens the directory, creating it if it does not exist.
+    pub fn new(path: impl AsRef<Path>, max_bytes: u64, keep: usize) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        std::fs::create_dir_all(path)?;
+        Ok(Self {
+            path: path.into(),
+            max_bytes,
+            keep,
+        })
+    }
+
+    /// Appends the content of the target, headed by the time and whether it has changed.
+    ///
+    /// This blocks the thread while writing the file.
+    pub fn append(&self, id: &Id, content: &str, changed: bool) -> std::io::Result<()> {
+        let path = self
+            .path
+            .join(format!("{}.{EXTENSION}", encode(id.as_str())));
+        // the files are opened for each sample, not to run out of the descriptors with many targets.
+        let mut file = RotatingFile::open(path, self.max_bytes, self.keep)?;
+
+        let header = match changed {
+            true => format!("--- {} (changed) ---\n", Timestamp::now()),
+            false => format!("--- {} ---\n", Timestamp::now()),
+        };
+        let limit = (self.max_bytes as usize).saturating_sub(header.len() + 1);
+        let mut sample = header;
+        sample.push_str(truncate(content, limit));
+        sample.push('\n');
+
+        // written at once, not to be split by the rotation.
+        file.write_all(sample.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// Cuts the text down to the bytes at most, on a character boundary.
+fn truncate(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
@@ -1,25 +1,60 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-use log::{debug, info};
+use log::debug;
 
 use crate::infrastructure::toml_file_proxy::{Error, TomlFileProxy};
 
-use crate::domain::{Data, DataRepository, Hash, Id, Timestamp};
+use crate::domain::{
+    Checkpoint, Data, DataOrder, DataQuery, DataReader, DataWriter, Hash, Id, Metadata, Stats,
+    Status, Timestamp,
+};
+
+/// The extension appended to the path of the data file, of the file of the checkpoint.
+const CHECKPOINT_EXTENSION: &str = "checkpoint";
 
 pub struct TomlDataRepository {
     proxy: TomlFileProxy<HashMap<Id, Data>>,
+    /// The file of the checkpoint, which exists only while a cycle is in flight.
+    checkpoint_path: PathBuf,
 }
 impl TomlDataRepository {
-    pub async fn new(path: &str) -> Result<Self, Error> {
-        let mut proxy = TomlFileProxy::<HashMap<Id, Data>>::new(path).await?;
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let proxy = TomlFileProxy::<HashMap<Id, Data>>::new(path).await?;
+        Self::load(path, proxy).await
+    }
+
+    /// Reads the data file, which must exist, without writing it, e.g. to diagnose it.
+    pub async fn new_read_only(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let proxy = TomlFileProxy::<HashMap<Id, Data>>::new_read_only(path).await?;
+        Self::load(path, proxy).await
+    }
+
+    async fn load(path: &Path, mut proxy: TomlFileProxy<HashMap<Id, Data>>) -> Result<Self, Error> {
         let map = proxy.load().await?;
-        debug!("{} has {} data entries.", path, map.len());
+        debug!("{} has {} data entries.", path.display(), map.len());
 
-        Ok(Self { proxy })
+        let mut checkpoint_path = OsString::from(path.as_os_str());
+        checkpoint_path.push(format!(".{CHECKPOINT_EXTENSION}"));
+        Ok(Self {
+            proxy,
+            checkpoint_path: checkpoint_path.into(),
+        })
     }
 
-    // Updates the inner hashmap and returns the old element.
-    fn update_map(&mut self, id: Id, hash: Hash, now: Timestamp) -> RestoreInfo {
+    // Updates the inner hashmap and returns the old element,
+    // along with the new `last_updated` if the hash has changed.
+    fn update_map(
+        &mut self,
+        id: Id,
+        hash: Hash,
+        now: Timestamp,
+    ) -> (RestoreInfo, Option<Timestamp>) {
         let mut data = self
             .proxy
             .get_cache_mut()
@@ -30,25 +65,81 @@ impl TomlDataRepository {
                 hash: None,
                 last_updated: None,
                 last_checked: now,
+                status: Status::New,
+                known_hashes: Vec::new(),
+                title: None,
+                favicon: None,
+                lossy: false,
+                stats: Stats::default(),
             });
 
         data.last_checked = now;
 
-        if data.hash.as_ref() != Some(&hash) {
+        let updated = data.hash.as_ref() != Some(&hash);
+        if updated {
             data.last_updated = now.into();
-            info!(
-                "[{id}]: {}",
-                ansi_term::Color::Fixed(15).bold().paint("updated.")
-            );
-        } else {
-            info!(
-                "[{id}]: {}",
-                ansi_term::Color::Fixed(8).paint("not yet updated.")
-            );
         }
         data.hash = hash.into();
 
         let old_data = self.proxy.get_cache_mut().unwrap().insert(id.clone(), data);
+        let last_updated = if updated { Some(now) } else { None };
+        (RestoreInfo { id, data: old_data }, last_updated)
+    }
+
+    fn update_status_map(&mut self, id: Id, status: Status, now: Timestamp) -> RestoreInfo {
+        let cache = self.proxy.get_cache_mut().unwrap();
+        let old_data = cache.get(&id).cloned();
+        let mut data = old_data.clone().unwrap_or_else(|| Data {
+            hash: None,
+            last_updated: None,
+            last_checked: now,
+            status: Status::New,
+            known_hashes: Vec::new(),
+            title: None,
+            favicon: None,
+            lossy: false,
+            stats: Stats::default(),
+        });
+        data.status = status;
+
+        let _ = cache.insert(id.clone(), data);
+        RestoreInfo { id, data: old_data }
+    }
+
+    fn update_metadata_map(&mut self, id: Id, metadata: Metadata) -> RestoreInfo {
+        let cache = self.proxy.get_cache_mut().unwrap();
+        let old_data = cache.get(&id).cloned();
+        if let Some(data) = cache.get_mut(&id) {
+            data.title = metadata.title;
+            data.favicon = metadata.favicon;
+        }
+        RestoreInfo { id, data: old_data }
+    }
+
+    fn update_known_hashes_map(&mut self, id: Id, hashes: Vec<Hash>) -> RestoreInfo {
+        let cache = self.proxy.get_cache_mut().unwrap();
+        let old_data = cache.get(&id).cloned();
+        if let Some(data) = cache.get_mut(&id) {
+            data.known_hashes = hashes;
+        }
+        RestoreInfo { id, data: old_data }
+    }
+
+    fn update_lossy_map(&mut self, id: Id, lossy: bool) -> RestoreInfo {
+        let cache = self.proxy.get_cache_mut().unwrap();
+        let old_data = cache.get(&id).cloned();
+        if let Some(data) = cache.get_mut(&id) {
+            data.lossy = lossy;
+        }
+        RestoreInfo { id, data: old_data }
+    }
+
+    fn update_stats_map(&mut self, id: Id, stats: Stats) -> RestoreInfo {
+        let cache = self.proxy.get_cache_mut().unwrap();
+        let old_data = cache.get(&id).cloned();
+        if let Some(data) = cache.get_mut(&id) {
+            data.stats = stats;
+        }
         RestoreInfo { id, data: old_data }
     }
 
@@ -71,16 +162,16 @@ impl TomlDataRepository {
 }
 
 #[async_trait::async_trait]
-impl DataRepository for TomlDataRepository {
+impl DataReader for TomlDataRepository {
     type Error = Error;
 
-    async fn get(&mut self, id: Id) -> Result<Option<Data>, Self::Error> {
+    async fn get(&self, id: Id) -> Result<Option<Data>, Self::Error> {
         let map = self.proxy.get_cache().unwrap();
-        let data = map.get(&id).map(|x| x.clone());
+        let data = map.get(&id).cloned();
         Ok(data)
     }
 
-    async fn get_multiple(&mut self, ids: HashSet<Id>) -> Result<HashMap<Id, Data>, Self::Error> {
+    async fn get_multiple(&self, ids: HashSet<Id>) -> Result<HashMap<Id, Data>, Self::Error> {
         let map = self.proxy.get_cache().unwrap();
         let iter = ids.into_iter().filter_map(|id| {
             let data = map.get(&id);
@@ -89,41 +180,173 @@ impl DataRepository for TomlDataRepository {
         Ok(iter.collect())
     }
 
-    async fn get_all(&mut self) -> Result<HashMap<Id, Data>, Self::Error> {
+    async fn get_all(&self) -> Result<HashMap<Id, Data>, Self::Error> {
         let map = self.proxy.get_cache().unwrap();
         let map = map
-            .into_iter()
+            .iter()
             .map(|(id, data)| (id.clone(), data.clone()))
             .collect();
         Ok(map)
     }
 
-    async fn update(&mut self, id: Id, hash: Hash) -> Result<(), Self::Error> {
+    async fn query(&self, query: DataQuery) -> Result<Vec<(Id, Data)>, Self::Error> {
+        let map = self.proxy.get_cache().unwrap();
+        let mut matched: Vec<_> = map
+            .iter()
+            .filter(|(id, _)| query.ids.as_ref().map(|x| x.contains(*id)).unwrap_or(true))
+            .filter(|(_, x)| query.statuses.len() == 0 || query.statuses.contains(&x.status))
+            .filter(|(_, x)| match query.updated_since {
+                Some(since) => x.last_updated.map(|x| since <= x).unwrap_or(false),
+                None => true,
+            })
+            .collect();
+        match query.order {
+            DataOrder::Id => matched.sort_by(|a, b| a.0.cmp(b.0)),
+            DataOrder::LastUpdated => {
+                matched.sort_by(|a, b| b.1.last_updated.cmp(&a.1.last_updated).then(a.0.cmp(b.0)))
+            }
+            DataOrder::LastChecked => {
+                matched.sort_by(|a, b| b.1.last_checked.cmp(&a.1.last_checked).then(a.0.cmp(b.0)))
+            }
+        }
+
+        // only the data in the range are cloned.
+        let page = matched
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(id, data)| (id.clone(), data.clone()))
+            .collect();
+        Ok(page)
+    }
+
+    async fn get_checkpoint(&self) -> Result<Option<Checkpoint>, Self::Error> {
+        match tokio::fs::read_to_string(&self.checkpoint_path).await {
+            Ok(toml) => Ok(Some(toml::from_str(&toml)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DataWriter for TomlDataRepository {
+    async fn update(&mut self, id: Id, hash: Hash) -> Result<Option<Timestamp>, Self::Error> {
         let now = Timestamp::now();
-        let restore_info = self.update_map(id, hash, now);
+        let (restore_info, last_updated) = self.update_map(id, hash, now);
 
         if let Err(e) = self.proxy.save().await {
             self.restore(restore_info);
-            Err(e.into())
+            Err(e)
         } else {
-            Ok(())
+            Ok(last_updated)
         }
     }
 
-    async fn update_multiple(&mut self, map: HashMap<Id, Hash>) -> Result<(), Self::Error> {
+    async fn update_multiple(
+        &mut self,
+        map: HashMap<Id, Hash>,
+    ) -> Result<HashMap<Id, Option<Timestamp>>, Self::Error> {
         let now = Timestamp::now();
 
         let mut restore_infos = Vec::with_capacity(map.len());
+        let mut results = HashMap::with_capacity(map.len());
         for (id, hash) in map.into_iter() {
-            let restore_info = self.update_map(id, hash, now);
+            let (restore_info, last_updated) = self.update_map(id.clone(), hash, now);
             restore_infos.push(restore_info);
+            let _ = results.insert(id, last_updated);
         }
 
         if let Err(e) = self.proxy.save().await {
             for restore_info in restore_infos.into_iter() {
                 self.restore(restore_info);
             }
-            Err(e.into())
+            Err(e)
+        } else {
+            Ok(results)
+        }
+    }
+
+    async fn update_statuses(&mut self, map: HashMap<Id, Status>) -> Result<(), Self::Error> {
+        let now = Timestamp::now();
+
+        let restore_infos: Vec<_> = map
+            .into_iter()
+            .map(|(id, status)| self.update_status_map(id, status, now))
+            .collect();
+
+        if let Err(e) = self.proxy.save().await {
+            for restore_info in restore_infos.into_iter() {
+                self.restore(restore_info);
+            }
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn update_known_hashes(
+        &mut self,
+        map: HashMap<Id, Vec<Hash>>,
+    ) -> Result<(), Self::Error> {
+        let restore_infos: Vec<_> = map
+            .into_iter()
+            .map(|(id, hashes)| self.update_known_hashes_map(id, hashes))
+            .collect();
+
+        if let Err(e) = self.proxy.save().await {
+            for restore_info in restore_infos.into_iter() {
+                self.restore(restore_info);
+            }
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn update_metadata(&mut self, map: HashMap<Id, Metadata>) -> Result<(), Self::Error> {
+        let restore_infos: Vec<_> = map
+            .into_iter()
+            .map(|(id, metadata)| self.update_metadata_map(id, metadata))
+            .collect();
+
+        if let Err(e) = self.proxy.save().await {
+            for restore_info in restore_infos.into_iter() {
+                self.restore(restore_info);
+            }
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn update_lossy(&mut self, map: HashMap<Id, bool>) -> Result<(), Self::Error> {
+        let restore_infos: Vec<_> = map
+            .into_iter()
+            .map(|(id, lossy)| self.update_lossy_map(id, lossy))
+            .collect();
+
+        if let Err(e) = self.proxy.save().await {
+            for restore_info in restore_infos.into_iter() {
+                self.restore(restore_info);
+            }
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn update_stats(&mut self, map: HashMap<Id, Stats>) -> Result<(), Self::Error> {
+        let restore_infos: Vec<_> = map
+            .into_iter()
+            .map(|(id, stats)| self.update_stats_map(id, stats))
+            .collect();
+
+        if let Err(e) = self.proxy.save().await {
+            for restore_info in restore_infos.into_iter() {
+                self.restore(restore_info);
+            }
+            Err(e)
         } else {
             Ok(())
         }
@@ -134,11 +357,29 @@ impl DataRepository for TomlDataRepository {
 
         if let Err(e) = self.proxy.save().await {
             self.restore(restore_info);
-            Err(e.into())
+            Err(e)
         } else {
             Ok(restore_info.data)
         }
     }
+
+    async fn save_checkpoint(&mut self, checkpoint: Option<Checkpoint>) -> Result<(), Self::Error> {
+        let checkpoint = match checkpoint {
+            Some(x) => x,
+            None => {
+                return match tokio::fs::remove_file(&self.checkpoint_path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                    _ => Ok(()),
+                };
+            }
+        };
+        let toml = toml::to_string_pretty(&checkpoint).unwrap();
+        // written to another file first, not to leave a partial checkpoint by a crash.
+        let temporary = self.checkpoint_path.with_extension("tmp");
+        tokio::fs::write(&temporary, toml).await?;
+        tokio::fs::rename(&temporary, &self.checkpoint_path).await?;
+        Ok(())
+    }
 }
 
 struct RestoreInfo {
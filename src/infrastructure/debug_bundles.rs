@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use super::content_directory::encode;
+use crate::domain::{Id, Timestamp, Url};
+
+/// The name of the file describing a bundle, listing the other files in it.
+pub const MANIFEST: &str = "bundle.json";
+const SCREENSHOT: &str = "screenshot.png";
+const SOURCE: &str = "page.html";
+const HAR: &str = "network.har";
+/// The headers left out of the HAR, not to keep the credentials and the sessions in the bundles.
+const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+];
+
+/// What was captured from the browser when a poll in the full mode failed, to diagnose it later.
+#[derive(Debug, Clone)]
+pub struct DebugBundle {
+    pub url: Url,
+    pub error: String,
+    /// The screenshot of the page in PNG.
+    pub screenshot: Option<Vec<u8>>,
+    /// The source of the page rendered by the browser.
+    pub source: Option<String>,
+    /// The HAR of the requests sent while polling, if the browser logs them.
+    pub har: Option<Value>,
+    /// The screenshots in PNG taken after the steps of the scripted flow which succeeded, with their names.
+    pub steps: Vec<(String, Vec<u8>)>,
+}
+impl DebugBundle {
+    pub fn new(url: Url, error: String) -> Self {
+        Self {
+            url,
+            error,
+            screenshot: None,
+            source: None,
+            har: None,
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// A directory keeping the debug bundle of the latest failed poll of each target, in a directory named after its id.
+///
+/// Each bundle has `bundle.json` with the error and the names of the files captured,
+/// e.g. `screenshot.png`, `page.html`, `network.har` and `step-1.png`.
+#[derive(Debug, Clone)]
+pub struct DebugBundles {
+    path: PathBuf,
+}
+impl DebugBundles {
+    /// Opens the directory, creating it if it does not exist.
+    pub async fn new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        tokio::fs::create_dir_all(path).await?;
+        Ok(Self { path: path.into() })
+    }
+
+    /// Saves the bundle of the target, replacing the one saved before.
+    pub async fn save(&self, id: &Id, bundle: DebugBundle) -> std::io::Result<()> {
+        let dir = self.path.join(encode(id.as_str()));
+        // written to another directory first, not to leave a partial bundle.
+        let temporary = dir.with_extension("tmp");
+        let _ = tokio::fs::remove_dir_all(&temporary).await;
+        tokio::fs::create_dir_all(&temporary).await?;
+
+        let mut files = Vec::new();
+        if let Some(x) = bundle.screenshot {
+            tokio::fs::write(temporary.join(SCREENSHOT), x).await?;
+            files.push(SCREENSHOT.to_owned());
+        }
+        if let Some(x) = bundle.source {
+            tokio::fs::write(temporary.join(SOURCE), x).await?;
+            files.push(SOURCE.to_owned());
+        }
+        if let Some(x) = bundle.har {
+            tokio::fs::write(temporary.join(HAR), x.to_string()).await?;
+            files.push(HAR.to_owned());
+        }
+        let mut steps = Vec::new();
+        for (i, (name, png)) in bundle.steps.into_iter().enumerate() {
+            let file = format!("step-{}.png", i + 1);
+            tokio::fs::write(temporary.join(&file), png).await?;
+            steps.push(json!({ "name": name, "screenshot": &file }));
+            files.push(file);
+        }
+        let manifest = json!({
+            "id": id.as_str(),
+            "url": bundle.url.as_str(),
+            "error": bundle.error,
+            "captured_at": Timestamp::now(),
+            "files": files,
+            "steps": steps,
+        });
+        tokio::fs::write(temporary.join(MANIFEST), manifest.to_string()).await?;
+
+        match tokio::fs::remove_dir_all(&dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        tokio::fs::rename(&temporary, &dir).await
+    }
+
+    /// Reads the file of the bundle of the target, e.g. `bundle.json`.
+    ///
+    /// Only the names of the files in the bundle are read, not to reach the outside of it.
+    pub async fn read(&self, id: &Id, name: &str) -> std::io::Result<Vec<u8>> {
+        let valid = !name.starts_with('.')
+            && name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '.');
+        if !valid {
+            return Err(std::io::ErrorKind::NotFound.into());
+        }
+        tokio::fs::read(self.path.join(encode(id.as_str())).join(name)).await
+    }
+}
+
+/// The `Content-Type` of the file of a bundle.
+pub fn content_type(name: &str) -> &'static str {
+    match name.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("html") => "text/html; charset=utf-8",
+        _ => "application/json",
+    }
+}
+
+/// Builds a HAR of the requests from the `Network` events of Chrome DevTools Protocol, in the order they were sent.
+///
+/// A request which has not been finished has the response of the status 0 with `_error`.
+/// The headers of the credentials and the cookies are left out, e.g. `Authorization` and `Set-Cookie`.
+pub fn har(events: &[(String, Value)]) -> Value {
+    struct Entry {
+        /// The wall time and the monotonic time when sent, in seconds.
+        sent: (f64, f64),
+        finished: Option<f64>,
+        request: Value,
+        response: Option<Value>,
+        size: Option<i64>,
+        error: Option<String>,
+    }
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+
+    for (method, params) in events.iter() {
+        let request_id = params["requestId"].as_str().unwrap_or_default();
+        let index = indices.get(request_id).copied();
+        match method.as_str() {
+            "Network.requestWillBeSent" => {
+                // a redirect is sent with the same id, after the response of the previous request.
+                if let (Some(i), false) = (index, params["redirectResponse"].is_null()) {
+                    entries[i].response = Some(params["redirectResponse"].clone());
+                    entries[i].finished = params["timestamp"].as_f64();
+                }
+                let _ = indices.insert(request_id, entries.len());
+                entries.push(Entry {
+                    sent: (
+                        params["wallTime"].as_f64().unwrap_or_default(),
+                        params["timestamp"].as_f64().unwrap_or_default(),
+                    ),
+                    finished: None,
+                    request: params["request"].clone(),
+                    response: None,
+                    size: None,
+                    error: None,
+                });
+            }
+            "Network.responseReceived" => {
+                if let Some(i) = index {
+                    entries[i].response = Some(params["response"].clone());
+                }
+            }
+            "Network.loadingFinished" => {
+                if let Some(i) = index {
+                    entries[i].finished = params["timestamp"].as_f64();
+                    entries[i].size = params["encodedDataLength"].as_f64().map(|x| x as i64);
+                }
+            }
+            "Network.loadingFailed" => {
+                if let Some(i) = index {
+                    entries[i].finished = params["timestamp"].as_f64();
+                    entries[i].error = params["errorText"].as_str().map(str::to_owned);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let headers = |x: &Value| -> Value {
+        let list: Vec<_> = x
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(name, _)| !SENSITIVE_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::String(x) => x.clone(),
+                    x => x.to_string(),
+                };
+                json!({ "name": name, "value": value })
+            })
+            .collect();
+        Value::from(list)
+    };
+    let entries: Vec<_> = entries
+        .into_iter()
+        .map(|x| {
+            // in milliseconds to the microseconds.
+            let time = match x.finished {
+                Some(finished) => ((finished - x.sent.1) * 1_000_000.0).round().max(0.0) / 1000.0,
+                None => 0.0,
+            };
+            let response = x.response.unwrap_or(Value::Null);
+            let error = match (x.error, x.finished) {
+                (Some(error), _) => Some(error),
+                (None, None) => Some("not finished".to_owned()),
+                (None, Some(_)) => None,
+            };
+            let mut har_response = json!({
+                "status": response["status"].as_i64().unwrap_or_default(),
+                "statusText": response["statusText"].as_str().unwrap_or_default(),
+                "httpVersion": response["protocol"].as_str().unwrap_or_default(),
+                "cookies": [],
+                "headers": headers(&response["headers"]),
+                "content": {
+                    "size": x.size.unwrap_or(-1),
+                    "mimeType": response["mimeType"].as_str().unwrap_or_default(),
+                },
+                "redirectURL": response["headers"]["location"]
+                    .as_str()
+                    .or_else(|| response["headers"]["Location"].as_str())
+                    .unwrap_or_default(),
+                "headersSize": -1,
+                "bodySize": x.size.unwrap_or(-1),
+            });
+            if let Some(error) = error {
+                har_response["_error"] = Value::from(error);
+            }
+            json!({
+                "startedDateTime": Timestamp::from_unix_millis((x.sent.0 * 1000.0) as i64),
+                "time": time,
+                "request": {
+                    "method": x.request["method"].as_str().unwrap_or_default(),
+                    "url": x.request["url"].as_str().unwrap_or_default(),
+                    "httpVersion": response["protocol"].as_str().unwrap_or_default(),
+                    "cookies": [],
+                    "headers": headers(&x.request["headers"]),
+                    "queryString": [],
+                    "headersSize": -1,
+                    "bodySize": -1,
+                },
+                "response": har_response,
+                "cache": {},
+                "timings": { "send": 0, "wait": time, "receive": 0 },
+            })
+        })
+        .collect();
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "patrol", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    })
+}
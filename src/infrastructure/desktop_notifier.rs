@@ -0,0 +1,107 @@
+use std::{fmt::Display, process::Command};
+
+use crate::domain::Url;
+
+/// Shows notifications by the notification system of the OS,
+/// i.e. the desktop notifications of D-Bus on Linux and BSDs, the Notification Center on macOS, or the toasts on Windows.
+#[derive(Debug, Clone)]
+pub struct DesktopNotifier {
+    app_name: String,
+}
+impl DesktopNotifier {
+    pub fn new() -> Self {
+        Self {
+            app_name: "patrol".to_owned(),
+        }
+    }
+
+    /// Shows the notification of the target of the URL.
+    ///
+    /// This blocks the thread while talking to the notification system.
+    pub fn show(&self, summary: &str, body: &str, url: &Url) -> Result<ShownNotification, Error> {
+        let handle = notify_rust::Notification::new()
+            .appname(&self.app_name)
+            .summary(summary)
+            .body(body)
+            // the default action is invoked by clicking the notification itself.
+            .action("default", "Open")
+            .show()?;
+        Ok(ShownNotification {
+            handle,
+            url: url.clone(),
+        })
+    }
+}
+
+/// A notification on the desktop, which opens the URL of its target when clicked.
+pub struct ShownNotification {
+    handle: notify_rust::NotificationHandle,
+    url: Url,
+}
+impl ShownNotification {
+    /// Waits for the notification to be clicked or closed, and opens the URL in the default browser if clicked.
+    ///
+    /// This blocks the thread until then.
+    pub fn wait_for_click(self) -> Result<(), Error> {
+        let Self { handle, url } = self;
+        let mut result = Ok(());
+        handle.wait_for_action(|action| {
+            if action == "default" {
+                result = open(&url).map_err(Error::Open);
+            }
+        });
+        result
+    }
+}
+
+/// Opens the URL by the default handler of the OS, e.g. the default browser.
+pub fn open(url: &Url) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("rundll32");
+        let _ = command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    let status = command.arg(url.as_str()).status()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(std::io::Error::other(format!(
+            "failed to open {}: {status}",
+            url.as_str()
+        ))),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Notification(notify_rust::error::Error),
+    Open(std::io::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Notification(e) => {
+                f.write_fmt(format_args!("failed to show the notification: {e}"))
+            }
+            Error::Open(e) => f.write_fmt(format_args!("failed to open the URL: {e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Notification(e) => Some(e),
+            Error::Open(e) => Some(e),
+        }
+    }
+}
+impl From<notify_rust::error::Error> for Error {
+    fn from(e: notify_rust::error::Error) -> Self {
+        Error::Notification(e)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{fmt::Display, num::NonZeroU32};
+
+use base64::Engine;
+
+use crate::domain::Decrypter;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// The prefix of an encrypted value.
+pub const PREFIX: &str = "enc:";
+
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Encrypts and decrypts values with ChaCha20-Poly1305, by the key derived from a passphrase with PBKDF2.
+///
+/// An encrypted value is `enc:` followed by the base64 of the salt, the nonce and the sealed value.
+#[derive(Clone)]
+pub struct Cipher {
+    passphrase: Vec<u8>,
+}
+// not to print the passphrase.
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher")
+    }
+}
+impl Decrypter for Cipher {
+    fn decrypt(&self, value: &str) -> Result<String, String> {
+        Cipher::decrypt(self, value).map_err(|e| e.to_string().trim_end_matches('.').to_owned())
+    }
+}
+impl Cipher {
+    pub fn new(passphrase: &str) -> Self {
+        Self {
+            passphrase: passphrase.as_bytes().to_owned(),
+        }
+    }
+
+    /// Whether the value is encrypted.
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(PREFIX)
+    }
+
+    pub fn encrypt(&self, value: &str) -> Result<String, Error> {
+        let random = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        random.fill(&mut salt).map_err(|_| Error::Random)?;
+        random.fill(&mut nonce).map_err(|_| Error::Random)?;
+
+        let mut sealed = value.as_bytes().to_owned();
+        self.key(&salt)
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .expect("a secret must be shorter than the limit of ChaCha20-Poly1305.");
+
+        let bytes = [&salt[..], &nonce[..], &sealed[..]].concat();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+        Ok(format!("{PREFIX}{encoded}"))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, Error> {
+        let encoded = value.strip_prefix(PREFIX).ok_or(Error::Malformed)?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|_| Error::Malformed)?;
+        if bytes.len() < SALT_LEN + NONCE_LEN {
+            return Err(Error::Malformed);
+        }
+        let (salt, rest) = bytes.split_at(SALT_LEN);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| Error::Malformed)?;
+
+        let mut sealed = sealed.to_owned();
+        let opened = self
+            .key(salt)
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| Error::Decryption)?;
+        String::from_utf8(opened.to_owned()).map_err(|_| Error::Malformed)
+    }
+
+    fn key(&self, salt: &[u8]) -> LessSafeKey {
+        let mut key = [0u8; 32];
+        let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            &self.passphrase,
+            &mut key,
+        );
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).unwrap())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The value is not what `Cipher::encrypt` returns.
+    Malformed,
+    /// The passphrase is wrong, or the value has been altered.
+    Decryption,
+    Random,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Malformed => f.write_str("the encrypted value is malformed."),
+            Error::Decryption => f.write_str(
+                "failed to decrypt the value. the passphrase is wrong, or the value has been altered.",
+            ),
+            Error::Random => f.write_str("failed to generate random bytes."),
+        }
+    }
+}
+impl std::error::Error for Error {}
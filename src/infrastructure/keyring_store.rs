@@ -0,0 +1,41 @@
+use crate::domain::SecretStore;
+
+/// The service under which the secrets of patrol are kept in the keyring.
+pub const SERVICE: &str = "patrol";
+
+/// Reads the secrets from the keyring of the OS: the keychain of macOS, the Credential Manager of Windows,
+/// or the Secret Service of Linux, e.g. GNOME Keyring or KWallet.
+///
+/// Each secret is the password of the entry whose service is `patrol` and whose user is the name.
+#[derive(Debug, Clone)]
+pub struct KeyringStore;
+impl KeyringStore {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Keeps the secret of the name in the keyring, replacing the one kept before.
+    pub fn set(&self, name: &str, secret: &str) -> Result<(), keyring::Error> {
+        let (name, secret) = (name.to_owned(), secret.to_owned());
+        outside_runtime(move || keyring::Entry::new(SERVICE, &name)?.set_password(&secret))
+    }
+}
+impl SecretStore for KeyringStore {
+    fn get(&self, name: &str) -> Result<String, String> {
+        let name = name.to_owned();
+        outside_runtime(move || keyring::Entry::new(SERVICE, &name)?.get_password())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Runs the call on a thread of its own, since the Secret Service blocks on a runtime of its own,
+/// which can't be started within the one of patrol.
+fn outside_runtime<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, keyring::Error> + Send + 'static,
+) -> Result<T, keyring::Error> {
+    std::thread::spawn(f).join().unwrap_or_else(|_| {
+        Err(keyring::Error::PlatformFailure(
+            "the keyring panicked".into(),
+        ))
+    })
+}
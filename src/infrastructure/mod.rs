@@ -1,10 +1,59 @@
+pub mod api_server;
+pub mod change_feeds;
+pub mod composites;
 pub mod config_repository;
+pub mod content_directory;
+pub mod content_samples;
 pub mod data_repository;
+pub mod debug_bundles;
+#[cfg(feature = "desktop")]
+pub mod desktop_notifier;
+pub mod encryption;
+#[cfg(feature = "keyring")]
+pub mod keyring_store;
+pub mod page_links;
 pub mod poller;
+pub mod publisher;
+pub mod recordings;
+pub mod rotating_file;
+pub mod secrets;
+pub mod selector_candidates;
+pub mod sms_notifier;
+pub mod ssh_tunnel;
+pub mod subscription_repository;
+#[cfg(unix)]
+pub mod systemd;
+#[cfg(feature = "integrity")]
+pub mod tls_certificate;
 pub mod toml_file_proxy;
+pub mod webhook;
+#[cfg(windows)]
+pub mod windows;
+#[cfg(feature = "xmpp")]
+pub mod xmpp_notifier;
 
 pub use self::config_repository::*;
 pub use self::data_repository::*;
 pub use self::poller::*;
+pub use self::publisher::*;
+pub use self::subscription_repository::*;
 
+pub use api_server::{ApiServer, NotifierTest, TargetFiles};
+pub use content_directory::ContentDirectory;
+pub use content_samples::ContentSamples;
+pub use debug_bundles::{DebugBundle, DebugBundles};
+#[cfg(feature = "desktop")]
+pub use desktop_notifier::DesktopNotifier;
+pub use encryption::Cipher;
+#[cfg(feature = "keyring")]
+pub use keyring_store::KeyringStore;
+pub use page_links::find_links;
+pub use recordings::Recordings;
+pub use rotating_file::RotatingFile;
+pub use selector_candidates::{find_candidates, Candidate};
+pub use sms_notifier::SmsNotifier;
+pub use ssh_tunnel::{SshTunnels, TunnelConfig};
 pub use toml_file_proxy::TomlFileProxy;
+pub use webhook::WebhookNotifier;
+#[cfg(feature = "xmpp")]
+pub use xmpp_notifier::XmppNotifier;
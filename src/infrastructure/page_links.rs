@@ -0,0 +1,53 @@
+use scraper::Html;
+
+use crate::domain::{Selector, Url};
+
+/// Lists the pages linked from the elements of the page matching the selector, e.g. the product pages of a category page.
+///
+/// A matched element which is not a link has the links in it listed instead.
+/// The links are resolved against the URL of the page, or its `<base>`, without the fragments.
+/// They are in document order without duplicates, and exclude the page itself and the non-HTTP ones, e.g. `mailto:`.
+pub fn find_links(html: &str, url: &Url, selector: &Selector) -> Vec<Url> {
+    let doc = Html::parse_document(html);
+    let selector = match scraper::Selector::parse(selector.as_str()) {
+        Ok(x) => x,
+        Err(_) => return Vec::new(),
+    };
+    let anchors = scraper::Selector::parse("a[href], area[href]").unwrap();
+    let base_selector = scraper::Selector::parse("base[href]").unwrap();
+
+    let base = doc
+        .select(&base_selector)
+        .next()
+        .and_then(|x| x.value().attr("href"))
+        .and_then(|x| url.as_url().join(x.trim()).ok())
+        .unwrap_or_else(|| url.as_url().clone());
+
+    let mut links: Vec<Url> = Vec::new();
+    for elem in doc.select(&selector) {
+        let hrefs: Vec<&str> = match elem.value().attr("href") {
+            Some(href) => vec![href],
+            None => elem
+                .select(&anchors)
+                .filter_map(|x| x.value().attr("href"))
+                .collect(),
+        };
+        for href in hrefs {
+            let mut link = match base.join(href.trim()) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            link.set_fragment(None);
+            let link = match Url::new(link.into()) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            if !link.is_http() || link == *url || links.contains(&link) {
+                continue;
+            }
+            links.push(link);
+        }
+    }
+
+    links
+}
@@ -0,0 +1,353 @@
+use std::{collections::HashMap, fmt::Display, sync::Arc, time::Instant};
+
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        emulation::{SetDeviceMetricsOverrideParams, SetTouchEmulationEnabledParams},
+        network,
+    },
+    handler::HandlerConfig,
+    types::MethodId,
+    Browser, BrowserConfig, Page,
+};
+use futures_util::StreamExt;
+use log::debug;
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+
+use crate::domain::{
+    Challenge, Config, Id, LanguageMismatch, PollOutput, PollStream, Poller, Url, WaitStrategy,
+};
+
+use super::common::{
+    blocked_urls, remove_script, FIND_SCRIPT, LANGUAGE_SCRIPT, PIERCING_FIND_SCRIPT,
+    RESOURCE_COUNT_SCRIPT, WAIT_PERIOD,
+};
+
+/// A poller talking Chrome DevTools Protocol directly to a chrome, without WebDriver.
+///
+/// Each poll opens a new tab, and closes it afterwards.
+#[derive(Debug)]
+pub struct CdpPoller {
+    browser: Arc<Browser>,
+    tabs: Arc<Semaphore>,
+    handler: tokio::task::JoinHandle<()>,
+}
+
+impl CdpPoller {
+    /// Connects to a running chrome, e.g. `http://localhost:9222` for `--remote-debugging-port=9222`.
+    pub async fn connect(url: &str, max_tabs: usize) -> Result<Self, Error> {
+        let config = HandlerConfig {
+            viewport: None,
+            ..Default::default()
+        };
+        let (browser, handler) = Browser::connect_with_config(url, config).await?;
+        Ok(Self::new(browser, handler, max_tabs))
+    }
+
+    /// Launches a headless chrome installed in the system.
+    pub async fn launch(max_tabs: usize) -> Result<Self, Error> {
+        let config = BrowserConfig::builder()
+            .viewport(None)
+            .build()
+            .map_err(Error::LaunchError)?;
+        let (browser, handler) = Browser::launch(config).await?;
+        Ok(Self::new(browser, handler, max_tabs))
+    }
+
+    fn new(browser: Browser, mut handler: chromiumoxide::Handler, max_tabs: usize) -> Self {
+        let handler = tokio::spawn(async move {
+            while let Some(result) = handler.next().await {
+                if let Err(why) = result {
+                    debug!("CDP handler: {why}");
+                }
+            }
+        });
+
+        Self {
+            browser: Arc::new(browser),
+            tabs: Arc::new(Semaphore::new(max_tabs.max(1))),
+            handler,
+        }
+    }
+}
+
+impl Drop for CdpPoller {
+    fn drop(&mut self) {
+        self.handler.abort();
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for CdpPoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        poll(&self.browser, &self.tabs, &config).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let browser = self.browser.clone();
+            let tabs = self.tabs.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                debug!("[{}]: start polling {}", &id, config.url.as_str());
+                let result = poll(&browser, &tabs, &config).await;
+                debug!("[{}]: polling finished", &id);
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x;
+            }
+        })
+    }
+}
+
+async fn poll(browser: &Browser, tabs: &Semaphore, config: &Config) -> Result<PollOutput, Error> {
+    if 0 < config.frames.len() {
+        return Err(Error::Unsupported("frames"));
+    }
+    if 0 < config.steps.len() {
+        return Err(Error::Unsupported("steps"));
+    }
+
+    let _permit = tabs.acquire().await.unwrap();
+    let page = browser.new_page("about:blank").await?;
+
+    let started = Instant::now();
+    let result = match visit(&page, config).await {
+        Err(why) => Err(check_challenge(&page, why).await),
+        x => x,
+    };
+    let elapsed = started.elapsed();
+    let final_url = match result.is_ok() {
+        true => page.url().await.ok().flatten(),
+        false => None,
+    };
+
+    if let Err(why) = page.close().await {
+        debug!("failed to close the tab: {why}");
+    }
+
+    let mut output = PollOutput::new(result?).with_elapsed(elapsed);
+    output.final_url = final_url.and_then(|x| Url::new(x).ok());
+    Ok(output)
+}
+
+/// Replaces the failure with `Error::Blocked` if the page is a challenge page of an anti-bot service.
+async fn check_challenge(page: &Page, why: Error) -> Error {
+    match page
+        .content()
+        .await
+        .ok()
+        .and_then(|x| Challenge::detect(&x))
+    {
+        Some(challenge) => Error::Blocked(challenge),
+        None => why,
+    }
+}
+
+/// Navigates to the target and reads the content.
+async fn visit(page: &Page, config: &Config) -> Result<String, Error> {
+    if let Some(viewport) = config.viewport.as_ref() {
+        let params = SetDeviceMetricsOverrideParams::new(
+            viewport.width,
+            viewport.height,
+            viewport.device_scale_factor.unwrap_or(0.0),
+            viewport.mobile,
+        );
+        page.execute(params).await?;
+        if viewport.mobile {
+            page.execute(SetTouchEmulationEnabledParams::new(true))
+                .await?;
+        }
+    }
+
+    let urls = blocked_urls(config);
+    if 0 < urls.len() {
+        page.execute(network::EnableParams::default()).await?;
+        page.execute(SetBlockedUrls { urls }).await?;
+    }
+
+    if let Some(language) = config.language.as_ref() {
+        let headers = network::Headers::new(json!({ "Accept-Language": language }));
+        page.execute(network::EnableParams::default()).await?;
+        page.execute(network::SetExtraHttpHeadersParams::new(headers))
+            .await?;
+    }
+
+    page.goto(config.url.as_str()).await?;
+
+    if let Some(delay) = config.delay {
+        tokio::time::sleep(delay.as_std()).await;
+    }
+
+    if let Some(language) = config.language.as_ref() {
+        let served = evaluate(page, LANGUAGE_SCRIPT, vec![]).await?;
+        LanguageMismatch::check(language, served.as_str().unwrap_or_default())?;
+    }
+
+    extract(page, config).await
+}
+
+/// Waits until the page is ready according to the strategy, and reads the content.
+async fn extract(page: &Page, config: &Config) -> Result<String, Error> {
+    let deadline = tokio::time::Instant::now() + config.wait.timeout();
+
+    match &config.wait {
+        WaitStrategy::Selector { .. } => (),
+        WaitStrategy::Text { .. } => loop {
+            if let Some(Value::String(text)) = read(page, config, None).await? {
+                if 0 < text.trim().len() {
+                    return Ok(text);
+                }
+            }
+            sleep_before(deadline).await?;
+        },
+        WaitStrategy::NetworkIdle { .. } => {
+            let idle = config.wait.idle();
+            let mut last = (Value::Null, tokio::time::Instant::now());
+            loop {
+                let count = evaluate(page, RESOURCE_COUNT_SCRIPT, vec![]).await?;
+                let now = tokio::time::Instant::now();
+                if count != last.0 {
+                    last = (count, now);
+                } else if !count.is_null() && idle <= now - last.1 {
+                    break;
+                }
+                sleep_before(deadline).await?;
+            }
+        }
+        WaitStrategy::Script { script, .. } => {
+            let script = format!("return !!({script});");
+            while evaluate(page, &script, vec![]).await? != Value::Bool(true) {
+                sleep_before(deadline).await?;
+            }
+        }
+    }
+
+    loop {
+        if let Some(content) = read(page, config, config.script.as_deref()).await? {
+            let content = match content {
+                Value::Null => String::new(),
+                Value::String(x) => x,
+                x => x.to_string(),
+            };
+            return Ok(content);
+        }
+        sleep_before(deadline).await?;
+    }
+}
+
+/// Finds the element matched by the selector, and returns its text or the return value of the script.
+///
+/// Returns `None` if no element is matched.
+async fn read(page: &Page, config: &Config, script: Option<&str>) -> Result<Option<Value>, Error> {
+    let find = match config.pierce_shadow {
+        true => PIERCING_FIND_SCRIPT,
+        false => FIND_SCRIPT,
+    };
+    let read = match script {
+        Some(script) => format!("(function() {{ {script} }}).apply(null, [elem])"),
+        None => "elem.innerText".to_owned(),
+    };
+    let remove = remove_script(&config.ignore_selectors);
+    let body = format!(
+        "{remove}
+        const elem = (function() {{ {find} }}).apply(null, arguments);
+        return elem ? {{ found: true, value: {read} }} : {{ found: false }};"
+    );
+
+    let result = evaluate(page, &body, vec![json!(config.selector.as_str())]).await?;
+    if result["found"] != Value::Bool(true) {
+        return Ok(None);
+    }
+    Ok(Some(result["value"].clone()))
+}
+
+/// Evaluates the body of a function with the arguments, in the same way as WebDriver's `execute`.
+async fn evaluate(page: &Page, body: &str, args: Vec<Value>) -> Result<Value, Error> {
+    let expression = format!(
+        "(function() {{ {body} }}).apply(null, {})",
+        Value::Array(args)
+    );
+    let result = page.evaluate(expression).await?;
+    Ok(result.value().cloned().unwrap_or(Value::Null))
+}
+
+/// Sleeps before checking the condition again, or fails if the deadline has passed.
+async fn sleep_before(deadline: tokio::time::Instant) -> Result<(), Error> {
+    if deadline <= tokio::time::Instant::now() {
+        return Err(Error::Timeout);
+    }
+    tokio::time::sleep(WAIT_PERIOD).await;
+    Ok(())
+}
+
+/// `Network.setBlockedURLs` with the wildcard `urls`,
+/// which the bundled protocol definition no longer has.
+#[derive(serde_derive::Serialize, Debug)]
+struct SetBlockedUrls {
+    urls: Vec<String>,
+}
+impl chromiumoxide::Method for SetBlockedUrls {
+    fn identifier(&self) -> MethodId {
+        "Network.setBlockedURLs".into()
+    }
+}
+impl chromiumoxide::Command for SetBlockedUrls {
+    type Response = Value;
+}
+
+#[derive(Debug)]
+pub enum Error {
+    CdpError(chromiumoxide::error::CdpError),
+    LaunchError(String),
+    Unsupported(&'static str),
+    Timeout,
+    Blocked(Challenge),
+    Language(LanguageMismatch),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CdpError(e) => {
+                f.write_fmt(format_args!("failed to manipulate the browser: {e}"))
+            }
+            Error::LaunchError(e) => f.write_fmt(format_args!("failed to launch the browser: {e}")),
+            Error::Unsupported(x) => {
+                f.write_fmt(format_args!("{x} is not supported by the CDP poller."))
+            }
+            Error::Timeout => f.write_fmt(format_args!("timeout waiting on condition.")),
+            Error::Blocked(e) => f.write_fmt(format_args!("{e}")),
+            Error::Language(e) => f.write_fmt(format_args!("{e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::CdpError(e) => Some(e),
+            Error::Blocked(e) => Some(e),
+            Error::Language(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<LanguageMismatch> for Error {
+    fn from(e: LanguageMismatch) -> Self {
+        Error::Language(e)
+    }
+}
+impl From<chromiumoxide::error::CdpError> for Error {
+    fn from(e: chromiumoxide::error::CdpError) -> Self {
+        Error::CdpError(e)
+    }
+}
@@ -0,0 +1,59 @@
+//! Pieces shared by the pollers driving a browser.
+
+use crate::domain::{Config, ResourceType, Selector};
+
+/// Interval between checks of a wait condition.
+pub const WAIT_PERIOD: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Returns the number of resources loaded so far, or `null` while the document is loading.
+pub const RESOURCE_COUNT_SCRIPT: &str = "return document.readyState === 'complete' ? performance.getEntriesByType('resource').length : null;";
+
+/// Returns the text of the element passed as the argument.
+pub const TEXT_SCRIPT: &str = "return arguments[0].innerText;";
+
+/// Returns the language of the document, i.e. the `lang` of `<html>`.
+pub const LANGUAGE_SCRIPT: &str = "return document.documentElement.lang;";
+
+/// Returns the first element matched by the selector passed as the argument.
+#[cfg(feature = "cdp")]
+pub const FIND_SCRIPT: &str = "return document.querySelector(arguments[0]);";
+
+/// Returns the first element matched by the selector, looking into open shadow roots recursively.
+pub const PIERCING_FIND_SCRIPT: &str = r#"
+const find = (root, selector) => {
+    const found = root.querySelector(selector);
+    if (found) return found;
+    for (const elem of root.querySelectorAll('*')) {
+        if (elem.shadowRoot) {
+            const x = find(elem.shadowRoot, selector);
+            if (x) return x;
+        }
+    }
+    return null;
+};
+return find(document, arguments[0]);
+"#;
+
+/// Returns the body of a script removing the elements matched by the selectors.
+pub fn remove_script(selectors: &[Selector]) -> String {
+    let selectors: Vec<_> = selectors.iter().map(Selector::as_str).collect();
+    format!(
+        "for (const selector of {}) {{ document.querySelectorAll(selector).forEach(x => x.remove()); }}",
+        serde_json::json!(selectors)
+    )
+}
+
+/// Returns the URL patterns to block for the target.
+pub fn blocked_urls(config: &Config) -> Vec<String> {
+    let patterns = config.block.iter().flat_map(|x| {
+        let extensions: &[&str] = match x {
+            ResourceType::Image => &["png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "ico"],
+            ResourceType::Font => &["woff", "woff2", "ttf", "otf", "eot"],
+            ResourceType::Media => &["mp4", "webm", "ogg", "mp3", "wav", "m4a", "m3u8"],
+        };
+        extensions
+            .iter()
+            .flat_map(|ext| [format!("*.{ext}"), format!("*.{ext}?*")])
+    });
+    patterns.chain(config.block_urls.iter().cloned()).collect()
+}
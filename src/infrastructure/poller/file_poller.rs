@@ -0,0 +1,161 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::domain::{Config, Hash, Id, PollOutput, PollStream, Poller, Url};
+
+/// The size of the largest file whose text is the content. A larger one is hashed as it is read.
+const MAX_TEXT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// A poller reading local files of `file://` URLs, whose path may be a glob pattern or a directory.
+///
+/// The content of a single text file is the text itself.
+/// A single file which is not UTF-8 or is larger than 16 MiB is the SHA-256 of it instead.
+/// Otherwise, it is a line of the path and the SHA-256 of each file, so that adding or removing a file is also a change.
+/// The selector is not used.
+#[derive(Debug, Default)]
+pub struct FilePoller;
+
+impl FilePoller {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for FilePoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        poll(config.url).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let result = poll(config.url).await;
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x
+            }
+        })
+    }
+}
+
+async fn poll(url: Url) -> Result<PollOutput, Error> {
+    let started = Instant::now();
+    let path = to_path(&url)?;
+    let content = match tokio::task::spawn_blocking(move || read(&path)).await {
+        Ok(result) => result?,
+        Err(why) => return Err(Error::IoError(std::io::Error::other(why))),
+    };
+    Ok(PollOutput::new(content).with_elapsed(started.elapsed()))
+}
+
+/// Returns the path of the URL, keeping the glob characters.
+fn to_path(url: &Url) -> Result<PathBuf, Error> {
+    Some(url.as_url())
+        .filter(|_| url.is_file())
+        .and_then(|x| x.to_file_path().ok())
+        .ok_or_else(|| Error::InvalidPath(url.as_str().to_owned()))
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    let pattern = path.to_string_lossy();
+    let is_pattern = pattern.contains(['*', '[']);
+
+    if !is_pattern && path.is_file() {
+        if MAX_TEXT_BYTES < std::fs::metadata(path)?.len() {
+            return Ok(Hash::from_reader(std::fs::File::open(path)?)?.to_string());
+        }
+        let bytes = std::fs::read(path)?;
+        return match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(e) => Ok(Hash::new(e.as_bytes()).to_string()),
+        };
+    }
+
+    let mut files = Vec::new();
+    if is_pattern {
+        for entry in glob::glob(&pattern)? {
+            collect_files(&entry?, &mut files)?;
+        }
+    } else {
+        collect_files(path, &mut files)?;
+    }
+    files.sort();
+
+    let mut lines = Vec::with_capacity(files.len());
+    for file in files.iter() {
+        let hash = Hash::from_reader(std::fs::File::open(file)?)?;
+        lines.push(format!("{}: {}", file.display(), hash));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Collects the file, or the files in the directory recursively.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        collect_files(&entry?.path(), files)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    InvalidPath(String),
+    PatternError(glob::PatternError),
+    GlobError(glob::GlobError),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => f.write_fmt(format_args!("failed to read the file: {e}")),
+            Error::InvalidPath(x) => f.write_fmt(format_args!("not a local file path: {x}")),
+            Error::PatternError(e) => f.write_fmt(format_args!("invalid glob pattern: {e}")),
+            Error::GlobError(e) => f.write_fmt(format_args!("failed to read the file: {e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::InvalidPath(_) => None,
+            Error::PatternError(e) => Some(e),
+            Error::GlobError(e) => Some(e),
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+impl From<glob::PatternError> for Error {
+    fn from(e: glob::PatternError) -> Self {
+        Error::PatternError(e)
+    }
+}
+impl From<glob::GlobError> for Error {
+    fn from(e: glob::GlobError) -> Self {
+        Error::GlobError(e)
+    }
+}
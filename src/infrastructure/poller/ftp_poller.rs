@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fmt::Display, net::IpAddr, time::Instant};
+
+use super::curl;
+use crate::domain::{Config, Hash, Id, PollOutput, PollStream, Poller, Url};
+
+/// Timeout of a transfer made by curl.
+const TRANSFER_TIMEOUT_SECONDS: u64 = 60;
+
+/// A poller of `ftp://`, `ftps://` and `sftp://` URLs, fetched by curl.
+///
+/// A URL ending with `/` is a directory, whose content is the listing of the server.
+/// Otherwise, the content is the text of the file, or its SHA-256 if it is not a text.
+/// The selector is not used.
+/// The URL is given to curl on the standard input, not to show the credentials in it to the other users.
+#[derive(Debug, Default)]
+pub struct FtpPoller {
+    /// Local SOCKS ports of the SSH tunnels keyed by the names.
+    tunnels: HashMap<String, u16>,
+}
+
+impl FtpPoller {
+    pub fn new() -> Self {
+        Self {
+            tunnels: HashMap::new(),
+        }
+    }
+
+    /// Lets the targets connect through the SSH tunnels, given the local SOCKS ports keyed by the names.
+    pub fn with_tunnels(mut self, ports: HashMap<String, u16>) -> Self {
+        self.tunnels = ports;
+        self
+    }
+
+    fn socks_port(&self, config: &Config) -> Result<Option<u16>, Error> {
+        match config.tunnel.as_ref() {
+            Some(name) => match self.tunnels.get(name) {
+                Some(x) => Ok(Some(*x)),
+                None => Err(Error::UnknownTunnel(name.clone())),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for FtpPoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        let socks_port = self.socks_port(&config)?;
+        fetch(&config.url, config.local_address, socks_port).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let tx = tx.clone();
+            let socks_port = self.socks_port(&config);
+            tokio::spawn(async move {
+                let result = match socks_port {
+                    Ok(socks_port) => fetch(&config.url, config.local_address, socks_port).await,
+                    Err(e) => Err(e),
+                };
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x
+            }
+        })
+    }
+}
+
+async fn fetch(
+    url: &Url,
+    local_address: Option<IpAddr>,
+    socks_port: Option<u16>,
+) -> Result<PollOutput, Error> {
+    let started = Instant::now();
+    let mut cmd = tokio::process::Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--fail"])
+        .args(["--max-time", &TRANSFER_TIMEOUT_SECONDS.to_string()]);
+    if let Some(addr) = local_address {
+        cmd.args(["--interface", &addr.to_string()]);
+    }
+    if let Some(port) = socks_port {
+        cmd.args(["--socks5-hostname", &format!("127.0.0.1:{port}")]);
+    }
+    let output = curl::run(cmd, &curl::config_line("url", url.as_str())).await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Transfer(stderr.trim().to_owned()));
+    }
+    let content = match String::from_utf8(output.stdout) {
+        Ok(text) => text,
+        Err(e) => Hash::new(e.as_bytes()).to_string(),
+    };
+    Ok(PollOutput::new(content).with_elapsed(started.elapsed()))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    Transfer(String),
+    UnknownTunnel(String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => f.write_fmt(format_args!("failed to run curl: {e}")),
+            Error::Transfer(e) => f.write_fmt(format_args!("failed to transfer: {e}")),
+            Error::UnknownTunnel(x) => f.write_fmt(format_args!("unknown tunnel: {x}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::Transfer(_) => None,
+            Error::UnknownTunnel(_) => None,
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
@@ -1,69 +1,539 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, net::IpAddr, time::Instant};
 
-use futures_util::Stream;
+use log::warn;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::domain::{Config, Id, Poller};
+use crate::domain::{
+    is_browser_name, Challenge, Config, ExtractionError, Hash, Id, LanguageMismatch, Metadata,
+    Mode, ParseError, PollOutput, PollStream, Poller, Selector, Url,
+};
+use crate::infrastructure::Recordings;
 
-#[derive(Debug)]
+/// Timeout of a request made by curl-impersonate.
+const IMPERSONATE_TIMEOUT_SECONDS: u64 = 60;
+/// The size of the largest body read, not to hold a pathological response in memory.
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// Clones share the connection pool of the client.
+#[derive(Debug, Clone)]
 pub struct HttpPoller {
     client: Client,
+    /// Clients bound to the local addresses or the tunnels, created on demand.
+    bound_clients: HashMap<(Option<IpAddr>, Option<u16>), Client>,
+    /// Local SOCKS ports of the SSH tunnels keyed by the names.
+    tunnels: HashMap<String, u16>,
+    recordings: Option<Recordings>,
 }
 
 impl HttpPoller {
     pub fn new() -> Self {
         let client = Client::new();
-        Self { client }
+        Self {
+            client,
+            bound_clients: HashMap::new(),
+            tunnels: HashMap::new(),
+            recordings: None,
+        }
+    }
+
+    /// Records the pages fetched in the simple mode, to replay them by `ReplayPoller`.
+    pub fn with_recordings(mut self, recordings: Recordings) -> Self {
+        self.recordings = recordings.into();
+        self
+    }
+
+    /// Lets the targets connect through the SSH tunnels, given the local SOCKS ports keyed by the names.
+    pub fn with_tunnels(mut self, ports: HashMap<String, u16>) -> Self {
+        self.tunnels = ports;
+        self
+    }
+
+    /// Returns the client bound to the local address and the tunnel of the target.
+    fn client_for(&mut self, config: &Config) -> Result<Client, Error> {
+        let key = (config.local_address, self.socks_port(config)?);
+        if key == (None, None) {
+            return Ok(self.client.clone());
+        }
+        if let Some(client) = self.bound_clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let mut builder = Client::builder().local_address(key.0);
+        if let Some(port) = key.1 {
+            builder = builder.proxy(reqwest::Proxy::all(format!("socks5h://127.0.0.1:{port}"))?);
+        }
+        let client = builder.build()?;
+        let _ = self.bound_clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    fn socks_port(&self, config: &Config) -> Result<Option<u16>, Error> {
+        match config.tunnel.as_ref() {
+            Some(name) => match self.tunnels.get(name) {
+                Some(x) => Ok(Some(*x)),
+                None => Err(Error::UnknownTunnel(name.clone())),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch the whole HTML of the page.
+    pub async fn fetch_source(&self, url: &Url) -> Result<String, reqwest::Error> {
+        let response = self.client.get(url.as_str()).send().await?;
+        response.text().await
     }
 }
 
 #[async_trait::async_trait]
 impl Poller for HttpPoller {
-    type Error = reqwest::Error;
-    type Stream = impl Stream<Item = (Id, Result<String, Self::Error>)>;
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
 
-    async fn poll(&mut self, _id: Id, config: Config) -> Result<String, Self::Error> {
-        poll(&self.client, config).await
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        let client = self.client_for(&config)?;
+        let socks_port = self.socks_port(&config)?;
+        poll(&client, config, socks_port, self.recordings.as_ref()).await
     }
 
     async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
 
         for (id, config) in configs.into_iter() {
-            let client = self.client.clone();
+            let client = self.client_for(&config);
+            let socks_port = self.socks_port(&config).ok().flatten();
+            let recordings = self.recordings.clone();
             let tx = tx.clone();
             tokio::spawn(async move {
-                let result = poll(&client, config).await;
+                let result = match client {
+                    Ok(client) => poll(&client, config, socks_port, recordings.as_ref()).await,
+                    Err(why) => Err(why),
+                };
                 let _ = tx.send((id, result));
             });
         }
         drop(tx);
 
-        async_stream::stream! {
+        Box::pin(async_stream::stream! {
             while let Some(x) = rx.recv().await {
                 yield x
             }
+        })
+    }
+
+    async fn metadata(&mut self, _id: Id, config: Config) -> Result<Metadata, Self::Error> {
+        let client = self.client_for(&config)?;
+        let response = client.get(config.url.as_str()).send().await?;
+        let base = response.url().clone();
+        let (txt, _) = read_body(response).await?;
+        tokio::task::spawn_blocking(move || discover_metadata(&txt, &base))
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn certificate(&mut self, _id: Id, config: Config) -> Result<Option<Hash>, Self::Error> {
+        if config.url.scheme() != "https" {
+            return Ok(None);
+        }
+        if let Some(tunnel) = config.tunnel {
+            return Err(Error::Certificate(std::io::Error::other(format!(
+                "not read through the tunnel {tunnel}"
+            ))));
         }
+        #[cfg(feature = "integrity")]
+        let fingerprint = crate::infrastructure::tls_certificate::fingerprint(&config.url).await;
+        #[cfg(not(feature = "integrity"))]
+        let fingerprint = Err(std::io::Error::other(
+            "the `integrity` feature is not built",
+        ));
+        fingerprint.map(Some).map_err(Error::Certificate)
     }
 }
 
-async fn poll(client: &Client, config: Config) -> Result<String, reqwest::Error> {
-    let Config { url, selector, .. } = config;
+/// Reads the `<title>` and the `<link rel="icon">` of the page, resolving the URL of the icon from the base.
+fn discover_metadata(txt: &str, base: &reqwest::Url) -> Metadata {
+    let doc = Html::parse_document(txt);
+
+    let title = scraper::Selector::parse("title").unwrap();
+    let title = doc
+        .select(&title)
+        .next()
+        .map(|x| x.text().collect::<String>())
+        .map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|x| 0 < x.len());
+
+    let link = scraper::Selector::parse("link[rel][href]").unwrap();
+    let favicon = doc
+        .select(&link)
+        .find(|x| {
+            let rel = x.value().attr("rel").unwrap_or_default();
+            rel.split_whitespace()
+                .any(|x| x.eq_ignore_ascii_case("icon"))
+        })
+        .and_then(|x| base.join(x.value().attr("href")?).ok())
+        .and_then(|x| Url::new(x.into()).ok());
+
+    Metadata { title, favicon }
+}
+
+async fn poll(
+    client: &Client,
+    config: Config,
+    socks_port: Option<u16>,
+    recordings: Option<&Recordings>,
+) -> Result<PollOutput, Error> {
+    let started = Instant::now();
+    if config.mode == Mode::Graphql {
+        return query(client, config, started).await;
+    }
+
+    let Config {
+        url,
+        selector,
+        impersonate,
+        local_address,
+        headers,
+        headers_only,
+        ignore_selectors,
+        language,
+        ..
+    } = config;
+
+    let with_headers = 0 < headers.len();
+    // the status and the final URL are not known from curl-impersonate.
+    let (received, (txt, lossy), status, final_url) = match impersonate {
+        Some(browser) => {
+            let (received, txt) = fetch_impersonating(
+                &browser,
+                &url,
+                local_address,
+                socks_port,
+                with_headers,
+                language.as_deref(),
+            )
+            .await?;
+            (received, txt, None, None)
+        }
+        None => {
+            let mut request = client.get(url.as_str());
+            if let Some(language) = language.as_ref() {
+                request = request.header(reqwest::header::ACCEPT_LANGUAGE, language);
+            }
+            let response = request.send().await?;
+            let status = response.status().as_u16();
+            let final_url = Url::new(response.url().to_string()).ok();
+            let received = response
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str().to_owned(), v.to_str().ok()?.to_owned())))
+                .collect();
+            let txt = match headers_only {
+                true => (String::new(), false),
+                false => read_body(response).await?,
+            };
+            (received, txt, Some(status), final_url)
+        }
+    };
+
+    if let (Some(recordings), false) = (recordings, headers_only) {
+        if let Err(why) = recordings.record(Mode::Simple, &url, &txt).await {
+            warn!("failed to record {}: {why}", url.as_str());
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    if !headers_only {
+        // parsing a large page takes long, so it is done off the workers of the runtime.
+        let extracted = tokio::task::spawn_blocking(move || {
+            extract(&txt, &selector, &ignore_selectors, language.as_deref())
+        });
+        lines.extend(extracted.await??);
+    }
+
+    let header = |name: &str| {
+        let found = received.iter().find(|(k, _)| k.eq_ignore_ascii_case(name));
+        found.map(|(_, v)| v.clone())
+    };
+    for name in headers.iter() {
+        if let Some(value) = header(name) {
+            lines.push(format!("{name}: {value}"));
+        }
+    }
 
-    let response = client.get(url.as_str()).send().await?;
-    let txt = response.text().await?;
+    let mut output = PollOutput::new(lines.join("\n")).with_elapsed(started.elapsed());
+    output.status = status;
+    output.content_type = header(reqwest::header::CONTENT_TYPE.as_str());
+    output.final_url = final_url;
+    output.lossy = lossy;
+    Ok(output)
+}
 
-    let doc = Html::parse_document(&txt);
-    let selector = scraper::Selector::parse(selector.as_str()).unwrap();
+/// Parses the page, and returns the lines of the text in the elements of the selector.
+pub(super) fn extract(
+    txt: &str,
+    selector: &Selector,
+    ignore_selectors: &[Selector],
+    language: Option<&str>,
+) -> Result<Vec<String>, Error> {
+    if let Some(challenge) = Challenge::detect(txt) {
+        return Err(Error::Blocked(challenge));
+    }
 
-    let content = doc
+    let mut doc = Html::parse_document(txt);
+    if let Some(language) = language {
+        let served = doc.root_element().value().attr("lang").unwrap_or_default();
+        LanguageMismatch::check(language, served)?;
+    }
+    for ignored in ignore_selectors.iter() {
+        let ignored = parse_selector(ignored)?;
+        let ids: Vec<_> = doc.select(&ignored).map(|x| x.id()).collect();
+        for id in ids {
+            if let Some(mut node) = doc.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+    }
+    let selector = parse_selector(selector)?;
+
+    Ok(doc
         .select(&selector)
         .flat_map(|x| x.text())
         .map(|x| x.trim_start().trim_end())
         .filter(|x| 0 < x.len())
-        .collect::<Vec<_>>()
-        .join("\n");
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Parses the selector for `scraper`, which may not support every selector valid at loading the config.
+fn parse_selector(selector: &Selector) -> Result<scraper::Selector, Error> {
+    scraper::Selector::parse(selector.as_str()).map_err(|_| {
+        Error::Extraction(ExtractionError(format!(
+            "unsupported selector: {}",
+            selector.as_str()
+        )))
+    })
+}
+
+/// Sends the GraphQL query of the target, and returns the value at its path of the response.
+async fn query(client: &Client, config: Config, started: Instant) -> Result<PollOutput, Error> {
+    let Config {
+        url,
+        query,
+        variables,
+        path,
+        ..
+    } = config;
+
+    let query = query.ok_or_else(|| Error::Graphql("no query is given.".to_owned()))?;
+    let body = serde_json::json!({
+        "query": query,
+        "variables": variables.unwrap_or(serde_json::Value::Null),
+    });
+    let response = client.post(url.as_str()).json(&body).send().await?;
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .map(str::to_owned);
+    let response: serde_json::Value = serde_json::from_str(&read_body(response).await?.0)
+        .map_err(|e| Error::Parse(ParseError(e.to_string())))?;
+
+    if let Some(errors) = response["errors"].as_array().filter(|x| 0 < x.len()) {
+        let messages: Vec<_> = errors
+            .iter()
+            .map(|x| x["message"].as_str().unwrap_or("unknown error"))
+            .collect();
+        return Err(Error::Graphql(messages.join("; ")));
+    }
+
+    let path = path.unwrap_or_else(|| "data".to_owned());
+    let mut value = &response;
+    for key in path.split('.') {
+        value = match key.parse::<usize>() {
+            Ok(index) if value.is_array() => &value[index],
+            _ => &value[key],
+        };
+    }
+    let content = match value {
+        serde_json::Value::Null => {
+            return Err(Error::Extraction(ExtractionError(format!(
+                "no value at {path}."
+            ))))
+        }
+        serde_json::Value::String(x) => x.clone(),
+        x => serde_json::to_string_pretty(x).unwrap_or_default(),
+    };
 
-    Ok(content)
+    let mut output = PollOutput::new(content).with_elapsed(started.elapsed());
+    output.status = Some(status);
+    output.content_type = content_type;
+    Ok(output)
+}
+
+/// Fetches the page with `curl_<browser>` of curl-impersonate, which mimics the TLS and HTTP/2 fingerprint of the browser.
+async fn fetch_impersonating(
+    browser: &str,
+    url: &Url,
+    local_address: Option<IpAddr>,
+    socks_port: Option<u16>,
+    with_headers: bool,
+    language: Option<&str>,
+) -> Result<(Vec<(String, String)>, (String, bool)), Error> {
+    // not to run any other command than curl-impersonate, for a config not built by the builder.
+    if !is_browser_name(browser) {
+        return Err(Error::Impersonate(format!("unknown browser {browser}")));
+    }
+    let command = format!("curl_{browser}");
+    let mut cmd = tokio::process::Command::new(&command);
+    cmd.args(["--silent", "--show-error", "--location", "--fail"])
+        .args(["--max-time", &IMPERSONATE_TIMEOUT_SECONDS.to_string()])
+        .args(["--max-filesize", &MAX_BODY_BYTES.to_string()]);
+    if let Some(addr) = local_address {
+        cmd.args(["--interface", &addr.to_string()]);
+    }
+    if let Some(port) = socks_port {
+        cmd.args(["--socks5-hostname", &format!("127.0.0.1:{port}")]);
+    }
+    if with_headers {
+        cmd.arg("--include");
+    }
+    if let Some(language) = language {
+        cmd.args(["--header", &format!("Accept-Language: {language}")]);
+    }
+    let output = cmd
+        .arg(url.as_str())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| Error::Impersonate(format!("{command}: {e}")))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Impersonate(format!("{command}: {}", stderr.trim())));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // borrowed unless some bytes are replaced.
+    let lossy = matches!(stdout, std::borrow::Cow::Owned(_));
+    if !with_headers {
+        return Ok((Vec::new(), (stdout.into_owned(), lossy)));
+    }
+
+    // every response of the redirects is preceded by its headers, and the last ones are of the page.
+    let mut body = stdout.as_ref();
+    let mut received = Vec::new();
+    while body.starts_with("HTTP/") {
+        let (block, rest) = match body.split_once("\r\n\r\n") {
+            Some(x) => x,
+            None => (body, ""),
+        };
+        received = block
+            .lines()
+            .skip(1)
+            .filter_map(|x| x.split_once(':'))
+            .map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+            .collect();
+        body = rest;
+    }
+    Ok((received, (body.to_owned(), lossy)))
+}
+
+/// Reads the body up to `MAX_BODY_BYTES`, and decodes it by the charset of the `Content-Type`, UTF-8 by default.
+///
+/// The bytes failing to be decoded are replaced with `U+FFFD`, instead of failing the poll, and it is returned whether any is.
+async fn read_body(mut response: reqwest::Response) -> Result<(String, bool), Error> {
+    if let Some(length) = response.content_length() {
+        if MAX_BODY_BYTES < length as usize {
+            return Err(Error::TooLarge(MAX_BODY_BYTES));
+        }
+    }
+    let encoding = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| {
+            x.split(';')
+                .filter_map(|x| x.trim().split_once('='))
+                .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+                .map(|(_, v)| v.trim().trim_matches('"').to_owned())
+        })
+        .and_then(|x| encoding_rs::Encoding::for_label(x.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if MAX_BODY_BYTES < bytes.len() + chunk.len() {
+            return Err(Error::TooLarge(MAX_BODY_BYTES));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let (txt, _, had_errors) = encoding.decode(&bytes);
+    Ok((txt.into_owned(), had_errors))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    RequestError(reqwest::Error),
+    /// The body is larger than the bytes.
+    TooLarge(usize),
+    Parse(ParseError),
+    Extraction(ExtractionError),
+    Impersonate(String),
+    Graphql(String),
+    Blocked(Challenge),
+    Language(LanguageMismatch),
+    UnknownTunnel(String),
+    Certificate(std::io::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::RequestError(e) => f.write_fmt(format_args!("{e}")),
+            Error::TooLarge(x) => f.write_fmt(format_args!("the body is larger than {x} bytes.")),
+            Error::Parse(e) => f.write_fmt(format_args!("{e}")),
+            Error::Extraction(e) => f.write_fmt(format_args!("{e}")),
+            Error::Impersonate(e) => f.write_fmt(format_args!("failed to impersonate: {e}")),
+            Error::Graphql(e) => f.write_fmt(format_args!("failed to query: {e}")),
+            Error::Blocked(e) => f.write_fmt(format_args!("{e}")),
+            Error::Language(e) => f.write_fmt(format_args!("{e}")),
+            Error::UnknownTunnel(x) => f.write_fmt(format_args!("unknown tunnel: {x}")),
+            Error::Certificate(e) => {
+                f.write_fmt(format_args!("failed to read the TLS certificate: {e}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::RequestError(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::Extraction(e) => Some(e),
+            Error::TooLarge(_)
+            | Error::Impersonate(_)
+            | Error::Graphql(_)
+            | Error::UnknownTunnel(_) => None,
+            Error::Blocked(e) => Some(e),
+            Error::Language(e) => Some(e),
+            Error::Certificate(e) => Some(e),
+        }
+    }
+}
+impl From<LanguageMismatch> for Error {
+    fn from(e: LanguageMismatch) -> Self {
+        Error::Language(e)
+    }
+}
+impl From<tokio::task::JoinError> for Error {
+    /// The extraction from the page has been cancelled or has panicked.
+    fn from(e: tokio::task::JoinError) -> Self {
+        Error::Extraction(ExtractionError(e.to_string()))
+    }
+}
+impl From<reqwest::Error> for Error {
+    /// The URL is left out of the error, which may have a secret resolved in it.
+    fn from(e: reqwest::Error) -> Self {
+        Error::RequestError(e.without_url())
+    }
 }
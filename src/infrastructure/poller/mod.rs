@@ -1,5 +1,32 @@
+#[cfg(feature = "cdp")]
+pub mod cdp_poller;
+#[cfg(any(feature = "webdriver", feature = "cdp", feature = "playwright"))]
+// some of the scripts are only used by WebDriver.
+#[cfg_attr(not(feature = "webdriver"), allow(dead_code))]
+mod common;
+mod curl;
+pub mod file_poller;
+pub mod ftp_poller;
 pub mod http_poller;
+pub mod imap_poller;
+#[cfg(feature = "playwright")]
+pub mod playwright_poller;
+pub mod replay_poller;
+#[cfg(feature = "webdriver")]
 pub mod webdriver_poller;
+#[cfg(feature = "websocket")]
+pub mod websocket_poller;
 
+#[cfg(feature = "cdp")]
+pub use self::cdp_poller::CdpPoller;
+pub use self::file_poller::FilePoller;
+pub use self::ftp_poller::FtpPoller;
 pub use self::http_poller::HttpPoller;
-pub use self::webdriver_poller::WebDriverPoller;
+pub use self::imap_poller::ImapPoller;
+#[cfg(feature = "playwright")]
+pub use self::playwright_poller::PlaywrightPoller;
+pub use self::replay_poller::ReplayPoller;
+#[cfg(feature = "webdriver")]
+pub use self::webdriver_poller::{PoolStats, WebDriverPoller};
+#[cfg(feature = "websocket")]
+pub use self::websocket_poller::WebSocketPoller;
@@ -0,0 +1,560 @@
+use std::{collections::HashMap, fmt::Display, sync::Arc, time::Instant};
+
+use async_tungstenite::{
+    tokio::{connect_async, ConnectStream},
+    tungstenite::{self, Message},
+    WebSocketStream,
+};
+use futures_util::StreamExt;
+use log::debug;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+use crate::domain::{
+    Challenge, Config, Id, LanguageMismatch, PollOutput, PollStream, Poller, WaitStrategy,
+};
+
+use super::common::{blocked_urls, remove_script, WAIT_PERIOD};
+
+/// Timeout of a navigation, the same as the default page load timeout of WebDriver.
+const NAVIGATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// A poller using a browser served by a Playwright server, e.g. `npx playwright run-server`.
+///
+/// Each poll creates a new browser context, and closes it afterwards.
+/// Selectors always look into open shadow roots, and `NetworkIdle` waits for Playwright's fixed idle time.
+#[derive(Debug)]
+pub struct PlaywrightPoller {
+    connection: Connection,
+    browser: String,
+    contexts: Arc<Semaphore>,
+    handler: tokio::task::JoinHandle<()>,
+}
+
+impl PlaywrightPoller {
+    /// Connects to the server, e.g. `ws://localhost:3000/?browser=chromium`.
+    ///
+    /// The browser launched by the server is used if any, otherwise a headless chromium is launched.
+    pub async fn connect(url: &str, max_contexts: usize) -> Result<Self, Error> {
+        let (ws, _) = connect_async(url).await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let objects = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let connection = Connection {
+            requests: tx,
+            objects: objects.clone(),
+        };
+        let handler = tokio::spawn(dispatch(ws, rx, objects));
+
+        let result = connection
+            .call("", "initialize", json!({ "sdkLanguage": "javascript" }))
+            .await?;
+        let playwright = connection.take_initializer(guid(&result["playwright"])?)?;
+        let browser = match guid(&playwright["preLaunchedBrowser"]) {
+            Ok(browser) => browser.to_owned(),
+            Err(_) => {
+                let params = json!({
+                    "headless": true,
+                    "timeout": NAVIGATION_TIMEOUT.as_millis() as u64,
+                });
+                let chromium = guid(&playwright["chromium"])?;
+                let result = connection.call(chromium, "launch", params).await?;
+                guid(&result["browser"])?.to_owned()
+            }
+        };
+
+        Ok(Self {
+            connection,
+            browser,
+            contexts: Arc::new(Semaphore::new(max_contexts.max(1))),
+            handler,
+        })
+    }
+}
+
+impl Drop for PlaywrightPoller {
+    fn drop(&mut self) {
+        self.handler.abort();
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for PlaywrightPoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        poll(&self.connection, &self.browser, &self.contexts, &config).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let connection = self.connection.clone();
+            let browser = self.browser.clone();
+            let contexts = self.contexts.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                debug!("[{}]: start polling {}", &id, config.url.as_str());
+                let result = poll(&connection, &browser, &contexts, &config).await;
+                debug!("[{}]: polling finished", &id);
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x;
+            }
+        })
+    }
+}
+
+async fn poll(
+    connection: &Connection,
+    browser: &str,
+    contexts: &Semaphore,
+    config: &Config,
+) -> Result<PollOutput, Error> {
+    if 0 < config.steps.len() {
+        return Err(Error::Unsupported("steps"));
+    }
+
+    let mut params = json!({});
+    if let Some(viewport) = config.viewport.as_ref() {
+        params["viewport"] = json!({ "width": viewport.width, "height": viewport.height });
+        if let Some(factor) = viewport.device_scale_factor {
+            params["deviceScaleFactor"] = json!(factor);
+        }
+        if viewport.mobile {
+            params["isMobile"] = json!(true);
+            params["hasTouch"] = json!(true);
+        }
+    }
+    if let Some(language) = config.language.as_ref() {
+        // also sends `Accept-Language` with the requests.
+        params["locale"] = json!(language);
+    }
+
+    let _permit = contexts.acquire().await.unwrap();
+    let result = connection.call(browser, "newContext", params).await?;
+    let context = guid(&result["context"])?.to_owned();
+
+    let started = Instant::now();
+    let result = visit(connection, &context, config).await;
+    let elapsed = started.elapsed();
+
+    if let Err(why) = connection.call(&context, "close", json!({})).await {
+        debug!("failed to close the browser context: {why}");
+    }
+
+    Ok(PollOutput::new(result?).with_elapsed(elapsed))
+}
+
+/// Navigates to the target in a new page of the context, and reads the content.
+async fn visit(connection: &Connection, context: &str, config: &Config) -> Result<String, Error> {
+    let urls = blocked_urls(config);
+    if 0 < urls.len() {
+        let patterns: Vec<_> = urls
+            .iter()
+            .map(|x| json!({ "regexSource": wildcard_to_regex(x), "regexFlags": "" }))
+            .collect();
+        let params = json!({ "patterns": patterns });
+        connection
+            .call(context, "setNetworkInterceptionPatterns", params)
+            .await?;
+    }
+
+    let result = connection.call(context, "newPage", json!({})).await?;
+    let page = connection.take_initializer(guid(&result["page"])?)?;
+    let main_frame = guid(&page["mainFrame"])?.to_owned();
+    let mut frame = main_frame.clone();
+
+    let (wait_until, timeout) = match &config.wait {
+        WaitStrategy::NetworkIdle { .. } => ("networkidle", config.wait.timeout()),
+        _ => ("load", NAVIGATION_TIMEOUT),
+    };
+    let params = json!({
+        "url": config.url.as_str(),
+        "waitUntil": wait_until,
+        "timeout": timeout.as_millis() as u64,
+    });
+    connection.call(&frame, "goto", params).await?;
+
+    if let Some(delay) = config.delay {
+        tokio::time::sleep(delay.as_std()).await;
+    }
+
+    if let Some(language) = config.language.as_ref() {
+        let params = json!({
+            "expression": "() => document.documentElement.lang",
+            "isFunction": true,
+            "arg": no_argument(),
+        });
+        let result = connection
+            .call(&frame, "evaluateExpression", params)
+            .await?;
+        let served = deserialize(&result["value"]);
+        LanguageMismatch::check(language, served.as_str().unwrap_or_default())?;
+    }
+
+    let deadline = tokio::time::Instant::now() + config.wait.timeout();
+    let result = async {
+        for selector in config.frames.iter() {
+            let elem = wait_for_selector(connection, &frame, selector.as_str(), deadline).await?;
+            let result = connection.call(&elem, "contentFrame", json!({})).await?;
+            frame = guid(&result["frame"])
+                .map_err(|_| Error::NotFrame(selector.as_str().to_owned()))?
+                .to_owned();
+        }
+        extract(connection, &frame, config, deadline).await
+    };
+
+    match result.await {
+        Err(why) => Err(check_challenge(connection, &main_frame, why).await),
+        x => x,
+    }
+}
+
+/// Replaces the failure with `Error::Blocked` if the frame is a challenge page of an anti-bot service.
+async fn check_challenge(connection: &Connection, frame: &str, why: Error) -> Error {
+    let result = connection.call(frame, "content", json!({})).await;
+    let html = result
+        .ok()
+        .and_then(|x| x["value"].as_str().map(str::to_owned));
+    match html.and_then(|x| Challenge::detect(&x)) {
+        Some(challenge) => Error::Blocked(challenge),
+        None => why,
+    }
+}
+
+/// Waits until the page is ready according to the strategy, and reads the content.
+async fn extract(
+    connection: &Connection,
+    frame: &str,
+    config: &Config,
+    deadline: tokio::time::Instant,
+) -> Result<String, Error> {
+    if let WaitStrategy::Script { script, .. } = &config.wait {
+        let params = json!({
+            "expression": script,
+            "isFunction": false,
+            "arg": no_argument(),
+            "timeout": remaining(deadline),
+            "pollingInterval": WAIT_PERIOD.as_millis() as u64,
+        });
+        connection.call(frame, "waitForFunction", params).await?;
+    }
+
+    let elem = wait_for_selector(connection, frame, config.selector.as_str(), deadline).await?;
+
+    if 0 < config.ignore_selectors.len() {
+        let params = json!({
+            "expression": format!("function() {{ {} }}", remove_script(&config.ignore_selectors)),
+            "isFunction": true,
+            "arg": no_argument(),
+        });
+        connection.call(frame, "evaluateExpression", params).await?;
+    }
+
+    if let Some(script) = config.script.as_ref() {
+        let params = json!({
+            "expression": format!("function() {{ {script} }}"),
+            "isFunction": true,
+            "arg": no_argument(),
+        });
+        let result = connection.call(&elem, "evaluateExpression", params).await?;
+        let content = match deserialize(&result["value"]) {
+            Value::Null => String::new(),
+            Value::String(x) => x,
+            x => x.to_string(),
+        };
+        return Ok(content);
+    }
+
+    loop {
+        let result = connection.call(&elem, "innerText", json!({})).await?;
+        let text = result["value"].as_str().unwrap_or_default().to_owned();
+        if !matches!(config.wait, WaitStrategy::Text { .. }) || 0 < text.trim().len() {
+            return Ok(text);
+        }
+        if deadline <= tokio::time::Instant::now() {
+            return Err(Error::Timeout);
+        }
+        tokio::time::sleep(WAIT_PERIOD).await;
+    }
+}
+
+/// Waits until the selector matches an element in the frame, and returns the guid of the element.
+async fn wait_for_selector(
+    connection: &Connection,
+    frame: &str,
+    selector: &str,
+    deadline: tokio::time::Instant,
+) -> Result<String, Error> {
+    let params = json!({
+        "selector": selector,
+        "state": "attached",
+        "timeout": remaining(deadline),
+    });
+    let result = connection.call(frame, "waitForSelector", params).await?;
+    Ok(guid(&result["element"])?.to_owned())
+}
+
+/// Milliseconds until the deadline, at least 1 since 0 means no timeout for Playwright.
+fn remaining(deadline: tokio::time::Instant) -> u64 {
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    (remaining.as_millis() as u64).max(1)
+}
+
+/// The argument passed to a function evaluated without any argument.
+fn no_argument() -> Value {
+    json!({ "value": { "v": "undefined" }, "handles": [] })
+}
+
+/// Converts a value serialized by Playwright into JSON.
+fn deserialize(value: &Value) -> Value {
+    if let Some(x) = value.get("s").or_else(|| value.get("b")) {
+        return x.clone();
+    }
+    if let Some(x) = value.get("n") {
+        return x.clone();
+    }
+    if let Some(x) = value.get("a").and_then(Value::as_array) {
+        return Value::Array(x.iter().map(deserialize).collect());
+    }
+    if let Some(x) = value.get("o").and_then(Value::as_array) {
+        let map = x
+            .iter()
+            .filter_map(|x| Some((x["k"].as_str()?.to_owned(), deserialize(&x["v"]))))
+            .collect();
+        return Value::Object(map);
+    }
+    for key in ["d", "u", "bi"] {
+        if let Some(x) = value.get(key) {
+            return x.clone();
+        }
+    }
+    Value::Null
+}
+
+/// Converts a URL pattern with `*` wildcards into a regular expression.
+fn wildcard_to_regex(pattern: &str) -> String {
+    let parts: Vec<String> = pattern
+        .split('*')
+        .map(|part| {
+            part.chars()
+                .map(|c| match c {
+                    '\\' | '^' | '$' | '.' | '|' | '?' | '+' | '(' | ')' | '[' | ']' | '{'
+                    | '}' | '/' => format!("\\{c}"),
+                    c => c.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    format!("^{}$", parts.join(".*"))
+}
+
+fn guid(reference: &Value) -> Result<&str, Error> {
+    reference["guid"].as_str().ok_or(Error::UnexpectedResponse)
+}
+
+/// A handle to the connection to the Playwright server.
+#[derive(Debug, Clone)]
+struct Connection {
+    requests: mpsc::UnboundedSender<Request>,
+    /// Initializers of the objects created by the server, which the poller needs.
+    objects: Arc<std::sync::Mutex<HashMap<String, Value>>>,
+}
+impl Connection {
+    /// Calls the method of the object, and returns the result.
+    async fn call(&self, guid: &str, method: &'static str, params: Value) -> Result<Value, Error> {
+        let (reply, rx) = oneshot::channel();
+        let request = Request {
+            guid: guid.to_owned(),
+            method,
+            params,
+            reply,
+        };
+        self.requests.send(request).map_err(|_| Error::Closed)?;
+        rx.await.map_err(|_| Error::Closed)?
+    }
+
+    fn take_initializer(&self, guid: &str) -> Result<Value, Error> {
+        let mut objects = self.objects.lock().unwrap();
+        objects.remove(guid).ok_or(Error::UnexpectedResponse)
+    }
+}
+
+#[derive(Debug)]
+struct Request {
+    guid: String,
+    method: &'static str,
+    params: Value,
+    reply: oneshot::Sender<Result<Value, Error>>,
+}
+
+/// Types of the objects whose initializers are kept.
+const KEPT_OBJECT_TYPES: &[&str] = &["Playwright", "Page"];
+
+/// Sends the requests to the server, and dispatches the responses and events.
+async fn dispatch(
+    mut ws: WebSocketStream<ConnectStream>,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+    objects: Arc<std::sync::Mutex<HashMap<String, Value>>>,
+) {
+    let mut pending = HashMap::new();
+    let mut next_id: u64 = 1;
+
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let request = match request {
+                    Some(x) => x,
+                    None => break,
+                };
+                let message = json!({
+                    "id": next_id,
+                    "guid": request.guid,
+                    "method": request.method,
+                    "params": request.params,
+                    "metadata": {},
+                });
+                if let Err(why) = ws.send(Message::text(message.to_string())).await {
+                    let _ = request.reply.send(Err(Error::WebSocket(why)));
+                    break;
+                }
+                pending.insert(next_id, request.reply);
+                next_id += 1;
+            }
+            message = ws.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(why)) => {
+                        debug!("Playwright connection: {why}");
+                        break;
+                    }
+                };
+                let message: Value = match serde_json::from_str(text.as_str()) {
+                    Ok(x) => x,
+                    Err(why) => {
+                        debug!("Playwright connection: {why}");
+                        continue;
+                    }
+                };
+
+                if let Some(id) = message["id"].as_u64() {
+                    if let Some(reply) = pending.remove(&id) {
+                        let error = &message["error"]["error"];
+                        let result = if error.is_object() {
+                            match error["name"].as_str() {
+                                Some("TimeoutError") => Err(Error::Timeout),
+                                _ => {
+                                    let message = error["message"].as_str().unwrap_or_default();
+                                    Err(Error::Playwright(message.to_owned()))
+                                },
+                            }
+                        } else {
+                            Ok(message["result"].clone())
+                        };
+                        let _ = reply.send(result);
+                    }
+                    continue;
+                }
+
+                let params = &message["params"];
+                match message["method"].as_str() {
+                    Some("__create__") => {
+                        let kept = params["type"]
+                            .as_str()
+                            .is_some_and(|x| KEPT_OBJECT_TYPES.contains(&x));
+                        if let (true, Some(guid)) = (kept, params["guid"].as_str()) {
+                            let mut objects = objects.lock().unwrap();
+                            objects.insert(guid.to_owned(), params["initializer"].clone());
+                        }
+                    }
+                    Some("__dispose__") => {
+                        if let Some(guid) = message["guid"].as_str() {
+                            objects.lock().unwrap().remove(guid);
+                        }
+                    }
+                    Some("route") => {
+                        // every intercepted request is one to block.
+                        let message = json!({
+                            "id": next_id,
+                            "guid": params["route"]["guid"],
+                            "method": "abort",
+                            "params": {},
+                            "metadata": {},
+                        });
+                        next_id += 1;
+                        if let Err(why) = ws.send(Message::text(message.to_string())).await {
+                            debug!("Playwright connection: {why}");
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    WebSocket(tungstenite::Error),
+    Playwright(String),
+    UnexpectedResponse,
+    NotFrame(String),
+    Closed,
+    Timeout,
+    Blocked(Challenge),
+    Language(LanguageMismatch),
+    Unsupported(&'static str),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::WebSocket(e) => {
+                f.write_fmt(format_args!("failed to talk to the Playwright server: {e}"))
+            }
+            Error::Playwright(e) => f.write_fmt(format_args!("Playwright: {e}")),
+            Error::UnexpectedResponse => f.write_fmt(format_args!(
+                "unexpected response from the Playwright server."
+            )),
+            Error::NotFrame(x) => f.write_fmt(format_args!("{x} is not a frame.")),
+            Error::Closed => {
+                f.write_fmt(format_args!("connection to the Playwright server closed."))
+            }
+            Error::Timeout => f.write_fmt(format_args!("timeout waiting on condition.")),
+            Error::Blocked(e) => f.write_fmt(format_args!("{e}")),
+            Error::Language(e) => f.write_fmt(format_args!("{e}")),
+            Error::Unsupported(x) => f.write_fmt(format_args!(
+                "{x} is not supported by the Playwright poller."
+            )),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::WebSocket(e) => Some(e),
+            Error::Blocked(e) => Some(e),
+            Error::Language(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<LanguageMismatch> for Error {
+    fn from(e: LanguageMismatch) -> Self {
+        Error::Language(e)
+    }
+}
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::WebSocket(e)
+    }
+}
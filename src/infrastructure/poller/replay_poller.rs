@@ -0,0 +1,102 @@
+use std::{collections::HashMap, fmt::Display, time::Instant};
+
+use crate::domain::{Config, Id, PollOutput, PollStream, Poller, Url};
+use crate::infrastructure::Recordings;
+
+use super::http_poller::{self, extract};
+
+/// A poller of the pages recorded by `HttpPoller` and `WebDriverPoller`, extracting the contents from them as the simple mode does.
+///
+/// The sources recorded in the full mode are not rendered again, i.e. neither the scripts nor the waits run,
+/// and the contents in the frames are not replayed. The `headers` are not recorded either.
+#[derive(Debug, Clone)]
+pub struct ReplayPoller {
+    recordings: Recordings,
+}
+
+impl ReplayPoller {
+    pub fn new(recordings: Recordings) -> Self {
+        Self { recordings }
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for ReplayPoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        poll(&self.recordings, config).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let recordings = self.recordings.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let result = poll(&recordings, config).await;
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x
+            }
+        })
+    }
+}
+
+async fn poll(recordings: &Recordings, config: Config) -> Result<PollOutput, Error> {
+    let started = Instant::now();
+    let Config {
+        url,
+        mode,
+        selector,
+        ignore_selectors,
+        language,
+        ..
+    } = config;
+
+    let txt = match recordings.replay(mode, &url).await {
+        Ok(x) => x,
+        Err(e) => return Err(Error::NotRecorded(url, e)),
+    };
+    let extracted = tokio::task::spawn_blocking(move || {
+        extract(&txt, &selector, &ignore_selectors, language.as_deref())
+    });
+    let lines = extracted.await.map_err(http_poller::Error::from)??;
+    Ok(PollOutput::new(lines.join("\n")).with_elapsed(started.elapsed()))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NotRecorded(Url, std::io::Error),
+    Extraction(http_poller::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotRecorded(url, e) => {
+                f.write_fmt(format_args!("no page of {} is recorded: {e}", url.as_str()))
+            }
+            Error::Extraction(e) => f.write_fmt(format_args!("{e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NotRecorded(_, e) => Some(e),
+            Error::Extraction(e) => Some(e),
+        }
+    }
+}
+impl From<http_poller::Error> for Error {
+    fn from(e: http_poller::Error) -> Self {
+        Error::Extraction(e)
+    }
+}
@@ -1,15 +1,32 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use fantoccini::{Client, ClientBuilder, Locator};
-use futures_util::Stream;
-use log::debug;
+use fantoccini::{elements::Element, Client, ClientBuilder, Locator};
+use log::{debug, info, warn};
 
-use crate::domain::{Config, Id, Poller};
+use crate::domain::{
+    BrowserSession, Challenge, Config, Duration, Id, LanguageMismatch, Mode, PollOutput,
+    PollStream, Poller, StepAction, StepTiming, Url, Viewport, WaitStrategy,
+};
+use crate::infrastructure::debug_bundles::{self, DebugBundle, DebugBundles};
+use crate::infrastructure::Recordings;
+
+use super::common::{
+    blocked_urls, remove_script, LANGUAGE_SCRIPT, PIERCING_FIND_SCRIPT, RESOURCE_COUNT_SCRIPT,
+    TEXT_SCRIPT, WAIT_PERIOD,
+};
 
 use serde_json::{json, Map, Value};
-use std::lazy::SyncLazy;
+use std::sync::LazyLock;
 
-static CAPABILITIES: SyncLazy<Map<String, Value>> = SyncLazy::new(|| {
+static CAPABILITIES: LazyLock<Map<String, Value>> = LazyLock::new(|| {
     let capabilities = json!({
         "goog:chromeOptions": {
             "args": ["--headless", "--disable-extensions", "--disable-gpu"],
@@ -27,40 +44,162 @@ static CAPABILITIES: SyncLazy<Map<String, Value>> = SyncLazy::new(|| {
     }
 });
 
+/// The capabilities with the performance log of chrome, which has the network events for the HAR of a debug bundle.
+static LOGGING_CAPABILITIES: LazyLock<Map<String, Value>> = LazyLock::new(|| {
+    let mut capabilities = CAPABILITIES.clone();
+    let _ = capabilities.insert(
+        "goog:loggingPrefs".to_owned(),
+        json!({ "performance": "ALL" }),
+    );
+    capabilities
+});
+
+/// Clicks the element passed as `arguments[0]`, which is returned by a script.
+const CLICK_SCRIPT: &str = "arguments[0].click();";
+/// Sets the value of the element passed as `arguments[0]` to `arguments[1]`, as if it were typed.
+const FILL_SCRIPT: &str = r#"
+const elem = arguments[0];
+elem.focus();
+elem.value = arguments[1];
+elem.dispatchEvent(new Event("input", { bubbles: true }));
+elem.dispatchEvent(new Event("change", { bubbles: true }));
+"#;
+
+/// Time limit of a health probe of a session.
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Upper limit of the interval between attempts to re-establish a session.
+const MAX_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
+static CDP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
 #[derive(Debug)]
 pub struct WebDriverPoller {
     _ports: Vec<u16>,
     client_pool: ClientPool,
+    health_check: Option<tokio::task::JoinHandle<()>>,
+    /// Local SOCKS ports of the SSH tunnels keyed by the names.
+    tunnels: Arc<HashMap<String, u16>>,
+    recordings: Option<Recordings>,
+    debug_bundles: Option<DebugBundles>,
 }
 
 impl WebDriverPoller {
     pub async fn new(ports: &[u16]) -> Result<Self, Error> {
-        let client_pool = ClientPool::new(ports).await?;
+        let client_pool = ClientPool::new(ports, &CAPABILITIES).await?;
         Ok(Self {
             _ports: ports.to_vec(),
             client_pool,
+            health_check: None,
+            tunnels: Default::default(),
+            recordings: None,
+            debug_bundles: None,
         })
     }
+
+    /// Connects the sessions which log the requests, and saves a debug bundle of each failed poll,
+    /// with the screenshots, the source and the HAR of the requests of the page.
+    pub async fn new_with_debug_bundles(
+        ports: &[u16],
+        debug_bundles: DebugBundles,
+    ) -> Result<Self, Error> {
+        let client_pool = ClientPool::new(ports, &LOGGING_CAPABILITIES).await?;
+        Ok(Self {
+            _ports: ports.to_vec(),
+            client_pool,
+            health_check: None,
+            tunnels: Default::default(),
+            recordings: None,
+            debug_bundles: Some(debug_bundles),
+        })
+    }
+
+    /// Records the sources of the pages rendered in the full mode, to replay them by `ReplayPoller`.
+    pub fn with_recordings(mut self, recordings: Recordings) -> Self {
+        self.recordings = recordings.into();
+        self
+    }
+
+    /// Lets the targets connect through the SSH tunnels, given the local SOCKS ports keyed by the names.
+    pub fn with_tunnels(mut self, ports: HashMap<String, u16>) -> Self {
+        self.tunnels = Arc::new(ports);
+        self
+    }
+
+    /// Probes the idle pooled sessions periodically, and re-establishes the unhealthy ones in the background.
+    pub fn with_health_check(mut self, period: std::time::Duration) -> Self {
+        let mut client_pool = self.client_pool.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            let _ = interval.tick().await;
+            loop {
+                let _ = interval.tick().await;
+                client_pool.check_health().await;
+            }
+        });
+        if let Some(old) = self.health_check.replace(handle) {
+            old.abort();
+        }
+        self
+    }
+
+    /// Returns the statistics of the pooled sessions.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.client_pool.stats()
+    }
+
+    /// Check that a session can be established on the port, then close it.
+    pub async fn probe(port: u16) -> Result<(), Error> {
+        let mut client = connect(port, &CAPABILITIES).await?;
+        client.close().await?;
+        Ok(())
+    }
+
+    /// Fetch the whole HTML of the page rendered by the browser.
+    pub async fn fetch_source(
+        &mut self,
+        url: &Url,
+        delay: Option<Duration>,
+    ) -> Result<String, Error> {
+        let mut item = self.client_pool.get().await;
+        let client = item.client();
+
+        client.goto(url.as_str()).await?;
+        client.wait().for_element(Locator::Css("html")).await?;
+
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay.as_std()).await;
+        }
+
+        let source = client.source().await?;
+        Ok(source)
+    }
+}
+
+impl Drop for WebDriverPoller {
+    fn drop(&mut self) {
+        if let Some(handle) = self.health_check.take() {
+            handle.abort();
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl Poller for WebDriverPoller {
     type Error = Error;
-    type Stream = impl Stream<Item = (Id, Result<String, Self::Error>)>;
-
-    async fn poll(&mut self, _id: Id, config: Config) -> Result<String, Self::Error> {
-        let Config {
-            url,
-            selector,
-            wait_seconds,
-            ..
-        } = config;
-        let mut item = self.client_pool.get().await;
-        let client = item.client();
-
-        let content = poll(client, url.as_str(), selector.as_str(), wait_seconds).await?;
+    type Stream = PollStream<Self::Error>;
 
-        Ok(content)
+    async fn poll(&mut self, id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        let recordings = self.recordings.as_ref();
+        let debug_bundles = self.debug_bundles.as_ref();
+        poll_with_pool(
+            &mut self.client_pool,
+            &self.tunnels,
+            &id,
+            config,
+            recordings,
+            debug_bundles,
+        )
+        .await
     }
 
     async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
@@ -68,51 +207,91 @@ impl Poller for WebDriverPoller {
 
         for (id, config) in configs.into_iter() {
             let mut client_pool = self.client_pool.clone();
+            let tunnels = self.tunnels.clone();
+            let recordings = self.recordings.clone();
+            let debug_bundles = self.debug_bundles.clone();
             let tx = tx.clone();
             tokio::spawn(async move {
-                let Config {
-                    url,
-                    selector,
-                    wait_seconds,
-                    ..
-                } = config;
-                let mut item = client_pool.get().await;
-                let client = item.client();
-                debug!("[{}]: start polling {}", &id, url.as_str());
-                let result = poll(client, url.as_str(), selector.as_str(), wait_seconds)
-                    .await
-                    .map_err(|e| Error::from(e));
+                debug!("[{}]: start polling {}", &id, config.url.as_str());
+                let result = poll_with_pool(
+                    &mut client_pool,
+                    &tunnels,
+                    &id,
+                    config,
+                    recordings.as_ref(),
+                    debug_bundles.as_ref(),
+                )
+                .await;
                 debug!("[{}]: polling succeeded", &id);
                 let _ = tx.send((id, result));
             });
         }
         drop(tx);
 
-        async_stream::stream! {
+        Box::pin(async_stream::stream! {
             while let Some(x) = rx.recv().await {
                 yield x;
             }
-        }
+        })
     }
 }
 
+/// Statistics of the pool of WebDriver sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolStats {
+    /// Number of sessions the pool is supposed to have.
+    pub size: usize,
+    /// Number of sessions being re-established.
+    pub reconnecting: usize,
+    /// Number of health probes done so far.
+    pub probes: u64,
+    /// Number of sessions evicted as unhealthy so far.
+    pub evictions: u64,
+}
+
+#[derive(Debug, Default)]
+struct PoolCounters {
+    reconnecting: AtomicUsize,
+    probes: AtomicU64,
+    evictions: AtomicU64,
+}
+
+#[derive(Debug)]
+struct PooledClient {
+    port: u16,
+    client: Client,
+}
+
 #[derive(Debug, Clone)]
 struct ClientPool {
-    lending_port: std::sync::Arc<tokio::sync::Mutex<tokio::sync::mpsc::UnboundedReceiver<Client>>>,
-    returning_port: tokio::sync::mpsc::UnboundedSender<Client>,
+    size: usize,
+    lending_port: Arc<tokio::sync::Mutex<tokio::sync::mpsc::UnboundedReceiver<PooledClient>>>,
+    returning_port: tokio::sync::mpsc::UnboundedSender<PooledClient>,
+    counters: Arc<PoolCounters>,
+    /// The capabilities of the sessions, including the fresh ones for the isolated or tunneled targets.
+    capabilities: Arc<Map<String, Value>>,
 }
 impl ClientPool {
-    async fn new(ports: &[u16]) -> Result<Self, fantoccini::error::NewSessionError> {
+    async fn new(
+        ports: &[u16],
+        capabilities: &Map<String, Value>,
+    ) -> Result<Self, fantoccini::error::NewSessionError> {
         let (returning_port, lending_port) = tokio::sync::mpsc::unbounded_channel();
         let r = Self {
-            lending_port: std::sync::Arc::new(tokio::sync::Mutex::new(lending_port)),
+            size: ports.len(),
+            lending_port: Arc::new(tokio::sync::Mutex::new(lending_port)),
             returning_port,
+            counters: Default::default(),
+            capabilities: Arc::new(capabilities.clone()),
         };
 
-        for port in ports.into_iter() {
-            let c = connect(*port).await?;
+        for port in ports.iter() {
+            let client = connect(*port, &r.capabilities).await?;
             debug!("webdriver connected to {port}.");
-            let _ = r.returning_port.send(c);
+            let _ = r.returning_port.send(PooledClient {
+                port: *port,
+                client,
+            });
         }
 
         Ok(r)
@@ -125,53 +304,705 @@ impl ClientPool {
             returning_port: self.returning_port.clone(),
         }
     }
+
+    /// Takes an idle session, if any, without waiting for one.
+    fn try_get(&mut self) -> Option<PoolItem> {
+        // a poll waiting for a session holds the lock, while none is idle.
+        let client = self.lending_port.try_lock().ok()?.try_recv().ok()?;
+        Some(PoolItem {
+            client: client.into(),
+            returning_port: self.returning_port.clone(),
+        })
+    }
+
+    fn stats(&self) -> PoolStats {
+        PoolStats {
+            size: self.size,
+            reconnecting: self.counters.reconnecting.load(Ordering::Relaxed),
+            probes: self.counters.probes.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Probes each idle session in turn, and replaces the unhealthy ones.
+    ///
+    /// The sessions are taken one at a time, and only while idle, not to keep the polls waiting for them.
+    async fn check_health(&mut self) {
+        let healthy = self.size - self.counters.reconnecting.load(Ordering::Relaxed);
+        for _ in 0..healthy {
+            let mut item = match self.try_get() {
+                Some(x) => x,
+                None => break,
+            };
+            let _ = self.counters.probes.fetch_add(1, Ordering::Relaxed);
+
+            let probe = tokio::time::timeout(PROBE_TIMEOUT, item.client().goto("about:blank"));
+            let why = match probe.await {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "timed out".to_owned(),
+            };
+
+            let pooled = item.client.take().unwrap();
+            warn!("webdriver session on {} is unhealthy: {why}", pooled.port);
+            let _ = self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+            self.spawn_reconnect(pooled);
+        }
+        debug!("webdriver pool: {:?}", self.stats());
+    }
+
+    /// Closes the session and establishes a new one in the background.
+    fn spawn_reconnect(&self, pooled: PooledClient) {
+        let _ = self.counters.reconnecting.fetch_add(1, Ordering::Relaxed);
+        let returning_port = self.returning_port.clone();
+        let counters = self.counters.clone();
+        let capabilities = self.capabilities.clone();
+
+        tokio::spawn(async move {
+            let PooledClient { port, mut client } = pooled;
+            let _ = tokio::time::timeout(PROBE_TIMEOUT, client.close()).await;
+
+            let mut backoff = std::time::Duration::from_secs(1);
+            let client = loop {
+                match connect(port, &capabilities).await {
+                    Ok(client) => break client,
+                    Err(why) => {
+                        warn!("failed to reconnect to webdriver on {port}: {why}");
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                    }
+                }
+            };
+
+            info!("webdriver session on {port} re-established.");
+            let _ = counters.reconnecting.fetch_sub(1, Ordering::Relaxed);
+            let _ = returning_port.send(PooledClient { port, client });
+        });
+    }
 }
 #[derive(Debug)]
 struct PoolItem {
-    client: Option<Client>,
-    returning_port: tokio::sync::mpsc::UnboundedSender<Client>,
+    client: Option<PooledClient>,
+    returning_port: tokio::sync::mpsc::UnboundedSender<PooledClient>,
 }
 impl PoolItem {
     pub fn client(&mut self) -> &mut Client {
-        self.client.as_mut().unwrap()
+        &mut self.client.as_mut().unwrap().client
+    }
+
+    pub fn port(&self) -> u16 {
+        self.client.as_ref().unwrap().port
     }
 }
 impl Drop for PoolItem {
     fn drop(&mut self) {
-        let _ = self.returning_port.send(self.client.take().unwrap());
+        if let Some(client) = self.client.take() {
+            let _ = self.returning_port.send(client);
+        }
     }
 }
 
-async fn connect(port: u16) -> Result<Client, fantoccini::error::NewSessionError> {
+async fn connect(
+    port: u16,
+    capabilities: &Map<String, Value>,
+) -> Result<Client, fantoccini::error::NewSessionError> {
+    ClientBuilder::rustls()
+        .capabilities(capabilities.clone())
+        .connect(&format!("http://localhost:{}", port))
+        .await
+}
+
+/// Connects a new session to the port, whose browser connects through the SOCKS proxy on the local port.
+async fn connect_through(
+    port: u16,
+    socks_port: u16,
+    capabilities: &Map<String, Value>,
+) -> Result<Client, fantoccini::error::NewSessionError> {
+    let mut capabilities = capabilities.clone();
+    let proxy = json!({
+        "proxyType": "manual",
+        "socksProxy": format!("127.0.0.1:{socks_port}"),
+        "socksVersion": 5,
+    });
+    let _ = capabilities.insert("proxy".to_owned(), proxy);
     ClientBuilder::rustls()
-        .capabilities(CAPABILITIES.clone())
+        .capabilities(capabilities)
         .connect(&format!("http://localhost:{}", port))
         .await
 }
 
+/// Polls the target with a pooled session, or with a fresh one on the same port if the target is isolated or tunneled.
+async fn poll_with_pool(
+    client_pool: &mut ClientPool,
+    tunnels: &HashMap<String, u16>,
+    id: &Id,
+    config: Config,
+    recordings: Option<&Recordings>,
+    debug_bundles: Option<&DebugBundles>,
+) -> Result<PollOutput, Error> {
+    let socks_port = match config.tunnel.as_ref() {
+        Some(name) => match tunnels.get(name) {
+            Some(x) => Some(*x),
+            None => return Err(Error::UnknownTunnel(name.clone())),
+        },
+        None => None,
+    };
+
+    let mut item = client_pool.get().await;
+
+    if let Some(socks_port) = socks_port {
+        let port = item.port();
+        let mut client = connect_through(port, socks_port, &client_pool.capabilities).await?;
+        let result = poll(&mut client, port, id, &config, recordings, debug_bundles).await;
+        if let Err(why) = client.close().await {
+            warn!("failed to close the tunneled session: {why}");
+        }
+        return result;
+    }
+
+    match config.session {
+        BrowserSession::Shared => {
+            let port = item.port();
+            poll(item.client(), port, id, &config, recordings, debug_bundles).await
+        }
+        BrowserSession::Cleared => {
+            let port = item.port();
+            let client = item.client();
+            clear_state(client, port).await;
+            let result = poll(client, port, id, &config, recordings, debug_bundles).await;
+            clear_state(client, port).await;
+            result
+        }
+        BrowserSession::Isolated => {
+            let port = item.port();
+            let mut client = connect(port, &client_pool.capabilities).await?;
+            let result = poll(&mut client, port, id, &config, recordings, debug_bundles).await;
+            if let Err(why) = client.close().await {
+                warn!("failed to close the isolated session: {why}");
+            }
+            result
+        }
+    }
+}
+
+/// Clears cookies, storages and cache of the session, as far as the browser allows.
+///
+/// WebDriver can only clear the cookies and storages of the current page,
+/// so the whole browser is also cleared via Chrome DevTools Protocol if available.
+async fn clear_state(client: &mut Client, port: u16) {
+    if let Err(why) = client.delete_all_cookies().await {
+        debug!("failed to delete cookies: {why}");
+    }
+    let script = "try { localStorage.clear(); sessionStorage.clear(); } catch (e) {}";
+    if let Err(why) = client.execute(script, vec![]).await {
+        debug!("failed to clear storages: {why}");
+    }
+
+    for cmd in ["Network.clearBrowserCookies", "Network.clearBrowserCache"] {
+        if !cdp(client, port, cmd, json!({})).await {
+            return;
+        }
+    }
+}
+
+/// Sends a Chrome DevTools Protocol command through chromedriver.
+///
+/// Returns `false` if the command failed, e.g. because the browser is not chrome.
+async fn cdp(client: &mut Client, port: u16, cmd: &str, params: Value) -> bool {
+    let session_id = match client.session_id().await {
+        Ok(Some(x)) => x,
+        _ => return false,
+    };
+    let endpoint = format!("http://localhost:{port}/session/{session_id}/goog/cdp/execute");
+    let body = json!({ "cmd": cmd, "params": params });
+    let result = CDP_CLIENT.post(&endpoint).json(&body).send().await;
+    match result.and_then(|x| x.error_for_status()) {
+        Ok(_) => true,
+        Err(why) => {
+            debug!("failed to send {cmd} via CDP: {why}");
+            false
+        }
+    }
+}
+
+/// Emulates the viewport, and returns the window size to restore afterwards.
+async fn emulate(client: &mut Client, port: u16, viewport: &Viewport) -> Result<(u32, u32), Error> {
+    let (width, height) = client.get_window_size().await?;
+    client
+        .set_window_size(viewport.width, viewport.height)
+        .await?;
+
+    let params = json!({
+        "width": viewport.width,
+        "height": viewport.height,
+        "deviceScaleFactor": viewport.device_scale_factor.unwrap_or(0.0),
+        "mobile": viewport.mobile,
+    });
+    if cdp(client, port, "Emulation.setDeviceMetricsOverride", params).await {
+        let params = json!({ "enabled": viewport.mobile });
+        let _ = cdp(client, port, "Emulation.setTouchEmulationEnabled", params).await;
+    } else if viewport.device_scale_factor.is_some() || viewport.mobile {
+        warn!("device emulation is not available, only the window size is applied.");
+    }
+
+    Ok((width as u32, height as u32))
+}
+
+async fn restore_viewport(client: &mut Client, port: u16, (width, height): (u32, u32)) {
+    if cdp(
+        client,
+        port,
+        "Emulation.clearDeviceMetricsOverride",
+        json!({}),
+    )
+    .await
+    {
+        let params = json!({ "enabled": false });
+        let _ = cdp(client, port, "Emulation.setTouchEmulationEnabled", params).await;
+    }
+    if let Err(why) = client.set_window_size(width, height).await {
+        debug!("failed to restore the window size: {why}");
+    }
+}
+
 async fn poll(
     client: &mut Client,
-    url: &str,
-    selector: &str,
-    wait_seconds: Option<u16>,
-) -> Result<String, Error> {
-    client.goto(url).await?;
+    port: u16,
+    id: &Id,
+    config: &Config,
+    recordings: Option<&Recordings>,
+    debug_bundles: Option<&DebugBundles>,
+) -> Result<PollOutput, Error> {
+    let window_size = match config.viewport.as_ref() {
+        Some(viewport) => Some(emulate(client, port, viewport).await?),
+        None => None,
+    };
+
+    let blocked_urls = blocked_urls(config);
+    let blocking = 0 < blocked_urls.len() && block_urls(client, port, blocked_urls).await;
+    let requesting_language = match config.language.as_ref() {
+        Some(language) => request_language(client, port, Some(language)).await,
+        None => false,
+    };
+
+    if debug_bundles.is_some() {
+        // discards the requests of the previous polls.
+        let _ = read_network_log(client, port).await;
+    }
+
+    let started = Instant::now();
+    let mut screenshots = Vec::new();
+    let result = visit(client, config, debug_bundles.map(|_| &mut screenshots)).await;
+    let elapsed = started.elapsed();
+    if let (Some(debug_bundles), Err(why)) = (debug_bundles, result.as_ref()) {
+        let bundle = capture(client, port, config, why, screenshots).await;
+        match debug_bundles.save(id, bundle).await {
+            Ok(()) => debug!("[{id}]: saved the debug bundle."),
+            Err(why) => warn!("[{id}]: failed to save the debug bundle: {why}"),
+        }
+    }
+    let final_url = match result.is_ok() {
+        true => client.current_url().await.ok(),
+        false => None,
+    };
+    if let (Some(recordings), true) = (recordings, result.is_ok()) {
+        match client.source().await {
+            Ok(source) => {
+                if let Err(why) = recordings.record(Mode::Full, &config.url, &source).await {
+                    warn!("failed to record {}: {why}", config.url.as_str());
+                }
+            }
+            Err(why) => warn!(
+                "failed to read the source of {}: {why}",
+                config.url.as_str()
+            ),
+        }
+    }
+
+    if blocking {
+        let _ = block_urls(client, port, Vec::new()).await;
+    }
+    if requesting_language {
+        let _ = request_language(client, port, None).await;
+    }
+    if let Some(window_size) = window_size {
+        restore_viewport(client, port, window_size).await;
+    }
+
+    let (content, steps) = result?;
+    let mut output = PollOutput::new(content).with_elapsed(elapsed);
+    output.final_url = final_url.and_then(|x| Url::new(x.into()).ok());
+    output.steps = steps;
+    Ok(output)
+}
+
+/// Blocks the requests to the URLs via CDP, or unblocks all if `patterns` is empty.
+///
+/// Returns `false` if the browser does not support it.
+async fn block_urls(client: &mut Client, port: u16, patterns: Vec<String>) -> bool {
+    if !cdp(client, port, "Network.enable", json!({})).await {
+        warn!("blocking resources is not available.");
+        return false;
+    }
+    cdp(
+        client,
+        port,
+        "Network.setBlockedURLs",
+        json!({ "urls": patterns }),
+    )
+    .await
+}
+
+/// Sends `Accept-Language` with the requests via CDP, or stops sending it if `language` is `None`.
+///
+/// Returns `false` if the browser does not support it.
+async fn request_language(client: &mut Client, port: u16, language: Option<&str>) -> bool {
+    if !cdp(client, port, "Network.enable", json!({})).await {
+        warn!("requesting the language is not available.");
+        return false;
+    }
+    let headers = match language {
+        Some(language) => json!({ "Accept-Language": language }),
+        None => json!({}),
+    };
+    cdp(
+        client,
+        port,
+        "Network.setExtraHTTPHeaders",
+        json!({ "headers": headers }),
+    )
+    .await
+}
+
+/// Navigates to the target, runs the steps and reads the content, with the timings of the steps.
+///
+/// The screenshots after the steps are taken into `screenshots` if given.
+async fn visit(
+    client: &mut Client,
+    config: &Config,
+    screenshots: Option<&mut Vec<(String, Vec<u8>)>>,
+) -> Result<(String, Vec<StepTiming>), Error> {
+    client.goto(config.url.as_str()).await?;
     client.wait().for_element(Locator::Css("html")).await?;
 
-    if let Some(secs) = wait_seconds {
-        tokio::time::sleep(std::time::Duration::from_secs(secs as u64)).await;
+    if let Some(delay) = config.delay {
+        tokio::time::sleep(delay.as_std()).await;
+    }
+
+    if let Some(language) = config.language.as_ref() {
+        let served = client.execute(LANGUAGE_SCRIPT, vec![]).await?;
+        LanguageMismatch::check(language, served.as_str().unwrap_or_default())?;
+    }
+
+    let steps = match run_steps(client, config, screenshots).await {
+        Ok(x) => x,
+        Err(why) => return Err(check_challenge(client, why).await),
+    };
+
+    for frame in config.frames.iter() {
+        let timeout = config.wait.timeout();
+        let elem = client
+            .wait()
+            .at_most(timeout)
+            .for_element(Locator::Css(frame.as_str()))
+            .await?;
+        let _ = elem.enter_frame().await?;
+    }
+
+    let result = extract(client, config).await;
+
+    if 0 < config.frames.len() {
+        if let Err(why) = client.clone().enter_frame(None).await {
+            debug!("failed to leave the frame: {why}");
+        }
+    }
+
+    match result {
+        Ok(content) => Ok((content, steps)),
+        Err(why) => Err(check_challenge(client, why).await),
+    }
+}
+
+/// Runs the steps of the scripted flow in order, and returns how long each took.
+async fn run_steps(
+    client: &mut Client,
+    config: &Config,
+    mut screenshots: Option<&mut Vec<(String, Vec<u8>)>>,
+) -> Result<Vec<StepTiming>, Error> {
+    let mut timings = Vec::with_capacity(config.steps.len());
+    for (i, step) in config.steps.iter().enumerate() {
+        let name = step.label(i + 1);
+        let started = Instant::now();
+        if let Err(why) = run_step(client, config, &step.action).await {
+            return Err(Error::Step(name, Box::new(why)));
+        }
+        let elapsed = started.elapsed();
+        debug!("step {name} of {} took {elapsed:?}", config.url.as_str());
+        if let Some(screenshots) = screenshots.as_deref_mut() {
+            match client.screenshot().await {
+                Ok(x) => screenshots.push((name.clone(), x)),
+                Err(why) => debug!("failed to take a screenshot after the step {name}: {why}"),
+            }
+        }
+        timings.push(StepTiming { name, elapsed });
+    }
+    Ok(timings)
+}
+
+async fn run_step(client: &mut Client, config: &Config, action: &StepAction) -> Result<(), Error> {
+    let wait = WaitStrategy::Selector {
+        timeout: config.wait.timeout().try_into().ok(),
+    };
+    match action {
+        StepAction::Goto { url } => {
+            client.goto(url.as_str()).await?;
+            let _ = client.wait().for_element(Locator::Css("html")).await?;
+        }
+        StepAction::Click { selector } => {
+            match wait_until(client, selector.as_str(), config.pierce_shadow, &wait).await? {
+                Found::Element(elem) => {
+                    let _ = elem.click().await?;
+                }
+                Found::Reference(x) => {
+                    let _ = client.execute(CLICK_SCRIPT, vec![x]).await?;
+                }
+            }
+        }
+        StepAction::Fill { selector, value } => {
+            match wait_until(client, selector.as_str(), config.pierce_shadow, &wait).await? {
+                Found::Element(mut elem) => {
+                    elem.clear().await?;
+                    elem.send_keys(value).await?;
+                }
+                Found::Reference(x) => {
+                    let _ = client
+                        .execute(FILL_SCRIPT, vec![x, Value::from(value.as_str())])
+                        .await?;
+                }
+            }
+        }
+        StepAction::Wait { selector, timeout } => {
+            let wait = match timeout {
+                Some(timeout) => WaitStrategy::Selector {
+                    timeout: Some(*timeout),
+                },
+                None => wait,
+            };
+            let _ = wait_until(client, selector.as_str(), config.pierce_shadow, &wait).await?;
+        }
+        StepAction::Script { script } => {
+            let _ = client.execute(script, vec![]).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Captures the page where the poll failed, with the screenshots taken after the steps.
+async fn capture(
+    client: &mut Client,
+    port: u16,
+    config: &Config,
+    why: &Error,
+    screenshots: Vec<(String, Vec<u8>)>,
+) -> DebugBundle {
+    let mut bundle = DebugBundle::new(config.url.clone(), why.to_string());
+    bundle.steps = screenshots;
+    match client.screenshot().await {
+        Ok(x) => bundle.screenshot = Some(x),
+        Err(why) => debug!("failed to take a screenshot: {why}"),
+    }
+    match client.source().await {
+        Ok(x) => bundle.source = Some(x),
+        Err(why) => debug!("failed to read the source: {why}"),
+    }
+    bundle.har = read_network_log(client, port)
+        .await
+        .map(|x| debug_bundles::har(&x));
+    bundle
+}
+
+/// Reads the `Network` events of Chrome DevTools Protocol logged since the last read, with their methods.
+///
+/// Returns `None` if the browser does not log them, e.g. because it is not chrome.
+async fn read_network_log(client: &mut Client, port: u16) -> Option<Vec<(String, Value)>> {
+    let session_id = client.session_id().await.ok().flatten()?;
+    let endpoint = format!("http://localhost:{port}/session/{session_id}/se/log");
+    let body = json!({ "type": "performance" });
+    let result = CDP_CLIENT.post(&endpoint).json(&body).send().await;
+    let log: Value = match result.and_then(|x| x.error_for_status()) {
+        Ok(x) => x.json().await.ok()?,
+        Err(why) => {
+            debug!("failed to read the performance log: {why}");
+            return None;
+        }
+    };
+
+    // each entry has the event in JSON, e.g. `{"message":{"method":"Network.requestWillBeSent","params":{..}}}`.
+    let events = log["value"]
+        .as_array()?
+        .iter()
+        .filter_map(|entry| {
+            let message: Value = serde_json::from_str(entry["message"].as_str()?).ok()?;
+            let method = message["message"]["method"].as_str()?;
+            method
+                .starts_with("Network.")
+                .then(|| (method.to_owned(), message["message"]["params"].clone()))
+        })
+        .collect();
+    Some(events)
+}
+
+/// Replaces the failure with `Error::Blocked` if the page is a challenge page of an anti-bot service.
+async fn check_challenge(client: &mut Client, why: Error) -> Error {
+    match client
+        .source()
+        .await
+        .ok()
+        .and_then(|x| Challenge::detect(&x))
+    {
+        Some(challenge) => Error::Blocked(challenge),
+        None => why,
     }
+}
 
-    let mut elem = client.wait().for_element(Locator::Css(selector)).await?;
-    let content = elem.text().await?;
+/// Reads the content in the current frame.
+async fn extract(client: &mut Client, config: &Config) -> Result<String, Error> {
+    let selector = config.selector.as_str();
+    let found = wait_until(client, selector, config.pierce_shadow, &config.wait).await?;
+    if 0 < config.ignore_selectors.len() {
+        let _ = client
+            .execute(&remove_script(&config.ignore_selectors), vec![])
+            .await?;
+    }
+    let content = match config.script.as_deref() {
+        Some(script) => {
+            let arg = found.to_json();
+            match client.execute(script, vec![arg]).await? {
+                Value::Null => String::new(),
+                Value::String(x) => x,
+                x => x.to_string(),
+            }
+        }
+        None => found.text(client).await?,
+    };
 
     Ok(content)
 }
 
+/// An element found in the page.
+enum Found {
+    Element(Element),
+    /// A reference to an element returned by a script, which can only be used as an argument of another script.
+    Reference(Value),
+}
+impl Found {
+    fn to_json(&self) -> Value {
+        match self {
+            Found::Element(elem) => serde_json::to_value(elem).unwrap(),
+            Found::Reference(x) => x.clone(),
+        }
+    }
+
+    async fn text(self, client: &mut Client) -> Result<String, Error> {
+        match self {
+            Found::Element(mut elem) => Ok(elem.text().await?),
+            Found::Reference(x) => {
+                let text = client.execute(TEXT_SCRIPT, vec![x]).await?;
+                Ok(text.as_str().unwrap_or_default().to_owned())
+            }
+        }
+    }
+}
+
+/// Finds the element matched by the selector, looking into open shadow roots if `pierce_shadow` is set.
+async fn find(
+    client: &mut Client,
+    selector: &str,
+    pierce_shadow: bool,
+) -> Result<Option<Found>, Error> {
+    if pierce_shadow {
+        let found = client
+            .execute(PIERCING_FIND_SCRIPT, vec![Value::from(selector)])
+            .await?;
+        return Ok(Some(found).filter(|x| !x.is_null()).map(Found::Reference));
+    }
+
+    match client.find(Locator::Css(selector)).await {
+        Ok(elem) => Ok(Some(Found::Element(elem))),
+        Err(fantoccini::error::CmdError::NoSuchElement(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Waits until the page is ready according to the strategy, and returns the element matched by the selector.
+async fn wait_until(
+    client: &mut Client,
+    selector: &str,
+    pierce_shadow: bool,
+    wait: &WaitStrategy,
+) -> Result<Found, Error> {
+    let deadline = tokio::time::Instant::now() + wait.timeout();
+
+    match wait {
+        WaitStrategy::Selector { .. } => (),
+        WaitStrategy::Text { .. } => loop {
+            if let Some(found) = find(client, selector, pierce_shadow).await? {
+                let arg = found.to_json();
+                let text = client.execute(TEXT_SCRIPT, vec![arg]).await?;
+                if 0 < text.as_str().unwrap_or_default().trim().len() {
+                    return Ok(found);
+                }
+            }
+            sleep_before(deadline).await?;
+        },
+        WaitStrategy::NetworkIdle { .. } => {
+            let idle = wait.idle();
+            let mut last = (Value::Null, tokio::time::Instant::now());
+            loop {
+                let count = client.execute(RESOURCE_COUNT_SCRIPT, vec![]).await?;
+                let now = tokio::time::Instant::now();
+                if count != last.0 {
+                    last = (count, now);
+                } else if !count.is_null() && idle <= now - last.1 {
+                    break;
+                }
+                sleep_before(deadline).await?;
+            }
+        }
+        WaitStrategy::Script { script, .. } => {
+            let script = format!("return !!({script});");
+            while client.execute(&script, vec![]).await? != Value::Bool(true) {
+                sleep_before(deadline).await?;
+            }
+        }
+    }
+
+    loop {
+        if let Some(found) = find(client, selector, pierce_shadow).await? {
+            return Ok(found);
+        }
+        sleep_before(deadline).await?;
+    }
+}
+
+/// Sleeps before checking the condition again, or fails if the deadline has passed.
+async fn sleep_before(deadline: tokio::time::Instant) -> Result<(), Error> {
+    if deadline <= tokio::time::Instant::now() {
+        return Err(fantoccini::error::CmdError::WaitTimeout.into());
+    }
+    tokio::time::sleep(WAIT_PERIOD).await;
+    Ok(())
+}
+
 #[derive(Debug)]
 pub enum Error {
     NewSessionError(fantoccini::error::NewSessionError),
     CmdError(fantoccini::error::CmdError),
+    Blocked(Challenge),
+    Language(LanguageMismatch),
+    UnknownTunnel(String),
+    /// Failed at the named step of the scripted flow.
+    Step(String, Box<Error>),
 }
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -182,10 +1013,30 @@ impl Display for Error {
             Error::CmdError(e) => {
                 f.write_fmt(format_args!("failed to manipulate the browser: {e}"))
             }
+            Error::Blocked(e) => f.write_fmt(format_args!("{e}")),
+            Error::Language(e) => f.write_fmt(format_args!("{e}")),
+            Error::UnknownTunnel(x) => f.write_fmt(format_args!("unknown tunnel: {x}")),
+            Error::Step(name, e) => f.write_fmt(format_args!("failed at the step {name}: {e}")),
         }
     }
 }
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // only the errors found through the sources, e.g. by `Challenge::find_in`, though in the messages too.
+        match self {
+            Error::NewSessionError(_) | Error::CmdError(_) => None,
+            Error::Blocked(e) => Some(e),
+            Error::Language(e) => Some(e),
+            Error::UnknownTunnel(_) => None,
+            Error::Step(_, e) => Some(e.as_ref()),
+        }
+    }
+}
+impl From<LanguageMismatch> for Error {
+    fn from(e: LanguageMismatch) -> Self {
+        Error::Language(e)
+    }
+}
 impl From<fantoccini::error::NewSessionError> for Error {
     fn from(e: fantoccini::error::NewSessionError) -> Self {
         Error::NewSessionError(e)
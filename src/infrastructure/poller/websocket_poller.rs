@@ -0,0 +1,124 @@
+use std::{collections::HashMap, fmt::Display, time::Instant};
+
+use async_tungstenite::{
+    tokio::connect_async,
+    tungstenite::{self, Message},
+};
+use futures_util::StreamExt;
+
+use crate::domain::{Config, Duration, Hash, Id, PollOutput, PollStream, Poller};
+
+/// How long to wait for a message if `timeout` is not given.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A poller of the websocket mode, using a message received from the URL as the content.
+///
+/// After connecting and sending the `send` message if any, the first message containing the `filter` is used.
+/// A binary message is used as it is if it is a text, otherwise as its SHA-256.
+#[derive(Debug, Default)]
+pub struct WebSocketPoller;
+
+impl WebSocketPoller {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl Poller for WebSocketPoller {
+    type Error = Error;
+    type Stream = PollStream<Self::Error>;
+
+    async fn poll(&mut self, _id: Id, config: Config) -> Result<PollOutput, Self::Error> {
+        poll(config).await
+    }
+
+    async fn poll_multiple(&mut self, configs: HashMap<Id, Config>) -> Self::Stream {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (id, config) in configs.into_iter() {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let result = poll(config).await;
+                let _ = tx.send((id, result));
+            });
+        }
+        drop(tx);
+
+        Box::pin(async_stream::stream! {
+            while let Some(x) = rx.recv().await {
+                yield x
+            }
+        })
+    }
+}
+
+async fn poll(config: Config) -> Result<PollOutput, Error> {
+    let started = Instant::now();
+    let timeout = config.timeout.unwrap_or(DEFAULT_TIMEOUT).as_std();
+    let content = match tokio::time::timeout(timeout, receive(&config)).await {
+        Ok(result) => result?,
+        Err(_) => return Err(Error::Timeout),
+    };
+    Ok(PollOutput::new(content).with_elapsed(started.elapsed()))
+}
+
+async fn receive(config: &Config) -> Result<String, Error> {
+    let (mut ws, _) = connect_async(config.url.as_str()).await?;
+
+    if let Some(message) = config.send.as_ref() {
+        ws.send(Message::text(message.clone())).await?;
+    }
+
+    while let Some(message) = ws.next().await {
+        let text = match message? {
+            Message::Text(x) => x.to_string(),
+            Message::Binary(x) => match String::from_utf8(x.to_vec()) {
+                Ok(x) => x,
+                Err(e) => Hash::new(e.as_bytes()).to_string(),
+            },
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let matched = match config.filter.as_ref() {
+            Some(filter) => text.contains(filter.as_str()),
+            None => true,
+        };
+        if matched {
+            let _ = ws.close(None).await;
+            return Ok(text);
+        }
+    }
+
+    Err(Error::Closed)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    WebSocket(tungstenite::Error),
+    Closed,
+    Timeout,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::WebSocket(e) => f.write_fmt(format_args!("{e}")),
+            Error::Closed => f.write_str("the connection is closed before a message."),
+            Error::Timeout => f.write_str("no message is received in time."),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::WebSocket(e) => Some(e),
+            Error::Closed | Error::Timeout => None,
+        }
+    }
+}
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::WebSocket(e)
+    }
+}
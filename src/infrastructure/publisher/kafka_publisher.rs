@@ -0,0 +1,104 @@
+use std::fmt::Display;
+
+use crate::domain::{Publisher, Url};
+
+/// The content type of the records of JSON of the REST Proxy.
+const CONTENT_TYPE: &str = "application/vnd.kafka.json.v2+json";
+
+/// Publishes the events to a topic of Kafka through the REST Proxy of Confluent, or the HTTP Proxy of Redpanda compatible with it.
+///
+/// Each event is a record whose value is its JSON, produced by `POST {url}/topics/{topic}`.
+/// The name of the event is not a header of the record, as the proxy does not support them, but `event` in the JSON.
+#[derive(Debug, Clone)]
+pub struct KafkaPublisher {
+    client: reqwest::Client,
+    url: String,
+}
+impl KafkaPublisher {
+    /// Makes the publisher to the topic through the proxy of the base URL, e.g. `http://localhost:8082`.
+    pub fn new(proxy_url: &Url, topic: &str) -> Result<Self, Error> {
+        let valid = 0 < topic.len()
+            && topic
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ['.', '_', '-'].contains(&c));
+        if !valid {
+            return Err(Error::InvalidTopic(topic.to_owned()));
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: format!(
+                "{}/topics/{topic}",
+                proxy_url.as_str().trim_end_matches('/')
+            ),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Publisher for KafkaPublisher {
+    type Error = Error;
+
+    async fn publish(&self, _name: &str, json: &str) -> Result<(), Self::Error> {
+        // the JSON is embedded as it is, not to parse it again.
+        let body = format!("{{\"records\":[{{\"value\":{json}}}]}}");
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+            .header(reqwest::header::ACCEPT, "application/vnd.kafka.v2+json")
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        let json: Option<serde_json::Value> = response.json().await.ok();
+        // the records failed to be produced are in `offsets` with their errors, even if the status is OK.
+        let failed = json.as_ref().and_then(|x| {
+            x.get("offsets")?
+                .as_array()?
+                .iter()
+                .find_map(|x| x.get("error")?.as_str().map(str::to_owned))
+        });
+        if status.is_success() && failed.is_none() {
+            return Ok(());
+        }
+        let message = failed.or_else(|| {
+            json.as_ref()
+                .and_then(|x| x.get("message")?.as_str().map(str::to_owned))
+        });
+        Err(Error::Status(status, message))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode, Option<String>),
+    InvalidTopic(String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => f.write_fmt(format_args!("failed to produce to Kafka: {e}")),
+            Error::Status(status, Some(message)) => f.write_fmt(format_args!(
+                "the REST Proxy of Kafka responded with {status}: {message}"
+            )),
+            Error::Status(status, None) => f.write_fmt(format_args!(
+                "the REST Proxy of Kafka responded with {status}"
+            )),
+            Error::InvalidTopic(x) => f.write_fmt(format_args!("invalid topic of Kafka: {x}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
@@ -0,0 +1,20 @@
+pub mod kafka_publisher;
+pub mod nats_publisher;
+pub mod pubsub_publisher;
+pub mod sns_publisher;
+
+pub use self::kafka_publisher::KafkaPublisher;
+pub use self::nats_publisher::NatsPublisher;
+pub use self::pubsub_publisher::PubSubPublisher;
+pub use self::sns_publisher::SnsPublisher;
+
+use std::fmt::Write;
+
+/// The hex digits of the bytes in lowercase.
+fn hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 2);
+    for x in bytes {
+        let _ = write!(text, "{x:02x}");
+    }
+    text
+}
@@ -0,0 +1,239 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use base64::Engine;
+use serde_derive::Deserialize;
+
+use crate::domain::{Publisher, Timestamp};
+
+/// The scope of the access tokens to publish.
+const SCOPE: &str = "https://www.googleapis.com/auth/pubsub";
+/// How long an access token is requested for, in seconds.
+const TOKEN_LIFETIME_SECS: i64 = 3600;
+/// How long before its expiry an access token is renewed, in seconds.
+const TOKEN_MARGIN_SECS: i64 = 60;
+
+/// Publishes the events to a topic of Google Cloud Pub/Sub, authenticated as a service account.
+///
+/// Each message has the attribute `event` of the name of the event, for the filters of the subscriptions.
+/// With `PUBSUB_EMULATOR_HOST`, the messages are published to the emulator without the authentication.
+#[derive(Debug, Clone)]
+pub struct PubSubPublisher {
+    client: reqwest::Client,
+    /// The URL of `:publish` of the topic.
+    url: String,
+    account: Option<Arc<ServiceAccount>>,
+    /// The access token and the unix seconds when it expires.
+    token: Arc<tokio::sync::Mutex<Option<(String, i64)>>>,
+}
+/// The key of a service account, as downloaded in JSON.
+#[derive(Deserialize)]
+pub struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+    #[serde(skip)]
+    key_pair: Option<ring::signature::RsaKeyPair>,
+}
+impl std::fmt::Debug for ServiceAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // not to leak the private key in the logs.
+        f.debug_struct("ServiceAccount")
+            .field("client_email", &self.client_email)
+            .finish_non_exhaustive()
+    }
+}
+impl ServiceAccount {
+    /// Parses the JSON of the key of the service account.
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let mut account: Self = serde_json::from_str(json).map_err(Error::InvalidKey)?;
+        let pem: String = account
+            .private_key
+            .lines()
+            .filter(|x| !x.starts_with("-----"))
+            .collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(pem.trim())
+            .map_err(|_| Error::InvalidPrivateKey)?;
+        let key_pair =
+            ring::signature::RsaKeyPair::from_pkcs8(&der).map_err(|_| Error::InvalidPrivateKey)?;
+        account.key_pair = Some(key_pair);
+        Ok(account)
+    }
+
+    /// The JWT asserting the service account, signed by its private key.
+    fn assertion(&self, now: i64) -> Result<String, Error> {
+        let encode = |x: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(x);
+        let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        let claims = serde_json::json!({
+            "iss": self.client_email,
+            "scope": SCOPE,
+            "aud": self.token_uri,
+            "iat": now,
+            "exp": now + TOKEN_LIFETIME_SECS,
+        });
+        let message = format!(
+            "{}.{}",
+            encode(header.to_string().as_bytes()),
+            encode(claims.to_string().as_bytes())
+        );
+
+        let key_pair = self.key_pair.as_ref().ok_or(Error::InvalidPrivateKey)?;
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &ring::signature::RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| Error::InvalidPrivateKey)?;
+        Ok(format!("{message}.{}", encode(&signature)))
+    }
+}
+impl PubSubPublisher {
+    /// Makes the publisher to the topic, e.g. `projects/my-project/topics/patrol`, as the service account.
+    pub fn new(topic: &str, account: ServiceAccount) -> Result<Self, Error> {
+        Self::validate(topic)?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: format!("https://pubsub.googleapis.com/v1/{topic}:publish"),
+            account: Some(Arc::new(account)),
+            token: Arc::new(tokio::sync::Mutex::new(None)),
+        })
+    }
+
+    /// Makes the publisher to the topic on the emulator at the host, e.g. `localhost:8085`.
+    pub fn with_emulator(topic: &str, host: &str) -> Result<Self, Error> {
+        Self::validate(topic)?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: format!("http://{host}/v1/{topic}:publish"),
+            account: None,
+            token: Arc::new(tokio::sync::Mutex::new(None)),
+        })
+    }
+
+    fn validate(topic: &str) -> Result<(), Error> {
+        match topic.split('/').collect::<Vec<_>>().as_slice() {
+            ["projects", project, "topics", name] if 0 < project.len() && 0 < name.len() => Ok(()),
+            _ => Err(Error::InvalidTopic(topic.to_owned())),
+        }
+    }
+
+    /// The access token of the service account, renewed if it is about to expire.
+    async fn access_token(&self, account: &ServiceAccount) -> Result<String, Error> {
+        let mut token = self.token.lock().await;
+        let now = Timestamp::now().unix_secs();
+        if let Some((x, expiry)) = token.as_ref() {
+            if now + TOKEN_MARGIN_SECS < *expiry {
+                return Ok(x.clone());
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: i64,
+        }
+        let assertion = account.assertion(now)?;
+        let response = self
+            .client
+            .post(&account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(Error::Token(status, message));
+        }
+        let response: TokenResponse = response.json().await?;
+        *token = Some((response.access_token.clone(), now + response.expires_in));
+        Ok(response.access_token)
+    }
+}
+
+#[async_trait::async_trait]
+impl Publisher for PubSubPublisher {
+    type Error = Error;
+
+    async fn publish(&self, name: &str, json: &str) -> Result<(), Self::Error> {
+        let body = serde_json::json!({
+            "messages": [{
+                "data": base64::engine::general_purpose::STANDARD.encode(json),
+                "attributes": { "event": name },
+            }],
+        });
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(account) = self.account.as_ref() {
+            request = request.bearer_auth(self.access_token(account).await?);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // the errors of the API have their messages in `error.message`.
+        let message = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|x| x.get("error")?.get("message")?.as_str().map(str::to_owned));
+        Err(Error::Status(status, message))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode, Option<String>),
+    /// Failed to get the access token.
+    Token(reqwest::StatusCode, String),
+    InvalidTopic(String),
+    InvalidKey(serde_json::Error),
+    InvalidPrivateKey,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => f.write_fmt(format_args!("failed to publish to Pub/Sub: {e}")),
+            Error::Status(status, Some(message)) => {
+                f.write_fmt(format_args!("Pub/Sub responded with {status}: {message}"))
+            }
+            Error::Status(status, None) => {
+                f.write_fmt(format_args!("Pub/Sub responded with {status}"))
+            }
+            Error::Token(status, message) => f.write_fmt(format_args!(
+                "failed to get the access token of Pub/Sub with {status}: {message}"
+            )),
+            Error::InvalidTopic(x) => f.write_fmt(format_args!(
+                "invalid topic of Pub/Sub, which must be like projects/PROJECT/topics/TOPIC: {x}"
+            )),
+            Error::InvalidKey(e) => {
+                f.write_fmt(format_args!("invalid key of the service account: {e}"))
+            }
+            Error::InvalidPrivateKey => f.write_str(
+                "invalid private key of the service account, which must be RSA in PKCS #8",
+            ),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            Error::InvalidKey(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+use sha2::{Digest, Sha256};
+
+use super::hex;
+use crate::domain::{Publisher, Timestamp};
+
+/// The version of the API of SNS.
+const API_VERSION: &str = "2010-03-31";
+
+/// Publishes the events to a topic of Amazon SNS, signing the requests by Signature Version 4.
+///
+/// Each message has the attribute `event` of the name of the event, for the filter policies of the subscriptions.
+#[derive(Debug, Clone)]
+pub struct SnsPublisher {
+    client: reqwest::Client,
+    topic_arn: String,
+    region: String,
+    endpoint: String,
+    credentials: Credentials,
+}
+/// The credentials of AWS.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// The token of the temporary credentials, if any.
+    pub session_token: Option<String>,
+}
+impl Credentials {
+    /// Reads `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    pub fn from_env() -> Result<Self, Error> {
+        let var = |name: &str| std::env::var(name).ok().filter(|x| 0 < x.len());
+        match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            _ => Err(Error::NoCredentials),
+        }
+    }
+}
+impl SnsPublisher {
+    /// Makes the publisher to the topic of the ARN, e.g. `arn:aws:sns:us-east-1:123456789012:patrol`,
+    /// at the endpoint of the region of the ARN.
+    pub fn new(topic_arn: &str, credentials: Credentials) -> Result<Self, Error> {
+        let region = match topic_arn.split(':').collect::<Vec<_>>().as_slice() {
+            ["arn", _, "sns", region, _, _] if 0 < region.len() => region.to_string(),
+            _ => return Err(Error::InvalidArn(topic_arn.to_owned())),
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            topic_arn: topic_arn.to_owned(),
+            endpoint: format!("https://sns.{region}.amazonaws.com/"),
+            region,
+            credentials,
+        })
+    }
+
+    /// Publishes to the endpoint instead of the one of the region, e.g. of LocalStack.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_owned();
+        self
+    }
+
+    /// The value of `Authorization` of the request, as Signature Version 4.
+    fn authorization(
+        &self,
+        host: &str,
+        amz_date: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> String {
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/sns/aws4_request", self.region);
+
+        let mut canonical_headers = vec![("host", host)];
+        canonical_headers.extend_from_slice(headers);
+        canonical_headers.sort();
+        let signed_headers: Vec<_> = canonical_headers.iter().map(|(k, _)| *k).collect();
+        let signed_headers = signed_headers.join(";");
+        let mut canonical_request = "POST\n/\n\n".to_owned();
+        for (k, v) in canonical_headers.iter() {
+            canonical_request.push_str(&format!("{k}:{}\n", v.trim()));
+        }
+        canonical_request.push_str(&format!(
+            "\n{signed_headers}\n{}",
+            hex(&Sha256::digest(body.as_bytes()))
+        ));
+
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let secret = format!("AWS4{}", self.credentials.secret_access_key);
+        let mut key = secret.into_bytes();
+        for x in [date, self.region.as_str(), "sns", "aws4_request"] {
+            key = hmac(&key, x);
+        }
+        let signature = hex(&hmac(&key, &string_to_sign));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.credentials.access_key_id
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Publisher for SnsPublisher {
+    type Error = Error;
+
+    async fn publish(&self, name: &str, json: &str) -> Result<(), Self::Error> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("Action", "Publish")
+            .append_pair("Version", API_VERSION)
+            .append_pair("TopicArn", &self.topic_arn)
+            .append_pair("Message", json)
+            .append_pair("MessageAttributes.entry.1.Name", "event")
+            .append_pair("MessageAttributes.entry.1.Value.DataType", "String")
+            .append_pair("MessageAttributes.entry.1.Value.StringValue", name)
+            .finish();
+
+        let url = url::Url::parse(&self.endpoint)
+            .map_err(|_| Error::InvalidEndpoint(self.endpoint.clone()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return Err(Error::InvalidEndpoint(self.endpoint.clone())),
+        };
+        let amz_date =
+            match chrono::NaiveDateTime::from_timestamp_opt(Timestamp::now().unix_secs(), 0) {
+                Some(x) => x.format("%Y%m%dT%H%M%SZ").to_string(),
+                None => "19700101T000000Z".to_owned(),
+            };
+        let content_type = "application/x-www-form-urlencoded; charset=utf-8";
+        let mut headers = vec![
+            ("content-type", content_type),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(token) = self.credentials.session_token.as_deref() {
+            headers.push(("x-amz-security-token", token));
+        }
+        let authorization = self.authorization(&host, &amz_date, &headers, &body);
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::AUTHORIZATION, authorization);
+        for (k, v) in headers.iter() {
+            request = request.header(*k, *v);
+        }
+        let response = request.body(body).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // the errors of the API have their messages in `<Message>`.
+        let text = response.text().await.unwrap_or_default();
+        let message = text
+            .split_once("<Message>")
+            .and_then(|(_, x)| x.split_once("</Message>"))
+            .map(|(x, _)| x.to_owned());
+        Err(Error::Status(status, message))
+    }
+}
+
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key);
+    ring::hmac::sign(&key, message.as_bytes()).as_ref().to_vec()
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode, Option<String>),
+    InvalidArn(String),
+    InvalidEndpoint(String),
+    NoCredentials,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => f.write_fmt(format_args!("failed to publish to SNS: {e}")),
+            Error::Status(status, Some(message)) => {
+                f.write_fmt(format_args!("SNS responded with {status}: {message}"))
+            }
+            Error::Status(status, None) => f.write_fmt(format_args!("SNS responded with {status}")),
+            Error::InvalidArn(x) => f.write_fmt(format_args!("invalid ARN of the SNS topic: {x}")),
+            Error::InvalidEndpoint(x) => f.write_fmt(format_args!("invalid endpoint of SNS: {x}")),
+            Error::NoCredentials => f.write_str(
+                "AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY are required to publish to SNS",
+            ),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+use crate::domain::{Hash, Mode, Url};
+
+/// The extension of the files of the pages.
+const EXTENSION: &str = "html";
+
+/// A directory of the pages recorded while polling, to replay them later instead of fetching them,
+/// e.g. in the regression tests of the selectors and the diffs against the real pages.
+///
+/// Each page is kept in a file named after the mode and the SHA-256 of the URL, e.g. `simple-<hash>.html`.
+/// The pages of the full mode are the sources rendered by the browser.
+#[derive(Debug, Clone)]
+pub struct Recordings {
+    path: PathBuf,
+}
+impl Recordings {
+    /// Opens the directory, creating it if it does not exist.
+    pub async fn new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        tokio::fs::create_dir_all(path).await?;
+        Ok(Self { path: path.into() })
+    }
+
+    /// Records the page of the URL fetched in the mode, replacing the one recorded before.
+    pub async fn record(&self, mode: Mode, url: &Url, page: &str) -> std::io::Result<()> {
+        let path = self.path_of(mode, url);
+        // written to another file first, not to leave a partial page.
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, page).await?;
+        tokio::fs::rename(&temporary, &path).await
+    }
+
+    /// Reads the page of the URL recorded in the mode.
+    pub async fn replay(&self, mode: Mode, url: &Url) -> std::io::Result<String> {
+        tokio::fs::read_to_string(self.path_of(mode, url)).await
+    }
+
+    fn path_of(&self, mode: Mode, url: &Url) -> PathBuf {
+        let hash = Hash::new(url.as_str().as_bytes());
+        self.path.join(format!("{mode}-{hash}.{EXTENSION}"))
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// A file that is rotated when it grows larger than the limit.
+///
+/// When rotated, `foo.log` is renamed to `foo.log.1`, `foo.log.1` to `foo.log.2`, and so on.
+/// Files beyond `keep` are removed.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    len: u64,
+    header: Option<Vec<u8>>,
+}
+
+impl RotatingFile {
+    pub fn open<P: Into<PathBuf>>(path: P, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_bytes,
+            keep,
+            file,
+            len,
+            header: None,
+        })
+    }
+
+    /// Writes the header at the top of each file before its first write, e.g. the header of a CSV.
+    pub fn with_header(mut self, header: impl Into<Vec<u8>>) -> Self {
+        self.header = Some(header.into());
+        self
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut s = OsString::from(self.path.as_os_str());
+        s.push(format!(".{n}"));
+        s.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+            self.len = 0;
+            return Ok(());
+        }
+
+        for n in (1..self.keep).rev() {
+            match std::fs::rename(self.rotated_path(n), self.rotated_path(n + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if 0 < self.len && self.max_bytes < self.len + buf.len() as u64 {
+            self.rotate()?;
+        }
+        if let (0, Some(header)) = (self.len, self.header.as_ref()) {
+            self.file.write_all(header)?;
+            self.len += header.len() as u64;
+        }
+
+        let n = self.file.write(buf)?;
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
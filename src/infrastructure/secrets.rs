@@ -0,0 +1,109 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::domain::Secrets;
+use crate::infrastructure::encryption::{self, Cipher};
+
+/// Reads the secrets from the file, which is a TOML table of strings, or `NAME=value` lines if it ends with `.env`.
+///
+/// On unix, the file must not be accessible by the group or the others, as the keys of `ssh`.
+/// The values encrypted by the cipher, which start with `enc:`, are decrypted in memory,
+/// and so are those of the configs by the cipher.
+pub async fn load(path: &str, cipher: Option<&Cipher>) -> Result<Secrets, Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = tokio::fs::metadata(path).await?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(Error::Permissions(mode & 0o777));
+        }
+    }
+
+    let text = tokio::fs::read_to_string(path).await?;
+    let mut map = match path.ends_with(".env") {
+        true => parse_env(&text),
+        false => toml::from_str::<HashMap<String, String>>(&text)?,
+    };
+    for (name, value) in map.iter_mut() {
+        if !Cipher::is_encrypted(value) {
+            continue;
+        }
+        let cipher = cipher.ok_or_else(|| Error::NoPassphrase(name.clone()))?;
+        *value = cipher
+            .decrypt(value)
+            .map_err(|e| Error::Encryption(name.clone(), e))?;
+    }
+    let secrets = Secrets::new(map);
+    match cipher {
+        Some(x) => Ok(secrets.with_decrypter(std::sync::Arc::new(x.clone()))),
+        None => Ok(secrets),
+    }
+}
+
+/// Parses the lines like `NAME=value` of an environment file, skipping the comments.
+/// The values may be quoted.
+fn parse_env(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|x| !x.starts_with('#'))
+        .filter_map(|x| x.split_once('='))
+        .map(|(name, value)| {
+            let name = name.trim().trim_start_matches("export ").trim();
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|q| value.strip_prefix(*q).and_then(|x| x.strip_suffix(*q)))
+                .unwrap_or(value);
+            (name.to_owned(), value.to_owned())
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    TomlParseError(toml::de::Error),
+    /// The file is accessible by the group or the others, with the mode.
+    Permissions(u32),
+    /// The secret of the name is encrypted, but no passphrase is given.
+    NoPassphrase(String),
+    Encryption(String, encryption::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => f.write_fmt(format_args!("failed to read the secrets: {e}")),
+            Error::TomlParseError(e) => {
+                f.write_fmt(format_args!("failed to parse the secrets: {e}"))
+            }
+            Error::Permissions(mode) => f.write_fmt(format_args!(
+                "the secrets must not be accessible by the others, but the mode is {mode:o}."
+            )),
+            Error::NoPassphrase(name) => f.write_fmt(format_args!(
+                "the secret {name} is encrypted, but no passphrase is given."
+            )),
+            Error::Encryption(name, e) => f.write_fmt(format_args!("the secret {name}: {e}")),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::TomlParseError(e) => Some(e),
+            Error::Permissions(_) => None,
+            Error::NoPassphrase(_) => None,
+            Error::Encryption(_, e) => Some(e),
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlParseError(e)
+    }
+}
@@ -0,0 +1,120 @@
+use scraper::{ElementRef, Html};
+
+use crate::domain::Selector;
+
+/// An element of the page that can be used as a target.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The selector that selects this element first.
+    pub selector: Selector,
+    /// The text directly contained in this element.
+    pub text: String,
+}
+
+/// Lists the elements which directly contain some text, along with the selectors generated for them.
+///
+/// The candidates are in document order and have unique selectors.
+pub fn find_candidates(html: &str) -> Vec<Candidate> {
+    let doc = Html::parse_document(html);
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    for elem in doc
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let is_invisible = std::iter::once(elem)
+            .chain(elem.ancestors().filter_map(ElementRef::wrap))
+            .any(|x| matches!(x.value().name(), "head" | "script" | "style" | "noscript"));
+        if is_invisible {
+            continue;
+        }
+
+        let text = own_text(elem);
+        if text.is_empty() {
+            continue;
+        }
+
+        let selector = match generate_selector(&doc, elem) {
+            Some(x) => x,
+            None => continue,
+        };
+        if candidates.iter().any(|x| x.selector == selector) {
+            continue;
+        }
+
+        candidates.push(Candidate { selector, text });
+    }
+
+    candidates
+}
+
+/// Returns the text nodes directly under the element, with whitespace collapsed.
+fn own_text(elem: ElementRef) -> String {
+    elem.children()
+        .filter_map(|x| x.value().as_text())
+        .flat_map(|x| x.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Generates a selector that selects the element first in the document.
+fn generate_selector(doc: &Html, elem: ElementRef) -> Option<Selector> {
+    let mut parts = Vec::new();
+    let mut current = Some(elem);
+
+    while let Some(e) = current {
+        let value = e.value();
+
+        if let Some(id) = value.id().filter(|x| is_ident(x)) {
+            parts.push(format!("#{id}"));
+            break;
+        }
+
+        let mut part = value.name().to_owned();
+        for class in value.classes().filter(|x| is_ident(x)) {
+            part.push('.');
+            part.push_str(class);
+        }
+
+        let same_name_siblings = e
+            .prev_siblings()
+            .filter_map(ElementRef::wrap)
+            .filter(|x| x.value().name() == value.name())
+            .count();
+        let has_next_same_name = e
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .any(|x| x.value().name() == value.name());
+        if 0 < same_name_siblings || has_next_same_name {
+            part.push_str(&format!(":nth-of-type({})", same_name_siblings + 1));
+        }
+
+        parts.push(part);
+
+        current = e.parent().and_then(ElementRef::wrap);
+    }
+
+    parts.reverse();
+    let selector = parts.join(" > ");
+
+    // make sure that the generated selector selects the element first.
+    let parsed = scraper::Selector::parse(&selector).ok()?;
+    if doc.select(&parsed).next().map(|x| x.id()) != Some(elem.id()) {
+        return None;
+    }
+
+    Selector::new(selector).ok()
+}
+
+/// Returns true if the string can be used in a selector without escaping.
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    let head = match chars.next() {
+        Some(c) => c,
+        None => return false,
+    };
+
+    (head.is_ascii_alphabetic() || head == '_' || head == '-')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
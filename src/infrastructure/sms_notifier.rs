@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+use crate::domain::{Notification, Notifier};
+
+/// The base URL of the REST API of Twilio.
+pub const TWILIO_API_URL: &str = "https://api.twilio.com";
+/// The longest text of a message, which is sent in up to 3 segments of SMS.
+const MAX_TEXT_CHARS: usize = 450;
+
+/// Sends the notifications as SMS by the REST API of Twilio, or of a provider compatible with it.
+///
+/// Each message is created by `POST {api_url}/2010-04-01/Accounts/{account}/Messages.json` for each recipient,
+/// authenticated by the account and the token.
+#[derive(Debug, Clone)]
+pub struct SmsNotifier {
+    client: reqwest::Client,
+    api_url: String,
+    account: String,
+    token: String,
+    from: String,
+    to: Vec<String>,
+}
+impl SmsNotifier {
+    /// Makes the notifier sending from the number to the numbers, e.g. `+15551234567`.
+    pub fn new(account: &str, token: &str, from: &str, to: Vec<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: TWILIO_API_URL.to_owned(),
+            account: account.to_owned(),
+            token: token.to_owned(),
+            from: from.to_owned(),
+            to,
+        }
+    }
+
+    /// Sends by the API of the base URL instead of the one of Twilio.
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    async fn send(&self, to: &str, text: &str) -> Result<(), Error> {
+        let url = format!(
+            "{}/2010-04-01/Accounts/{}/Messages.json",
+            self.api_url, self.account
+        );
+        let response = self
+            .client
+            .post(url)
+            .basic_auth(&self.account, Some(&self.token))
+            .form(&[("To", to), ("From", self.from.as_str()), ("Body", text)])
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // the errors of the API have their messages in `message`.
+        let message = response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|x| x.get("message")?.as_str().map(str::to_owned));
+        Err(Error::Status(status, message))
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for SmsNotifier {
+    type Error = Error;
+
+    /// Sends the notification to every recipient, failing on the first error.
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::Error> {
+        let text = notification.to_text();
+        let text = match text.char_indices().nth(MAX_TEXT_CHARS) {
+            Some((end, _)) => &text[..end],
+            None => &text,
+        };
+        for to in self.to.iter() {
+            self.send(to, text).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode, Option<String>),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => f.write_fmt(format_args!("failed to send the SMS: {e}")),
+            Error::Status(status, Some(message)) => f.write_fmt(format_args!(
+                "the SMS API responded with {status}: {message}"
+            )),
+            Error::Status(status, None) => {
+                f.write_fmt(format_args!("the SMS API responded with {status}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            Error::Status(..) => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
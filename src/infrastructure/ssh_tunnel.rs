@@ -0,0 +1,170 @@
+use std::{collections::HashMap, fmt::Display};
+
+use log::{debug, info, warn};
+use serde_derive::Deserialize;
+use tokio::task::JoinHandle;
+
+/// Maximum wait before restarting a tunnel which has exited.
+const MAX_RESTART_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+/// How long to wait for a tunnel to accept connections on start.
+const READY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// An SSH connection forwarding a local SOCKS port, e.g. through a bastion host.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TunnelConfig {
+    /// The destination passed to `ssh`, e.g. `user@bastion.example.com`, which must not start with `-`.
+    pub host: String,
+    pub port: Option<u16>,
+    /// The identity file passed to `ssh -i`.
+    pub key: Option<String>,
+    /// The local port of the SOCKS proxy.
+    pub local_port: u16,
+}
+
+/// SSH tunnels kept alive while this is alive, restarted whenever `ssh` exits.
+#[derive(Debug)]
+pub struct SshTunnels {
+    ports: HashMap<String, u16>,
+    tasks: Vec<JoinHandle<()>>,
+}
+impl SshTunnels {
+    /// Reads the tunnels from the TOML file, a table of `TunnelConfig`s keyed by the names.
+    pub async fn load(path: &str) -> Result<HashMap<String, TunnelConfig>, Error> {
+        let toml = tokio::fs::read_to_string(path).await?;
+        let configs: HashMap<String, TunnelConfig> = toml::from_str(&toml)?;
+        // not to be taken as an option of ssh.
+        if let Some((name, config)) = configs.iter().find(|(_, x)| x.host.starts_with('-')) {
+            return Err(Error::InvalidHost(name.clone(), config.host.clone()));
+        }
+        Ok(configs)
+    }
+
+    /// Starts the tunnels, and waits a while until they accept connections.
+    pub async fn start(configs: HashMap<String, TunnelConfig>) -> Self {
+        let ports = configs
+            .iter()
+            .map(|(name, config)| (name.clone(), config.local_port))
+            .collect();
+
+        let mut tasks = Vec::new();
+        for (name, config) in configs.into_iter() {
+            let local_port = config.local_port;
+            tasks.push(tokio::spawn(keep_alive(name.clone(), config)));
+
+            if wait_until_ready(local_port).await {
+                info!("tunnel {name} is ready on port {local_port}.");
+            } else {
+                warn!("tunnel {name} is not ready on port {local_port} yet.");
+            }
+        }
+
+        Self { ports, tasks }
+    }
+
+    /// Returns the local SOCKS ports keyed by the names of the tunnels.
+    pub fn ports(&self) -> HashMap<String, u16> {
+        self.ports.clone()
+    }
+}
+impl Drop for SshTunnels {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
+
+/// Runs `ssh` for the tunnel, and restarts it with a backoff whenever it exits.
+async fn keep_alive(name: String, config: TunnelConfig) {
+    let mut backoff = std::time::Duration::from_secs(1);
+
+    loop {
+        let mut command = tokio::process::Command::new("ssh");
+        command
+            .args(["-N", "-D", &format!("127.0.0.1:{}", config.local_port)])
+            .args(["-o", "BatchMode=yes"])
+            .args(["-o", "ExitOnForwardFailure=yes"])
+            .args(["-o", "ServerAliveInterval=30"])
+            .kill_on_drop(true);
+        if let Some(port) = config.port {
+            command.args(["-p", &port.to_string()]);
+        }
+        if let Some(key) = config.key.as_ref() {
+            command.args(["-i", key]);
+        }
+        command.arg("--").arg(&config.host);
+
+        let started_at = tokio::time::Instant::now();
+        match command.spawn() {
+            Ok(mut child) => {
+                debug!("tunnel {name}: ssh started.");
+                match child.wait().await {
+                    Ok(status) => warn!("tunnel {name}: ssh exited with {status}."),
+                    Err(why) => warn!("tunnel {name}: {why}"),
+                }
+            }
+            Err(why) => warn!("tunnel {name}: failed to start ssh: {why}"),
+        }
+
+        // a tunnel which has been up for a while starts over from a short backoff.
+        if MAX_RESTART_BACKOFF < started_at.elapsed() {
+            backoff = std::time::Duration::from_secs(1);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+    }
+}
+
+async fn wait_until_ready(local_port: u16) -> bool {
+    let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if tokio::net::TcpStream::connect(("127.0.0.1", local_port))
+            .await
+            .is_ok()
+        {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+    false
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    TomlParseError(toml::de::Error),
+    /// The host of the tunnel of the name starts with `-`.
+    InvalidHost(String, String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => f.write_fmt(format_args!("failed to read the tunnels: {e}")),
+            Error::TomlParseError(e) => {
+                f.write_fmt(format_args!("failed to parse the tunnels: {e}"))
+            }
+            Error::InvalidHost(name, host) => f.write_fmt(format_args!(
+                "the host of the tunnel {name} must not start with '-': {host}"
+            )),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::TomlParseError(e) => Some(e),
+            Error::InvalidHost(..) => None,
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlParseError(e)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod toml_subscription_repository;
+
+pub use self::toml_subscription_repository::TomlSubscriptionRepository;
@@ -0,0 +1,155 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use log::debug;
+
+use crate::domain::{Id, Subscription};
+use crate::infrastructure::encryption::{self, Cipher};
+use crate::infrastructure::toml_file_proxy::{Error as TomlProxyError, TomlFileProxy};
+
+/// A TOML file keeping the webhook subscriptions registered at runtime.
+///
+/// With the cipher, the secrets of the subscriptions added are kept encrypted in the file, starting with `enc:`,
+/// and decrypted in memory.
+pub struct TomlSubscriptionRepository {
+    proxy: TomlFileProxy<HashMap<Id, Subscription>>,
+    cipher: Option<Cipher>,
+    /// The decrypted secrets of the subscriptions.
+    secrets: HashMap<Id, String>,
+}
+impl TomlSubscriptionRepository {
+    pub async fn new(path: impl AsRef<Path>, cipher: Option<Cipher>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut proxy = TomlFileProxy::<HashMap<Id, Subscription>>::new(path).await?;
+        let map = proxy.load().await?;
+        debug!("{} has {} subscriptions.", path.display(), map.len());
+
+        let mut secrets = HashMap::new();
+        for (id, subscription) in map.iter() {
+            let secret = match subscription.secret.as_ref() {
+                Some(x) if Cipher::is_encrypted(x) => {
+                    let cipher = cipher
+                        .as_ref()
+                        .ok_or_else(|| Error::NoPassphrase(id.clone()))?;
+                    cipher
+                        .decrypt(x)
+                        .map_err(|e| Error::Encryption(id.clone(), e))?
+                }
+                Some(x) => x.clone(),
+                None => continue,
+            };
+            let _ = secrets.insert(id.clone(), secret);
+        }
+        Ok(Self {
+            proxy,
+            cipher,
+            secrets,
+        })
+    }
+
+    /// Lists the subscriptions with the decrypted secrets, ordered by the ids.
+    pub fn list(&self) -> Vec<(Id, Subscription)> {
+        let map = self.proxy.get_cache().unwrap();
+        let mut list: Vec<_> = map
+            .iter()
+            .map(|(id, x)| {
+                let subscription = Subscription {
+                    secret: self.secrets.get(id).cloned(),
+                    ..x.clone()
+                };
+                (id.clone(), subscription)
+            })
+            .collect();
+        list.sort_by(|a, b| a.0.cmp(&b.0));
+        list
+    }
+
+    /// Adds the subscription, and returns the id generated for it.
+    pub async fn add(&mut self, subscription: Subscription) -> Result<Id, Error> {
+        let old = self.proxy.get_cache().cloned();
+        let map = self.proxy.get_cache_mut().unwrap();
+        let id = Id::new().unique_among(|x| map.contains_key(x));
+        let stored = match (subscription.secret.as_ref(), self.cipher.as_ref()) {
+            (Some(secret), Some(cipher)) => Some(
+                cipher
+                    .encrypt(secret)
+                    .map_err(|e| Error::Encryption(id.clone(), e))?,
+            ),
+            (secret, _) => secret.cloned(),
+        };
+        let secret = subscription.secret.clone();
+        let _ = map.insert(
+            id.clone(),
+            Subscription {
+                secret: stored,
+                ..subscription
+            },
+        );
+        self.save_or_restore(old).await?;
+        if let Some(secret) = secret {
+            let _ = self.secrets.insert(id.clone(), secret);
+        }
+        Ok(id)
+    }
+
+    /// Removes the subscription, and returns it if found.
+    pub async fn remove(&mut self, id: &Id) -> Result<Option<Subscription>, Error> {
+        let old = self.proxy.get_cache().cloned();
+        let removed = self.proxy.get_cache_mut().unwrap().remove(id);
+        if removed.is_none() {
+            return Ok(None);
+        }
+        self.save_or_restore(old).await?;
+        let secret = self.secrets.remove(id);
+        Ok(removed.map(|x| Subscription { secret, ..x }))
+    }
+
+    async fn save_or_restore(
+        &mut self,
+        old: Option<HashMap<Id, Subscription>>,
+    ) -> Result<(), Error> {
+        if let Err(e) = self.proxy.save().await {
+            if let Some(old) = old {
+                self.proxy.update_cache(old);
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    FileError(TomlProxyError),
+    /// The secret of the subscription is encrypted, but no passphrase is given.
+    NoPassphrase(Id),
+    Encryption(Id, encryption::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::FileError(e) => {
+                f.write_fmt(format_args!("failed to access the subscriptions: {e}"))
+            }
+            Error::NoPassphrase(id) => f.write_fmt(format_args!(
+                "the secret of the subscription {id} is encrypted, but no passphrase is given."
+            )),
+            Error::Encryption(id, e) => {
+                f.write_fmt(format_args!("the secret of the subscription {id}: {e}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::FileError(e) => Some(e),
+            Error::NoPassphrase(_) => None,
+            Error::Encryption(_, e) => Some(e),
+        }
+    }
+}
+impl From<TomlProxyError> for Error {
+    fn from(e: TomlProxyError) -> Self {
+        Error::FileError(e)
+    }
+}
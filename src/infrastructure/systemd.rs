@@ -0,0 +1,62 @@
+use std::os::unix::net::UnixDatagram;
+
+use log::{debug, warn};
+
+/// Sends the state (e.g. `READY=1`) to the service manager.
+///
+/// Returns `Ok(false)` without doing anything if the process is not started by systemd as `Type=notify`.
+pub fn notify(state: &str) -> std::io::Result<bool> {
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+
+    let socket = UnixDatagram::unbound()?;
+    let bytes = path.to_string_lossy();
+
+    #[cfg(target_os = "linux")]
+    if let Some(name) = bytes.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(true);
+    }
+
+    socket.send_to(state.as_bytes(), bytes.as_ref())?;
+    Ok(true)
+}
+
+/// Returns the watchdog timeout requested by the service manager.
+pub fn watchdog_timeout() -> Option<std::time::Duration> {
+    if let Some(pid) = std::env::var_os("WATCHDOG_PID") {
+        if pid.to_string_lossy() != std::process::id().to_string() {
+            return None;
+        }
+    }
+
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if usec == 0 {
+        return None;
+    }
+
+    Some(std::time::Duration::from_micros(usec))
+}
+
+/// Spawns a task which keeps the watchdog alive, if the service manager requested it.
+pub fn spawn_watchdog() -> Option<tokio::task::JoinHandle<()>> {
+    let timeout = watchdog_timeout()?;
+    debug!("systemd watchdog timeout: {timeout:?}");
+
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(timeout / 2);
+        loop {
+            let _ = interval.tick().await;
+            if let Err(why) = notify("WATCHDOG=1") {
+                warn!("failed to notify the watchdog: {why}");
+            }
+        }
+    });
+
+    Some(handle)
+}
@@ -0,0 +1,101 @@
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{
+    self,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    DigitallySignedStruct, SignatureScheme,
+};
+
+use crate::domain::{Hash, Url};
+
+/// How long the connection and the handshake may take.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Reads the fingerprint of the certificate served by the TLS server of the URL, the SHA-256 of it in DER.
+///
+/// Any certificate is accepted, even an invalid one, as the fingerprint is what is compared.
+/// The host is connected directly, through neither the proxies nor the tunnels.
+pub async fn fingerprint(url: &Url) -> std::io::Result<Hash> {
+    let host = url
+        .as_url()
+        .host_str()
+        .map(|x| x.trim_start_matches('[').trim_end_matches(']').to_owned())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "the URL has no host"))?;
+    let port = url.as_url().port_or_known_default().unwrap_or(443);
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(Error::other)?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+        .with_no_client_auth();
+    let name =
+        ServerName::try_from(host.clone()).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+    let handshake = async {
+        let tcp = TcpStream::connect((host.as_str(), port)).await?;
+        tokio_rustls::TlsConnector::from(Arc::new(config))
+            .connect(name, tcp)
+            .await
+    };
+    let tls = tokio::time::timeout(TIMEOUT, handshake).await??;
+    let (_, connection) = tls.get_ref();
+    let certificate = connection
+        .peer_certificates()
+        .and_then(|x| x.first())
+        .ok_or_else(|| Error::other("no certificate is served"))?;
+    Ok(Hash::new(certificate.as_ref()))
+}
+
+/// Accepts any certificate, still verifying that the server holds its key.
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
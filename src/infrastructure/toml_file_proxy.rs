@@ -1,13 +1,35 @@
-use std::{fmt::Display, io::SeekFrom};
+use std::{
+    fmt::Display,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
 
 use tokio::{
     fs::{File, OpenOptions},
     io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
 };
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+/// The byte order mark of UTF-8.
+const BOM: char = '\u{feff}';
 
+/// A proxy to a toml file, caching its data.
+///
+/// `save` keeps the comments, the order and the formatting of the file for the data unchanged since `load`,
+/// so that a file maintained by hand survives the updates.
 pub struct TomlFileProxy<T> {
+    path: PathBuf,
     file: File,
+    read_only: bool,
+    /// Whether the file had the byte order mark of UTF-8, e.g. written by Notepad, which is kept when saved.
+    bom: bool,
+    /// Whether the lines of the file ended with CRLF, which are kept when saved.
+    crlf: bool,
     cache: Option<T>,
+    /// The file as loaded or saved last, with the comments and the formatting.
+    document: Option<DocumentMut>,
+    /// The data of `document` as serialized, to tell which parts of the cache have changed.
+    base: Option<DocumentMut>,
 }
 
 impl<T> TomlFileProxy<T>
@@ -15,15 +37,47 @@ where
     T: serde::de::DeserializeOwned + serde::Serialize,
 {
     /// Create a new proxy to the toml file.
-    pub async fn new(path: &str) -> Result<Self, Error> {
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(path)
-            .await?;
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = open(path, false).await?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            read_only: false,
+            bom: false,
+            crlf: false,
+            cache: None,
+            document: None,
+            base: None,
+        })
+    }
+
+    /// Create a new proxy to the toml file, which is only read and must exist.
+    ///
+    /// `save` fails with the error of the file.
+    pub async fn new_read_only(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = open(path, true).await?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            read_only: true,
+            bom: false,
+            crlf: false,
+            cache: None,
+            document: None,
+            base: None,
+        })
+    }
 
-        Ok(Self { file, cache: None })
+    /// Open the file again, in case it has been replaced by another file (e.g. by an editor).
+    ///
+    /// The cache is kept as it is.
+    pub async fn reopen(&mut self) -> Result<(), Error> {
+        self.file = open(&self.path, self.read_only).await?;
+        Ok(())
     }
 
     /// Load data from the file to cache, and returns the cached data
@@ -32,21 +86,59 @@ where
 
         self.file.seek(SeekFrom::Start(0)).await?;
         self.file.read_to_string(&mut toml).await?;
+        self.bom = toml.starts_with(BOM);
+        self.crlf = toml.contains("\r\n");
+        let toml = toml.strip_prefix(BOM).unwrap_or(&toml);
 
-        self.cache = toml::from_str::<T>(&toml)?.into();
+        let cache = toml::from_str::<T>(toml)?;
+        // the file is rewritten as a whole if it cannot be edited, e.g. when it is empty.
+        self.base = serialize(&cache).ok();
+        self.document = toml.parse::<DocumentMut>().ok();
+        self.cache = cache.into();
 
         Ok(self.cache.as_ref().unwrap())
     }
 
     /// Save the cached data to the file
     pub async fn save(&mut self) -> Result<(), Error> {
-        let Self { file, cache } = self;
+        let Self {
+            file,
+            bom,
+            crlf,
+            cache,
+            document,
+            base,
+            ..
+        } = self;
         let cache = match cache {
             Some(c) => c,
             None => return Err(Error::CacheEmpty),
         };
 
-        let toml = toml::to_string_pretty(cache).unwrap();
+        let new = serialize(cache)?;
+        let toml = match (document.as_mut(), base.as_ref()) {
+            (Some(document), Some(base)) => {
+                merge_table(
+                    document.as_table_mut(),
+                    Some(base.as_table()),
+                    new.as_table(),
+                );
+                document.to_string()
+            }
+            _ => {
+                *document = Some(new.clone());
+                new.to_string()
+            }
+        };
+        *base = Some(new);
+        // the lines added in LF follow the ones of the file.
+        let mut toml = match crlf {
+            true => toml.replace("\r\n", "\n").replace('\n', "\r\n"),
+            false => toml,
+        };
+        if *bom {
+            toml.insert(0, BOM);
+        }
 
         file.seek(SeekFrom::Start(0)).await?;
         file.set_len(0).await?;
@@ -69,6 +161,8 @@ where
         self.cache = data.into();
     }
 
+    // `if let` borrows the cache until the end of the function, which conflicts with `load`.
+    #[allow(clippy::unnecessary_unwrap)]
     pub async fn get_cache_or_load(&mut self) -> Result<&T, Error> {
         if self.cache.is_some() {
             return Ok(self.cache.as_ref().unwrap());
@@ -82,10 +176,155 @@ where
     }
 }
 
+/// Serializes the data into a pretty document.
+fn serialize<T: serde::Serialize>(data: &T) -> Result<DocumentMut, Error> {
+    let toml = toml_edit::ser::to_string_pretty(data)?;
+    // the pretty document is reparsed to have the positions of its tables.
+    Ok(toml
+        .parse::<DocumentMut>()
+        .expect("the serialized toml is parsed"))
+}
+
+/// Merges the new table into the table of the file, keeping the entries of the file unchanged from the base.
+///
+/// The entries removed from the base are removed from the file, and the added ones are appended.
+fn merge_table(table: &mut dyn TableLike, base: Option<&dyn TableLike>, new: &dyn TableLike) {
+    let removed: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_owned())
+        .filter(|key| !new.contains_key(key))
+        .collect();
+    for key in removed.iter() {
+        let _ = table.remove(key);
+    }
+
+    let is_empty = table.is_empty();
+    for (key, new) in new.iter() {
+        let base = base.and_then(|x| x.get(key));
+        match table.get_mut(key) {
+            Some(item) => merge_item(item, base, new),
+            None => {
+                let mut new = new.clone();
+                forget_positions(&mut new);
+                if let (Item::Table(new), false) = (&mut new, is_empty) {
+                    if new
+                        .decor()
+                        .prefix()
+                        .and_then(|x| x.as_str())
+                        .unwrap_or_default()
+                        .is_empty()
+                    {
+                        new.decor_mut().set_prefix("\n");
+                    }
+                }
+                let _ = table.insert(key, new);
+            }
+        }
+    }
+}
+
+/// Merges the new item into the item of the file, which is kept as it is if the base is unchanged.
+fn merge_item(item: &mut Item, base: Option<&Item>, new: &Item) {
+    if base.is_some_and(|base| item_eq(base, new)) {
+        return;
+    }
+    if let (Some(table), Some(new)) = (item.as_table_like_mut(), new.as_table_like()) {
+        merge_table(table, base.and_then(Item::as_table_like), new);
+        return;
+    }
+    match (item, new) {
+        (Item::ArrayOfTables(tables), Item::ArrayOfTables(new)) if tables.len() == new.len() => {
+            let base = base.and_then(Item::as_array_of_tables);
+            for (i, (table, new)) in tables.iter_mut().zip(new.iter()).enumerate() {
+                let base = base.and_then(|x| x.get(i)).map(|x| x as &dyn TableLike);
+                merge_table(table, base, new);
+            }
+        }
+        // the comment after the value is kept.
+        (Item::Value(value), Item::Value(new)) => {
+            let decor = value.decor().clone();
+            *value = new.clone();
+            *value.decor_mut() = decor;
+        }
+        (item, new) => {
+            *item = new.clone();
+            forget_positions(item);
+        }
+    }
+}
+
+/// Forgets the positions of the tables in the item, to put them after the tables of the file.
+fn forget_positions(item: &mut Item) {
+    match item {
+        Item::Table(table) => {
+            table.set_position(None);
+            for (_, item) in table.iter_mut() {
+                forget_positions(item);
+            }
+        }
+        Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                table.set_position(None);
+                for (_, item) in table.iter_mut() {
+                    forget_positions(item);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Whether the items have the same data, regardless of their formatting.
+fn item_eq(a: &Item, b: &Item) -> bool {
+    if let (Some(a), Some(b)) = (a.as_table_like(), b.as_table_like()) {
+        return table_eq(a, b);
+    }
+    match (a, b) {
+        (Item::Value(a), Item::Value(b)) => value_eq(a, b),
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| table_eq(a, b))
+        }
+        _ => false,
+    }
+}
+
+fn table_eq(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, a)| b.get(key).is_some_and(|b| item_eq(a, b)))
+}
+
+fn value_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| value_eq(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => table_eq(a, b),
+        _ => false,
+    }
+}
+
+async fn open(path: &Path, read_only: bool) -> Result<File, std::io::Error> {
+    OpenOptions::new()
+        .read(true)
+        .write(!read_only)
+        .create(!read_only)
+        // `save` truncates the file before writing.
+        .truncate(false)
+        .open(path)
+        .await
+}
+
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
     TomlError(toml::de::Error),
+    TomlSerError(toml_edit::ser::Error),
     CacheEmpty,
 }
 impl Display for Error {
@@ -93,10 +332,12 @@ impl Display for Error {
         match self {
             Error::IoError(e) => f.write_fmt(format_args!("IO error: {e}")),
             Error::TomlError(e) => f.write_fmt(format_args!("Toml error: {e}")),
+            Error::TomlSerError(e) => f.write_fmt(format_args!("Toml serialize error: {e}")),
             Error::CacheEmpty => f.write_fmt(format_args!("Cache is empty.")),
         }
     }
 }
+// the errors are in the messages, so not the sources.
 impl std::error::Error for Error {}
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
@@ -108,3 +349,8 @@ impl From<toml::de::Error> for Error {
         Error::TomlError(e)
     }
 }
+impl From<toml_edit::ser::Error> for Error {
+    fn from(e: toml_edit::ser::Error) -> Self {
+        Error::TomlSerError(e)
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt::{Display, Write};
+
+use crate::domain::{Timestamp, Url};
+
+/// The header of the unix seconds when the payload was sent.
+pub const TIMESTAMP_HEADER: &str = "X-Patrol-Timestamp";
+/// The header of the random string unique to each payload, to reject the replayed ones.
+pub const NONCE_HEADER: &str = "X-Patrol-Nonce";
+/// The header of the signature of the payload, `sha256=` and the hex digits of the HMAC.
+pub const SIGNATURE_HEADER: &str = "X-Patrol-Signature";
+
+/// Posts the JSON of the events to the URL.
+///
+/// With a secret, each payload is signed by HMAC-SHA256 of `{timestamp}.{nonce}.{body}`,
+/// so the receiver sharing the secret can verify that it came from this patrol.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: Url,
+    key: Option<ring::hmac::Key>,
+}
+impl WebhookNotifier {
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            key: None,
+        }
+    }
+
+    /// Signs the payloads with the secret shared with the receiver.
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes()).into();
+        self
+    }
+
+    /// Posts the JSON, with its signature if the secret is given.
+    pub async fn send(&self, json: String) -> Result<(), Error> {
+        let timestamp = Timestamp::now().unix_secs().to_string();
+        let nonce = uuid::Uuid::new_v4().to_string();
+
+        let mut request = self
+            .client
+            .post(self.url.as_str())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(NONCE_HEADER, &nonce);
+        if let Some(signature) = self.sign(&timestamp, &nonce, &json) {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let response = request.body(json).send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(Error::Status(status)),
+        }
+    }
+
+    /// The value of the signature header of the payload, if the secret is given.
+    pub fn sign(&self, timestamp: &str, nonce: &str, body: &str) -> Option<String> {
+        let key = self.key.as_ref()?;
+        let message = format!("{timestamp}.{nonce}.{body}");
+        let tag = ring::hmac::sign(key, message.as_bytes());
+
+        let mut signature = "sha256=".to_owned();
+        for x in tag.as_ref() {
+            let _ = write!(signature, "{x:02x}");
+        }
+        Some(signature)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(e) => f.write_fmt(format_args!("failed to post the webhook: {e}")),
+            Error::Status(status) => {
+                f.write_fmt(format_args!("the webhook responded with {status}"))
+            }
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(e) => Some(e),
+            Error::Status(_) => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
+}
@@ -0,0 +1,140 @@
+use std::ffi::OsString;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use log::error;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// The name under which patrol is registered to the service control manager.
+pub const SERVICE_NAME: &str = "patrol";
+
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+/// The function run as the service, which returns the exit code.
+type Serve = Box<dyn FnOnce() -> i32 + Send>;
+
+static SERVE: Mutex<Option<Serve>> = Mutex::new(None);
+static STOP: OnceLock<tokio::sync::watch::Sender<bool>> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Registers the running executable as the service started with Windows by the arguments.
+///
+/// The arguments are given to the service as are, with `--service` appended.
+pub fn install(mut arguments: Vec<OsString>) -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+    let executable_path = std::env::current_exe().map_err(windows_service::Error::Winapi)?;
+    arguments.push("--service".into());
+
+    let info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: "patrol".into(),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments: arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description("Watches the pages and notifies their changes.")
+}
+
+/// Removes the service registered by [install], stopping it if it is running.
+pub fn uninstall() -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        SERVICE_NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+
+    service.delete()?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    Ok(())
+}
+
+/// Runs `serve` as the service started by the service control manager, and blocks until it returns.
+///
+/// This must be called on the main thread without a runtime, since the dispatcher takes it over.
+pub fn run(serve: impl FnOnce() -> i32 + Send + 'static) -> windows_service::Result<()> {
+    *SERVE.lock().unwrap() = Some(Box::new(serve));
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+/// Resolves when the service control manager asks the service to stop, or never if not run as the service.
+pub async fn stopped() {
+    let mut rx = match STOP.get() {
+        Some(x) => x.subscribe(),
+        None => return std::future::pending().await,
+    };
+    while !*rx.borrow_and_update() {
+        if rx.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(why) = serve_as_service() {
+        error!("failed to run as the service: {why}");
+    }
+}
+
+fn serve_as_service() -> windows_service::Result<()> {
+    let stop = STOP.get_or_init(|| tokio::sync::watch::channel(false).0);
+    let handler = move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            stop.send_replace(true);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let handle = service_control_handler::register(SERVICE_NAME, handler)?;
+
+    handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::Win32(0),
+    ))?;
+    let serve = SERVE.lock().unwrap().take();
+    let code = serve.map_or(0, |serve| serve());
+    let exit_code = match code {
+        0 => ServiceExitCode::Win32(0),
+        x => ServiceExitCode::ServiceSpecific(x as u32),
+    };
+    handle.set_service_status(status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    ))
+}
+
+fn status(
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+) -> ServiceStatus {
+    ServiceStatus {
+        service_type: SERVICE_TYPE,
+        current_state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use base64::Engine;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls;
+
+use super::change_feeds::escape_xml;
+use crate::domain::{Notification, Notifier};
+
+/// The port of the client connections of XMPP.
+const DEFAULT_PORT: u16 = 5222;
+/// How long a notification may take from the connection to the end of the stream.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// The largest reply of the server read at once, e.g. the features of the stream.
+const MAX_REPLY_BYTES: usize = 64 * 1024;
+
+/// Sends the notifications as chat messages over XMPP, e.g. to a self-hosted Prosody or ejabberd.
+///
+/// Each notification is sent over its own connection, secured by STARTTLS and authenticated by SASL PLAIN.
+/// The connection in plain text is allowed only to the loopback addresses.
+#[derive(Debug, Clone)]
+pub struct XmppNotifier {
+    jid: Jid,
+    password: String,
+    recipient: String,
+    host: String,
+    port: u16,
+}
+impl XmppNotifier {
+    /// Makes the notifier sending from the account of the JID, e.g. `patrol@example.com`, to the recipient.
+    ///
+    /// The server is the domain of the JID on the port 5222 unless `with_server` is given, as the SRV records are not looked up.
+    pub fn new(jid: &str, password: &str, recipient: &str) -> Result<Self, Error> {
+        let jid = Jid::parse(jid).ok_or_else(|| Error::InvalidJid(jid.to_owned()))?;
+        if Jid::parse(recipient).is_none() {
+            return Err(Error::InvalidJid(recipient.to_owned()));
+        }
+        Ok(Self {
+            host: jid.domain.clone(),
+            port: DEFAULT_PORT,
+            jid,
+            password: password.to_owned(),
+            recipient: recipient.to_owned(),
+        })
+    }
+
+    /// Connects to the server of `host` or `host:port` instead of the domain of the JID.
+    pub fn with_server(mut self, server: &str) -> Result<Self, Error> {
+        let (host, port) = match server.rsplit_once(':') {
+            Some((host, port)) if !host.ends_with(':') => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(Error::InvalidServer(server.to_owned())),
+            },
+            _ => (server, DEFAULT_PORT),
+        };
+        self.host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned();
+        self.port = port;
+        Ok(self)
+    }
+
+    async fn deliver(&self, text: &str) -> Result<(), Error> {
+        let mut tcp = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let features = self.open_stream(&mut tcp).await?;
+        if !features.contains("<starttls") {
+            if !self.is_loopback() {
+                return Err(Error::NoTls);
+            }
+            return self.session(&mut tcp, features, text).await;
+        }
+
+        write(
+            &mut tcp,
+            "<starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>",
+        )
+        .await?;
+        let reply = read_until(&mut tcp, |x| {
+            x.contains("<proceed") || x.contains("<failure")
+        })
+        .await?;
+        if !reply.contains("<proceed") {
+            return Err(Error::NoTls);
+        }
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .map_err(Error::Tls)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        // the certificate of a server of XMPP is of the domain, not of the host connected.
+        let name = rustls::pki_types::ServerName::try_from(self.jid.domain.clone())
+            .map_err(|_| Error::InvalidJid(self.jid.to_string()))?;
+        let mut tls = tokio_rustls::TlsConnector::from(Arc::new(config))
+            .connect(name, tcp)
+            .await?;
+
+        let features = self.open_stream(&mut tls).await?;
+        self.session(&mut tls, features, text).await
+    }
+
+    /// Authenticates, binds a resource, and sends the message over the stream whose features are given.
+    async fn session<S>(&self, stream: &mut S, features: String, text: &str) -> Result<(), Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        if !features.contains(">PLAIN<") {
+            return Err(Error::Auth("the server does not offer PLAIN".to_owned()));
+        }
+        let credentials = format!("\0{}\0{}", self.jid.local, self.password);
+        let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+        let auth = format!(
+            "<auth xmlns='urn:ietf:params:xml:ns:xmpp-sasl' mechanism='PLAIN'>{credentials}</auth>"
+        );
+        write(stream, &auth).await?;
+        let reply = read_until(stream, |x| {
+            x.contains("<success") || x.contains("</failure>")
+        })
+        .await?;
+        if !reply.contains("<success") {
+            return Err(Error::Auth(reply));
+        }
+
+        // the stream is restarted after the authentication.
+        let _ = self.open_stream(stream).await?;
+        let resource = escape_xml(self.jid.resource.as_deref().unwrap_or("patrol"));
+        let bind = format!(
+            "<iq type='set' id='bind'><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'><resource>{resource}</resource></bind></iq>"
+        );
+        write(stream, &bind).await?;
+        let reply = read_until(stream, |x| x.contains("</iq>")).await?;
+        if !reply.contains("type='result'") && !reply.contains("type=\"result\"") {
+            return Err(Error::Rejected(reply));
+        }
+
+        let message = format!(
+            "<message to=\"{}\" type=\"chat\" id=\"{}\"><body>{}</body></message>",
+            escape_xml(&self.recipient),
+            uuid::Uuid::new_v4(),
+            escape_xml(text)
+        );
+        write(stream, &message).await?;
+        // the server has processed the message when it closes its stream in reply.
+        write(stream, "</stream:stream>").await?;
+        let _ = read_until(stream, |x| x.contains("</stream:stream>")).await;
+        let _ = stream.shutdown().await;
+        Ok(())
+    }
+
+    /// Opens the stream, and returns the features the server offers on it.
+    async fn open_stream<S>(&self, stream: &mut S) -> Result<String, Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let header = format!(
+            "<?xml version='1.0'?><stream:stream to=\"{}\" version='1.0' xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams'>",
+            escape_xml(&self.jid.domain)
+        );
+        write(stream, &header).await?;
+        read_until(stream, |x| {
+            x.contains("</stream:features>") || x.contains("<stream:features/>")
+        })
+        .await
+    }
+
+    fn is_loopback(&self) -> bool {
+        match self.host.parse::<IpAddr>() {
+            Ok(addr) => addr.is_loopback(),
+            Err(_) => self.host.eq_ignore_ascii_case("localhost"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for XmppNotifier {
+    type Error = Error;
+
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::Error> {
+        match tokio::time::timeout(TIMEOUT, self.deliver(&notification.to_text())).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+}
+
+/// The address of an account of XMPP, `local@domain/resource` whose resource is optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Jid {
+    local: String,
+    domain: String,
+    resource: Option<String>,
+}
+impl Jid {
+    fn parse(text: &str) -> Option<Self> {
+        let (bare, resource) = match text.split_once('/') {
+            Some((bare, resource)) => (bare, Some(resource.to_owned())),
+            None => (text, None),
+        };
+        let (local, domain) = bare.split_once('@')?;
+        if local.len() == 0 || domain.len() == 0 || domain.contains('@') {
+            return None;
+        }
+        Some(Self {
+            local: local.to_owned(),
+            domain: domain.to_owned(),
+            resource,
+        })
+    }
+}
+impl Display for Jid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}@{}", self.local, self.domain))?;
+        match self.resource.as_ref() {
+            Some(x) => f.write_fmt(format_args!("/{x}")),
+            None => Ok(()),
+        }
+    }
+}
+
+async fn write<S>(stream: &mut S, text: &str) -> Result<(), Error>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream.write_all(text.as_bytes()).await?;
+    Ok(stream.flush().await?)
+}
+
+/// Reads the replies of the server until they are done, failing on the error of the stream.
+async fn read_until<S, F>(stream: &mut S, done: F) -> Result<String, Error>
+where
+    S: AsyncRead + Unpin,
+    F: Fn(&str) -> bool,
+{
+    let mut bytes = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(Error::Closed);
+        }
+        bytes.extend_from_slice(&chunk[..n]);
+        let text = String::from_utf8_lossy(&bytes);
+        if done(&text) {
+            return Ok(text.into_owned());
+        }
+        if text.contains("</stream:error>") {
+            return Err(Error::Rejected(text.into_owned()));
+        }
+        if MAX_REPLY_BYTES < bytes.len() {
+            return Err(Error::Rejected("the reply is too large".to_owned()));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(std::io::Error),
+    Tls(rustls::Error),
+    InvalidJid(String),
+    InvalidServer(String),
+    /// The server does not secure the connection by STARTTLS.
+    NoTls,
+    Auth(String),
+    /// The server has rejected the request.
+    Rejected(String),
+    Closed,
+    Timeout,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::IoError(e) => {
+                f.write_fmt(format_args!("failed to talk to the XMPP server: {e}"))
+            }
+            Error::Tls(e) => f.write_fmt(format_args!("failed to secure the XMPP connection: {e}")),
+            Error::InvalidJid(x) => f.write_fmt(format_args!("invalid JID: {x}")),
+            Error::InvalidServer(x) => f.write_fmt(format_args!("invalid XMPP server: {x}")),
+            Error::NoTls => f.write_str("the XMPP server does not offer STARTTLS"),
+            Error::Auth(x) => f.write_fmt(format_args!("failed to log in to the XMPP server: {x}")),
+            Error::Rejected(x) => f.write_fmt(format_args!("the XMPP server rejected: {x}")),
+            Error::Closed => f.write_str("the XMPP server closed the connection"),
+            Error::Timeout => f.write_str("the XMPP server timed out"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(e) => Some(e),
+            Error::Tls(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
@@ -1,6 +1,8 @@
-#![feature(once_cell)]
-#![feature(type_alias_impl_trait)]
-
 pub mod application;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod domain;
+pub mod error;
 pub mod infrastructure;
+
+pub use error::{Error, ErrorCode, Report};
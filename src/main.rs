@@ -1,11 +1,67 @@
-use clap::Parser;
-use env_logger::Env;
-use log::{error, info};
+use std::{
+    collections::HashMap,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgEnum, Parser, Subcommand};
+use env_logger::{Env, WriteStyle};
+use log::{debug, error, info, warn};
+use serde_derive::Serialize;
 
-use patrol::application::{App, SelectivePoller};
+use patrol::application::{
+    App, Event, EventBus, PatrolEvent, PollerRegistry, RunPolicy, SelectivePoller, CSV_HEADER,
+    EVENT_SCHEMA,
+};
+use patrol::domain::{
+    diff_excerpt, Config, ConfigEvent, ConfigRepository, DataOrder, DataQuery, DataReader,
+    Duration, DurationParseError, Hash, Id, Integrity, Mode, Poller, Secrets, Selector, Status,
+    TimeZone, Timestamp, Url,
+};
+#[cfg(unix)]
+use patrol::infrastructure::systemd;
+#[cfg(windows)]
+use patrol::infrastructure::windows;
+#[cfg(feature = "cdp")]
+use patrol::infrastructure::CdpPoller;
+#[cfg(feature = "keyring")]
+use patrol::infrastructure::KeyringStore;
+#[cfg(feature = "playwright")]
+use patrol::infrastructure::PlaywrightPoller;
+#[cfg(feature = "webdriver")]
+use patrol::infrastructure::WebDriverPoller;
+#[cfg(feature = "websocket")]
+use patrol::infrastructure::WebSocketPoller;
 use patrol::infrastructure::{
-    HttpPoller, TomlConfigRepository, TomlDataRepository, WebDriverPoller,
+    composites, find_candidates, find_links, secrets, toml_config_repository, ApiServer, Cipher,
+    ContentDirectory, ContentSamples, DebugBundles, FilePoller, FtpPoller, HttpPoller, ImapPoller,
+    NotifierTest as _, Recordings, ReplayPoller, RotatingFile, SshTunnels, TargetFiles,
+    TomlConfigRepository, TomlConfigTrash, TomlDataRepository, TomlSubscriptionRepository,
+    WebhookNotifier,
 };
+use patrol::Report;
+
+mod sinks;
+#[cfg(feature = "tui")]
+mod tui;
+
+use sinks::{is_notifiable, notified_events, SinkArgs, SinkSet};
+
+/// Exit code of `check` when the content has not changed.
+const EXIT_UNCHANGED: i32 = 0;
+/// Exit code of `check` when the content has changed.
+const EXIT_CHANGED: i32 = 10;
+/// Exit code of `check` when an error occurred.
+const EXIT_ERROR: i32 = 1;
+
+/// Number of rotated log files to keep.
+const LOG_FILES_TO_KEEP: usize = 5;
+/// The number of the rotated files of the samples of each target to keep.
+const SAMPLE_FILES_TO_KEEP: usize = 2;
+/// The path of the managed file of `--read-only-config`, relative to the directory of the config file.
+const MANAGED_CONFIG_PATH: &str = "targets.d/managed.toml";
+/// The number of the rotated files of `--events-file` to keep.
+const EVENT_FILES_TO_KEEP: usize = 5;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -13,68 +69,2542 @@ struct Args {
     #[clap(
         short,
         long,
+        parse(from_os_str),
+        global = true,
         help = "Specify the config file.",
         default_value = "./config.toml"
     )]
-    config_path: String,
+    config_path: PathBuf,
+    #[clap(
+        long,
+        global = true,
+        help = "Keep the config file read-only, saving the targets added or changed by the commands and the API to `targets.d/managed.toml` beside it, which overlays the config file."
+    )]
+    read_only_config: bool,
     #[clap(
         short,
         long,
+        parse(from_os_str),
+        global = true,
         help = "Specify the data file.",
         default_value = "./data.toml"
     )]
-    data_path: String,
+    data_path: PathBuf,
     #[clap(
         short('p'),
         long,
+        global = true,
         help = "Specify the Web Driver port to connect to.\nThis can be specified multiple times.",
         default_value = "9515"
     )]
     webdriver_ports: Vec<u16>,
+    #[clap(
+        long,
+        global = true,
+        help = "Specify the time zone to display the times in, `utc`, `local` or an offset like `+09:00`.\nThe times in the files and the events are always in UTC.",
+        default_value = "utc",
+        value_name = "ZONE",
+        allow_hyphen_values = true
+    )]
+    timezone: TimeZone,
     #[clap(
         short('i'),
         long,
-        help = "Specify the patrol interval in minutes.",
-        default_value_t = 1
+        alias = "interval-minutes",
+        help = "Specify the patrol interval, e.g. 90s or 15m, which is in minutes if an integer.",
+        default_value = "1",
+        parse(try_from_str = parse_minutes),
+        value_name = "DURATION"
+    )]
+    interval: Duration,
+    #[clap(
+        long,
+        help = "Patrol just once, and print a summary of the targets.\nExits with 10 if any target changed, 1 if none changed but any failed, and 0 otherwise.",
+        conflicts_with_all = &["cycles", "run-for"]
     )]
-    interval_minutes: u16,
-    #[clap(long, help = "Patrol just once.")]
     once: bool,
+    #[clap(
+        long,
+        help = "Write the summary of `--once` to the file as JSON.",
+        requires = "once",
+        value_name = "PATH"
+    )]
+    report_file: Option<String>,
+    #[clap(
+        long,
+        help = "Patrol the specified number of cycles, then exit.",
+        conflicts_with = "run-for"
+    )]
+    cycles: Option<u64>,
+    #[clap(
+        long,
+        help = "Patrol for the specified duration, e.g. 2h, then exit.\nAn integer is in minutes.",
+        parse(try_from_str = parse_minutes),
+        value_name = "DURATION"
+    )]
+    run_for: Option<Duration>,
+    #[clap(
+        short,
+        long,
+        arg_enum,
+        help = "Specify the output format.\n`jsonl` prints one JSON object per event to stdout instead of the summary.",
+        default_value = "text"
+    )]
+    output: OutputFormat,
+    #[clap(
+        long,
+        help = "Specify the version of the JSON of the events.\n1 is the one without `schema` and the unix milliseconds of the times, whose times have no offset.",
+        default_value_t = EVENT_SCHEMA,
+        possible_values = ["1", "2"]
+    )]
+    event_schema: u32,
+    #[clap(
+        long,
+        help = "Probe the idle WebDriver sessions every specified duration, and re-establish the unhealthy ones.\nAn integer is in seconds, and 0 disables the probes.",
+        default_value = "60",
+        parse(try_from_str = parse_seconds),
+        value_name = "DURATION"
+    )]
+    webdriver_health_check: Duration,
+    #[clap(
+        long,
+        arg_enum,
+        help = "Specify the browser backend of the full mode.\n`cdp` and `playwright` require patrol built with the features of the same names.",
+        default_value = "webdriver"
+    )]
+    browser: BrowserArg,
+    #[cfg(feature = "cdp")]
+    #[clap(
+        long,
+        help = "Connect to a running chrome via CDP, e.g. http://localhost:9222.\nA headless chrome is launched if omitted."
+    )]
+    cdp_url: Option<String>,
+    #[cfg(feature = "cdp")]
+    #[clap(
+        long,
+        help = "Specify the maximum number of tabs open at once via CDP.",
+        default_value_t = 4
+    )]
+    cdp_max_tabs: usize,
+    #[cfg(feature = "playwright")]
+    #[clap(
+        long,
+        help = "Connect to the Playwright server, e.g. ws://localhost:3000/?browser=chromium.",
+        required_if_eq("browser", "playwright")
+    )]
+    playwright_url: Option<String>,
+    #[cfg(feature = "playwright")]
+    #[clap(
+        long,
+        help = "Specify the maximum number of browser contexts open at once via Playwright.",
+        default_value_t = 4
+    )]
+    playwright_max_contexts: usize,
+    #[clap(
+        long,
+        global = true,
+        help = "Specify the TOML file of the SSH tunnels, which the targets can connect through.",
+        value_name = "PATH"
+    )]
+    ssh_tunnels: Option<String>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        global = true,
+        help = "Specify the directory to keep the latest content of each target in, to `search` them.",
+        value_name = "PATH"
+    )]
+    contents_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        global = true,
+        help = "Append the content of each poll to a file of each target in the directory, to debug the selectors later.",
+        value_name = "PATH"
+    )]
+    samples_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        arg_enum,
+        help = "Specify which polls `--samples-dir` keeps the contents of.",
+        default_value = "change",
+        value_name = "WHEN"
+    )]
+    samples_on: SamplesArg,
+    #[clap(
+        long,
+        parse(from_os_str),
+        global = true,
+        help = "Keep a debug bundle of the latest failed poll of each target in the full mode in the directory, with a screenshot, the page source and a HAR of the requests.\nServed as `GET /targets/:id/debug` with `--api`.",
+        value_name = "PATH"
+    )]
+    debug_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        help = "Rotate the file of the samples of a target when it grows larger than the specified KiB, which also caps each sample.",
+        default_value_t = 256,
+        value_name = "KIB"
+    )]
+    samples_max_kib: u64,
+    #[clap(
+        long,
+        global = true,
+        help = concat!("Append ", notified_events!(), " to the file, in the format of `--events-file-format`."),
+        value_name = "PATH"
+    )]
+    events_file: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        arg_enum,
+        help = "Specify the format of `--events-file`.\n`jsonl` is one JSON object per line in the schema of `--event-schema`, and `csv` is the rows of the time, the event, the target, the URL, the summary and the body.",
+        default_value = "jsonl"
+    )]
+    events_file_format: EventsFileFormat,
+    #[clap(
+        long,
+        global = true,
+        help = "Rotate `--events-file` when it grows larger than the specified MiB.",
+        default_value_t = 10,
+        value_name = "MIB"
+    )]
+    events_file_max_mib: u64,
+    #[clap(flatten)]
+    sinks: SinkArgs,
+    #[clap(
+        long,
+        help = "Emit a `failed` event of each target skipped as it was not polled within the cycle or its `budget`, e.g. to the webhook."
+    )]
+    notify_overruns: bool,
+    #[clap(
+        long,
+        help = "Checkpoint the targets polled in each cycle next to the data file, and resume the cycle interrupted by a crash or a restart."
+    )]
+    checkpoint: bool,
+    #[clap(
+        long,
+        help = "Spread the polls of the first cycle over the duration, the targets checked the longest ago first, e.g. 5m.\nCut down to the interval.",
+        value_name = "DURATION"
+    )]
+    ramp_up: Option<Duration>,
+    #[clap(
+        long,
+        requires = "api-token",
+        help = "Serve the statistics of the targets on the address, as `GET /targets/:id/stats`, their changes as `GET /calendar.ics`, `GET /feed.atom` and `GET /feed.json`, and their events as `GET /updates?since=`, e.g. 127.0.0.1:8080.\nEvery route requires `--api-token`.",
+        value_name = "ADDR"
+    )]
+    api: Option<std::net::SocketAddr>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        requires_all = &["api", "api-token"],
+        help = "Keep the webhooks subscribed by `POST /subscriptions` of the API in the file, and post the events to them as `--webhook`.\nRequires `--api-token`, not to let anyone reaching the API subscribe any URL to the events.",
+        value_name = "PATH"
+    )]
+    subscriptions: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "api",
+        help = "Require the token as `Authorization: Bearer <TOKEN>` or `?token=` on every route of the API.\nA reference like `${secrets.NAME}` is resolved from `--secrets`.",
+        value_name = "TOKEN"
+    )]
+    api_token: Option<String>,
+    #[clap(
+        long,
+        requires = "api-token",
+        help = "Also accept the token of a viewer on the routes of the API reading the targets, the statistics, the feeds and the updates, e.g. for a dashboard.\nThe subscriptions and the debug bundles require `--api-token`.\nA reference like `${secrets.NAME}` is resolved from `--secrets`.",
+        value_name = "TOKEN"
+    )]
+    api_viewer_token: Option<String>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        help = "Record the pages fetched in the simple mode, and the sources rendered by WebDriver in the full mode, into the directory.",
+        value_name = "DIR",
+        conflicts_with = "replay"
+    )]
+    record: Option<PathBuf>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        help = "Replay the pages recorded by `--record` in the directory instead of fetching them, extracting the contents without the browser.",
+        value_name = "DIR"
+    )]
+    replay: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        help = "Specify the file of the secrets referenced like `${secrets.NAME}` in the config.\nA TOML table of strings, or `NAME=value` lines if it ends with `.env`.",
+        value_name = "PATH"
+    )]
+    secrets: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        help = "Specify the file of the passphrase decrypting the secrets encrypted by `encrypt`.\n`PATROL_PASSPHRASE` is used if not given.",
+        value_name = "PATH"
+    )]
+    key_file: Option<String>,
+    #[clap(
+        long,
+        parse(from_os_str),
+        global = true,
+        help = "Specify the TOML file of the removed targets.\n`<CONFIG_PATH>.trash` is used if not given.",
+        value_name = "PATH"
+    )]
+    trash: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        help = "Purge the removed targets from the trash, and delete their data, after the duration. [default: kept]",
+        value_name = "DURATION"
+    )]
+    retention: Option<Duration>,
+    #[clap(
+        long,
+        help = "Specify the TOML file of the composite targets, whose states derive from the other targets.",
+        value_name = "PATH"
+    )]
+    composites: Option<String>,
+    #[clap(long, help = "Run in the background.")]
+    daemon: bool,
+    #[clap(
+        long,
+        help = "Run as the Windows service, started by the service control manager.\nRegister it by `service-install`."
+    )]
+    service: bool,
+    #[clap(long, help = "Write the process id to the file while running.")]
+    pid_file: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        help = "Write the log to the file instead of stderr."
+    )]
+    log_file: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        help = "Rotate the log file when it grows larger than the specified MiB.",
+        default_value_t = 10
+    )]
+    log_max_mib: u64,
+    #[clap(
+        long,
+        global = true,
+        arg_enum,
+        help = "Color the log and the summary.\n`auto` colors them only when they go to a terminal and `NO_COLOR` is not set.",
+        default_value = "auto",
+        value_name = "WHEN"
+    )]
+    color: ColorArg,
+    #[clap(
+        long,
+        global = true,
+        help = "Log only the errors, and print no progress messages to stderr.\n`RUST_LOG` still takes precedence."
+    )]
+    quiet: bool,
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Jsonl,
+}
 
-    info!("config_path:      {}", args.config_path);
-    info!("data_path:        {}", args.data_path);
-    info!("interval_minutes: {}", args.interval_minutes);
-    info!("webdriver_ports:  {:?}", args.webdriver_ports);
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
 
-    let config_repo = TomlConfigRepository::new(&args.config_path).await?;
-    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+/// The format of `--events-file`.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum EventsFileFormat {
+    Jsonl,
+    Csv,
+}
+
+/// Which polls `--samples-dir` keeps the contents of.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SamplesArg {
+    /// Every poll.
+    Poll,
+    /// The polls of which the contents have changed.
+    Change,
+}
 
-    let full_mode_poller = WebDriverPoller::new(args.webdriver_ports.as_slice()).await?;
-    let simple_modepoller = HttpPoller::new();
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum BrowserArg {
+    Webdriver,
+    Cdp,
+    Playwright,
+}
 
-    let poller = SelectivePoller::new(full_mode_poller, simple_modepoller);
+#[derive(Subcommand)]
+enum Command {
+    /// Poll a single target once and print the content.
+    ///
+    /// A URL is checked as the target watching it, if any.
+    /// Exits with 0 if the content is unchanged from the stored data, or the pinned version, 10 if changed, and 1 on error.
+    /// The lines changed from the content kept in `--contents-dir` are also printed.
+    /// The data file is not updated.
+    Check(CheckArgs),
+    /// Fetch the page, choose an element, and add it as a new target.
+    Pick(PickArgs),
+    /// List the new pages linked from the seed page, e.g. the product pages of a category page, and add them as targets by `--add`.
+    Discover(DiscoverArgs),
+    /// Print the status of every target.
+    Status(StatusArgs),
+    /// Print the statistics of the polls of the targets, e.g. the durations, the failure rate and the changes per week.
+    Stats(TargetsArgs),
+    /// Pause the targets, which are kept in the config but not polled.
+    Pause(TargetsArgs),
+    /// Resume the paused targets.
+    Resume(TargetsArgs),
+    /// Pin the current versions of the contents of the targets as the references, which every other content is a change from.
+    Pin(PinArgs),
+    /// Unpin the versions of the targets, whose contents are compared with the previous ones again.
+    ///
+    /// The targets watched for the integrity are pinned again on the next polls, with their TLS certificates.
+    Unpin(TargetsArgs),
+    /// Remove the targets from the config into the trash, to be restored until purged by `--retention`.
+    Remove(TargetsArgs),
+    /// Restore the removed targets from the trash.
+    Restore(TargetsArgs),
+    /// Print the removed targets in the trash.
+    Trash,
+    /// Add or update the targets defined in another config file.
+    Import(ImportArgs),
+    /// Print the lines containing the text in the latest contents kept in `--contents-dir`.
+    Search(SearchArgs),
+    /// Encrypt a secret read from stdin with the passphrase, and print it to put in the file of `--secrets`.
+    Encrypt,
+    /// Keep a secret read from stdin in the keyring of the OS, referenced as `${keyring.NAME}`.
+    ///
+    /// Requires the `keyring` feature.
+    KeyringSet(KeyringSetArgs),
+    /// Register patrol as the Windows service started with Windows, run by the arguments after `--`.
+    ///
+    /// The paths in the arguments should be absolute, since the service runs in the system directory.
+    ServiceInstall(ServiceInstallArgs),
+    /// Stop and remove the Windows service registered by `service-install`.
+    ServiceUninstall,
+    /// Print a synthetic `checked` event of a change in JSON lines, to test the notifier reading them.
+    NotifyTest(NotifyTestArgs),
+    /// Check the environment and print a report.
+    ///
+    /// Exits with 0 if every check passed, and 1 otherwise.
+    Doctor,
+    /// Patrol with a live view of the targets and the recent events, instead of the table printed every cycle.
+    ///
+    /// Requires the `tui` feature.
+    Tui,
+}
 
-    let interval_period_secs = args.interval_minutes.max(1) as u64 * 60;
-    let interval_limit = if args.once { Some(1) } else { None };
+#[derive(clap::Args)]
+struct CheckArgs {
+    #[clap(help = "The id of a configured target, or a URL.")]
+    target: String,
+    #[clap(
+        short,
+        long,
+        help = "Specify the selector.\nRequired when the target is a URL which no target watches."
+    )]
+    selector: Option<String>,
+    #[clap(short, long, arg_enum, help = "Specify the mode.")]
+    mode: Option<ModeArg>,
+}
 
-    info!("start app.");
-    let app = App::new(
-        config_repo,
-        data_repo,
-        poller,
-        interval_period_secs,
-        interval_limit,
-    );
+#[derive(clap::Args)]
+struct PickArgs {
+    #[clap(help = "The URL of the page.")]
+    url: String,
+    #[clap(
+        short,
+        long,
+        help = "Choose the first element containing the text instead of asking."
+    )]
+    query: Option<String>,
+    #[clap(
+        long,
+        help = "Specify the id of the new target.\nGenerated from the host and the path of the URL if omitted, e.g. example-com-news."
+    )]
+    id: Option<String>,
+    #[clap(long, help = "Replace the target of `--id` if it exists.")]
+    force: bool,
+    #[clap(short, long, arg_enum, help = "Specify the mode.")]
+    mode: Option<ModeArg>,
+    #[clap(
+        short('w'),
+        long,
+        alias = "wait-seconds",
+        help = "Specify the duration to wait after the page is loaded, e.g. 5s.\nAn integer is in seconds.",
+        parse(try_from_str = parse_seconds),
+        value_name = "DURATION"
+    )]
+    delay: Option<Duration>,
+}
+
+#[derive(clap::Args)]
+struct DiscoverArgs {
+    #[clap(help = "The URL of the seed page.")]
+    url: String,
+    #[clap(
+        short,
+        long,
+        help = "Select the links to the pages, or the elements containing them, e.g. `.product a`.",
+        value_name = "SELECTOR"
+    )]
+    links: String,
+    #[clap(
+        long,
+        help = "Add the new pages as copies of the target but for the URLs and `--selector`, which are resumed if it is paused.",
+        value_name = "ID"
+    )]
+    template: Option<String>,
+    #[clap(
+        short,
+        long,
+        required_unless_present = "template",
+        help = "Specify the selector of the new pages, instead of the one of `--template`.",
+        value_name = "SELECTOR"
+    )]
+    selector: Option<String>,
+    #[clap(
+        long,
+        help = "Add the new pages as targets, instead of only listing them."
+    )]
+    add: bool,
+    #[clap(
+        long = "tag",
+        help = "Tag the added targets, in addition to the tags of `--template`.",
+        value_name = "TAG",
+        multiple_occurrences = true
+    )]
+    tags: Vec<String>,
+    #[clap(
+        short,
+        long,
+        arg_enum,
+        help = "Specify the mode of fetching the seed page and of the added targets.\nThe one of `--template` is used if omitted."
+    )]
+    mode: Option<ModeArg>,
+    #[clap(
+        short('w'),
+        long,
+        help = "Specify the duration to wait after the seed page is loaded, e.g. 5s, which is also the one of the added targets without `--template`.\nAn integer is in seconds.",
+        parse(try_from_str = parse_seconds),
+        value_name = "DURATION"
+    )]
+    delay: Option<Duration>,
+}
 
-    if let Err(why) = app.run().await {
-        error!("{why}")
+#[derive(clap::Args)]
+struct StatusArgs {
+    #[clap(
+        long,
+        help = "Print only the targets stored with any of the statuses.",
+        value_name = "STATUS",
+        multiple_values = true,
+        use_value_delimiter = true
+    )]
+    status: Vec<Status>,
+    #[clap(
+        long,
+        help = "Print only the targets updated within the duration, e.g. 1d.",
+        value_name = "DURATION"
+    )]
+    updated_within: Option<Duration>,
+    #[clap(
+        long,
+        arg_enum,
+        help = "Specify the order.\nThe times are the latest first."
+    )]
+    sort: Option<SortArg>,
+    #[clap(long, help = "Print at most the number of the targets.")]
+    limit: Option<usize>,
+    #[clap(long, help = "Skip the number of the targets from the first.")]
+    offset: Option<usize>,
+}
+impl StatusArgs {
+    /// Returns the query of the stored data, if any of the options is given.
+    fn query(&self) -> Option<DataQuery> {
+        let given = 0 < self.status.len()
+            || self.updated_within.is_some()
+            || self.sort.is_some()
+            || self.limit.is_some()
+            || self.offset.is_some();
+        if !given {
+            return None;
+        }
+        Some(DataQuery {
+            ids: None,
+            statuses: self.status.clone(),
+            updated_since: self.updated_within.map(|x| Timestamp::now() - x),
+            order: self.sort.map(DataOrder::from).unwrap_or_default(),
+            offset: self.offset.unwrap_or_default(),
+            limit: self.limit,
+        })
     }
+}
 
-    Ok(())
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum SortArg {
+    Id,
+    LastUpdated,
+    LastChecked,
+}
+impl From<SortArg> for DataOrder {
+    fn from(s: SortArg) -> Self {
+        match s {
+            SortArg::Id => DataOrder::Id,
+            SortArg::LastUpdated => DataOrder::LastUpdated,
+            SortArg::LastChecked => DataOrder::LastChecked,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct TargetsArgs {
+    #[clap(
+        help = "The ids of the targets.",
+        required_unless_present = "all",
+        conflicts_with = "all"
+    )]
+    ids: Vec<String>,
+    #[clap(long, help = "Apply to every target.")]
+    all: bool,
+}
+
+impl TargetsArgs {
+    /// Returns the ids of the arguments, or all of the ids if `--all`.
+    fn ids<'a>(
+        &self,
+        all: impl Iterator<Item = &'a Id>,
+    ) -> Result<Vec<Id>, Box<dyn std::error::Error>> {
+        match self.all {
+            true => Ok(all.cloned().collect()),
+            false => Ok(self
+                .ids
+                .iter()
+                .map(|x| Id::try_from(x.clone()))
+                .collect::<Result<_, _>>()?),
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct SearchArgs {
+    #[clap(help = "The text to search for, case-insensitively.")]
+    text: String,
+}
+
+#[derive(clap::Args)]
+struct KeyringSetArgs {
+    #[clap(help = "The name of the secret.")]
+    name: String,
+}
+
+#[derive(clap::Args)]
+struct ServiceInstallArgs {
+    #[clap(
+        last = true,
+        parse(from_os_str),
+        help = "The arguments to run the service by, e.g. `-- -c C:\\patrol\\config.toml -d C:\\patrol\\data.toml --log-file C:\\patrol\\patrol.log`."
+    )]
+    arguments: Vec<std::ffi::OsString>,
+}
+
+#[derive(clap::Args)]
+struct NotifyTestArgs {
+    #[clap(
+        help = "The id of a configured target to report the change of.\nA target named `notify-test` is made up if not given."
+    )]
+    target: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct PinArgs {
+    #[clap(flatten)]
+    targets: TargetsArgs,
+    #[clap(
+        long,
+        parse(try_from_str = Hash::from_hash_str),
+        help = "Pin the version of the hash, e.g. of a past `checked` event, instead of the current one."
+    )]
+    hash: Option<Hash>,
+}
+
+#[derive(clap::Args)]
+struct ImportArgs {
+    #[clap(help = "The config file defining the targets to add or update.")]
+    path: String,
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ModeArg {
+    Simple,
+    Full,
+}
+impl From<ModeArg> for Mode {
+    fn from(m: ModeArg) -> Self {
+        match m {
+            ModeArg::Simple => Mode::Simple,
+            ModeArg::Full => Mode::Full,
+        }
+    }
+}
+
+/// Parses a duration like `15m`, or an integer in minutes.
+fn parse_minutes(text: &str) -> Result<Duration, DurationParseError> {
+    Duration::parse_in(text, Duration::from_mins(1))
+}
+
+/// Parses a duration like `90s`, or an integer in seconds.
+fn parse_seconds(text: &str) -> Result<Duration, DurationParseError> {
+    Duration::parse_in(text, Duration::from_secs(1))
+}
+
+impl Args {
+    fn run_policy(&self) -> RunPolicy {
+        if self.once {
+            RunPolicy::Cycles(1)
+        } else if let Some(n) = self.cycles {
+            RunPolicy::Cycles(n)
+        } else if let Some(duration) = self.run_for {
+            RunPolicy::Duration(duration.as_std())
+        } else {
+            RunPolicy::Unlimited
+        }
+    }
+
+    /// The managed file overlaying the config file if `--read-only-config`.
+    fn managed_config_path(&self) -> Option<PathBuf> {
+        if !self.read_only_config {
+            return None;
+        }
+        let dir = self.config_path.parent().unwrap_or_else(|| Path::new(""));
+        Some(dir.join(MANAGED_CONFIG_PATH))
+    }
+
+    /// The trash of the removed targets, `--trash` or `<CONFIG_PATH>.trash`.
+    fn trash_path(&self) -> PathBuf {
+        match self.trash.as_ref() {
+            Some(x) => x.clone(),
+            None => {
+                let mut path = self.config_path.clone().into_os_string();
+                path.push(".trash");
+                path.into()
+            }
+        }
+    }
+
+    /// Whether to color the log and the summary.
+    fn colored(&self) -> bool {
+        // the logs in the view of `tui` are styled by the view.
+        if matches!(self.command, Some(Command::Tui)) {
+            return false;
+        }
+        match self.color {
+            ColorArg::Always => true,
+            ColorArg::Never => false,
+            ColorArg::Auto => {
+                self.log_file.is_none()
+                    && std::env::var_os("NO_COLOR").is_none()
+                    && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    // this must be done before the runtime starts its threads.
+    if args.daemon && args.command.is_none() {
+        daemonize()?;
+    }
+
+    let level = if args.quiet { "error" } else { "warn" };
+    let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or(level));
+    logger.write_style(match args.colored() {
+        true => WriteStyle::Always,
+        false => WriteStyle::Never,
+    });
+    if let Some(path) = args.log_file.as_ref() {
+        let file = RotatingFile::open(path, args.log_max_mib * 1024 * 1024, LOG_FILES_TO_KEEP)?;
+        logger.target(env_logger::Target::Pipe(Box::new(file)));
+    } else if matches!(args.command, Some(Command::Tui)) {
+        // the logs would break the view.
+        #[cfg(feature = "tui")]
+        logger.target(env_logger::Target::Pipe(Box::new(tui::log_pane())));
+    }
+    logger.init();
+
+    if args.service && args.command.is_none() {
+        return run_service(args);
+    }
+
+    match serve(args)? {
+        EXIT_UNCHANGED => Ok(()),
+        code => std::process::exit(code),
+    }
+}
+
+/// Runs the command in a runtime, and returns the exit code.
+fn serve(args: Args) -> Result<i32, Box<dyn std::error::Error>> {
+    let pid_file = match (&args.command, &args.pid_file) {
+        (None, Some(path)) => {
+            std::fs::write(path, format!("{}\n", std::process::id()))?;
+            Some(path.clone())
+        }
+        _ => None,
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(run(args));
+
+    if let Some(path) = pid_file {
+        let _ = std::fs::remove_file(path);
+    }
+
+    result
+}
+
+#[cfg(windows)]
+fn run_service(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    windows::run(move || match serve(args) {
+        Ok(code) => code,
+        Err(why) => {
+            error!("{why}");
+            EXIT_ERROR
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn run_service(_: Args) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--service is not supported on this platform.".into())
+}
+
+#[cfg(unix)]
+fn daemonize() -> Result<(), Box<dyn std::error::Error>> {
+    let cwd = std::env::current_dir()?;
+    daemonize::Daemonize::new().working_directory(cwd).start()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn daemonize() -> Result<(), Box<dyn std::error::Error>> {
+    Err("--daemon is not supported on this platform.".into())
+}
+
+/// Resolves when the process is asked to stop.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => (),
+                _ = terminate.recv() => (),
+            }
+            return;
+        }
+    }
+
+    #[cfg(windows)]
+    let stopped = windows::stopped();
+    #[cfg(not(windows))]
+    let stopped = std::future::pending::<()>();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => (),
+        _ = stopped => (),
+    }
+}
+
+/// Runs the command, and returns the exit code.
+async fn run(args: Args) -> Result<i32, Box<dyn std::error::Error>> {
+    match &args.command {
+        Some(Command::Check(check_args)) => {
+            let code = match check(&args, check_args).await {
+                Ok(code) => code,
+                Err(why) => {
+                    error!("{why}");
+                    EXIT_ERROR
+                }
+            };
+            std::process::exit(code)
+        }
+        Some(Command::Pick(pick_args)) => pick(&args, pick_args).await,
+        Some(Command::Discover(discover_args)) => discover(&args, discover_args).await,
+        Some(Command::Status(status_args)) => status(&args, status_args).await,
+        Some(Command::Stats(targets_args)) => stats(&args, targets_args).await,
+        Some(Command::Pause(targets_args)) => {
+            modify(&args, targets_args, |x| Some(Config { paused: true, ..x })).await
+        }
+        Some(Command::Resume(targets_args)) => {
+            modify(&args, targets_args, |x| Some(Config { paused: false, ..x })).await
+        }
+        Some(Command::Pin(pin_args)) => pin(&args, pin_args).await,
+        Some(Command::Unpin(targets_args)) => {
+            let unpin = |x: Config| {
+                let integrity = x.integrity.map(|x| Integrity {
+                    certificate: None,
+                    ..x
+                });
+                Some(Config {
+                    pinned: None,
+                    integrity,
+                    ..x
+                })
+            };
+            modify(&args, targets_args, unpin).await
+        }
+        Some(Command::Remove(targets_args)) => remove(&args, targets_args).await,
+        Some(Command::Restore(targets_args)) => restore(&args, targets_args).await,
+        Some(Command::Trash) => trash(&args).await,
+        Some(Command::Import(import_args)) => import(&args, import_args).await,
+        Some(Command::Search(search_args)) => search(&args, search_args).await,
+        Some(Command::Encrypt) => encrypt(&args).await,
+        Some(Command::KeyringSet(keyring_set_args)) => keyring_set(keyring_set_args),
+        Some(Command::ServiceInstall(service_install_args)) => {
+            service_install(service_install_args)
+        }
+        Some(Command::ServiceUninstall) => service_uninstall(),
+        Some(Command::NotifyTest(notify_test_args)) => notify_test(&args, notify_test_args).await,
+        Some(Command::Doctor) => {
+            let code = match doctor(&args).await {
+                Ok(true) => EXIT_UNCHANGED,
+                Ok(false) => EXIT_ERROR,
+                Err(why) => {
+                    error!("{why}");
+                    EXIT_ERROR
+                }
+            };
+            std::process::exit(code)
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui) => return patrol(args).await,
+        #[cfg(not(feature = "tui"))]
+        Some(Command::Tui) => Err(not_built("tui").into()),
+        None => return patrol(args).await,
+    }?;
+    Ok(EXIT_UNCHANGED)
+}
+
+async fn patrol(args: Args) -> Result<i32, Box<dyn std::error::Error>> {
+    info!("config_path:      {}", args.config_path.display());
+    info!("data_path:        {}", args.data_path.display());
+    info!("interval: {}", args.interval);
+    info!("webdriver_ports:  {:?}", args.webdriver_ports);
+    info!("run_policy:       {:?}", args.run_policy());
+
+    let tunnels = start_tunnels(&args).await?;
+    let tunnel_ports = tunnels.as_ref().map(SshTunnels::ports).unwrap_or_default();
+
+    let recordings = match args.record.as_ref().or(args.replay.as_ref()) {
+        Some(path) => Some(Recordings::new(path).await?),
+        None => None,
+    };
+
+    let registry = PollerRegistry::new();
+    // the recorded pages are replayed without the browser.
+    if let (Some(_), Some(recordings)) = (args.replay.as_ref(), recordings.as_ref()) {
+        let replay_poller = ReplayPoller::new(recordings.clone());
+        let registry = registry.register(Mode::Full.to_string(), replay_poller);
+        return run_patrol(args, registry, tunnel_ports, recordings.clone().into()).await;
+    }
+    if args.record.is_some() && args.browser != BrowserArg::Webdriver {
+        warn!("the sources of the full mode are only recorded by WebDriver.");
+    }
+    if args.debug_dir.is_some() && args.browser != BrowserArg::Webdriver {
+        warn!("the debug bundles of the full mode are only captured by WebDriver.");
+    }
+
+    let registry = match args.browser {
+        #[cfg(feature = "webdriver")]
+        BrowserArg::Webdriver => {
+            let mut full_mode_poller = new_webdriver_poller(&args).await?;
+            if 0 < args.webdriver_health_check.as_nanos() {
+                let period = args.webdriver_health_check.as_std();
+                full_mode_poller = full_mode_poller.with_health_check(period);
+            }
+            if let Some(recordings) = recordings.clone() {
+                full_mode_poller = full_mode_poller.with_recordings(recordings);
+            }
+            let full_mode_poller = full_mode_poller.with_tunnels(tunnel_ports.clone());
+            registry.register(Mode::Full.to_string(), full_mode_poller)
+        }
+        #[cfg(not(feature = "webdriver"))]
+        BrowserArg::Webdriver => {
+            warn!(
+                "{} the targets in the full mode fail.",
+                not_built("webdriver")
+            );
+            registry
+        }
+        #[cfg(feature = "cdp")]
+        BrowserArg::Cdp => {
+            let full_mode_poller = match args.cdp_url.as_ref() {
+                Some(url) => CdpPoller::connect(url, args.cdp_max_tabs).await?,
+                None => CdpPoller::launch(args.cdp_max_tabs).await?,
+            };
+            registry.register(Mode::Full.to_string(), full_mode_poller)
+        }
+        #[cfg(feature = "playwright")]
+        BrowserArg::Playwright => {
+            let url = args.playwright_url.as_deref().unwrap_or_default();
+            let full_mode_poller =
+                PlaywrightPoller::connect(url, args.playwright_max_contexts).await?;
+            registry.register(Mode::Full.to_string(), full_mode_poller)
+        }
+        #[allow(unreachable_patterns)]
+        browser => {
+            let name = browser.to_possible_value().unwrap().get_name();
+            return Err(not_built(name).into());
+        }
+    };
+    run_patrol(args, registry, tunnel_ports, recordings).await
+}
+
+/// The message that a component is not built in, as its feature is disabled.
+fn not_built(feature: &str) -> String {
+    format!("patrol is built without the `{feature}` feature.")
+}
+
+/// Starts the SSH tunnels if specified.
+async fn start_tunnels(args: &Args) -> Result<Option<SshTunnels>, Box<dyn std::error::Error>> {
+    match args.ssh_tunnels.as_ref() {
+        Some(path) => {
+            let configs = SshTunnels::load(path).await?;
+            Ok(Some(SshTunnels::start(configs).await))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Runs the app with the poller for the full mode, and returns the exit code.
+///
+/// Connects to WebDriver, capturing the debug bundles of the failed polls into `--debug-dir` if given.
+#[cfg(feature = "webdriver")]
+async fn new_webdriver_poller(args: &Args) -> Result<WebDriverPoller, Box<dyn std::error::Error>> {
+    let ports = args.webdriver_ports.as_slice();
+    let poller = match args.debug_dir.as_ref() {
+        Some(path) => {
+            let debug_bundles = DebugBundles::new(path).await?;
+            WebDriverPoller::new_with_debug_bundles(ports, debug_bundles).await?
+        }
+        None => WebDriverPoller::new(ports).await?,
+    };
+    Ok(poller)
+}
+
+/// The recordings are of `--record` or `--replay`.
+async fn run_patrol(
+    args: Args,
+    registry: PollerRegistry,
+    tunnel_ports: HashMap<String, u16>,
+    recordings: Option<Recordings>,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    // shared with the reload of the sinks.
+    let args = std::sync::Arc::new(args);
+    let mut config_repo = open_config_repo(&args).await?;
+    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+
+    let mut http_poller = HttpPoller::new().with_tunnels(tunnel_ports.clone());
+    if let (Some(_), Some(recordings)) = (args.record.as_ref(), recordings.as_ref()) {
+        http_poller = http_poller.with_recordings(recordings.clone());
+    }
+    let ftp_poller = FtpPoller::new().with_tunnels(tunnel_ports.clone());
+    let imap_poller = ImapPoller::new().with_tunnels(tunnel_ports);
+
+    let registry = registry
+        .register(Mode::Simple.to_string(), http_poller.clone())
+        .register(Mode::Graphql.to_string(), http_poller)
+        .register("file", FilePoller::new())
+        .register("ftp", ftp_poller)
+        .register("imap", imap_poller);
+    #[cfg(feature = "websocket")]
+    let registry = registry.register(Mode::Websocket.to_string(), WebSocketPoller::new());
+    let registry = match (args.replay.as_ref(), recordings) {
+        (Some(_), Some(recordings)) => {
+            registry.register(Mode::Simple.to_string(), ReplayPoller::new(recordings))
+        }
+        _ => registry,
+    };
+    let poller = SelectivePoller::new(registry);
+
+    let interval_period_secs = args.interval.as_std().as_secs().max(1);
+    let run_policy = args.run_policy();
+
+    let is_tui = matches!(args.command, Some(Command::Tui));
+    if is_tui && args.output == OutputFormat::Jsonl {
+        return Err("`--output jsonl` cannot be used with `tui`.".into());
+    }
+    // the configurations and the statistics served until they are changed.
+    let (api_configs, stats) = match args.api {
+        Some(_) => {
+            let stats = data_repo.get_all().await?;
+            let stats = stats.into_iter().map(|(id, data)| (id, data.stats));
+            (config_repo.get_all().await?, stats.collect())
+        }
+        None => (HashMap::new(), HashMap::new()),
+    };
+    // the configurations of the tags of the targets to send the SMS of.
+    let sms_configs = match args.sinks.sms_tag {
+        Some(_) => config_repo.get_all().await?,
+        None => HashMap::new(),
+    };
+    // the rows of the view until the first cycle is completed.
+    let initial = match is_tui {
+        true => Some((config_repo.get_all().await?, data_repo.get_all().await?)),
+        false => None,
+    };
+
+    info!("start app.");
+    let mut app = App::new(
+        config_repo,
+        data_repo,
+        poller,
+        interval_period_secs,
+        run_policy,
+    )
+    .with_time_zone(args.timezone)
+    .with_color(args.colored())
+    .with_overrun_events(args.notify_overruns)
+    .with_checkpoints(args.checkpoint);
+    if let Some(ramp_up) = args.ramp_up {
+        app = app.with_ramp_up(ramp_up.as_std());
+    }
+
+    let secrets = load_secrets(&args).await?;
+    // the sinks are opened before the subscribers, not to start them with a wrong option.
+    let bus = EventBus::new();
+    let sinks = SinkSet::open(&args.sinks, &secrets, args.event_schema, Some(sms_configs)).await?;
+    let (sinks_tx, sinks_rx) = tokio::sync::mpsc::unbounded_channel();
+    let sinks = tokio::spawn(sinks::relay(bus.subscribe(), sinks, sinks_rx));
+    let api_token = match args.api_token.as_ref() {
+        Some(x) => Some(secrets.resolve(x)?),
+        None => None,
+    };
+    let api_viewer_token = match args.api_viewer_token.as_ref() {
+        Some(x) => Some(secrets.resolve(x)?),
+        None => None,
+    };
+    app = app.with_secrets(secrets.clone());
+    if let Some(retention) = args.retention {
+        app = app.with_retention(retention);
+    }
+    if let Some(path) = args.composites.as_ref() {
+        let composites = composites::load(path).await?;
+        app = app.with_composites(composites);
+    }
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let shutdown_tx = std::sync::Arc::new(shutdown_tx);
+    app = app.with_shutdown_signal(shutdown_rx);
+    {
+        let shutdown_tx = shutdown_tx.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            info!("received a signal to stop.");
+            let _ = shutdown_tx.send(true);
+        });
+    }
+
+    let (reload_tx, reload_rx) = tokio::sync::mpsc::unbounded_channel();
+    app = app.with_reload_signal(reload_rx);
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reload_tx = reload_tx.clone();
+        let args = args.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                info!("received SIGHUP. reloading the configurations.");
+                if reload_tx.send(()).is_err() {
+                    break;
+                }
+                match reopen_sinks(&args).await {
+                    Ok(sinks) => {
+                        if sinks_tx.send(sinks).is_err() {
+                            break;
+                        }
+                    }
+                    Err(why) => {
+                        warn!("failed to reload the sinks, keeping the previous ones: {why}")
+                    }
+                }
+            }
+        });
+    }
+    #[cfg(not(unix))]
+    let _ = sinks_tx;
+
+    // the subscribers finish when the app drops the bus at the end of the run.
+    let printer = if args.output == OutputFormat::Jsonl {
+        app = app.with_summary(false);
+        Some(tokio::spawn(print_jsonl(
+            bus.subscribe(),
+            args.event_schema,
+        )))
+    } else {
+        None
+    };
+
+    let contents = match args.contents_dir.as_ref() {
+        Some(path) => Some(ContentDirectory::new(path).await?),
+        None => None,
+    };
+    let keeper = contents
+        .clone()
+        .map(|x| tokio::spawn(keep_contents(x, bus.subscribe())));
+
+    let api_server = match args.api.as_ref() {
+        Some(addr) => {
+            let mut server = ApiServer::new(api_configs.clone(), stats).with_data(&args.data_path);
+            if let Some(path) = args.subscriptions.as_ref() {
+                let subscriptions =
+                    TomlSubscriptionRepository::new(path, load_cipher(&args).await?).await?;
+                let subscriptions = std::sync::Arc::new(tokio::sync::Mutex::new(subscriptions));
+                server = server.with_subscriptions(subscriptions.clone());
+                tokio::spawn(post_subscriptions(
+                    subscriptions,
+                    api_configs,
+                    bus.subscribe(),
+                    args.event_schema,
+                ));
+            }
+            if let Some(token) = api_token.as_ref() {
+                server = server.with_token(token);
+            }
+            if let Some(token) = api_viewer_token.as_ref() {
+                server = server.with_viewer_token(token);
+            }
+            if let Some(contents) = contents {
+                server = server.with_contents(contents);
+            }
+            if let Some(path) = args.debug_dir.as_ref() {
+                server = server.with_debug_bundles(DebugBundles::new(path).await?);
+            }
+            server = server.with_targets(TargetFiles {
+                config_path: args.config_path.clone(),
+                managed_path: args.managed_config_path(),
+                trash_path: args.trash_path(),
+                retention: args.retention,
+                reload: reload_tx.clone(),
+            });
+            server = server.with_notifier_test(sinks::Tester {
+                args: args.sinks.clone(),
+                secrets: secrets.clone(),
+                schema: args.event_schema,
+            });
+            let serving = server.clone().bind(addr)?;
+            info!("serving the API on {addr}.");
+            tokio::spawn(async move {
+                if let Err(why) = serving.await {
+                    warn!("{why}");
+                }
+            });
+            Some(tokio::spawn(update_api(server, bus.subscribe())))
+        }
+        None => None,
+    };
+
+    let sampler = match args.samples_dir.as_ref() {
+        Some(path) => {
+            let max_bytes = args.samples_max_kib * 1024;
+            let samples = ContentSamples::new(path, max_bytes, SAMPLE_FILES_TO_KEEP)?;
+            let only_changed = args.samples_on == SamplesArg::Change;
+            let rx = bus.subscribe();
+            Some(tokio::task::spawn_blocking(move || {
+                keep_samples(samples, only_changed, rx)
+            }))
+        }
+        None => None,
+    };
+
+    let events_writer = match args.events_file.as_ref() {
+        Some(path) => {
+            let file = open_events_file(path, &args)?;
+            let (schema, format) = (args.event_schema, args.events_file_format);
+            let rx = bus.subscribe();
+            Some(tokio::task::spawn_blocking(move || {
+                write_events(file, rx, schema, format)
+            }))
+        }
+        None => None,
+    };
+
+    #[cfg(unix)]
+    tokio::spawn(report_to_systemd(bus.subscribe()));
+
+    let summarizer = match args.once {
+        true => Some(tokio::spawn(summarize_once(bus.subscribe()))),
+        false => None,
+    };
+
+    #[cfg(feature = "tui")]
+    let view = match initial {
+        Some((configs, data)) => {
+            let (poll_tx, poll_rx) = tokio::sync::mpsc::unbounded_channel();
+            app = app.with_summary(false).with_poll_signal(poll_rx);
+            let controls = tui::Controls {
+                config_path: args.config_path.clone(),
+                managed_config_path: args.managed_config_path(),
+                poll: poll_tx,
+                reload: reload_tx,
+                shutdown: shutdown_tx,
+            };
+            Some(tui::run(
+                configs,
+                data,
+                args.timezone,
+                bus.subscribe(),
+                controls,
+            ))
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "tui"))]
+    let _ = (initial, reload_tx);
+
+    app = app.with_event_bus(bus);
+
+    #[cfg(unix)]
+    let watchdog = {
+        if let Err(why) = systemd::notify("READY=1") {
+            error!("failed to notify systemd: {why}");
+        }
+        systemd::spawn_watchdog()
+    };
+
+    #[cfg(feature = "tui")]
+    let result = match view {
+        // the view stops the app when it is closed, and is closed when the app stops.
+        Some(view) => {
+            let (result, view) = tokio::join!(app.run(), view);
+            view?;
+            result
+        }
+        None => app.run().await,
+    };
+    #[cfg(not(feature = "tui"))]
+    let result = app.run().await;
+    if let Err(why) = result {
+        error!("{}", Report(&why))
+    }
+
+    #[cfg(unix)]
+    {
+        let _ = systemd::notify("STOPPING=1");
+        if let Some(watchdog) = watchdog {
+            watchdog.abort();
+        }
+    }
+
+    if let Some(printer) = printer {
+        let _ = printer.await;
+    }
+    if let Some(keeper) = keeper {
+        let _ = keeper.await;
+    }
+    if let Some(sampler) = sampler {
+        let _ = sampler.await;
+    }
+    if let Some(events_writer) = events_writer {
+        let _ = events_writer.await;
+    }
+    if let Some(api_server) = api_server {
+        let _ = api_server.await;
+    }
+    let _ = sinks.await;
+
+    let summary = match summarizer {
+        Some(summarizer) => summarizer.await?,
+        None => return Ok(EXIT_UNCHANGED),
+    };
+    if !args.quiet {
+        summary.print();
+    }
+    if let Some(path) = args.report_file.as_ref() {
+        let json = serde_json::to_string_pretty(&summary)?;
+        tokio::fs::write(path, format!("{json}\n")).await?;
+    }
+    Ok(summary.exit_code())
+}
+
+async fn check(args: &Args, check_args: &CheckArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+
+    let selector = check_args
+        .selector
+        .as_ref()
+        .map(|s| Selector::new(s.clone()))
+        .transpose()?;
+
+    let mut configs = config_repo.get_all().await?;
+    let (id, mut config) = match Id::try_from(check_args.target.clone()) {
+        Ok(id) if configs.contains_key(&id) => {
+            let config = configs.remove(&id).unwrap();
+            (id, config)
+        }
+        _ => {
+            let url = Url::new(check_args.target.clone())?;
+            // the target watching the URL, whose data is compared with.
+            let mut existing: Vec<_> = configs.into_iter().filter(|(_, x)| x.url == url).collect();
+            existing.sort_by(|a, b| a.0.cmp(&b.0));
+            match existing.into_iter().next() {
+                Some(x) => x,
+                None => {
+                    let selector = selector
+                        .clone()
+                        .ok_or("--selector is required for a URL which no target watches.")?;
+                    let config = Config::builder(url, selector).build()?;
+                    (Id::new(), config)
+                }
+            }
+        }
+    };
+    if let Some(selector) = selector {
+        config.selector = selector;
+    }
+    if let Some(mode) = check_args.mode {
+        config.mode = mode.into();
+    }
+
+    let config = load_secrets(args).await?.resolve_config(config)?;
+    let pinned = config.pinned.clone();
+
+    let tunnels = start_tunnels(args).await?;
+    let tunnel_ports = tunnels.as_ref().map(SshTunnels::ports).unwrap_or_default();
+
+    let content = match config.mode {
+        _ if config.url.is_file() => FilePoller::new().poll(id.clone(), config).await?,
+        _ if config.url.is_ftp() => {
+            let mut poller = FtpPoller::new().with_tunnels(tunnel_ports);
+            poller.poll(id.clone(), config).await?
+        }
+        _ if config.url.is_imap() => {
+            let mut poller = ImapPoller::new().with_tunnels(tunnel_ports);
+            poller.poll(id.clone(), config).await?
+        }
+        Mode::Simple | Mode::Graphql => {
+            let mut poller = HttpPoller::new().with_tunnels(tunnel_ports);
+            poller.poll(id.clone(), config).await?
+        }
+        #[cfg(feature = "webdriver")]
+        Mode::Full => {
+            let poller = new_webdriver_poller(args).await?;
+            let mut poller = poller.with_tunnels(tunnel_ports);
+            poller.poll(id.clone(), config).await?
+        }
+        #[cfg(not(feature = "webdriver"))]
+        Mode::Full => return Err(not_built("webdriver").into()),
+        #[cfg(feature = "websocket")]
+        Mode::Websocket => WebSocketPoller::new().poll(id.clone(), config).await?,
+        #[cfg(not(feature = "websocket"))]
+        Mode::Websocket => return Err(not_built("websocket").into()),
+    };
+    let output = content.trimmed();
+    let content = output.text();
+    println!("{content}");
+
+    let hash = Hash::new(content.as_bytes());
+    let stored_hash = data_repo.get(id.clone()).await?.and_then(|x| x.hash);
+    let stored_content = match args.contents_dir.as_ref() {
+        Some(path) => ContentDirectory::new(path).await?.get(&id).await?,
+        None => None,
+    };
+
+    // the content kept in `--contents-dir` is of the last poll, not of the pinned one.
+    let excerpt = match (pinned.as_ref(), stored_content) {
+        (None, Some(x)) => diff_excerpt(&x, content),
+        _ => None,
+    };
+    let (code, message) = match (pinned, stored_hash) {
+        (Some(pinned), _) if pinned != hash => (
+            EXIT_CHANGED,
+            format!("changed from the pinned: {pinned} -> {hash}"),
+        ),
+        (Some(_), _) => (EXIT_UNCHANGED, format!("unchanged from the pinned: {hash}")),
+        (None, Some(stored_hash)) if stored_hash != hash => {
+            (EXIT_CHANGED, format!("changed: {stored_hash} -> {hash}"))
+        }
+        (None, Some(_)) => (EXIT_UNCHANGED, format!("unchanged: {hash}")),
+        (None, None) => (EXIT_UNCHANGED, format!("no stored data: {hash}")),
+    };
+    if !args.quiet {
+        eprintln!("{message}");
+        if let Some(excerpt) = excerpt {
+            eprintln!("{excerpt}");
+        }
+    }
+    Ok(code)
+}
+
+async fn pick(args: &Args, pick_args: &PickArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+
+    let url = Url::new(pick_args.url.clone())?;
+    let mode = pick_args.mode.map(Mode::from).unwrap_or_default();
+    let existing = config_repo.get_all().await?;
+    let id = match pick_args.id.as_ref() {
+        Some(id) => {
+            let id = Id::try_from(id.clone())?;
+            if existing.contains_key(&id) && !pick_args.force {
+                return Err(format!("{id} already exists, which is replaced by `--force`.").into());
+            }
+            id
+        }
+        None => Id::slug_from_url(&url).unique_among(|x| existing.contains_key(x)),
+    };
+
+    let html = fetch_source(args, &url, mode, pick_args.delay).await?;
+
+    let mut candidates = find_candidates(&html);
+    if let Some(query) = pick_args.query.as_ref() {
+        let query = query.to_lowercase();
+        candidates.retain(|x| x.text.to_lowercase().contains(&query));
+    }
+
+    let candidate = match (candidates.len(), pick_args.query.is_some()) {
+        (0, _) => return Err("no element found.".into()),
+        (_, true) => candidates.swap_remove(0),
+        (len, false) => {
+            for (i, x) in candidates.iter().enumerate() {
+                let text: String = x.text.chars().take(60).collect();
+                eprintln!("[{i}] {}\n      {text}", x.selector.as_str());
+            }
+            eprint!("choose an element (0-{}): ", len - 1);
+
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            let index: usize = line.trim().parse()?;
+            if len <= index {
+                return Err("out of range.".into());
+            }
+            candidates.swap_remove(index)
+        }
+    };
+
+    let mut builder = Config::builder(url, candidate.selector).mode(mode);
+    if let Some(delay) = pick_args.delay {
+        builder = builder.delay(delay);
+    }
+    let config = builder.build()?;
+
+    if !args.quiet {
+        eprintln!("add [{id}]: {}", config.selector.as_str());
+    }
+    config_repo.update(id, config).await?;
+
+    Ok(())
+}
+
+/// Fetches the HTML of the page, rendered by WebDriver in the full mode after the delay.
+#[cfg_attr(not(feature = "webdriver"), allow(unused_variables))]
+async fn fetch_source(
+    args: &Args,
+    url: &Url,
+    mode: Mode,
+    delay: Option<Duration>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let html = match mode {
+        Mode::Simple | Mode::Graphql | Mode::Websocket => {
+            HttpPoller::new().fetch_source(url).await?
+        }
+        #[cfg(feature = "webdriver")]
+        Mode::Full => {
+            let mut poller = WebDriverPoller::new(args.webdriver_ports.as_slice()).await?;
+            poller.fetch_source(url, delay).await?
+        }
+        #[cfg(not(feature = "webdriver"))]
+        Mode::Full => return Err(not_built("webdriver").into()),
+    };
+    Ok(html)
+}
+
+/// Lists the pages linked from the seed page which no target watches by the selector, and adds them as targets if `--add`.
+///
+/// The new pages are printed to stdout one per line.
+async fn discover(
+    args: &Args,
+    discover_args: &DiscoverArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let existing = config_repo.get_all().await?;
+
+    let template = match discover_args.template.as_ref() {
+        Some(id) => {
+            let id = Id::try_from(id.clone())?;
+            match existing.get(&id) {
+                Some(x) => Some(x.clone()),
+                None => return Err(format!("{id} is not found.").into()),
+            }
+        }
+        None => None,
+    };
+    let selector = match (discover_args.selector.as_ref(), template.as_ref()) {
+        (Some(x), _) => Selector::new(x.clone())?,
+        (None, Some(template)) => template.selector.clone(),
+        (None, None) => return Err("`--selector` or `--template` is required.".into()),
+    };
+    let mode = discover_args
+        .mode
+        .map(Mode::from)
+        .or(template.as_ref().map(|x| x.mode))
+        .unwrap_or_default();
+
+    let url = Url::new(discover_args.url.clone())?;
+    let html = fetch_source(args, &url, mode, discover_args.delay).await?;
+    let links = find_links(&html, &url, &Selector::new(discover_args.links.clone())?);
+    if links.is_empty() {
+        return Err("no link found.".into());
+    }
+
+    // a page is new if no target watches it by the same selector, compared by the normalized URLs as `import`.
+    let watched: std::collections::HashSet<_> =
+        existing.values().map(|x| (&x.url, &x.selector)).collect();
+    let found = links.len();
+    let new: Vec<_> = links
+        .into_iter()
+        .filter(|x| !watched.contains(&(x, &selector)))
+        .collect();
+    for link in new.iter() {
+        println!("{}", link.as_str());
+    }
+    if !args.quiet {
+        eprintln!("{} new pages of {found} links.", new.len());
+    }
+    if !discover_args.add || new.is_empty() {
+        return Ok(());
+    }
+
+    let mut changes = HashMap::new();
+    for link in new.into_iter() {
+        let id = Id::slug_from_url(&link)
+            .unique_among(|x| existing.contains_key(x) || changes.contains_key(x));
+        let config = match template.clone() {
+            Some(template) => {
+                let mut tags = template.tags.clone();
+                for tag in discover_args.tags.iter() {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
+                    }
+                }
+                Config {
+                    url: link,
+                    selector: selector.clone(),
+                    paused: false,
+                    tags,
+                    ..template
+                }
+            }
+            None => {
+                let mut builder = Config::builder(link, selector.clone())
+                    .mode(mode)
+                    .tags(discover_args.tags.clone());
+                if let Some(delay) = discover_args.delay {
+                    builder = builder.delay(delay);
+                }
+                builder.build()?
+            }
+        };
+        if !args.quiet {
+            eprintln!("add [{id}]: {}", config.url.as_str());
+        }
+        let _ = changes.insert(id, Some(config));
+    }
+    let added = changes.len();
+    config_repo.apply(changes).await?;
+    if !args.quiet {
+        eprintln!("{added} targets added.");
+    }
+
+    Ok(())
+}
+
+/// Applies the modification to each of the targets at once, or to none of them if any is not found.
+async fn modify(
+    args: &Args,
+    targets_args: &TargetsArgs,
+    modification: impl Fn(Config) -> Option<Config>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let mut configs = config_repo.get_all().await?;
+
+    let ids = targets_args.ids(configs.keys())?;
+    let mut changes = HashMap::new();
+    for id in ids.into_iter() {
+        let config = match configs.remove(&id) {
+            Some(x) => x,
+            None => return Err(format!("{id} is not found.").into()),
+        };
+        let _ = changes.insert(id, modification(config));
+    }
+
+    let count = changes.len();
+    config_repo.apply(changes).await?;
+    if !args.quiet {
+        eprintln!("{count} targets modified.");
+    }
+
+    Ok(())
+}
+
+/// Pins the versions of the contents of the targets, the stored ones or the one of `--hash`, or none of them if any is not found.
+async fn pin(args: &Args, pin_args: &PinArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+    let mut configs = config_repo.get_all().await?;
+    let data_map = data_repo.get_all().await?;
+
+    let ids = pin_args.targets.ids(configs.keys())?;
+    let mut changes = HashMap::new();
+    for id in ids.into_iter() {
+        let config = match configs.remove(&id) {
+            Some(x) => x,
+            None => return Err(format!("{id} is not found.").into()),
+        };
+        let stored = data_map.get(&id).and_then(|x| x.hash.clone());
+        let hash = match pin_args.hash.clone().or(stored) {
+            Some(x) => x,
+            None => return Err(format!("{id} has no content to pin yet.").into()),
+        };
+        let pinned = Config {
+            pinned: Some(hash),
+            ..config
+        };
+        let _ = changes.insert(id, Some(pinned));
+    }
+
+    let count = changes.len();
+    config_repo.apply(changes).await?;
+    if !args.quiet {
+        eprintln!("{count} targets pinned.");
+    }
+
+    Ok(())
+}
+
+/// Moves the targets from the config into the trash, or none of them if any is not found.
+async fn remove(args: &Args, targets_args: &TargetsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let mut trash = open_trash(args).await?;
+    let mut configs = config_repo.get_all().await?;
+
+    let mut removed = HashMap::new();
+    for id in targets_args.ids(configs.keys())?.into_iter() {
+        match configs.remove(&id) {
+            Some(config) => {
+                let _ = removed.insert(id, config);
+            }
+            None => return Err(format!("{id} is not found.").into()),
+        }
+    }
+
+    // kept in the trash first, not to lose them if the config fails to be saved.
+    let ids: Vec<_> = removed.keys().cloned().collect();
+    trash.put(removed, Timestamp::now()).await?;
+    let changes = ids.iter().map(|id| (id.clone(), None)).collect();
+    if let Err(why) = config_repo.apply(changes).await {
+        let _ = trash.take(&ids).await;
+        return Err(why.into());
+    }
+    if !args.quiet {
+        match args.retention {
+            Some(retention) => eprintln!(
+                "{} targets removed, which can be restored within {retention}.",
+                ids.len()
+            ),
+            None => eprintln!("{} targets removed, which can be restored.", ids.len()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the targets from the trash back into the config, or none of them if any is not found.
+async fn restore(
+    args: &Args,
+    targets_args: &TargetsArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let mut trash = open_trash(args).await?;
+    let removed: Vec<_> = trash.list().into_iter().map(|(id, _, _)| id).collect();
+
+    let ids = targets_args.ids(removed.iter())?;
+    if let Some(id) = ids.iter().find(|x| !removed.contains(x)) {
+        return Err(format!("{id} is not in the trash.").into());
+    }
+    let configs = trash.take(&ids).await?;
+    let count = configs.len();
+    let changes = configs
+        .clone()
+        .into_iter()
+        .map(|(id, x)| (id, Some(x)))
+        .collect();
+    if let Err(why) = config_repo.apply(changes).await {
+        let _ = trash.put(configs, Timestamp::now()).await;
+        return Err(why.into());
+    }
+    if !args.quiet {
+        eprintln!("{count} targets restored.");
+    }
+
+    Ok(())
+}
+
+async fn trash(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let trash = open_trash(args).await?;
+
+    println!("{:<32}  {:<29}  URL", "ID", "REMOVED AT");
+    for (id, removed_at, config) in trash.list().into_iter() {
+        println!(
+            "{:<32}  {:<29}  {}",
+            id.as_str(),
+            removed_at.display_in(args.timezone).to_string(),
+            config.url.as_str()
+        );
+    }
+
+    Ok(())
+}
+
+/// Opens the config file, overlaid by the managed file if `--read-only-config`.
+async fn open_config_repo(
+    args: &Args,
+) -> Result<TomlConfigRepository, toml_config_repository::Error> {
+    let managed_path = args.managed_config_path();
+    TomlConfigRepository::open(&args.config_path, managed_path.as_deref()).await
+}
+
+/// Opens the trash, purging the targets removed longer than `--retention` ago.
+async fn open_trash(args: &Args) -> Result<TomlConfigTrash, Box<dyn std::error::Error>> {
+    let mut trash = TomlConfigTrash::new(args.trash_path()).await?;
+    if let Some(retention) = args.retention {
+        // a retention longer than the range of the time purges none.
+        if let Some(before) = Timestamp::now().checked_sub(retention) {
+            for id in trash.purge(before).await? {
+                info!("[{id}]: purged from the trash.");
+            }
+        }
+    }
+    Ok(trash)
+}
+
+async fn import(args: &Args, import_args: &ImportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // not to create the file by opening it.
+    if !std::path::Path::new(&import_args.path).is_file() {
+        return Err(format!("{} is not found.", import_args.path).into());
+    }
+    let mut config_repo = open_config_repo(args).await?;
+    let mut imported = TomlConfigRepository::new(&import_args.path).await?;
+
+    let existing = config_repo.get_all().await?;
+    let mut configs = imported.get_all().await?;
+
+    // a new target watching the same page by the same selector as another is a duplicate, compared by the normalized URLs.
+    let watched: HashMap<_, _> = existing
+        .iter()
+        .map(|(id, x)| ((&x.url, &x.selector), id))
+        .collect();
+    let duplicates: Vec<_> = configs
+        .iter()
+        .filter(|(id, _)| !existing.contains_key(id))
+        .filter_map(|(id, x)| {
+            let other = watched.get(&(&x.url, &x.selector))?;
+            Some((id.clone(), (*other).clone()))
+        })
+        .collect();
+    for (id, other) in duplicates.iter() {
+        eprintln!("{id} is skipped, since {other} watches the same.");
+        let _ = configs.remove(id);
+    }
+    let added = configs.keys().filter(|x| !existing.contains_key(x)).count();
+    let updated = configs.len() - added;
+
+    let changes = configs.into_iter().map(|(id, x)| (id, Some(x))).collect();
+    config_repo.apply(changes).await?;
+    if !args.quiet {
+        eprintln!("{added} targets added, and {updated} updated.");
+    }
+
+    Ok(())
+}
+
+async fn notify_test(
+    args: &Args,
+    notify_test_args: &NotifyTestArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target = match notify_test_args.target.as_ref() {
+        Some(target) => {
+            let mut config_repo = open_config_repo(args).await?;
+            let id = Id::try_from(target.clone())?;
+            match config_repo.get_all().await?.remove(&id) {
+                Some(config) => Some((id, config.url)),
+                None => return Err(format!("{id} is not found.").into()),
+            }
+        }
+        None => None,
+    };
+
+    let bus = EventBus::new();
+    let rx = bus.subscribe();
+    let secrets = load_secrets(args).await?;
+    let sinks = sinks::spawn(&args.sinks, &secrets, &bus, args.event_schema, None, None).await?;
+    sinks::publish_test(&bus, target)?;
+    drop(bus);
+    print_jsonl(rx, args.event_schema).await;
+    for sink in sinks {
+        let _ = sink.await;
+    }
+
+    Ok(())
+}
+
+async fn search(args: &Args, search_args: &SearchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match args.contents_dir.as_ref() {
+        Some(x) => x,
+        None => return Err("--contents-dir is required to search the contents.".into()),
+    };
+    let directory = ContentDirectory::new(path).await?;
+
+    for (id, lines) in directory.search(&search_args.text).await? {
+        for line in lines.iter() {
+            let line: String = line.chars().take(200).collect();
+            println!("[{id}]: {line}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn status(args: &Args, status_args: &StatusArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = open_config_repo(args).await?;
+    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+
+    let configs = config_repo.get_all().await?;
+    let (ids, data_map) = match status_args.query() {
+        // only the stored targets are queried.
+        Some(query) => {
+            let page = data_repo.query(query).await?;
+            let ids = page.iter().map(|(id, _)| id.clone()).collect();
+            (ids, page.into_iter().collect())
+        }
+        None => {
+            let data_map = data_repo.get_all().await?;
+            let mut ids: Vec<_> = configs.keys().chain(data_map.keys()).cloned().collect();
+            ids.sort();
+            ids.dedup();
+            (ids, data_map)
+        }
+    };
+    let now = Timestamp::now();
+
+    println!(
+        "{:<32}  {:<13}  {:<29}  URL",
+        "ID", "STATUS", "LAST UPDATED"
+    );
+    for id in ids.into_iter() {
+        let config = configs.get(&id);
+        let data = data_map.get(&id);
+
+        let status = match (config, data) {
+            (None, _) => Status::Expired,
+            (Some(config), _) if config.paused => Status::Paused,
+            (Some(config), _) if config.maintenance.iter().any(|x| x.contains(now)) => {
+                Status::Maintenance
+            }
+            (Some(_), Some(data)) => data.status,
+            (Some(_), None) => Status::New,
+        };
+        let last_updated = data
+            .and_then(|x| x.last_updated)
+            .map(|x| x.display_in(args.timezone).to_string())
+            .unwrap_or_else(|| "-".to_owned());
+        let url = config.map(|x| x.url.as_str()).unwrap_or("-");
+        let title = match data.and_then(|x| x.title.as_ref()) {
+            Some(x) => format!(" ({x})"),
+            None => String::new(),
+        };
+
+        println!(
+            "{:<32}  {:<13}  {last_updated:<29}  {url}{title}",
+            id.as_str(),
+            status.as_str()
+        );
+    }
+
+    Ok(())
+}
+
+async fn stats(args: &Args, targets_args: &TargetsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let data_repo = TomlDataRepository::new(&args.data_path).await?;
+    let data_map = data_repo.get_all().await?;
+    let mut ids = targets_args.ids(data_map.keys())?;
+    ids.sort();
+    let now = Timestamp::now();
+
+    let millis = |x: Option<u64>| match x {
+        Some(x) => Duration::checked_from_millis(x).map_or("-".to_owned(), |x| x.to_string()),
+        None => "-".to_owned(),
+    };
+    println!(
+        "{:<32}  {:>7}  {:>8}  {:>9}  {:>9}  {:>9}  {:>12}",
+        "ID", "POLLS", "FAILURES", "MEAN", "P50", "P95", "CHANGES/WEEK"
+    );
+    for id in ids.into_iter() {
+        let summary = match data_map.get(&id) {
+            Some(data) if !data.stats.is_empty() => data.stats.summary(now),
+            _ => {
+                println!("{:<32}  {:>7}", id.as_str(), "-");
+                continue;
+            }
+        };
+        println!(
+            "{:<32}  {:>7}  {:>7.1}%  {:>9}  {:>9}  {:>9}  {:>12.1}",
+            id.as_str(),
+            summary.polls,
+            summary.failure_rate * 100.0,
+            millis(summary.mean_ms),
+            millis(summary.p50_ms),
+            millis(summary.p95_ms),
+            summary.changes_per_week
+        );
+        for step in summary.steps.iter() {
+            println!(
+                "  {:<49}  {:>9}  {:>9}  {:>9}",
+                step.name,
+                millis(step.mean_ms),
+                millis(step.p50_ms),
+                millis(step.p95_ms)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs every check of `doctor` and returns whether all of them passed.
+async fn doctor(args: &Args) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut passed = true;
+    let mut report = |name: String, result: Result<String, String>| match result {
+        Ok(detail) => println!("[ OK ] {name}: {detail}"),
+        Err(why) => {
+            println!("[FAIL] {name}: {why}");
+            passed = false;
+        }
+    };
+
+    // only read, not to create the files missing.
+    let managed_path = args.managed_config_path();
+    match managed_path.as_ref() {
+        Some(path) => report(
+            format!("managed config file {}", path.display()),
+            check_writable(path),
+        ),
+        None => report(
+            format!("config file {}", args.config_path.display()),
+            check_writable(&args.config_path),
+        ),
+    }
+    report(
+        format!("data file {}", args.data_path.display()),
+        check_writable(&args.data_path),
+    );
+
+    let config_repo =
+        TomlConfigRepository::open_read_only(&args.config_path, managed_path.as_deref()).await;
+    let configs = match config_repo {
+        Ok(mut config_repo) => config_repo.get_all().await?,
+        Err(why) => {
+            report("configurations".to_owned(), Err(why.to_string()));
+            Default::default()
+        }
+    };
+    if 0 < configs.len() {
+        report(
+            "configurations".to_owned(),
+            Ok(format!("{} targets", configs.len())),
+        );
+    }
+    if args.data_path.exists() {
+        if let Err(why) = TomlDataRepository::new_read_only(&args.data_path).await {
+            report("data".to_owned(), Err(why.to_string()));
+        }
+    }
+
+    // each notifier sends a test notification, to check its credentials.
+    match load_secrets(args).await {
+        Ok(secrets) => {
+            let tester = sinks::Tester {
+                args: args.sinks.clone(),
+                secrets,
+                schema: args.event_schema,
+            };
+            for name in sinks::NOTIFIERS.iter() {
+                let result = match tester.send(name, None).await {
+                    Some(Ok(())) => Ok("test notification sent".to_owned()),
+                    Some(Err(why)) => Err(why),
+                    None => continue,
+                };
+                report(format!("notifier {name}"), result);
+            }
+        }
+        Err(why) => report("secrets".to_owned(), Err(why.to_string())),
+    }
+
+    let needs_webdriver = configs.values().any(|x| x.mode == Mode::Full);
+    #[cfg(feature = "webdriver")]
+    for port in args.webdriver_ports.iter() {
+        let result = match WebDriverPoller::probe(*port).await {
+            Ok(()) => Ok("session established".to_owned()),
+            Err(why) if !needs_webdriver => Ok(format!("not used by any target ({why})")),
+            Err(why) => Err(why.to_string()),
+        };
+        report(format!("webdriver port {port}"), result);
+    }
+    #[cfg(not(feature = "webdriver"))]
+    if needs_webdriver && args.browser == BrowserArg::Webdriver {
+        report("webdriver".to_owned(), Err(not_built("webdriver")));
+    }
+
+    let mut hosts: Vec<_> = configs
+        .values()
+        .filter_map(|x| Some((x.url.host()?.to_owned(), x.url.port()?)))
+        .collect();
+    hosts.sort();
+    hosts.dedup_by(|a, b| a.0 == b.0);
+    for (host, port) in hosts.into_iter() {
+        let result = match tokio::net::lookup_host((host.as_str(), port)).await {
+            Ok(mut addrs) => match addrs.next() {
+                Some(addr) => Ok(addr.ip().to_string()),
+                None => Err("no address found".to_owned()),
+            },
+            Err(why) => Err(why.to_string()),
+        };
+        report(format!("dns {host}"), result);
+    }
+
+    Ok(passed)
+}
+
+/// Checks that the file exists and can be written, without creating it.
+fn check_writable(path: &Path) -> Result<String, String> {
+    match std::fs::OpenOptions::new().append(true).open(path) {
+        Ok(_) => Ok("writable".to_owned()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err("not found".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Reads the secrets of `--secrets`, or none if not given.
+async fn load_secrets(args: &Args) -> Result<Secrets, Box<dyn std::error::Error>> {
+    let secrets = match args.secrets.as_ref() {
+        Some(path) => {
+            let cipher = load_cipher(args).await?;
+            secrets::load(path, cipher.as_ref()).await?
+        }
+        None => match load_cipher(args).await? {
+            Some(x) => Secrets::default().with_decrypter(std::sync::Arc::new(x)),
+            None => Secrets::default(),
+        },
+    };
+    #[cfg(feature = "keyring")]
+    let secrets = secrets.with_store(std::sync::Arc::new(KeyringStore::new()));
+    Ok(secrets)
+}
+
+/// Opens the sinks again by the secrets and the configurations in their files, on a reload.
+#[cfg(unix)]
+async fn reopen_sinks(args: &Args) -> Result<SinkSet, Box<dyn std::error::Error>> {
+    let secrets = load_secrets(args).await?;
+    let configs = match args.sinks.sms_tag {
+        Some(_) => {
+            let managed_path = args.managed_config_path();
+            TomlConfigRepository::open_read_only(&args.config_path, managed_path.as_deref())
+                .await?
+                .get_all()
+                .await?
+        }
+        None => HashMap::new(),
+    };
+    SinkSet::open(&args.sinks, &secrets, args.event_schema, Some(configs)).await
+}
+
+/// Makes the cipher of the passphrase in `--key-file` or `PATROL_PASSPHRASE`, if any.
+async fn load_cipher(args: &Args) -> Result<Option<Cipher>, Box<dyn std::error::Error>> {
+    let passphrase = match args.key_file.as_ref() {
+        Some(path) => tokio::fs::read_to_string(path).await?,
+        None => match std::env::var("PATROL_PASSPHRASE") {
+            Ok(x) => x,
+            Err(_) => return Ok(None),
+        },
+    };
+    let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+    if passphrase.len() == 0 {
+        return Err("the passphrase is empty.".into());
+    }
+    Ok(Some(Cipher::new(passphrase)))
+}
+
+async fn encrypt(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let cipher = match load_cipher(args).await? {
+        Some(x) => x,
+        None => return Err("--key-file or PATROL_PASSPHRASE is required to encrypt.".into()),
+    };
+
+    let mut secret = String::new();
+    std::io::stdin().read_line(&mut secret)?;
+    let secret = secret.trim_end_matches(['\r', '\n']);
+    println!("{}", cipher.encrypt(secret)?);
+
+    Ok(())
+}
+
+fn keyring_set(args: &KeyringSetArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut secret = String::new();
+    std::io::stdin().read_line(&mut secret)?;
+    let secret = secret.trim_end_matches(['\r', '\n']);
+    #[cfg(feature = "keyring")]
+    {
+        KeyringStore::new()
+            .set(&args.name, secret)
+            .map_err(|e| e.to_string())?;
+        info!("kept {} in the keyring.", args.name);
+        Ok(())
+    }
+    #[cfg(not(feature = "keyring"))]
+    {
+        let _ = (args, secret);
+        Err(not_built("keyring").into())
+    }
+}
+
+#[cfg(windows)]
+fn service_install(args: &ServiceInstallArgs) -> Result<(), Box<dyn std::error::Error>> {
+    windows::install(args.arguments.clone())?;
+    info!("registered the service {}.", windows::SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn service_install(_: &ServiceInstallArgs) -> Result<(), Box<dyn std::error::Error>> {
+    Err("the Windows service is not supported on this platform.".into())
+}
+
+#[cfg(windows)]
+fn service_uninstall() -> Result<(), Box<dyn std::error::Error>> {
+    windows::uninstall()?;
+    info!("removed the service {}.", windows::SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn service_uninstall() -> Result<(), Box<dyn std::error::Error>> {
+    Err("the Windows service is not supported on this platform.".into())
+}
+
+async fn keep_contents(
+    directory: ContentDirectory,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>,
+) {
+    while let Some(event) = rx.recv().await {
+        if let PatrolEvent::ContentPolled {
+            id,
+            content,
+            changed: true,
+        } = event
+        {
+            if let Err(why) = directory.save(&id, content.text()).await {
+                warn!("[{id}]: failed to keep the content: {why}");
+            }
+        }
+    }
+}
+
+/// Updates the configurations, the statistics and the events served by the API as they change, until the bus is dropped.
+async fn update_api(server: ApiServer, mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>) {
+    while let Some(event) = rx.recv().await {
+        match event {
+            PatrolEvent::Recorded(event) if is_notifiable(&event) => {
+                let now = Timestamp::now();
+                let at = event.occurred_at().unwrap_or(now);
+                server.push_update(event.id().clone(), at, event.to_flat_json(now));
+            }
+            PatrolEvent::StatsRecorded { stats } => server.update_stats(stats),
+            PatrolEvent::ConfigChanged(event) => server.update_config(event),
+            PatrolEvent::ContentPolled { id, content, .. } => server.update_content(id, content),
+            _ => (),
+        }
+    }
+}
+
+/// Appends the polled contents to the samples, only the changed ones if specified, until the bus is dropped.
+///
+/// This blocks the thread while writing the files.
+fn keep_samples(
+    samples: ContentSamples,
+    only_changed: bool,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>,
+) {
+    while let Some(event) = rx.blocking_recv() {
+        if let PatrolEvent::ContentPolled {
+            id,
+            content,
+            changed,
+        } = event
+        {
+            if only_changed && !changed {
+                continue;
+            }
+            if let Err(why) = samples.append(&id, content.text(), changed) {
+                warn!("[{id}]: failed to keep the sample of the content: {why}");
+            }
+        }
+    }
+}
+
+/// Opens `--events-file`, which is headed in each file if it is a CSV.
+fn open_events_file(path: &str, args: &Args) -> std::io::Result<RotatingFile> {
+    let max_bytes = args.events_file_max_mib * 1024 * 1024;
+    let file = RotatingFile::open(path, max_bytes, EVENT_FILES_TO_KEEP)?;
+    Ok(match args.events_file_format {
+        EventsFileFormat::Jsonl => file,
+        EventsFileFormat::Csv => file.with_header(format!("{CSV_HEADER}\n")),
+    })
+}
+
+/// Appends the changes, the failures, the flappings, the triggers and the tamperings to the file, until the bus is dropped.
+///
+/// This blocks the thread while writing the file.
+fn write_events(
+    mut file: RotatingFile,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>,
+    schema: u32,
+    format: EventsFileFormat,
+) {
+    use std::io::Write;
+
+    while let Some(event) = rx.blocking_recv() {
+        let event = match event {
+            PatrolEvent::Recorded(x) if is_notifiable(&x) => x,
+            _ => continue,
+        };
+        let line = match format {
+            EventsFileFormat::Jsonl => match serde_json::to_string(&event.to_json(schema)) {
+                Ok(json) => format!("{json}\n"),
+                Err(why) => {
+                    error!("{why}");
+                    continue;
+                }
+            },
+            EventsFileFormat::Csv => event.to_csv_record(Timestamp::now()),
+        };
+        // written at once, not to be split by the rotation.
+        if let Err(why) = file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+            warn!(
+                "[{}]: failed to write the event to the file: {why}",
+                event.id()
+            );
+        }
+    }
+}
+
+/// The results of the targets in a `--once` run.
+#[derive(Serialize, Default)]
+struct OnceSummary {
+    changed: Vec<Id>,
+    unchanged: Vec<Id>,
+    failed: Vec<FailedTarget>,
+    /// The targets which were not polled, e.g. paused, skipped or removed.
+    not_checked: Vec<Id>,
+}
+
+#[derive(Serialize)]
+struct FailedTarget {
+    id: Id,
+    status: Status,
+    reason: Option<String>,
+}
+
+impl OnceSummary {
+    fn exit_code(&self) -> i32 {
+        if 0 < self.changed.len() {
+            EXIT_CHANGED
+        } else if 0 < self.failed.len() {
+            EXIT_ERROR
+        } else {
+            EXIT_UNCHANGED
+        }
+    }
+
+    fn print(&self) {
+        eprintln!(
+            "{} changed, {} unchanged, {} failed, {} not checked.",
+            self.changed.len(),
+            self.unchanged.len(),
+            self.failed.len(),
+            self.not_checked.len()
+        );
+        for id in self.changed.iter() {
+            eprintln!("changed: [{id}]");
+        }
+        for x in self.failed.iter() {
+            let reason = x.reason.as_deref().unwrap_or("-");
+            eprintln!("{}: [{}] {reason}", x.status, x.id);
+        }
+    }
+}
+
+/// Summarizes the statuses reported at the end of the cycle.
+async fn summarize_once(mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>) -> OnceSummary {
+    let mut summary = OnceSummary::default();
+    let mut reasons = HashMap::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            PatrolEvent::PollFailed { id, reason, .. } => {
+                let _ = reasons.insert(id, reason);
+            }
+            PatrolEvent::Recorded(Event::Status { id, status, .. }) => match status {
+                Status::Changed => summary.changed.push(id),
+                // the changes of a flapping target are muted.
+                Status::Ok | Status::Flapping => summary.unchanged.push(id),
+                Status::Failing | Status::SelectorMiss | Status::Blocked => {
+                    let reason = reasons.remove(&id);
+                    summary.failed.push(FailedTarget { id, status, reason });
+                }
+                _ => summary.not_checked.push(id),
+            },
+            _ => (),
+        }
+    }
+    for ids in [
+        &mut summary.changed,
+        &mut summary.unchanged,
+        &mut summary.not_checked,
+    ] {
+        ids.sort();
+    }
+    summary.failed.sort_by(|a, b| a.id.cmp(&b.id));
+    summary
+}
+
+/// Posts the events of `post_webhook` to the subscriptions whose filters they pass, in the JSON of `--event-schema`.
+///
+/// The configurations are kept up to date for the tags of the targets.
+async fn post_subscriptions(
+    subscriptions: std::sync::Arc<tokio::sync::Mutex<TomlSubscriptionRepository>>,
+    mut configs: HashMap<Id, Config>,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>,
+    schema: u32,
+) {
+    let mut notifiers: HashMap<Id, WebhookNotifier> = HashMap::new();
+    while let Some(event) = rx.recv().await {
+        let event = match event {
+            PatrolEvent::Recorded(x) if is_notifiable(&x) => x,
+            PatrolEvent::ConfigChanged(ConfigEvent::Added { id, config })
+            | PatrolEvent::ConfigChanged(ConfigEvent::Updated { id, config }) => {
+                let _ = configs.insert(id, config);
+                continue;
+            }
+            PatrolEvent::ConfigChanged(ConfigEvent::Removed { id, .. }) => {
+                let _ = configs.remove(&id);
+                continue;
+            }
+            _ => continue,
+        };
+        let tags = configs
+            .get(event.id())
+            .map(|x| x.tags.as_slice())
+            .unwrap_or_default();
+
+        let list = subscriptions.lock().await.list();
+        notifiers.retain(|id, _| list.iter().any(|(x, _)| x == id));
+        let json = match serde_json::to_string(&event.to_json(schema)) {
+            Ok(x) => x,
+            Err(why) => {
+                error!("{why}");
+                continue;
+            }
+        };
+        for (id, subscription) in list.into_iter() {
+            if !subscription.matches(event.name(), event.id(), tags) {
+                continue;
+            }
+            let notifier = notifiers.entry(id.clone()).or_insert_with(|| {
+                let notifier = WebhookNotifier::new(subscription.url.clone());
+                match subscription.secret.as_ref() {
+                    Some(secret) => notifier.with_secret(secret),
+                    None => notifier,
+                }
+            });
+            // a slow subscriber does not hold up the others.
+            let notifier = notifier.clone();
+            let json = json.clone();
+            tokio::spawn(async move {
+                if let Err(why) = notifier.send(json).await {
+                    warn!("[{id}]: {why}");
+                }
+            });
+        }
+    }
+}
+
+/// Shows the result of the last cycle as the status of the service, e.g. in `systemctl status`.
+#[cfg(unix)]
+async fn report_to_systemd(mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>) {
+    while let Some(event) = rx.recv().await {
+        if let PatrolEvent::CycleCompleted {
+            cycle,
+            succeeded,
+            changed,
+            failed,
+            overrun,
+            elapsed,
+        } = event
+        {
+            let status = format!(
+                "STATUS=cycle {cycle}: {succeeded} succeeded, {changed} changed, {failed} failed, {overrun} overrun in {:.1}s.",
+                elapsed.as_secs_f64()
+            );
+            if let Err(why) = systemd::notify(&status) {
+                debug!("failed to notify systemd: {why}");
+            }
+        }
+    }
+}
+
+async fn print_jsonl(mut rx: tokio::sync::mpsc::UnboundedReceiver<PatrolEvent>, schema: u32) {
+    use std::io::Write;
+
+    while let Some(event) = rx.recv().await {
+        let event = match event {
+            PatrolEvent::Recorded(x) => x,
+            _ => continue,
+        };
+        let mut stdout = std::io::stdout().lock();
+        match serde_json::to_string(&event.to_json(schema)) {
+            Ok(json) => {
+                let _ = writeln!(stdout, "{json}");
+                let _ = stdout.flush();
+            }
+            Err(why) => error!("{why}"),
+        }
+    }
 }
@@ -0,0 +1,486 @@
+//! The live view of `patrol tui`, with the table of the targets and the recent events.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use ratatui::{
+    crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Clear, List, ListItem, Paragraph, Row, Table, TableState, Wrap},
+    DefaultTerminal, Frame,
+};
+use tokio::sync::{mpsc, watch};
+
+use patrol::application::{Event, PatrolEvent};
+use patrol::domain::{
+    Config, ConfigEvent, ConfigRepository, Data, Id, Status, TimeZone, Timestamp, Url,
+};
+use patrol::infrastructure::TomlConfigRepository;
+
+/// The number of the recent events kept to be shown.
+const MAX_RECENT_EVENTS: usize = 200;
+
+static LOG_LINES: OnceLock<Mutex<Option<mpsc::UnboundedReceiver<String>>>> = OnceLock::new();
+
+/// Returns the target of the logger, which shows the logs in the recent events instead of breaking the view.
+pub fn log_pane() -> LogPane {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let _ = LOG_LINES.set(Mutex::new(Some(rx)));
+    LogPane { tx }
+}
+
+/// The lines logged to `log_pane`, which can be taken only once.
+fn take_log_lines() -> Option<mpsc::UnboundedReceiver<String>> {
+    LOG_LINES.get()?.lock().unwrap().take()
+}
+
+pub struct LogPane {
+    tx: mpsc::UnboundedSender<String>,
+}
+impl Write for LogPane {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        for line in text.lines().filter(|x| !x.is_empty()) {
+            let _ = self.tx.send(line.to_owned());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The ways of the view to control the app.
+pub struct Controls {
+    pub config_path: PathBuf,
+    /// The managed file overlaying the read-only config file, if any.
+    pub managed_config_path: Option<PathBuf>,
+    pub poll: mpsc::UnboundedSender<()>,
+    pub reload: mpsc::UnboundedSender<()>,
+    pub shutdown: Arc<watch::Sender<bool>>,
+}
+
+struct Target {
+    id: Id,
+    url: Option<Url>,
+    status: Status,
+    paused: bool,
+    last_checked: Option<Timestamp>,
+    last_updated: Option<Timestamp>,
+    /// The lines removed and added by the last change.
+    excerpt: Option<String>,
+}
+
+struct View {
+    targets: Vec<Target>,
+    table: TableState,
+    recent: VecDeque<Line<'static>>,
+    /// The result of the last cycle, or what is going on.
+    header: String,
+    /// Whether the diff of the selected target is shown.
+    diff: bool,
+    time_zone: TimeZone,
+}
+
+/// Shows the view until `q` is pressed or the app stops, and asks the app to stop then.
+///
+/// The keys are `↑`/`↓` to select a target, `p` to poll now, `space` to pause or resume the target,
+/// `d` to open its last diff, `r` to reload the configurations, and `q` to quit.
+pub async fn run(
+    configs: HashMap<Id, Config>,
+    data: HashMap<Id, Data>,
+    time_zone: TimeZone,
+    mut events: mpsc::UnboundedReceiver<PatrolEvent>,
+    controls: Controls,
+) -> std::io::Result<()> {
+    let mut targets: Vec<_> = configs
+        .into_iter()
+        .map(|(id, config)| {
+            let data = data.get(&id);
+            Target {
+                url: Some(config.url),
+                status: match config.paused {
+                    true => Status::Paused,
+                    false => data.map(|x| x.status).unwrap_or_default(),
+                },
+                paused: config.paused,
+                last_checked: data.map(|x| x.last_checked),
+                last_updated: data.and_then(|x| x.last_updated),
+                excerpt: None,
+                id,
+            }
+        })
+        .collect();
+    targets.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut view = View {
+        table: TableState::default().with_selected((0 < targets.len()).then_some(0)),
+        targets,
+        recent: VecDeque::new(),
+        header: "waiting for the first cycle...".to_owned(),
+        diff: false,
+        time_zone,
+    };
+
+    // the keys are read on a thread, as reading them blocks.
+    let (key_tx, mut keys) = mpsc::unbounded_channel();
+    let _ = std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(event::Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if key_tx.send(key).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => (),
+            Err(_) => break,
+        }
+    });
+    let mut logs = take_log_lines();
+
+    let mut terminal = ratatui::init();
+    let result = async {
+        loop {
+            draw(&mut terminal, &mut view)?;
+            tokio::select! {
+                Some(key) = keys.recv() => {
+                    if !handle_key(&mut view, key, &controls).await {
+                        break;
+                    }
+                }
+                event = events.recv() => match event {
+                    Some(event) => handle_event(&mut view, event),
+                    None => break,
+                },
+                Some(line) = recv_log(&mut logs) => push_recent(&mut view, Line::from(line).dark_gray()),
+            }
+        }
+        Ok(())
+    }
+    .await;
+    ratatui::restore();
+
+    let _ = controls.shutdown.send(true);
+    result
+}
+
+async fn recv_log(logs: &mut Option<mpsc::UnboundedReceiver<String>>) -> Option<String> {
+    match logs.as_mut() {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn draw(terminal: &mut DefaultTerminal, view: &mut View) -> std::io::Result<()> {
+    let _ = terminal.draw(|frame| {
+        let [header, table, recent, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Paragraph::new(view.header.as_str()).bold(), header);
+        draw_table(frame, view, table);
+
+        let items: Vec<_> = view
+            .recent
+            .iter()
+            .rev()
+            .cloned()
+            .map(ListItem::new)
+            .collect();
+        let list = List::new(items).block(Block::bordered().title("recent events"));
+        frame.render_widget(list, recent);
+
+        let keys = "↑↓ select  p poll now  space pause/resume  d diff  r reload  q quit";
+        frame.render_widget(Paragraph::new(keys).dark_gray(), help);
+
+        if view.diff {
+            draw_diff(frame, view);
+        }
+    })?;
+    Ok(())
+}
+
+fn draw_table(frame: &mut Frame, view: &mut View, area: Rect) {
+    let zone = view.time_zone;
+    let time = |x: Option<Timestamp>| {
+        x.map(|x| x.display_in(zone).to_string())
+            .unwrap_or_else(|| "-".to_owned())
+    };
+    let rows: Vec<_> = view
+        .targets
+        .iter()
+        .map(|x| {
+            Row::new(vec![
+                Cell::from(x.id.to_string()),
+                Cell::from(x.status.as_str()).style(style_of(x.status)),
+                Cell::from(time(x.last_checked)),
+                Cell::from(time(x.last_updated)),
+                Cell::from(
+                    x.url
+                        .as_ref()
+                        .map(Url::as_str)
+                        .unwrap_or_default()
+                        .to_owned(),
+                ),
+            ])
+        })
+        .collect();
+    let header = Row::new(vec!["id", "status", "last checked", "last updated", "url"])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Max(24),
+            Constraint::Length(13),
+            Constraint::Length(26),
+            Constraint::Length(26),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .block(Block::bordered().title(format!("{} targets", view.targets.len())))
+    .row_highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(table, area, &mut view.table);
+}
+
+fn draw_diff(frame: &mut Frame, view: &View) {
+    let target = match view.table.selected().and_then(|i| view.targets.get(i)) {
+        Some(x) => x,
+        None => return,
+    };
+    let lines: Vec<_> = match target.excerpt.as_ref() {
+        Some(excerpt) => excerpt
+            .lines()
+            .map(|x| match x.chars().next() {
+                Some('+') => Line::from(x.to_owned()).green(),
+                Some('-') => Line::from(x.to_owned()).red(),
+                _ => Line::from(x.to_owned()),
+            })
+            .collect(),
+        None => vec![Line::from("no change has been seen since patrol started.").dark_gray()],
+    };
+
+    let area = frame.area();
+    let area = Rect {
+        x: area.width / 10,
+        y: area.height / 10,
+        width: area.width * 8 / 10,
+        height: area.height * 8 / 10,
+    };
+    let title = format!("diff of {} (esc to close)", target.id);
+    let diff = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(title));
+    frame.render_widget(Clear, area);
+    frame.render_widget(diff, area);
+}
+
+fn style_of(status: Status) -> Style {
+    match status {
+        Status::Ok => Style::new().fg(Color::Green),
+        Status::Changed => Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        Status::Failing | Status::Blocked => Style::new().fg(Color::Red),
+        Status::SelectorMiss | Status::Flapping => Style::new().fg(Color::Yellow),
+        Status::New => Style::new(),
+        Status::Skipped | Status::Paused | Status::Maintenance | Status::Expired => {
+            Style::new().fg(Color::DarkGray)
+        }
+    }
+}
+
+/// Handles the key, and returns whether to go on.
+async fn handle_key(view: &mut View, key: KeyEvent, controls: &Controls) -> bool {
+    let len = view.targets.len();
+    match key.code {
+        KeyCode::Esc if view.diff => view.diff = false,
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Up | KeyCode::Char('k') if 0 < len => {
+            let i = view.table.selected().unwrap_or(0);
+            view.table.select(Some(i.saturating_sub(1)));
+        }
+        KeyCode::Down | KeyCode::Char('j') if 0 < len => {
+            let i = view.table.selected().map(|x| x + 1).unwrap_or(0);
+            view.table.select(Some(i.min(len - 1)));
+        }
+        KeyCode::Char('d') | KeyCode::Enter => view.diff = !view.diff,
+        KeyCode::Char('p') => {
+            let _ = controls.poll.send(());
+            view.header = "polling...".to_owned();
+        }
+        KeyCode::Char('r') => {
+            let _ = controls.reload.send(());
+            push_recent(view, Line::from("reloading the configurations."));
+        }
+        KeyCode::Char(' ') => {
+            let target = match view.table.selected().and_then(|i| view.targets.get(i)) {
+                Some(x) => x,
+                None => return true,
+            };
+            let (id, paused) = (target.id.clone(), !target.paused);
+            let line = match set_paused(controls, &id, paused).await {
+                Ok(()) => {
+                    let _ = controls.reload.send(());
+                    let verb = if paused { "paused" } else { "resumed" };
+                    Line::from(format!("[{id}]: {verb}."))
+                }
+                Err(why) => Line::from(format!("[{id}]: {why}")).red(),
+            };
+            push_recent(view, line);
+        }
+        _ => (),
+    }
+    true
+}
+
+/// Pauses or resumes the target in the config file, which the app reloads then.
+async fn set_paused(
+    controls: &Controls,
+    id: &Id,
+    paused: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_repo = match controls.managed_config_path.as_ref() {
+        Some(managed_path) => {
+            TomlConfigRepository::with_managed(&controls.config_path, managed_path).await?
+        }
+        None => TomlConfigRepository::new(&controls.config_path).await?,
+    };
+    let config = match config_repo.get_all().await?.remove(id) {
+        Some(x) => x,
+        None => return Err(format!("{id} is not found.").into()),
+    };
+    let changes = HashMap::from([(id.clone(), Some(Config { paused, ..config }))]);
+    config_repo.apply(changes).await?;
+    Ok(())
+}
+
+fn handle_event(view: &mut View, event: PatrolEvent) {
+    match event {
+        PatrolEvent::PollStarted { ids } => {
+            view.header = format!("polling {} targets...", ids.len());
+        }
+        PatrolEvent::PollFailed { id, reason, .. } => {
+            push_recent(view, Line::from(format!("[{id}]: {reason}")).red());
+        }
+        PatrolEvent::ChangeDetected { id, change, .. } => {
+            let change = change.map(|x| format!(" ({x})")).unwrap_or_default();
+            let line = Line::from(vec![
+                Span::raw(format!("[{id}]: ")),
+                Span::raw(format!("changed{change}.")).cyan().bold(),
+            ]);
+            push_recent(view, line);
+        }
+        PatrolEvent::CycleCompleted {
+            cycle,
+            succeeded,
+            changed,
+            failed,
+            overrun,
+            elapsed,
+        } => {
+            view.header = format!(
+                "cycle {cycle}: {succeeded} succeeded, {changed} changed, {failed} failed, {overrun} overrun in {:.1}s.",
+                elapsed.as_secs_f64()
+            );
+        }
+        PatrolEvent::ConfigChanged(event) => {
+            let (verb, id) = match &event {
+                ConfigEvent::Added { id, .. } => ("added", id),
+                ConfigEvent::Updated { id, .. } => ("updated", id),
+                ConfigEvent::Removed { id, .. } => ("removed", id),
+            };
+            push_recent(view, Line::from(format!("[{id}]: {verb} in the config.")));
+            match event {
+                ConfigEvent::Added { id, config } | ConfigEvent::Updated { id, config } => {
+                    let target = target_mut(view, &id);
+                    target.url = Some(config.url);
+                    target.paused = config.paused;
+                    if config.paused {
+                        target.status = Status::Paused;
+                    }
+                }
+                ConfigEvent::Removed { .. } => (),
+            }
+        }
+        PatrolEvent::Recorded(Event::Status {
+            id,
+            url,
+            status,
+            last_updated,
+            last_checked,
+        }) => {
+            let target = target_mut(view, &id);
+            target.url = url.or(target.url.take());
+            target.status = status;
+            target.last_checked = Some(last_checked);
+            target.last_updated = last_updated;
+        }
+        PatrolEvent::Recorded(Event::Checked {
+            id,
+            excerpt: Some(excerpt),
+            ..
+        }) => target_mut(view, &id).excerpt = Some(excerpt),
+        PatrolEvent::Recorded(Event::Flapping { id, .. }) => {
+            push_recent(view, Line::from(format!("[{id}]: flapping.")).yellow());
+        }
+        PatrolEvent::Recorded(Event::Tampered { id, kind, .. }) => {
+            push_recent(
+                view,
+                Line::from(format!("[{id}]: tampered ({kind})."))
+                    .red()
+                    .bold(),
+            );
+        }
+        PatrolEvent::Recorded(Event::Triggered { id, .. }) => {
+            push_recent(
+                view,
+                Line::from(format!("[{id}]: triggered.")).cyan().bold(),
+            );
+        }
+        PatrolEvent::Recorded(_)
+        | PatrolEvent::ContentPolled { .. }
+        | PatrolEvent::StatsRecorded { .. } => (),
+    }
+}
+
+/// Returns the target of the id, adding it in the order of the ids if not shown yet.
+fn target_mut<'a>(view: &'a mut View, id: &Id) -> &'a mut Target {
+    let index = match view.targets.binary_search_by(|x| x.id.cmp(id)) {
+        Ok(i) => i,
+        Err(i) => {
+            let target = Target {
+                id: id.clone(),
+                url: None,
+                status: Status::New,
+                paused: false,
+                last_checked: None,
+                last_updated: None,
+                excerpt: None,
+            };
+            view.targets.insert(i, target);
+            match view.table.selected() {
+                Some(selected) if i <= selected => view.table.select(Some(selected + 1)),
+                None => view.table.select(Some(0)),
+                _ => (),
+            }
+            i
+        }
+    };
+    &mut view.targets[index]
+}
+
+fn push_recent(view: &mut View, line: Line<'static>) {
+    if MAX_RECENT_EVENTS <= view.recent.len() {
+        let _ = view.recent.pop_front();
+    }
+    view.recent.push_back(line);
+}